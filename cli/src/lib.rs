@@ -1,6 +1,6 @@
 pub mod repl;
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -23,6 +23,21 @@ pub enum Commands {
         /// Level of verbosity
         #[arg(short, long, default_value_t = false)]
         verbose: bool,
+        /// Directory the program's file system access is confined to
+        #[arg(long, value_name = "DIR")]
+        sandbox: Option<PathBuf>,
+        /// Seed of the random numbers, making them reproducible
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Run on a fake clock starting at the given milliseconds since the epoch
+        #[arg(long, value_name = "MS")]
+        fake_clock: Option<i64>,
+        /// Log every interaction of the program with the OS to the file
+        #[arg(long, value_name = "LOG", conflicts_with = "dry")]
+        record: Option<PathBuf>,
+        /// Rerun with the inputs of a recorded log, checking that the program does the same
+        #[arg(long, value_name = "LOG", conflicts_with_all = ["dry", "record"])]
+        replay: Option<PathBuf>,
         /// Environmental arguments to pass to the program
         #[arg(last = true)]
         args: Vec<String>,
@@ -36,6 +51,21 @@ pub enum Commands {
         #[arg(short, long, default_value_t = false)]
         verbose: bool,
     },
+    /// Compile a zydeco program to a C executable or WebAssembly module
+    Build {
+        /// Path to the file to compile
+        #[arg(value_name = "FILE")]
+        files: Vec<PathBuf>,
+        /// Target to compile to
+        #[arg(long, value_enum, default_value_t = Target::C)]
+        target: Target,
+        /// Path of the produced executable or module
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Level of verbosity
+        #[arg(short, long, default_value_t = false)]
+        verbose: bool,
+    },
     /// Start a REPL
     Repl {
         /// Level of verbosity
@@ -44,4 +74,16 @@ pub enum Commands {
     },
 }
 
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Target {
+    /// Portable C, compiled with the system C compiler; the executable takes
+    /// `--zy-sandbox=DIR`, `--zy-seed=N` and `--zy-fake-clock=MS` before the
+    /// program's arguments
+    C,
+    /// A WebAssembly module using WASI, runnable by any WASI runtime; only the
+    /// arithmetic, basic string and stdin/stdout builtins are supported, and
+    /// programs using effect handlers, prompts, refs or arrays are rejected
+    Wasm,
+}
+
 pub use repl::Repl;
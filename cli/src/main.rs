@@ -1,29 +1,55 @@
 use clap::Parser;
-use cli::{Cli, Commands, Repl};
+use cli::{Cli, Commands, Repl, Target};
+use std::path::{Path, PathBuf};
 use zydeco_lang::{
+    dynamics::{
+        host::{Host, OsHost},
+        replay::{Recorder, Replayer},
+    },
     prelude::*,
     zydeco::{ProgKont, ZydecoFile},
 };
 
-fn main() -> Result<(), ()> {
+fn main() {
     let res = match Cli::parse().command {
-        Commands::Run { files, dry, verbose, args } => run_files(files, dry, verbose, args),
-        Commands::Check { files, verbose } => run_files(files, true, verbose, vec![]),
+        Commands::Run { files, dry, verbose, sandbox, seed, fake_clock, record, replay, args } => {
+            let mut host = OsHost::new(args);
+            if let Some(root) = sandbox {
+                host = host.sandbox(root);
+            }
+            if let Some(seed) = seed {
+                host = host.seed(seed);
+            }
+            if let Some(start) = fake_clock {
+                host = host.fake_clock(start);
+            }
+            if let Some(log) = replay {
+                replay_files(files, verbose, &log, host)
+            } else if let Some(log) = record {
+                record_files(files, verbose, &log, host)
+            } else {
+                run_files(files, dry, verbose, &mut host)
+            }
+        }
+        Commands::Check { files, verbose } => {
+            run_files(files, true, verbose, &mut OsHost::new(vec![]))
+        }
+        Commands::Build { files, target, output, verbose } => {
+            build_files(files, target, output, verbose)
+        }
         Commands::Repl { .. } => Repl::launch(),
     };
     match res {
-        Ok(x) => {
-            std::process::exit(x);
-        }
+        Ok(x) => std::process::exit(x),
         Err(e) => {
             eprintln!("Error: {}", e);
-            Ok(())
+            std::process::exit(1)
         }
     }
 }
 
 fn run_files(
-    paths: Vec<std::path::PathBuf>, dry_run: bool, verbose: bool, args: Vec<String>,
+    paths: Vec<std::path::PathBuf>, dry_run: bool, verbose: bool, host: &mut dyn Host,
 ) -> Result<i32, String> {
     let title =
         &paths.iter().map(|path| format!("{}", path.display())).collect::<Vec<_>>().join(", ");
@@ -36,7 +62,9 @@ fn run_files(
     }
     // type check
     announce_phase(verbose, title, "tyck");
-    ZydecoFile::tyck(m.clone())?;
+    for goal in ZydecoFile::tyck(m.clone())? {
+        eprint!("{}", goal);
+    }
 
     // if not dry run, link and eval
     if !dry_run {
@@ -48,11 +76,91 @@ fn run_files(
         }
         // eval
         announce_phase(verbose, title, "eval");
-        let res = ZydecoFile::eval_os(sem_m, &args);
-        let ProgKont::ExitCode(x) = res.entry else {
-            Err("Program did not exit".to_string())?
+        let res = ZydecoFile::eval_host(sem_m, host);
+        return match res.entry {
+            ProgKont::ExitCode(x) => Ok(x),
+            ProgKont::Hole(hole) => Err(format!("Reached {}", ProgKont::Hole(hole).fmt())),
+            ProgKont::Ret(_) => Err("Program did not exit".to_string()),
         };
-        return Ok(x);
+    }
+    Ok(0)
+}
+
+fn record_files(
+    paths: Vec<PathBuf>, verbose: bool, log: &Path, host: OsHost,
+) -> Result<i32, String> {
+    let log = std::fs::File::create(log).map_err(|e| format!("{}: {}", log.display(), e))?;
+    let mut host = Recorder::new(host, log);
+    let res = run_files(paths, false, verbose, &mut host);
+    let recorded = match &res {
+        Ok(x) => host.exit(*x),
+        Err(e) => host.fail(e),
+    };
+    recorded.map_err(|e| format!("Failed to record: {}", e))?;
+    res
+}
+
+fn replay_files(
+    paths: Vec<PathBuf>, verbose: bool, log: &Path, host: OsHost,
+) -> Result<i32, String> {
+    let log = std::fs::read_to_string(log).map_err(|e| format!("{}: {}", log.display(), e))?;
+    let mut host = Replayer::new(&log, host).map_err(|e| format!("{}", e))?;
+    let res = run_files(paths, false, verbose, &mut host);
+    let replayed = match &res {
+        Ok(x) => host.exit(*x),
+        Err(e) => host.fail(e),
+    };
+    match (res, replayed) {
+        (res, Ok(_)) => res,
+        (Ok(_), Err(divergence)) => Err(format!("{}", divergence)),
+        // a program stopping early may well have diverged before, which is
+        // what went wrong first
+        (Err(e), Err(divergence)) => Err(format!("{}\n{}", divergence, e)),
+    }
+}
+
+fn build_files(
+    paths: Vec<PathBuf>, target: Target, output: Option<PathBuf>, verbose: bool,
+) -> Result<i32, String> {
+    let title =
+        &paths.iter().map(|path| format!("{}", path.display())).collect::<Vec<_>>().join(", ");
+    let output = match output {
+        Some(output) => output,
+        None => {
+            let Some(stem) = paths.last().and_then(|path| path.file_stem()) else {
+                Err("No file to build".to_string())?
+            };
+            match target {
+                Target::C => PathBuf::from(stem),
+                Target::Wasm => PathBuf::from(stem).with_extension("wasm"),
+            }
+        }
+    };
+    // parse
+    announce_phase(verbose, title, "parse");
+    let m = ZydecoFile::parse(paths)?;
+    let m = ZydecoFile::elab(m)?;
+    // type check
+    announce_phase(verbose, title, "tyck");
+    for goal in ZydecoFile::tyck(m.clone())? {
+        eprint!("{}", goal);
+    }
+    // link
+    announce_phase(verbose, title, "link");
+    let sem_m = ZydecoFile::link(m.inner())?;
+    // codegen
+    match target {
+        Target::C => {
+            announce_phase(verbose, title, "codegen");
+            let src = ZydecoFile::build_c(&sem_m, &output)?;
+            if verbose {
+                println!("{} -> {}", src.display(), output.display());
+            }
+        }
+        Target::Wasm => {
+            announce_phase(verbose, title, "codegen");
+            ZydecoFile::build_wasm(&sem_m, &output)?;
+        }
     }
     Ok(0)
 }
@@ -84,6 +84,9 @@ impl Repl {
             Err(e) => Err(format!("Type Error: {}", e))?,
             Ok(ty) => ty,
         };
+        for goal in zydeco_expr.goals() {
+            print!("{}", goal);
+        }
         if dry || matches!(term.inner_ref(), ss::Term::Value(_)) {
             // Note: not evaluating the value, just printing its type
             println!("{} : {}", term.inner_ref().fmt(), ty.fmt());
@@ -100,21 +103,27 @@ impl Repl {
                 if let Some(()) = ty.clone().elim_os(zydeco_expr.ctx.clone(), &SpanInfo::dummy()) {
                     let c = ZydecoExpr::link_computation(c);
                     let c = zydeco_expr.eval_os(c, &[]);
-                    let ds::ProgKont::ExitCode(i) = c.entry else {
-                        unreachable!()
-                    };
-                    println!("Program exited with code {}", i);
-                    Ok(())
+                    match c.entry {
+                        ds::ProgKont::ExitCode(i) => {
+                            println!("Program exited with code {}", i);
+                            Ok(())
+                        }
+                        k @ ds::ProgKont::Hole(_) => Err(format!("Reached {}", k.fmt())),
+                        ds::ProgKont::Ret(_) => unreachable!(),
+                    }
                 } else if let Some(ty) =
                     ty.clone().elim_ret(zydeco_expr.ctx.clone(), &SpanInfo::dummy())
                 {
                     let c = ZydecoExpr::link_computation(c);
                     let c = zydeco_expr.eval_ret_computation(c);
-                    let ds::ProgKont::Ret(value) = c else {
-                        unreachable!()
-                    };
-                    println!("{} : {}", value.fmt(), ty.fmt());
-                    Ok(())
+                    match c {
+                        ds::ProgKont::Ret(value) => {
+                            println!("{} : {}", value.fmt(), ty.fmt());
+                            Ok(())
+                        }
+                        k @ ds::ProgKont::Hole(_) => Err(format!("Reached {}", k.fmt())),
+                        ds::ProgKont::ExitCode(_) => unreachable!(),
+                    }
                 } else {
                     let mut s = String::new();
                     s += &format!("Can't run computation of type {}", ty.fmt());
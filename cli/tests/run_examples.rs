@@ -1,5 +1,5 @@
 use cli::Repl;
-use std::{io::Read, path::PathBuf};
+use std::{io::Read, path::PathBuf, process::Command};
 use zydeco_lang::zydeco::ZydecoExpr;
 
 fn wrapper<T>(r: Result<T, String>) {
@@ -66,3 +66,49 @@ mod command_test {
     mk_test!(command_test, env, "env.in");
     mk_test!(command_test, r#type, "type.in");
 }
+
+/// Runs the CLI, returning its exit code, standard output and error.
+fn zydeco(args: &[&str]) -> (Option<i32>, String, String) {
+    let out = Command::new(env!("CARGO_BIN_EXE_zydeco")).args(args).output().unwrap();
+    (
+        out.status.code(),
+        String::from_utf8(out.stdout).unwrap(),
+        String::from_utf8(out.stderr).unwrap(),
+    )
+}
+
+mod build_tests {
+    use super::*;
+
+    #[test]
+    fn build_failure() {
+        let exe = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("hole");
+        let _ = std::fs::remove_file(&exe);
+        let (code, _, err) = zydeco(&["build", "-o", exe.to_str().unwrap(), "tests/run/hole.zy"]);
+        assert_eq!(code, Some(1));
+        assert!(err.ends_with("must be filled before code generation\n"), "{}", err);
+        assert!(!exe.exists());
+    }
+}
+
+mod record_tests {
+    use super::*;
+
+    #[test]
+    fn record_failure() {
+        let log = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("hole.log");
+        let log = log.to_str().unwrap();
+        let error = "Reached hole ?code (tests/run/hole.zy:5:12 - 5:17)";
+        let (code, out, err) = zydeco(&["run", "--record", log, "tests/run/hole.zy"]);
+        assert_eq!(code, Some(1));
+        assert_eq!(out, "before the hole\n");
+        assert!(err.ends_with(&format!("Error: {}\n", error)), "{}", err);
+        let recorded = std::fs::read_to_string(log).unwrap();
+        assert_eq!(recorded.lines().last(), Some(format!("error\t{}", error).as_str()));
+        let (code, out, err) = zydeco(&["run", "--replay", log, "tests/run/hole.zy"]);
+        assert_eq!(code, Some(1));
+        assert_eq!(out, "before the hole\n");
+        assert!(err.ends_with(&format!("\nError: {}\n", error)), "{}", err);
+        assert!(!err.contains("diverged"), "{}", err);
+    }
+}
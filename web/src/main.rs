@@ -48,14 +48,16 @@ fn ZydecoUI() -> Html {
 fn run(input: &str) -> Result<String, String> {
     let p = ZydecoFile::parse_src(input, PathBuf::new())?;
     let p = ZydecoFile::elab(p)?;
-    ZydecoFile::tyck(p.clone())?;
+    let goals = ZydecoFile::tyck(p.clone())?;
     let p = ZydecoFile::link(p.inner)?;
-    let p = ZydecoFile::eval_os(p, &[]);
+    let (p, output) = ZydecoFile::eval_virtual_os(p, "", &[]);
     let s = match p.entry {
         ds::ProgKont::Ret(v) => format!("{}", v.fmt()),
         ds::ProgKont::ExitCode(i) => format!("exit code: {}", i),
+        k @ ds::ProgKont::Hole(_) => format!("reached {}", k.fmt()),
     };
-    Ok(String::from(s))
+    let goals: String = goals.iter().map(|goal| goal.to_string()).collect();
+    Ok(goals + &output + &s)
 }
 
 fn main() {
@@ -186,3 +186,129 @@ pub fn fmt_args_derive(input: TokenStream) -> TokenStream {
     res.extend(TokenStream::from(gen));
     res
 }
+
+/// The constructor name and the bindings of the fields of a variant (or a
+/// struct), as a pattern and the list of bound names in order.
+fn ctor_fields(
+    path: proc_macro2::TokenStream, fields: &Fields,
+) -> (proc_macro2::TokenStream, Vec<syn::Ident>) {
+    match fields {
+        Fields::Named(fields) => {
+            let idents: Vec<_> = fields.named.iter().map(|f| f.ident.clone().unwrap()).collect();
+            (quote! { #path { #( #idents ),* } }, idents)
+        }
+        Fields::Unnamed(fields) => {
+            let idents: Vec<_> = (0..fields.unnamed.len())
+                .map(|i| syn::Ident::new(&format!("f{}", i), proc_macro2::Span::call_site()))
+                .collect();
+            (quote! { #path ( #( #idents ),* ) }, idents)
+        }
+        Fields::Unit => (quote! { #path }, Vec::new()),
+    }
+}
+
+/// The constructors of a data type: for an enum its variants, for a struct the
+/// struct itself, with the path to build and match it.
+fn ctors(input: &DeriveInput) -> Vec<(String, proc_macro2::TokenStream, Fields)> {
+    let ident = &input.ident;
+    match &input.data {
+        Data::Enum(data) => data
+            .variants
+            .iter()
+            .map(|v| {
+                let variant_ident = &v.ident;
+                (variant_ident.to_string(), quote! { #ident::#variant_ident }, v.fields.clone())
+            })
+            .collect(),
+        Data::Struct(data) => vec![(ident.to_string(), quote! { #ident }, data.fields.clone())],
+        Data::Union(_) => panic!("Zydeco conversions can only be derived for enums and structs"),
+    }
+}
+
+/// Adds `bound` to every type parameter.
+fn add_bounds(generics: &syn::Generics, bound: proc_macro2::TokenStream) -> syn::Generics {
+    let mut generics = generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(syn::parse2(bound.clone()).unwrap());
+    }
+    generics
+}
+
+#[proc_macro_derive(IntoZydeco)]
+pub fn into_zydeco_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let ident = &input.ident;
+    let generics = add_bounds(&input.generics, quote!(::zydeco_lang::library::convert::IntoZydeco));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let arms = ctors(&input).into_iter().map(|(name, path, fields)| {
+        let (pat, idents) = ctor_fields(path, &fields);
+        quote! {
+            #pat => ::zydeco_lang::library::convert::ctor(
+                #name,
+                vec![#( ::zydeco_lang::library::convert::IntoZydeco::into_zydeco(#idents) ),*],
+            )
+        }
+    });
+    let gen = quote!(
+        impl #impl_generics ::zydeco_lang::library::convert::IntoZydeco for #ident #ty_generics #where_clause {
+            fn into_zydeco(self) -> ::zydeco_lang::dynamics::syntax::SemVal {
+                match self {
+                    #( #arms ),*
+                }
+            }
+        }
+    );
+    TokenStream::from(gen)
+}
+
+#[proc_macro_derive(FromZydeco)]
+pub fn from_zydeco_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let ident = &input.ident;
+    let data_name = ident.to_string();
+    let generics = add_bounds(&input.generics, quote!(::zydeco_lang::library::convert::FromZydeco));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let arms = ctors(&input).into_iter().map(|(name, path, fields)| {
+        let (pat, idents) = ctor_fields(path, &fields);
+        quote! {
+            (#name, [#( #idents ),*]) => {
+                #( let #idents = ::zydeco_lang::library::convert::FromZydeco::from_zydeco(#idents)?; )*
+                Ok(#pat)
+            }
+        }
+    });
+    let gen = quote!(
+        impl #impl_generics ::zydeco_lang::library::convert::FromZydeco for #ident #ty_generics #where_clause {
+            fn from_zydeco(
+                value: &::zydeco_lang::dynamics::syntax::SemVal,
+            ) -> Result<Self, ::zydeco_lang::library::err::ConvertError> {
+                match ::zydeco_lang::library::convert::unctor(value, #data_name)? {
+                    #( #arms, )*
+                    _ => Err(::zydeco_lang::library::err::ConvertError::mismatch(#data_name, value)),
+                }
+            }
+        }
+    );
+    TokenStream::from(gen)
+}
+
+#[proc_macro_derive(ZydecoType)]
+pub fn zydeco_type_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let ident = &input.ident;
+    let data_name = ident.to_string();
+    let params: Vec<_> = input.generics.type_params().map(|p| &p.ident).collect();
+    let generics = add_bounds(&input.generics, quote!(::zydeco_lang::library::convert::ZydecoType));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let gen = quote!(
+        impl #impl_generics ::zydeco_lang::library::convert::ZydecoType for #ident #ty_generics #where_clause {
+            fn zydeco_type() -> ::zydeco_lang::statics::syntax::Type {
+                ::zydeco_lang::library::convert::data_type(
+                    #data_name,
+                    vec![#( <#params as ::zydeco_lang::library::convert::ZydecoType>::zydeco_type() ),*],
+                )
+            }
+        }
+    );
+    TokenStream::from(gen)
+}
@@ -0,0 +1,533 @@
+//! Compiles a linked program to portable C.
+//!
+//! Each computation that can be jumped to (thunk bodies, `do` continuations
+//! and `rec` bodies) becomes a code block driving the CK machine in
+//! `runtime.c`; every other computation is compiled inline into its parent.
+//! Locals live in a linked environment addressed by de Bruijn indices resolved
+//! here, while module definitions are stored in a global table.
+
+use super::{
+    err::CodegenError,
+    prune::prune,
+    unicode::{self, CaseTable},
+};
+use crate::library::syntax::*;
+use std::{collections::HashMap, fmt::Write, path::Path, process::Command};
+
+const RUNTIME: &str = include_str!("runtime.c");
+
+/// Constructors the runtime builds itself, interned first and in this order.
+const RUNTIME_CTORS: [&str; 10] =
+    ["True", "False", "None", "Some", "Pair", "Nil", "Cons", "Unit", "Ok", "Err"];
+/// Destructors the runtime builds itself, interned first and in this order.
+const RUNTIME_DTORS: [&str; 1] = ["arg"];
+/// Builtins implemented by the runtime as `zy_prim_<name>`.
+const RUNTIME_PRIMS: [&str; 61] = [
+    "add",
+    "sub",
+    "mul",
+    "div",
+    "mod",
+    "int_eq",
+    "int_lt",
+    "int_gt",
+    "str_length",
+    "str_append",
+    "str_split_once",
+    "str_split_n",
+    "str_substring",
+    "str_eq",
+    "str_index",
+    "str_find",
+    "str_starts_with",
+    "str_ends_with",
+    "str_lt",
+    "str_replace",
+    "str_trim",
+    "str_to_upper",
+    "str_to_lower",
+    "str_split",
+    "str_join",
+    "str_to_list",
+    "list_to_str",
+    "int_to_str",
+    "char_to_str",
+    "char_to_int",
+    "int_to_char",
+    "str_to_int",
+    "write_str",
+    "read_line",
+    "read_till_eof",
+    "exit",
+    "arg_list",
+    "write_err",
+    "env_var",
+    "clock_wall",
+    "clock_monotonic",
+    "sleep",
+    "random_int",
+    "file_open",
+    "file_read",
+    "file_write",
+    "file_append",
+    "file_exists",
+    "file_remove",
+    "dir_list",
+    "new_prompt",
+    "push_prompt",
+    "take_subcont",
+    "push_subcont",
+    "ref_new",
+    "ref_get",
+    "ref_set",
+    "array_new",
+    "array_get",
+    "array_set",
+    "array_length",
+];
+
+/// Compile-time view of the environment a block runs in.
+#[derive(Clone, Default)]
+struct Scope {
+    locals: Vec<TermV>,
+}
+
+impl Scope {
+    fn bind(&self, var: &TermV) -> Self {
+        let mut locals = self.locals.clone();
+        locals.push(var.clone());
+        Self { locals }
+    }
+}
+
+#[derive(Default)]
+pub struct CEmitter {
+    ctors: HashMap<String, usize>,
+    dtors: HashMap<String, usize>,
+    ops: HashMap<TermV, usize>,
+    globals: HashMap<TermV, usize>,
+    global_count: usize,
+    blocks: Vec<String>,
+    handlers: Vec<String>,
+}
+
+impl CEmitter {
+    pub fn new() -> Self {
+        let mut emitter = Self::default();
+        for ctor in RUNTIME_CTORS {
+            emitter.ctor(ctor);
+        }
+        for dtor in RUNTIME_DTORS {
+            emitter.dtor(dtor);
+        }
+        emitter
+    }
+
+    /// Emits a self-contained C translation unit for the program.
+    pub fn emit(mut self, program: &Program) -> Result<String, CodegenError> {
+        let mut init = String::new();
+        for (var, def) in prune(program) {
+            let def = self.value(def, &Scope::default())?;
+            let idx = self.global_count;
+            self.global_count += 1;
+            writeln!(init, "  zy_globals[{}] = {};", idx, def).unwrap();
+            self.globals.insert(var.clone(), idx);
+        }
+        let main = self.comp(&program.entry, &Scope::default(), 1)?;
+        let mut s = String::new();
+        s += RUNTIME;
+        s += "\n/* generated program */\n\n";
+        s += "static const char *zy_ctor_names[] = {";
+        s += &names(&self.ctors).join(", ");
+        s += "};\n";
+        s += "static const char *zy_dtor_names[] = {";
+        s += &names(&self.dtors).join(", ");
+        s += "};\n";
+        writeln!(s, "static zv *zy_globals[{}];", self.global_count.max(1)).unwrap();
+        for idx in 0..self.blocks.len() {
+            writeln!(s, "static void zy_block_{}(zm *m);", idx).unwrap();
+        }
+        s += "\n";
+        s += &unicode_tables();
+        s += "\n";
+        for handler in &self.handlers {
+            s += handler;
+        }
+        s += "\n";
+        for block in &self.blocks {
+            s += block;
+            s += "\n";
+        }
+        s += "static void zy_init_globals(void) {\n";
+        s += "  (void)zy_ctor_names;\n";
+        s += "  (void)zy_dtor_names;\n";
+        s += &init;
+        s += "}\n\n";
+        s += "static void zy_main(zm *m) {\n";
+        s += &main;
+        s += "}\n";
+        Ok(s)
+    }
+
+    fn ctor(&mut self, name: &str) -> usize {
+        let len = self.ctors.len();
+        *self.ctors.entry(name.to_owned()).or_insert(len)
+    }
+
+    fn dtor(&mut self, name: &str) -> usize {
+        let len = self.dtors.len();
+        *self.dtors.entry(name.to_owned()).or_insert(len)
+    }
+
+    fn op(&mut self, op: &TermV) -> usize {
+        let len = self.ops.len();
+        *self.ops.entry(op.clone()).or_insert(len)
+    }
+
+    /// Registers a new code block and returns its name.
+    fn block(&mut self, comp: &SynComp, scope: &Scope) -> Result<String, CodegenError> {
+        let idx = self.blocks.len();
+        self.blocks.push(String::new());
+        let body = self.comp(comp, scope, 1)?;
+        self.blocks[idx] = format!("static void zy_block_{}(zm *m) {{\n{}}}\n", idx, body);
+        Ok(format!("zy_block_{}", idx))
+    }
+
+    fn var(&self, var: &TermV, scope: &Scope) -> Result<String, CodegenError> {
+        if let Some(idx) = scope.locals.iter().rev().position(|x| x == var) {
+            Ok(format!("zy_lookup(m->env, {})", idx))
+        } else if let Some(idx) = self.globals.get(var) {
+            Ok(format!("zy_globals[{}]", idx))
+        } else {
+            Err(CodegenError::UnboundVar { var: var.clone() })
+        }
+    }
+
+    /// Compiles a value into a C expression of type `zv *`.
+    fn value(&mut self, value: &SynVal, scope: &Scope) -> Result<String, CodegenError> {
+        Ok(match value {
+            SynVal::Var(var) => self.var(var, scope)?,
+            SynVal::Thunk(Thunk(body)) => {
+                let code = match body.as_ref() {
+                    SynComp::Prim(Prim { name, .. }) => prim(name)?,
+                    body => self.block(body, scope)?,
+                };
+                if scope.locals.is_empty() {
+                    format!("zy_thunk({}, NULL)", code)
+                } else {
+                    format!("zy_thunk({}, m->env)", code)
+                }
+            }
+            SynVal::Ctor(Ctor { ctorv, args }) => {
+                let ctor = self.ctor(ctorv.name());
+                if args.is_empty() {
+                    format!("zy_ctor({}, 0, NULL)", ctor)
+                } else {
+                    let args = (args.iter())
+                        .map(|arg| self.value(arg, scope))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    format!("zy_ctor({}, {}, (zv *[]){{{}}})", ctor, args.len(), args.join(", "))
+                }
+            }
+            SynVal::Literal(Literal::Int(i)) => {
+                if *i == i64::MIN {
+                    format!("zy_int(INT64_MIN)")
+                } else {
+                    format!("zy_int(INT64_C({}))", i)
+                }
+            }
+            SynVal::Literal(Literal::Char(c)) => format!("zy_char({})", *c as u32),
+            SynVal::Literal(Literal::String(s)) => {
+                format!("zy_str({}, {})", c_string(s), s.len())
+            }
+            SynVal::SemValue(_) => Err(CodegenError::SemanticValue)?,
+            SynVal::Hole(hole) => Err(CodegenError::Hole { hole: hole.clone() })?,
+        })
+    }
+
+    /// Compiles a computation into C statements that leave the machine ready
+    /// for its next step.
+    fn comp(
+        &mut self, comp: &SynComp, scope: &Scope, depth: usize,
+    ) -> Result<String, CodegenError> {
+        let ind = "  ".repeat(depth);
+        let mut s = String::new();
+        match comp {
+            SynComp::Ret(Ret(v)) => {
+                writeln!(s, "{}zy_return(m, {});", ind, self.value(v, scope)?).unwrap();
+            }
+            SynComp::Force(Force(v)) => {
+                writeln!(s, "{}zy_force(m, {});", ind, self.value(v, scope)?).unwrap();
+            }
+            SynComp::Let(Let { var, def, body }) => {
+                writeln!(s, "{}m->env = zy_cons({}, m->env);", ind, self.value(def, scope)?)
+                    .unwrap();
+                s += &self.comp(body, &scope.bind(var), depth)?;
+            }
+            SynComp::Do(Do { var, comp, body }) => {
+                let kont = self.block(body, &scope.bind(var))?;
+                writeln!(s, "{}zy_push_kont(m, {});", ind, kont).unwrap();
+                s += &self.comp(comp, scope, depth)?;
+            }
+            SynComp::Rec(Rec { var, body }) => {
+                // the block rebinds the variable each time it is entered
+                let idx = self.blocks.len();
+                self.blocks.push(String::new());
+                let mut b = String::new();
+                writeln!(b, "  m->env = zy_cons(zy_thunk(zy_block_{}, m->env), m->env);", idx)
+                    .unwrap();
+                b += &self.comp(body, &scope.bind(var), 1)?;
+                self.blocks[idx] = format!("static void zy_block_{}(zm *m) {{\n{}}}\n", idx, b);
+                writeln!(s, "{}m->pc = zy_block_{};", ind, idx).unwrap();
+            }
+            SynComp::Match(Match { scrut, arms }) => {
+                writeln!(s, "{}zv *scrut = {};", ind, self.value(scrut, scope)?).unwrap();
+                writeln!(s, "{}switch (scrut->u.ctor.ctor) {{", ind).unwrap();
+                for Matcher { ctorv, vars, body } in arms {
+                    let ctor = self.ctor(ctorv.name());
+                    writeln!(s, "{}case {}: {{", ind, ctor).unwrap();
+                    let mut scope = scope.clone();
+                    for (i, var) in vars.iter().enumerate() {
+                        writeln!(
+                            s,
+                            "{}  m->env = zy_cons(scrut->u.ctor.args[{}], m->env);",
+                            ind, i
+                        )
+                        .unwrap();
+                        scope = scope.bind(var);
+                    }
+                    s += &self.comp(body, &scope, depth + 1)?;
+                    writeln!(s, "{}  return;", ind).unwrap();
+                    writeln!(s, "{}}}", ind).unwrap();
+                }
+                writeln!(s, "{}default:", ind).unwrap();
+                writeln!(s, "{}  zy_panic(\"no matching arm\");", ind).unwrap();
+                writeln!(s, "{}}}", ind).unwrap();
+            }
+            SynComp::MatchLit(MatchLit { scrut, arms, default }) => {
+                writeln!(s, "{}zv *scrut = {};", ind, self.value(scrut, scope)?).unwrap();
+                let mut switch = false;
+                for (lit, body) in arms {
+                    let test = match lit {
+                        Literal::Int(i) => {
+                            if !switch {
+                                writeln!(s, "{}switch (scrut->u.i) {{", ind).unwrap();
+                            }
+                            switch = true;
+                            if *i == i64::MIN {
+                                format!("case INT64_MIN:")
+                            } else {
+                                format!("case INT64_C({}):", i)
+                            }
+                        }
+                        Literal::Char(c) => {
+                            if !switch {
+                                writeln!(s, "{}switch (scrut->u.c) {{", ind).unwrap();
+                            }
+                            switch = true;
+                            format!("case {}:", *c as u32)
+                        }
+                        Literal::String(lit) => format!(
+                            "if (scrut->u.s.len == {} && memcmp(scrut->u.s.ptr, {}, {}) == 0)",
+                            lit.len(),
+                            c_string(lit),
+                            lit.len()
+                        ),
+                    };
+                    writeln!(s, "{}{} {{", ind, test).unwrap();
+                    s += &self.comp(body, scope, depth + 1)?;
+                    writeln!(s, "{}  return;", ind).unwrap();
+                    writeln!(s, "{}}}", ind).unwrap();
+                }
+                if switch {
+                    writeln!(s, "{}default: {{", ind).unwrap();
+                } else {
+                    writeln!(s, "{}{{", ind).unwrap();
+                }
+                s += &self.comp(default, scope, depth + 1)?;
+                writeln!(s, "{}}}", ind).unwrap();
+                if switch {
+                    writeln!(s, "{}}}", ind).unwrap();
+                }
+            }
+            SynComp::Comatch(Comatch { arms }) => {
+                writeln!(s, "{}zframe frame = zy_pop_dtor(m);", ind).unwrap();
+                writeln!(s, "{}switch (frame.dtor) {{", ind).unwrap();
+                for Comatcher { dtorv, vars, body } in arms {
+                    let dtor = self.dtor(dtorv.name());
+                    writeln!(s, "{}case {}: {{", ind, dtor).unwrap();
+                    let mut scope = scope.clone();
+                    for (i, var) in vars.iter().enumerate() {
+                        writeln!(s, "{}  m->env = zy_cons(frame.args[{}], m->env);", ind, i)
+                            .unwrap();
+                        scope = scope.bind(var);
+                    }
+                    s += &self.comp(body, &scope, depth + 1)?;
+                    writeln!(s, "{}  return;", ind).unwrap();
+                    writeln!(s, "{}}}", ind).unwrap();
+                }
+                writeln!(s, "{}default:", ind).unwrap();
+                writeln!(s, "{}  zy_panic(\"no matching arm\");", ind).unwrap();
+                writeln!(s, "{}}}", ind).unwrap();
+            }
+            SynComp::Dtor(Dtor { body, dtorv, args }) => {
+                let dtor = self.dtor(dtorv.name());
+                writeln!(s, "{}{{", ind).unwrap();
+                writeln!(s, "{}  zv **args = zy_args({});", ind, args.len()).unwrap();
+                for (i, arg) in args.iter().enumerate() {
+                    writeln!(s, "{}  args[{}] = {};", ind, i, self.value(arg, scope)?).unwrap();
+                }
+                writeln!(s, "{}  zy_push_dtor(m, {}, {}, args);", ind, dtor, args.len()).unwrap();
+                writeln!(s, "{}}}", ind).unwrap();
+                s += &self.comp(body, scope, depth)?;
+            }
+            SynComp::Handle(Handle { body, ret, arms }) => {
+                let ret = match ret {
+                    Some((var, body)) => self.block(body, &scope.bind(var))?,
+                    None => format!("NULL"),
+                };
+                let mut clauses = Vec::new();
+                for Handler { op, vars, kont, body } in arms {
+                    let mut scope = scope.clone();
+                    for var in vars {
+                        scope = scope.bind(var);
+                    }
+                    let code = self.block(body, &scope.bind(kont))?;
+                    clauses.push(format!("{{{}, {}}}", self.op(op), code));
+                }
+                let idx = self.handlers.len();
+                let mut h = String::new();
+                if !clauses.is_empty() {
+                    writeln!(
+                        h,
+                        "static const zarm zy_arms_{}[] = {{{}}};",
+                        idx,
+                        clauses.join(", ")
+                    )
+                    .unwrap();
+                }
+                let table =
+                    if clauses.is_empty() { format!("NULL") } else { format!("zy_arms_{}", idx) };
+                writeln!(
+                    h,
+                    "static const zhandler zy_handler_{} = {{{}, {}, {}}};",
+                    idx,
+                    ret,
+                    clauses.len(),
+                    table
+                )
+                .unwrap();
+                self.handlers.push(h);
+                writeln!(s, "{}zy_push_handler(m, &zy_handler_{});", ind, idx).unwrap();
+                s += &self.comp(body, scope, depth)?;
+            }
+            SynComp::Perform(Perform { op, args }) => {
+                writeln!(s, "{}{{", ind).unwrap();
+                writeln!(s, "{}  zv **args = zy_args({});", ind, args.len()).unwrap();
+                for (i, arg) in args.iter().enumerate() {
+                    writeln!(s, "{}  args[{}] = {};", ind, i, self.value(arg, scope)?).unwrap();
+                }
+                let (name, op) = (c_string(op.name()), self.op(op));
+                writeln!(s, "{}  zy_perform(m, {}, {}, {}, args);", ind, op, name, args.len())
+                    .unwrap();
+                writeln!(s, "{}}}", ind).unwrap();
+            }
+            SynComp::Prim(Prim { name, .. }) => {
+                writeln!(s, "{}m->pc = {};", ind, prim(name)?).unwrap();
+            }
+            SynComp::Hole(hole) => Err(CodegenError::Hole { hole: hole.clone() })?,
+        }
+        Ok(s)
+    }
+}
+
+fn prim(name: &'static str) -> Result<String, CodegenError> {
+    if RUNTIME_PRIMS.contains(&name) {
+        Ok(format!("zy_prim_{}", name))
+    } else {
+        Err(CodegenError::UnsupportedBuiltin { name, target: "C" })
+    }
+}
+
+/// Renders the Unicode tables the runtime's string builtins consult.
+fn unicode_tables() -> String {
+    let mut s = String::new();
+    for (name, table) in [("upper", CaseTable::upper()), ("lower", CaseTable::lower())] {
+        let runs = (table.runs.iter())
+            .map(|r| format!("{{{}, {}, {}, {}}}", r.lo, r.hi, r.delta, r.stride))
+            .collect::<Vec<_>>();
+        let specials = (table.specials.iter())
+            .map(|(c, [a, b, d])| format!("{{{}, {{{}, {}, {}}}}}", c, a, b, d))
+            .collect::<Vec<_>>();
+        writeln!(s, "static const zcase_run zy_{}_runs[] = {{{}}};", name, runs.join(", "))
+            .unwrap();
+        writeln!(
+            s,
+            "static const zcase_special zy_{}_specials[] = {{{}}};",
+            name,
+            specials.join(", ")
+        )
+        .unwrap();
+        writeln!(
+            s,
+            "static const zcase_table zy_{0} = {{{1}, zy_{0}_runs, {2}, zy_{0}_specials}};",
+            name,
+            runs.len(),
+            specials.len()
+        )
+        .unwrap();
+    }
+    let ranges = (unicode::whitespace().iter())
+        .map(|(lo, hi)| format!("{{{}, {}}}", lo, hi))
+        .collect::<Vec<_>>();
+    writeln!(s, "static const uint32_t zy_whitespace_ranges[][2] = {{{}}};", ranges.join(", "))
+        .unwrap();
+    writeln!(s, "static const zranges zy_whitespace = {{{}, zy_whitespace_ranges}};", ranges.len())
+        .unwrap();
+    s
+}
+
+fn names(table: &HashMap<String, usize>) -> Vec<String> {
+    let mut names = vec![String::new(); table.len()];
+    for (name, idx) in table {
+        names[*idx] = c_string(name);
+    }
+    names
+}
+
+/// Renders a C string literal holding exactly the bytes of `s`.
+fn c_string(s: &str) -> String {
+    let mut res = String::from("\"");
+    for b in s.bytes() {
+        match b {
+            b'"' => res += "\\\"",
+            b'\\' => res += "\\\\",
+            b' '..=b'~' => res.push(b as char),
+            _ => write!(res, "\\{:03o}", b).unwrap(),
+        }
+    }
+    res += "\"";
+    res
+}
+
+/// Compiles the C source at `src` into the executable `exe` with the system C
+/// compiler, honoring `$CC`. The runtime is included whole, so the builtins a
+/// program doesn't use are left unused; the warnings that point at a bug in
+/// the generated code fail the compilation.
+pub fn compile(src: &Path, exe: &Path) -> Result<(), CodegenError> {
+    let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_owned());
+    let output = Command::new(&cc)
+        .arg("-O2")
+        .args(["-Wall", "-Wno-unused-function"])
+        .args(["-Werror=implicit-function-declaration", "-Werror=int-conversion"])
+        .arg("-Werror=incompatible-pointer-types")
+        .arg("-o")
+        .arg(exe)
+        .arg(src)
+        .output()
+        .map_err(|e| CodegenError::Compiler(format!("{}: {}", cc, e)))?;
+    if !output.status.success() {
+        Err(CodegenError::Compiler(String::from_utf8_lossy(&output.stderr).into_owned()))?
+    }
+    Ok(())
+}
@@ -0,0 +1,21 @@
+use crate::{
+    library::syntax::{TermHole, TermV},
+    utils::{fmt::FmtArgs, span::SpanView},
+};
+use thiserror::Error;
+
+#[derive(Error, Clone, Debug)]
+pub enum CodegenError {
+    #[error("Unbound variable {var} during code generation")]
+    UnboundVar { var: TermV },
+    #[error("Builtin {name} is not supported by the {target} backend")]
+    UnsupportedBuiltin { name: &'static str, target: &'static str },
+    #[error("Effect handlers are not supported by the {target} backend")]
+    UnsupportedHandler { target: &'static str },
+    #[error("Hole {} ({}) must be filled before code generation", .hole.fmt(), .hole.span())]
+    Hole { hole: TermHole },
+    #[error("Runtime value found in a program before evaluation")]
+    SemanticValue,
+    #[error("Failed to run the C compiler: {0}")]
+    Compiler(String),
+}
@@ -0,0 +1,69 @@
+//! Drops the module definitions a program can't reach from its entry, so that
+//! a backend only needs to support the builtins the program actually uses.
+//! Variables are matched by name, which over-approximates under shadowing.
+
+use crate::library::syntax::*;
+use std::collections::HashSet;
+
+pub(super) fn prune(program: &Program) -> Vec<&(TermV, SynVal)> {
+    let Program { module, entry } = program;
+    let mut used = HashSet::new();
+    comp(entry, &mut used);
+    // definitions only refer to earlier ones, so a single backward pass suffices
+    let mut live = Vec::new();
+    for def in module.define.iter().rev() {
+        if used.contains(&def.0) {
+            value(&def.1, &mut used);
+            live.push(def);
+        }
+    }
+    live.reverse();
+    live
+}
+
+fn value(v: &SynVal, used: &mut HashSet<TermV>) {
+    match v {
+        SynVal::Var(var) => {
+            used.insert(var.clone());
+        }
+        SynVal::Thunk(Thunk(body)) => comp(body, used),
+        SynVal::Ctor(Ctor { ctorv: _, args }) => args.iter().for_each(|arg| value(arg, used)),
+        SynVal::Literal(_) | SynVal::SemValue(_) | SynVal::Hole(_) => {}
+    }
+}
+
+fn comp(c: &SynComp, used: &mut HashSet<TermV>) {
+    match c {
+        SynComp::Ret(Ret(v)) | SynComp::Force(Force(v)) => value(v, used),
+        SynComp::Let(Let { var: _, def, body }) => {
+            value(def, used);
+            comp(body, used);
+        }
+        SynComp::Do(Do { var: _, comp: c, body }) => {
+            comp(c, used);
+            comp(body, used);
+        }
+        SynComp::Rec(Rec { var: _, body }) => comp(body, used),
+        SynComp::Match(Match { scrut, arms }) => {
+            value(scrut, used);
+            arms.iter().for_each(|arm| comp(&arm.body, used));
+        }
+        SynComp::MatchLit(MatchLit { scrut, arms, default }) => {
+            value(scrut, used);
+            arms.iter().for_each(|(_, body)| comp(body, used));
+            comp(default, used);
+        }
+        SynComp::Comatch(Comatch { arms }) => arms.iter().for_each(|arm| comp(&arm.body, used)),
+        SynComp::Dtor(Dtor { body, dtorv: _, args }) => {
+            comp(body, used);
+            args.iter().for_each(|arg| value(arg, used));
+        }
+        SynComp::Handle(Handle { body, ret, arms }) => {
+            comp(body, used);
+            ret.iter().for_each(|(_, body)| comp(body, used));
+            arms.iter().for_each(|arm| comp(&arm.body, used));
+        }
+        SynComp::Perform(Perform { op: _, args }) => args.iter().for_each(|arg| value(arg, used)),
+        SynComp::Prim(_) | SynComp::Hole(_) => {}
+    }
+}
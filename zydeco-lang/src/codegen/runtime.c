@@ -0,0 +1,1314 @@
+/* zydeco C runtime
+ *
+ * The generated program is a set of code blocks (`zcode`) that drive a CK
+ * machine: `m->pc` is the next block to run, `m->env` the local environment
+ * and `m->stack` the explicit continuation stack of `Kont`, `Dtor`, prompt and
+ * handler frames, mirroring `dynamics::syntax::SemComp`. Memory is never
+ * reclaimed.
+ *
+ * The executable takes the flags of the host before the program's arguments,
+ * like `zydeco run` does: `--zy-sandbox=DIR` confines the file system
+ * builtins to DIR, `--zy-seed=N` seeds the random numbers and
+ * `--zy-fake-clock=MS` runs on a fake clock starting at MS, which only moves
+ * when the program sleeps.
+ */
+
+#define _XOPEN_SOURCE 700
+
+#include <dirent.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <stdarg.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <time.h>
+#include <unistd.h>
+
+typedef struct zv zv;
+typedef struct zenv zenv;
+typedef struct zm zm;
+typedef struct zframe zframe;
+typedef void (*zcode)(zm *m);
+
+enum { ZY_INT, ZY_STR, ZY_CHAR, ZY_CTOR, ZY_THUNK, ZY_PROMPT, ZY_SUBCONT, ZY_REF, ZY_ARRAY };
+
+struct zv {
+  int tag;
+  union {
+    int64_t i;
+    uint32_t c;
+    struct {
+      size_t len;
+      const char *ptr;
+    } s;
+    struct {
+      int ctor;
+      int n;
+      zv **args;
+    } ctor;
+    struct {
+      zcode code;
+      zenv *env;
+    } thunk;
+    struct {
+      size_t n;
+      zframe *frames;
+    } subcont;
+    zv *ref;
+    struct {
+      int64_t len;
+      zv **elems;
+    } array;
+  } u;
+};
+
+struct zenv {
+  zv *v;
+  zenv *next;
+};
+
+/* the arms of a `handle`, generated for each one; `ret` is NULL when the
+   return clause is absent */
+typedef struct {
+  int op;
+  zcode code;
+} zarm;
+
+typedef struct {
+  zcode ret;
+  int n;
+  const zarm *arms;
+} zhandler;
+
+/* `ZY_MARK` is a prompt pushed by `push_prompt` */
+enum { ZY_KONT, ZY_DTOR, ZY_MARK, ZY_HANDLER };
+
+struct zframe {
+  int kind;
+  zcode code;
+  zenv *env;
+  int dtor;
+  int n;
+  zv **args;
+  int64_t prompt;
+  const zhandler *handler;
+};
+
+struct zm {
+  zcode pc;
+  zenv *env;
+  zframe *stack;
+  size_t sp, cap;
+  int halted;
+  int exit_code;
+  zv *ret;
+  int64_t prompts;
+  int argc;
+  char **argv;
+};
+
+/* constructors and destructors the runtime relies on; the code generator
+ * interns them first, in this order */
+enum {
+  ZY_CTOR_True,
+  ZY_CTOR_False,
+  ZY_CTOR_None,
+  ZY_CTOR_Some,
+  ZY_CTOR_Pair,
+  ZY_CTOR_Nil,
+  ZY_CTOR_Cons,
+  ZY_CTOR_Unit,
+  ZY_CTOR_Ok,
+  ZY_CTOR_Err
+};
+enum { ZY_DTOR_arg };
+
+/* Unicode tables, generated from the standard library by the code generator;
+   see `codegen::unicode` */
+typedef struct {
+  uint32_t lo, hi;
+  int32_t delta;
+  uint32_t stride;
+} zcase_run;
+
+typedef struct {
+  uint32_t c;
+  uint32_t to[3];
+} zcase_special;
+
+typedef struct {
+  size_t nruns;
+  const zcase_run *runs;
+  size_t nspecials;
+  const zcase_special *specials;
+} zcase_table;
+
+typedef struct {
+  size_t n;
+  const uint32_t (*ranges)[2];
+} zranges;
+
+static const zcase_table zy_upper, zy_lower;
+static const zranges zy_whitespace;
+
+static void zy_panic(const char *msg) {
+  fprintf(stderr, "zydeco runtime panicked: %s\n", msg);
+  exit(101);
+}
+
+static void zy_halt(zm *m, int exit_code) {
+  m->halted = 1;
+  m->exit_code = exit_code;
+}
+
+/* reports an error in the program the way the interpreter's `panic` does,
+   halting with exit code 1 */
+static void zy_fail(zm *m, const char *fmt, ...) {
+  va_list ap;
+  va_start(ap, fmt);
+  printf("Program panicked with message: ");
+  vprintf(fmt, ap);
+  printf("\n");
+  va_end(ap);
+  zy_halt(m, 1);
+}
+
+static void *zy_alloc(size_t size) {
+  void *p = malloc(size);
+  if (!p) zy_panic("out of memory");
+  return p;
+}
+
+/* values */
+
+static zv *zy_int(int64_t i) {
+  zv *v = zy_alloc(sizeof(zv));
+  v->tag = ZY_INT;
+  v->u.i = i;
+  return v;
+}
+
+static zv *zy_char(uint32_t c) {
+  zv *v = zy_alloc(sizeof(zv));
+  v->tag = ZY_CHAR;
+  v->u.c = c;
+  return v;
+}
+
+static zv *zy_str(const char *ptr, size_t len) {
+  zv *v = zy_alloc(sizeof(zv));
+  v->tag = ZY_STR;
+  v->u.s.ptr = ptr;
+  v->u.s.len = len;
+  return v;
+}
+
+static zv *zy_str_copy(const char *ptr, size_t len) {
+  char *buf = zy_alloc(len + 1);
+  memcpy(buf, ptr, len);
+  buf[len] = '\0';
+  return zy_str(buf, len);
+}
+
+static zv *zy_ctor(int ctor, int n, zv **args) {
+  zv *v = zy_alloc(sizeof(zv));
+  v->tag = ZY_CTOR;
+  v->u.ctor.ctor = ctor;
+  v->u.ctor.n = n;
+  v->u.ctor.args = zy_alloc(sizeof(zv *) * (n ? n : 1));
+  memcpy(v->u.ctor.args, args, sizeof(zv *) * n);
+  return v;
+}
+
+static zv *zy_thunk(zcode code, zenv *env) {
+  zv *v = zy_alloc(sizeof(zv));
+  v->tag = ZY_THUNK;
+  v->u.thunk.code = code;
+  v->u.thunk.env = env;
+  return v;
+}
+
+static zv *zy_bool(int b) { return zy_ctor(b ? ZY_CTOR_True : ZY_CTOR_False, 0, NULL); }
+
+static zv *zy_pair(zv *a, zv *b) {
+  zv *args[2] = {a, b};
+  return zy_ctor(ZY_CTOR_Pair, 2, args);
+}
+
+static zv *zy_some(zv *a) { return zy_ctor(ZY_CTOR_Some, 1, &a); }
+
+static zv *zy_none(void) { return zy_ctor(ZY_CTOR_None, 0, NULL); }
+
+static zv *zy_unit(void) { return zy_ctor(ZY_CTOR_Unit, 0, NULL); }
+
+static zv *zy_nil(void) { return zy_ctor(ZY_CTOR_Nil, 0, NULL); }
+
+static zv *zy_cons_list(zv *head, zv *tail) {
+  zv *args[2] = {head, tail};
+  return zy_ctor(ZY_CTOR_Cons, 2, args);
+}
+
+/* the list of the `n` values at `items` */
+static zv *zy_list(zv **items, size_t n) {
+  zv *list = zy_nil();
+  while (n > 0) list = zy_cons_list(items[--n], list);
+  return list;
+}
+
+static zv *zy_prompt(int64_t id) {
+  zv *v = zy_alloc(sizeof(zv));
+  v->tag = ZY_PROMPT;
+  v->u.i = id;
+  return v;
+}
+
+static int64_t zy_get_int(zv *v) {
+  if (v->tag != ZY_INT) zy_panic("expected an integer");
+  return v->u.i;
+}
+
+static uint32_t zy_get_char(zv *v) {
+  if (v->tag != ZY_CHAR) zy_panic("expected a character");
+  return v->u.c;
+}
+
+static zv *zy_get_str(zv *v) {
+  if (v->tag != ZY_STR) zy_panic("expected a string");
+  return v;
+}
+
+/* environments */
+
+static zenv *zy_cons(zv *v, zenv *next) {
+  zenv *e = zy_alloc(sizeof(zenv));
+  e->v = v;
+  e->next = next;
+  return e;
+}
+
+static zv *zy_lookup(zenv *env, int idx) {
+  while (idx--) env = env->next;
+  return env->v;
+}
+
+/* the machine */
+
+static void zy_push(zm *m, zframe f) {
+  if (m->sp == m->cap) {
+    m->cap = m->cap ? m->cap * 2 : 64;
+    m->stack = realloc(m->stack, sizeof(zframe) * m->cap);
+    if (!m->stack) zy_panic("out of memory");
+  }
+  m->stack[m->sp++] = f;
+}
+
+static void zy_push_kont(zm *m, zcode code) {
+  zframe f = {ZY_KONT, code, m->env, 0, 0, NULL};
+  zy_push(m, f);
+}
+
+static zv **zy_args(int n) { return zy_alloc(sizeof(zv *) * (n ? n : 1)); }
+
+static void zy_push_dtor(zm *m, int dtor, int n, zv **args) {
+  zframe f = {ZY_DTOR, NULL, NULL, dtor, n, args};
+  zy_push(m, f);
+}
+
+static void zy_push_handler(zm *m, const zhandler *handler) {
+  zframe f = {ZY_HANDLER, NULL, m->env, 0, 0, NULL, 0, handler};
+  zy_push(m, f);
+}
+
+/* pops the frame on top of the stack into `f`, discarding prompts on the way;
+   fails on an empty stack */
+static int zy_pop(zm *m, zframe *f) {
+  while (m->sp > 0) {
+    *f = m->stack[--m->sp];
+    if (f->kind != ZY_MARK) return 1;
+  }
+  return 0;
+}
+
+static zframe zy_pop_dtor(zm *m) {
+  zframe f;
+  if (!zy_pop(m, &f) || f.kind != ZY_DTOR) zy_panic("Comatch on non-Dtor");
+  return f;
+}
+
+/* returns `v` to the nearest continuation or handler return clause; handlers
+   without one pass it on */
+static void zy_return(zm *m, zv *v) {
+  zframe f;
+  while (zy_pop(m, &f)) {
+    if (f.kind == ZY_KONT) {
+      m->env = zy_cons(v, f.env);
+      m->pc = f.code;
+      return;
+    }
+    if (f.kind != ZY_HANDLER) zy_panic("Kont not at stacktop");
+    if (f.handler->ret) {
+      m->env = zy_cons(v, f.env);
+      m->pc = f.handler->ret;
+      return;
+    }
+  }
+  m->halted = 1;
+  m->ret = v;
+}
+
+static void zy_force(zm *m, zv *v) {
+  if (v->tag != ZY_THUNK) zy_panic("Force on non-thunk");
+  m->env = v->u.thunk.env;
+  m->pc = v->u.thunk.code;
+}
+
+/* pops the single argument of the `.arg` frame on top of the stack */
+static zv *zy_arg(zm *m) {
+  zframe f = zy_pop_dtor(m);
+  if (f.n < 1) zy_panic("empty arg");
+  return f.args[0];
+}
+
+/* applies the thunk `k` to `v`, i.e. `! k v` */
+static void zy_apply(zm *m, zv *k, zv *v) {
+  zv **args = zy_args(1);
+  args[0] = v;
+  zy_push_dtor(m, ZY_DTOR_arg, 1, args);
+  zy_force(m, k);
+}
+
+/* delimited continuations */
+
+/* cuts the frames from `from` up off the stack into a captured continuation */
+static zv *zy_subcont(zm *m, size_t from) {
+  zv *v = zy_alloc(sizeof(zv));
+  v->tag = ZY_SUBCONT;
+  v->u.subcont.n = m->sp - from;
+  v->u.subcont.frames = zy_alloc(sizeof(zframe) * (m->sp > from ? m->sp - from : 1));
+  memcpy(v->u.subcont.frames, m->stack + from, sizeof(zframe) * (m->sp - from));
+  m->sp = from;
+  return v;
+}
+
+static void zy_push_subcont(zm *m, zv *k) {
+  for (size_t i = 0; i < k->u.subcont.n; i++) zy_push(m, k->u.subcont.frames[i]);
+}
+
+/* the `kont` of a handler arm: reinstates the captured frames and returns the
+   answer to the operation */
+static void zy_resume(zm *m) {
+  zframe f;
+  if (!zy_pop(m, &f) || f.kind != ZY_DTOR || f.n < 1) {
+    zy_fail(m, "resume is missing an argument");
+    return;
+  }
+  zv *v = f.args[0];
+  zy_push_subcont(m, zy_lookup(m->env, 0));
+  zy_return(m, v);
+}
+
+/* runs the arm for `op` of the nearest handler with one, binding its `n`
+   arguments and then its `kont` */
+static void zy_perform(zm *m, int op, const char *name, int n, zv **args) {
+  for (size_t i = m->sp; i-- > 0;) {
+    const zhandler *h = m->stack[i].handler;
+    if (m->stack[i].kind != ZY_HANDLER) continue;
+    for (int j = 0; j < h->n; j++) {
+      if (h->arms[j].op != op) continue;
+      zenv *env = m->stack[i].env;
+      /* the captured frames include the handler itself, so that it keeps
+         handling the computation once resumed */
+      zv *k = zy_subcont(m, i);
+      for (int a = 0; a < n; a++) env = zy_cons(args[a], env);
+      m->env = zy_cons(zy_thunk(zy_resume, zy_cons(k, NULL)), env);
+      m->pc = h->arms[j].code;
+      return;
+    }
+  }
+  /* only reachable by code the type checker hasn't seen */
+  zy_fail(m, "unhandled effect operation %s", name);
+}
+
+/* strings */
+
+static size_t zy_utf8_encode(uint32_t c, char *buf) {
+  if (c < 0x80) {
+    buf[0] = (char)c;
+    return 1;
+  } else if (c < 0x800) {
+    buf[0] = (char)(0xC0 | (c >> 6));
+    buf[1] = (char)(0x80 | (c & 0x3F));
+    return 2;
+  } else if (c < 0x10000) {
+    buf[0] = (char)(0xE0 | (c >> 12));
+    buf[1] = (char)(0x80 | ((c >> 6) & 0x3F));
+    buf[2] = (char)(0x80 | (c & 0x3F));
+    return 3;
+  } else {
+    buf[0] = (char)(0xF0 | (c >> 18));
+    buf[1] = (char)(0x80 | ((c >> 12) & 0x3F));
+    buf[2] = (char)(0x80 | ((c >> 6) & 0x3F));
+    buf[3] = (char)(0x80 | (c & 0x3F));
+    return 4;
+  }
+}
+
+/* decodes the code point starting at `s[*i]` and advances `*i` past it */
+static uint32_t zy_utf8_next(const char *s, size_t *i) {
+  const unsigned char *u = (const unsigned char *)s + *i;
+  uint32_t c;
+  size_t n;
+  if (u[0] < 0x80) {
+    c = u[0];
+    n = 1;
+  } else if (u[0] < 0xE0) {
+    c = ((uint32_t)(u[0] & 0x1F) << 6) | (u[1] & 0x3F);
+    n = 2;
+  } else if (u[0] < 0xF0) {
+    c = ((uint32_t)(u[0] & 0x0F) << 12) | ((uint32_t)(u[1] & 0x3F) << 6) | (u[2] & 0x3F);
+    n = 3;
+  } else {
+    c = ((uint32_t)(u[0] & 0x07) << 18) | ((uint32_t)(u[1] & 0x3F) << 12) |
+        ((uint32_t)(u[2] & 0x3F) << 6) | (u[3] & 0x3F);
+    n = 4;
+  }
+  *i += n;
+  return c;
+}
+
+/* the byte offset of the `n`th code point of `s`, its length for the one past
+   the end, or -1 if `n` is out of range */
+static int64_t zy_char_offset(zv *s, int64_t n) {
+  size_t i = 0;
+  for (int64_t k = 0; k < n; k++) {
+    if (i >= s->u.s.len) return -1;
+    zy_utf8_next(s->u.s.ptr, &i);
+  }
+  return n < 0 ? -1 : (int64_t)i;
+}
+
+static zv *zy_split_pair(zv *s, size_t at, size_t skip) {
+  zv *a = zy_str_copy(s->u.s.ptr, at);
+  zv *b = zy_str_copy(s->u.s.ptr + at + skip, s->u.s.len - at - skip);
+  return zy_pair(a, b);
+}
+
+/* the byte offset of the first occurrence of `p` in `s` at or after `from`,
+   or -1 if there is none */
+static int64_t zy_find(zv *s, zv *p, size_t from) {
+  for (size_t i = from; i + p->u.s.len <= s->u.s.len; i++) {
+    if (memcmp(s->u.s.ptr + i, p->u.s.ptr, p->u.s.len) == 0) return (int64_t)i;
+  }
+  return -1;
+}
+
+/* a growable byte buffer for building strings */
+typedef struct {
+  char *ptr;
+  size_t len, cap;
+} zbuf;
+
+static void zy_buf_push(zbuf *b, const char *ptr, size_t len) {
+  if (b->len + len + 1 > b->cap) {
+    while (b->len + len + 1 > b->cap) b->cap = b->cap ? b->cap * 2 : 64;
+    b->ptr = realloc(b->ptr, b->cap);
+    if (!b->ptr) zy_panic("out of memory");
+  }
+  memcpy(b->ptr + b->len, ptr, len);
+  b->len += len;
+  b->ptr[b->len] = '\0';
+}
+
+static void zy_buf_push_char(zbuf *b, uint32_t c) {
+  char buf[4];
+  zy_buf_push(b, buf, zy_utf8_encode(c, buf));
+}
+
+static zv *zy_buf_str(zbuf *b) { return b->ptr ? zy_str(b->ptr, b->len) : zy_str("", 0); }
+
+static int zy_is_whitespace(uint32_t c) {
+  for (size_t i = 0; i < zy_whitespace.n; i++) {
+    if (zy_whitespace.ranges[i][0] <= c && c <= zy_whitespace.ranges[i][1]) return 1;
+  }
+  return 0;
+}
+
+/* writes the mapping of `c` under the case table `t` to `out`, returning the
+   number of code points it maps to */
+static size_t zy_case(const zcase_table *t, uint32_t c, uint32_t *out) {
+  size_t lo = 0, hi = t->nspecials;
+  while (lo < hi) {
+    size_t mid = (lo + hi) / 2;
+    if (t->specials[mid].c < c) {
+      lo = mid + 1;
+    } else if (t->specials[mid].c > c) {
+      hi = mid;
+    } else {
+      size_t n = 0;
+      while (n < 3 && t->specials[mid].to[n]) out[n] = t->specials[mid].to[n], n++;
+      return n;
+    }
+  }
+  lo = 0, hi = t->nruns;
+  while (lo < hi) {
+    size_t mid = (lo + hi) / 2;
+    const zcase_run *r = &t->runs[mid];
+    if (r->hi < c) {
+      lo = mid + 1;
+    } else if (r->lo > c) {
+      hi = mid;
+    } else {
+      out[0] = (c - r->lo) % r->stride ? c : (uint32_t)((int32_t)c + r->delta);
+      return 1;
+    }
+  }
+  out[0] = c;
+  return 1;
+}
+
+static zv *zy_map_case(zv *s, const zcase_table *t) {
+  zbuf b = {0};
+  uint32_t out[3];
+  for (size_t i = 0; i < s->u.s.len;) {
+    size_t n = zy_case(t, zy_utf8_next(s->u.s.ptr, &i), out);
+    for (size_t k = 0; k < n; k++) zy_buf_push_char(&b, out[k]);
+  }
+  return zy_buf_str(&b);
+}
+
+/* builtins; each pops its arguments off the stack like `SynComp::Prim` */
+
+#define ZY_ARITH(name, op)                                                                         \
+  static void zy_prim_##name(zm *m) {                                                              \
+    int64_t a = zy_get_int(zy_arg(m));                                                             \
+    int64_t b = zy_get_int(zy_arg(m));                                                             \
+    zy_return(m, zy_int(op));                                                                      \
+  }
+
+ZY_ARITH(add, (int64_t)((uint64_t)a + (uint64_t)b))
+ZY_ARITH(sub, (int64_t)((uint64_t)a - (uint64_t)b))
+ZY_ARITH(mul, (int64_t)((uint64_t)a * (uint64_t)b))
+
+static void zy_prim_div(zm *m) {
+  int64_t a = zy_get_int(zy_arg(m));
+  int64_t b = zy_get_int(zy_arg(m));
+  if (b == 0) zy_panic("attempt to divide by zero");
+  zy_return(m, zy_int(a / b));
+}
+
+static void zy_prim_mod(zm *m) {
+  int64_t a = zy_get_int(zy_arg(m));
+  int64_t b = zy_get_int(zy_arg(m));
+  if (b == 0) zy_panic("attempt to calculate the remainder with a divisor of zero");
+  zy_return(m, zy_int(a % b));
+}
+
+#define ZY_INTCOMP(name, op)                                                                       \
+  static void zy_prim_##name(zm *m) {                                                              \
+    int64_t a = zy_get_int(zy_arg(m));                                                             \
+    int64_t b = zy_get_int(zy_arg(m));                                                             \
+    zy_return(m, zy_bool(a op b));                                                                 \
+  }
+
+ZY_INTCOMP(int_eq, ==)
+ZY_INTCOMP(int_lt, <)
+ZY_INTCOMP(int_gt, >)
+
+static void zy_prim_str_length(zm *m) {
+  zv *s = zy_get_str(zy_arg(m));
+  int64_t n = 0;
+  for (size_t i = 0; i < s->u.s.len; n++) zy_utf8_next(s->u.s.ptr, &i);
+  zy_return(m, zy_int(n));
+}
+
+static void zy_prim_str_append(zm *m) {
+  zv *a = zy_get_str(zy_arg(m));
+  zv *b = zy_get_str(zy_arg(m));
+  char *buf = zy_alloc(a->u.s.len + b->u.s.len + 1);
+  memcpy(buf, a->u.s.ptr, a->u.s.len);
+  memcpy(buf + a->u.s.len, b->u.s.ptr, b->u.s.len);
+  buf[a->u.s.len + b->u.s.len] = '\0';
+  zy_return(m, zy_str(buf, a->u.s.len + b->u.s.len));
+}
+
+static void zy_prim_str_split_once(zm *m) {
+  zv *s = zy_get_str(zy_arg(m));
+  char pat[4];
+  size_t plen = zy_utf8_encode(zy_get_char(zy_arg(m)), pat);
+  for (size_t i = 0; i + plen <= s->u.s.len; i++) {
+    if (memcmp(s->u.s.ptr + i, pat, plen) == 0) {
+      zy_return(m, zy_some(zy_split_pair(s, i, plen)));
+      return;
+    }
+  }
+  zy_return(m, zy_none());
+}
+
+static void zy_prim_str_split_n(zm *m) {
+  zv *s = zy_get_str(zy_arg(m));
+  int64_t at = zy_char_offset(s, zy_get_int(zy_arg(m)));
+  if (at < 0) {
+    zy_return(m, zy_none());
+    return;
+  }
+  zy_return(m, zy_some(zy_split_pair(s, (size_t)at, 0)));
+}
+
+static void zy_prim_str_substring(zm *m) {
+  zv *s = zy_get_str(zy_arg(m));
+  int64_t i = zy_get_int(zy_arg(m));
+  int64_t j = zy_get_int(zy_arg(m));
+  int64_t start = zy_char_offset(s, i < 0 ? 0 : i);
+  int64_t end = zy_char_offset(s, j < 0 ? 0 : j);
+  if (start < 0) start = (int64_t)s->u.s.len;
+  if (end < 0) end = (int64_t)s->u.s.len;
+  if (end < start) end = start;
+  zy_return(m, zy_str_copy(s->u.s.ptr + start, (size_t)(end - start)));
+}
+
+static void zy_prim_str_eq(zm *m) {
+  zv *a = zy_get_str(zy_arg(m));
+  zv *b = zy_get_str(zy_arg(m));
+  zy_return(m, zy_bool(a->u.s.len == b->u.s.len && memcmp(a->u.s.ptr, b->u.s.ptr, a->u.s.len) == 0));
+}
+
+static void zy_prim_str_index(zm *m) {
+  zv *s = zy_get_str(zy_arg(m));
+  int64_t n = zy_get_int(zy_arg(m));
+  size_t i = 0;
+  for (int64_t k = 0; i < s->u.s.len; k++) {
+    uint32_t c = zy_utf8_next(s->u.s.ptr, &i);
+    if (k == n) {
+      zy_return(m, zy_some(zy_char(c)));
+      return;
+    }
+  }
+  zy_return(m, zy_none());
+}
+
+static void zy_prim_int_to_str(zm *m) {
+  char buf[32];
+  int len = snprintf(buf, sizeof(buf), "%lld", (long long)zy_get_int(zy_arg(m)));
+  zy_return(m, zy_str_copy(buf, (size_t)len));
+}
+
+static void zy_prim_char_to_str(zm *m) {
+  char buf[4];
+  size_t len = zy_utf8_encode(zy_get_char(zy_arg(m)), buf);
+  zy_return(m, zy_str_copy(buf, len));
+}
+
+static void zy_prim_str_find(zm *m) {
+  zv *s = zy_get_str(zy_arg(m));
+  zv *p = zy_get_str(zy_arg(m));
+  int64_t at = zy_find(s, p, 0);
+  if (at < 0) {
+    zy_return(m, zy_none());
+    return;
+  }
+  int64_t n = 0;
+  for (size_t i = 0; i < (size_t)at; n++) zy_utf8_next(s->u.s.ptr, &i);
+  zy_return(m, zy_some(zy_int(n)));
+}
+
+static void zy_prim_str_starts_with(zm *m) {
+  zv *s = zy_get_str(zy_arg(m));
+  zv *p = zy_get_str(zy_arg(m));
+  zy_return(m, zy_bool(p->u.s.len <= s->u.s.len && memcmp(s->u.s.ptr, p->u.s.ptr, p->u.s.len) == 0));
+}
+
+static void zy_prim_str_ends_with(zm *m) {
+  zv *s = zy_get_str(zy_arg(m));
+  zv *p = zy_get_str(zy_arg(m));
+  size_t at = s->u.s.len - p->u.s.len;
+  zy_return(m, zy_bool(p->u.s.len <= s->u.s.len && memcmp(s->u.s.ptr + at, p->u.s.ptr, p->u.s.len) == 0));
+}
+
+/* compares the bytes of the strings, as `str`'s `Ord` does */
+static void zy_prim_str_lt(zm *m) {
+  zv *a = zy_get_str(zy_arg(m));
+  zv *b = zy_get_str(zy_arg(m));
+  int c = memcmp(a->u.s.ptr, b->u.s.ptr, a->u.s.len < b->u.s.len ? a->u.s.len : b->u.s.len);
+  zy_return(m, zy_bool(c < 0 || (c == 0 && a->u.s.len < b->u.s.len)));
+}
+
+/* like `str::replace`, an empty pattern matches around every code point */
+static void zy_prim_str_replace(zm *m) {
+  zv *s = zy_get_str(zy_arg(m));
+  zv *from = zy_get_str(zy_arg(m));
+  zv *to = zy_get_str(zy_arg(m));
+  zbuf b = {0};
+  if (from->u.s.len == 0) {
+    zy_buf_push(&b, to->u.s.ptr, to->u.s.len);
+    for (size_t i = 0; i < s->u.s.len;) {
+      size_t start = i;
+      zy_utf8_next(s->u.s.ptr, &i);
+      zy_buf_push(&b, s->u.s.ptr + start, i - start);
+      zy_buf_push(&b, to->u.s.ptr, to->u.s.len);
+    }
+  } else {
+    size_t i = 0;
+    for (int64_t at; (at = zy_find(s, from, i)) >= 0; i = (size_t)at + from->u.s.len) {
+      zy_buf_push(&b, s->u.s.ptr + i, (size_t)at - i);
+      zy_buf_push(&b, to->u.s.ptr, to->u.s.len);
+    }
+    zy_buf_push(&b, s->u.s.ptr + i, s->u.s.len - i);
+  }
+  zy_return(m, zy_buf_str(&b));
+}
+
+static void zy_prim_str_trim(zm *m) {
+  zv *s = zy_get_str(zy_arg(m));
+  size_t start = s->u.s.len, end = 0;
+  for (size_t i = 0; i < s->u.s.len;) {
+    size_t at = i;
+    if (!zy_is_whitespace(zy_utf8_next(s->u.s.ptr, &i))) {
+      if (start > at) start = at;
+      end = i;
+    }
+  }
+  if (end < start) end = start;
+  zy_return(m, zy_str_copy(s->u.s.ptr + start, end - start));
+}
+
+static void zy_prim_str_to_upper(zm *m) { zy_return(m, zy_map_case(zy_get_str(zy_arg(m)), &zy_upper)); }
+
+static void zy_prim_str_to_lower(zm *m) { zy_return(m, zy_map_case(zy_get_str(zy_arg(m)), &zy_lower)); }
+
+/* like `str::split`, except that an empty separator splits out every code
+   point */
+static void zy_prim_str_split(zm *m) {
+  zv *s = zy_get_str(zy_arg(m));
+  zv *sep = zy_get_str(zy_arg(m));
+  zv **parts = zy_args((int)s->u.s.len + 1);
+  size_t n = 0, i = 0;
+  if (sep->u.s.len == 0) {
+    while (i < s->u.s.len) {
+      size_t start = i;
+      zy_utf8_next(s->u.s.ptr, &i);
+      parts[n++] = zy_str_copy(s->u.s.ptr + start, i - start);
+    }
+  } else {
+    for (int64_t at; (at = zy_find(s, sep, i)) >= 0; i = (size_t)at + sep->u.s.len) {
+      parts[n++] = zy_str_copy(s->u.s.ptr + i, (size_t)at - i);
+    }
+    parts[n++] = zy_str_copy(s->u.s.ptr + i, s->u.s.len - i);
+  }
+  zy_return(m, zy_list(parts, n));
+}
+
+static void zy_prim_str_join(zm *m) {
+  zv *parts = zy_arg(m);
+  zv *sep = zy_get_str(zy_arg(m));
+  zbuf b = {0};
+  for (int first = 1; parts->u.ctor.ctor == ZY_CTOR_Cons; parts = parts->u.ctor.args[1], first = 0) {
+    zv *part = zy_get_str(parts->u.ctor.args[0]);
+    if (!first) zy_buf_push(&b, sep->u.s.ptr, sep->u.s.len);
+    zy_buf_push(&b, part->u.s.ptr, part->u.s.len);
+  }
+  zy_return(m, zy_buf_str(&b));
+}
+
+static void zy_prim_str_to_list(zm *m) {
+  zv *s = zy_get_str(zy_arg(m));
+  zv **chars = zy_args((int)s->u.s.len);
+  size_t n = 0;
+  for (size_t i = 0; i < s->u.s.len;) chars[n++] = zy_char(zy_utf8_next(s->u.s.ptr, &i));
+  zy_return(m, zy_list(chars, n));
+}
+
+static void zy_prim_list_to_str(zm *m) {
+  zbuf b = {0};
+  for (zv *l = zy_arg(m); l->u.ctor.ctor == ZY_CTOR_Cons; l = l->u.ctor.args[1]) {
+    zy_buf_push_char(&b, zy_get_char(l->u.ctor.args[0]));
+  }
+  zy_return(m, zy_buf_str(&b));
+}
+
+static void zy_prim_char_to_int(zm *m) {
+  zy_return(m, zy_int(zy_get_char(zy_arg(m))));
+}
+
+static void zy_prim_int_to_char(zm *m) {
+  int64_t n = zy_get_int(zy_arg(m));
+  if (n < 0 || n > 0x10FFFF || (n >= 0xD800 && n <= 0xDFFF)) {
+    zy_return(m, zy_none());
+    return;
+  }
+  zy_return(m, zy_some(zy_char((uint32_t)n)));
+}
+
+static void zy_prim_str_to_int(zm *m) {
+  zv *s = zy_get_str(zy_arg(m));
+  const char *p = s->u.s.ptr;
+  size_t len = s->u.s.len, i = 0;
+  int neg = 0;
+  uint64_t acc = 0;
+  if (len > 0 && (p[0] == '+' || p[0] == '-')) {
+    neg = p[0] == '-';
+    i = 1;
+  }
+  if (i == len) {
+    zy_return(m, zy_none());
+    return;
+  }
+  for (; i < len; i++) {
+    uint64_t d = (uint64_t)(p[i] - '0');
+    /* invalid digits, or overflows past INT64_MAX (INT64_MIN when negative) */
+    if (p[i] < '0' || p[i] > '9' || acc > ((uint64_t)INT64_MAX + neg - d) / 10) {
+      zy_return(m, zy_none());
+      return;
+    }
+    acc = acc * 10 + d;
+  }
+  zy_return(m, zy_some(zy_int(neg ? (int64_t)(0 - acc) : (int64_t)acc)));
+}
+
+static void zy_prim_write_str(zm *m) {
+  zv *s = zy_get_str(zy_arg(m));
+  zv *k = zy_arg(m);
+  fwrite(s->u.s.ptr, 1, s->u.s.len, stdout);
+  zy_force(m, k);
+}
+
+static zv *zy_read(int till_eof) {
+  size_t len = 0, cap = 128;
+  char *buf = zy_alloc(cap);
+  int c;
+  while ((c = getchar()) != EOF) {
+    if (len + 1 >= cap) {
+      cap *= 2;
+      buf = realloc(buf, cap);
+      if (!buf) zy_panic("out of memory");
+    }
+    buf[len++] = (char)c;
+    if (!till_eof && c == '\n') break;
+  }
+  buf[len] = '\0';
+  return zy_str(buf, len);
+}
+
+static void zy_prim_read_line(zm *m) {
+  zv *k = zy_arg(m);
+  fflush(stdout);
+  zv *line = zy_read(0);
+  /* drop the last character, usually the newline */
+  while (line->u.s.len > 0 && (((unsigned char)line->u.s.ptr[line->u.s.len - 1]) & 0xC0) == 0x80)
+    line->u.s.len--;
+  if (line->u.s.len > 0) line->u.s.len--;
+  zy_apply(m, k, line);
+}
+
+static void zy_prim_read_till_eof(zm *m) {
+  zv *k = zy_arg(m);
+  fflush(stdout);
+  zy_apply(m, k, zy_read(1));
+}
+
+static void zy_prim_arg_list(zm *m) {
+  zv *k = zy_arg(m);
+  zv *list = zy_ctor(ZY_CTOR_Nil, 0, NULL);
+  for (int i = m->argc - 1; i >= 0; i--) {
+    zv *args[2] = {zy_str(m->argv[i], strlen(m->argv[i])), list};
+    list = zy_ctor(ZY_CTOR_Cons, 2, args);
+  }
+  zy_apply(m, k, list);
+}
+
+static void zy_prim_exit(zm *m) { zy_halt(m, (int)zy_get_int(zy_arg(m))); }
+
+static void zy_prim_new_prompt(zm *m) { zy_return(m, zy_prompt(m->prompts++)); }
+
+static void zy_prim_push_prompt(zm *m) {
+  zv *p = zy_arg(m);
+  zv *e = zy_arg(m);
+  zframe f = {ZY_MARK, NULL, NULL, 0, 0, NULL, p->u.i, NULL};
+  zy_push(m, f);
+  zy_force(m, e);
+}
+
+/* captures the frames above the prompt, which is dropped, and passes them to
+   the thunk */
+static void zy_prim_take_subcont(zm *m) {
+  zv *p = zy_arg(m);
+  zv *f = zy_arg(m);
+  for (size_t i = m->sp; i-- > 0;) {
+    if (m->stack[i].kind == ZY_MARK && m->stack[i].prompt == p->u.i) {
+      zv *k = zy_subcont(m, i + 1);
+      m->sp = i;
+      zy_apply(m, f, k);
+      return;
+    }
+  }
+  zy_fail(m, "prompt not found on the stack");
+}
+
+static void zy_prim_push_subcont(zm *m) {
+  zv *k = zy_arg(m);
+  zv *e = zy_arg(m);
+  zy_push_subcont(m, k);
+  zy_force(m, e);
+}
+
+static void zy_prim_ref_new(zm *m) {
+  zv *v = zy_alloc(sizeof(zv));
+  v->tag = ZY_REF;
+  v->u.ref = zy_arg(m);
+  zy_return(m, v);
+}
+
+static void zy_prim_ref_get(zm *m) { zy_return(m, zy_arg(m)->u.ref); }
+
+static void zy_prim_ref_set(zm *m) {
+  zv *r = zy_arg(m);
+  r->u.ref = zy_arg(m);
+  zy_return(m, zy_unit());
+}
+
+static void zy_prim_array_new(zm *m) {
+  int64_t n = zy_get_int(zy_arg(m));
+  zv *init = zy_arg(m);
+  if (n < 0) {
+    zy_fail(m, "negative array length %lld", (long long)n);
+    return;
+  }
+  zv **elems = (uint64_t)n <= SIZE_MAX / sizeof(zv *) ? malloc(sizeof(zv *) * (n ? n : 1)) : NULL;
+  if (!elems) {
+    zy_fail(m, "cannot allocate an array of length %lld", (long long)n);
+    return;
+  }
+  for (int64_t i = 0; i < n; i++) elems[i] = init;
+  zv *v = zy_alloc(sizeof(zv));
+  v->tag = ZY_ARRAY;
+  v->u.array.len = n;
+  v->u.array.elems = elems;
+  zy_return(m, v);
+}
+
+/* checks an index into the array `a`, failing if it is out of bounds */
+static int zy_array_index(zm *m, zv *a, int64_t i) {
+  if (i >= 0 && i < a->u.array.len) return 1;
+  zy_fail(m, "array index %lld out of bounds for length %lld", (long long)i, (long long)a->u.array.len);
+  return 0;
+}
+
+static void zy_prim_array_get(zm *m) {
+  zv *a = zy_arg(m);
+  int64_t i = zy_get_int(zy_arg(m));
+  if (zy_array_index(m, a, i)) zy_return(m, a->u.array.elems[i]);
+}
+
+static void zy_prim_array_set(zm *m) {
+  zv *a = zy_arg(m);
+  int64_t i = zy_get_int(zy_arg(m));
+  zv *v = zy_arg(m);
+  if (!zy_array_index(m, a, i)) return;
+  a->u.array.elems[i] = v;
+  zy_return(m, zy_unit());
+}
+
+static void zy_prim_array_length(zm *m) { zy_return(m, zy_int(zy_arg(m)->u.array.len)); }
+
+/* the host, see `dynamics::host` */
+
+static struct {
+  const char *root;
+  int fake_clock;
+  int64_t start;
+  int64_t elapsed;
+  uint64_t rng;
+} zy_host;
+
+static int64_t zy_clock_ms(clockid_t id) {
+  struct timespec ts;
+  clock_gettime(id, &ts);
+  return (int64_t)ts.tv_sec * 1000 + ts.tv_nsec / 1000000;
+}
+
+/* SplitMix64, as `dynamics::host::Rng` */
+static uint64_t zy_random(void) {
+  uint64_t z = (zy_host.rng += 0x9e3779b97f4a7c15ULL);
+  z = (z ^ (z >> 30)) * 0xbf58476d1ce4e5b9ULL;
+  z = (z ^ (z >> 27)) * 0x94d049bb133111ebULL;
+  return z ^ (z >> 31);
+}
+
+/* consumes the flags of the host at the front of the arguments, returning how
+   many there were */
+static int zy_host_init(int argc, char **argv) {
+  int i = 0, seeded = 0;
+  for (; i < argc && strncmp(argv[i], "--zy-", 5) == 0; i++) {
+    const char *flag = argv[i] + 5;
+    if (strncmp(flag, "sandbox=", 8) == 0) {
+      zy_host.root = flag + 8;
+    } else if (strncmp(flag, "seed=", 5) == 0) {
+      zy_host.rng = strtoull(flag + 5, NULL, 10);
+      seeded = 1;
+    } else if (strncmp(flag, "fake-clock=", 11) == 0) {
+      zy_host.fake_clock = 1;
+      zy_host.start = strtoll(flag + 11, NULL, 10);
+    } else {
+      fprintf(stderr, "Error: unknown runtime flag %s\n", argv[i]);
+      exit(1);
+    }
+  }
+  if (!zy_host.fake_clock) zy_host.start = zy_clock_ms(CLOCK_MONOTONIC);
+  if (!seeded) zy_host.rng = (uint64_t)zy_clock_ms(CLOCK_REALTIME);
+  return i;
+}
+
+static void zy_prim_write_err(zm *m) {
+  zv *s = zy_get_str(zy_arg(m));
+  zv *k = zy_arg(m);
+  fflush(stdout);
+  fwrite(s->u.s.ptr, 1, s->u.s.len, stderr);
+  zy_force(m, k);
+}
+
+static void zy_prim_env_var(zm *m) {
+  zv *name = zy_get_str(zy_arg(m));
+  zv *k = zy_arg(m);
+  const char *value = getenv(zy_str_copy(name->u.s.ptr, name->u.s.len)->u.s.ptr);
+  zy_apply(m, k, value ? zy_some(zy_str(value, strlen(value))) : zy_none());
+}
+
+static void zy_prim_clock_wall(zm *m) {
+  zv *k = zy_arg(m);
+  int64_t now = zy_host.fake_clock ? zy_host.start + zy_host.elapsed : zy_clock_ms(CLOCK_REALTIME);
+  zy_apply(m, k, zy_int(now));
+}
+
+static void zy_prim_clock_monotonic(zm *m) {
+  zv *k = zy_arg(m);
+  int64_t now = zy_host.fake_clock ? zy_host.elapsed : zy_clock_ms(CLOCK_MONOTONIC) - zy_host.start;
+  zy_apply(m, k, zy_int(now));
+}
+
+static void zy_prim_sleep(zm *m) {
+  int64_t ms = zy_get_int(zy_arg(m));
+  zv *k = zy_arg(m);
+  if (ms < 0) ms = 0;
+  if (zy_host.fake_clock) {
+    zy_host.elapsed += ms;
+  } else {
+    struct timespec ts = {ms / 1000, (ms % 1000) * 1000000};
+    fflush(stdout);
+    while (nanosleep(&ts, &ts) != 0) {}
+  }
+  zy_force(m, k);
+}
+
+static void zy_prim_random_int(zm *m) {
+  int64_t bound = zy_get_int(zy_arg(m));
+  zv *k = zy_arg(m);
+  if (bound <= 0) {
+    zy_fail(m, "non-positive random bound %lld", (long long)bound);
+    return;
+  }
+  zy_apply(m, k, zy_int((int64_t)(zy_random() % (uint64_t)bound)));
+}
+
+/* the file system, failing with the errors of `std::io::Error` */
+
+static zv *zy_ok(zv *v) { return zy_ctor(ZY_CTOR_Ok, 1, &v); }
+
+static zv *zy_err(zv *e) { return zy_ctor(ZY_CTOR_Err, 1, &e); }
+
+static zv *zy_os_err(void) {
+  zbuf b = {0};
+  const char *msg = strerror(errno);
+  char code[32];
+  int len = snprintf(code, sizeof(code), " (os error %d)", errno);
+  zy_buf_push(&b, msg, strlen(msg));
+  zy_buf_push(&b, code, (size_t)len);
+  return zy_err(zy_buf_str(&b));
+}
+
+static zv *zy_outside(zv *path) {
+  zbuf b = {0};
+  zy_buf_push(&b, path->u.s.ptr, path->u.s.len);
+  zy_buf_push(&b, " is outside of the sandbox", 26);
+  return zy_err(zy_buf_str(&b));
+}
+
+/* the file `path` refers to, confined to the sandbox as `OsHost` does;
+   sets `*err` if it escapes */
+static const char *zy_path(zv *path, zv **err) {
+  const char *p = path->u.s.ptr, *end = p + path->u.s.len;
+  zbuf b = {0};
+  if (!zy_host.root) return zy_str_copy(p, path->u.s.len)->u.s.ptr;
+  size_t root = strlen(zy_host.root);
+  zy_buf_push(&b, zy_host.root, root);
+  while (p < end) {
+    const char *q = memchr(p, '/', (size_t)(end - p));
+    size_t len = q ? (size_t)(q - p) : (size_t)(end - p);
+    if (len == 2 && p[0] == '.' && p[1] == '.') {
+      if (b.len == root) {
+        *err = zy_outside(path);
+        return NULL;
+      }
+      while (b.ptr[--b.len] != '/') {}
+      b.ptr[b.len] = '\0';
+    } else if (len > 0 && !(len == 1 && p[0] == '.')) {
+      zy_buf_push(&b, "/", 1);
+      zy_buf_push(&b, p, len);
+    }
+    p += len + 1;
+  }
+  /* the part of the path that exists, links included, must really be under
+     the root; what's missing is created in there by the operation */
+  char *prefix = strdup(b.ptr), *top = realpath(zy_host.root, NULL), *real = NULL;
+  size_t len = b.len;
+  struct stat st;
+  while (len > root && lstat(prefix, &st) != 0) {
+    while (prefix[--len] != '/') {}
+    prefix[len] = '\0';
+  }
+  if (!top || !(real = realpath(prefix, NULL))) {
+    *err = zy_os_err();
+  } else {
+    size_t n = strlen(top);
+    if (strncmp(real, top, n) != 0 || (n > 1 && real[n] != '/' && real[n] != '\0')) *err = zy_outside(path);
+  }
+  free(prefix);
+  free(top);
+  free(real);
+  return *err ? NULL : b.ptr;
+}
+
+static zv *zy_file_open(const char *path, zv *contents) {
+  (void)contents;
+  int fd = open(path, O_WRONLY | O_CREAT | O_APPEND, 0666);
+  if (fd < 0) return zy_os_err();
+  close(fd);
+  return zy_ok(zy_unit());
+}
+
+static zv *zy_file_read(const char *path, zv *contents) {
+  (void)contents;
+  zbuf b = {0};
+  char chunk[4096];
+  ssize_t n;
+  int fd = open(path, O_RDONLY);
+  if (fd < 0) return zy_os_err();
+  while ((n = read(fd, chunk, sizeof(chunk))) > 0) zy_buf_push(&b, chunk, (size_t)n);
+  if (n < 0) {
+    zv *e = zy_os_err();
+    close(fd);
+    return e;
+  }
+  close(fd);
+  return zy_ok(zy_buf_str(&b));
+}
+
+static zv *zy_file_put(const char *path, zv *contents, int flags) {
+  int fd = open(path, O_WRONLY | O_CREAT | flags, 0666);
+  if (fd < 0) return zy_os_err();
+  for (size_t done = 0; done < contents->u.s.len;) {
+    ssize_t n = write(fd, contents->u.s.ptr + done, contents->u.s.len - done);
+    if (n < 0) {
+      zv *e = zy_os_err();
+      close(fd);
+      return e;
+    }
+    done += (size_t)n;
+  }
+  close(fd);
+  return zy_ok(zy_unit());
+}
+
+static zv *zy_file_write(const char *path, zv *contents) {
+  return zy_file_put(path, contents, O_TRUNC);
+}
+
+static zv *zy_file_append(const char *path, zv *contents) {
+  return zy_file_put(path, contents, O_APPEND);
+}
+
+static zv *zy_file_exists(const char *path, zv *contents) {
+  (void)contents;
+  struct stat st;
+  if (stat(path, &st) == 0) return zy_ok(zy_bool(1));
+  return errno == ENOENT ? zy_ok(zy_bool(0)) : zy_os_err();
+}
+
+static zv *zy_file_remove(const char *path, zv *contents) {
+  (void)contents;
+  return unlink(path) == 0 ? zy_ok(zy_unit()) : zy_os_err();
+}
+
+static int zy_str_cmp(const void *a, const void *b) {
+  return strcmp(*(const char *const *)a, *(const char *const *)b);
+}
+
+static zv *zy_dir_list(const char *path, zv *contents) {
+  (void)contents;
+  DIR *dir = opendir(path);
+  if (!dir) return zy_os_err();
+  size_t n = 0, cap = 16;
+  const char **names = zy_alloc(sizeof(char *) * cap);
+  struct dirent *entry;
+  while ((entry = readdir(dir))) {
+    if (strcmp(entry->d_name, ".") == 0 || strcmp(entry->d_name, "..") == 0) continue;
+    if (n == cap) {
+      cap *= 2;
+      names = realloc(names, sizeof(char *) * cap);
+      if (!names) zy_panic("out of memory");
+    }
+    names[n++] = zy_str_copy(entry->d_name, strlen(entry->d_name))->u.s.ptr;
+  }
+  closedir(dir);
+  qsort(names, n, sizeof(char *), zy_str_cmp);
+  zv **items = zy_args((int)n);
+  for (size_t i = 0; i < n; i++) items[i] = zy_str(names[i], strlen(names[i]));
+  return zy_ok(zy_list(items, n));
+}
+
+/* pops the path, and the contents if `op` takes them, then passes the result
+   of `op` to the continuation */
+static void zy_file_prim(zm *m, int has_contents, zv *(*op)(const char *, zv *)) {
+  zv *path = zy_get_str(zy_arg(m));
+  zv *contents = has_contents ? zy_get_str(zy_arg(m)) : NULL;
+  zv *k = zy_arg(m);
+  zv *err = NULL;
+  const char *p = zy_path(path, &err);
+  zy_apply(m, k, p ? op(p, contents) : err);
+}
+
+static void zy_prim_file_open(zm *m) { zy_file_prim(m, 0, zy_file_open); }
+
+static void zy_prim_file_read(zm *m) { zy_file_prim(m, 0, zy_file_read); }
+
+static void zy_prim_file_write(zm *m) { zy_file_prim(m, 1, zy_file_write); }
+
+static void zy_prim_file_append(zm *m) { zy_file_prim(m, 1, zy_file_append); }
+
+static void zy_prim_file_exists(zm *m) { zy_file_prim(m, 0, zy_file_exists); }
+
+static void zy_prim_file_remove(zm *m) { zy_file_prim(m, 0, zy_file_remove); }
+
+static void zy_prim_dir_list(zm *m) { zy_file_prim(m, 0, zy_dir_list); }
+
+/* entry point, provided by the generated code */
+
+static void zy_init_globals(void);
+static void zy_main(zm *m);
+
+int main(int argc, char **argv) {
+  zm machine = {0};
+  int flags = zy_host_init(argc - 1, argv + 1);
+  machine.argc = argc - 1 - flags;
+  machine.argv = argv + 1 + flags;
+  machine.pc = zy_main;
+  zy_init_globals();
+  while (!machine.halted) machine.pc(&machine);
+  fflush(stdout);
+  if (machine.ret) {
+    fprintf(stderr, "Error: Program did not exit\n");
+    return 1;
+  }
+  return machine.exit_code;
+}
@@ -0,0 +1,74 @@
+//! Unicode tables for the compiled runtimes, generated from the standard
+//! library so that compiled programs agree with the interpreter's builtins.
+
+/// The code points `lo..=hi`, stepping by `stride`, map to themselves plus
+/// `delta`; the code points stepped over map to themselves.
+pub struct CaseRun {
+    pub lo: u32,
+    pub hi: u32,
+    pub delta: i32,
+    pub stride: u32,
+}
+
+/// A case mapping, as the code points mapping to a single other one, in
+/// sorted runs, and those mapping to several, sorted and padded with zeros.
+///
+/// `str::to_lowercase` also maps a word-final `Σ` to `ς`; that depends on
+/// the context and is left out.
+pub struct CaseTable {
+    pub runs: Vec<CaseRun>,
+    pub specials: Vec<(u32, [u32; 3])>,
+}
+
+impl CaseTable {
+    pub fn upper() -> Self {
+        Self::new(|c| c.to_uppercase().collect())
+    }
+    pub fn lower() -> Self {
+        Self::new(|c| c.to_lowercase().collect())
+    }
+    fn new(map: impl Fn(char) -> Vec<char>) -> Self {
+        let mut singles: Vec<(u32, i32)> = Vec::new();
+        let mut specials = Vec::new();
+        for c in (0..=char::MAX as u32).filter_map(char::from_u32) {
+            match map(c).as_slice() {
+                [d] if *d == c => {}
+                [d] => singles.push((c as u32, *d as i32 - c as i32)),
+                to => {
+                    let mut padded = [0; 3];
+                    for (p, d) in padded.iter_mut().zip(to) {
+                        *p = *d as u32;
+                    }
+                    specials.push((c as u32, padded))
+                }
+            }
+        }
+        let mut runs: Vec<CaseRun> = Vec::new();
+        for (c, delta) in singles {
+            if let Some(run) = runs.last_mut() {
+                let step = c - run.hi;
+                let extends = if run.lo == run.hi { step <= 2 } else { step == run.stride };
+                if run.delta == delta && extends {
+                    run.stride = step;
+                    run.hi = c;
+                    continue;
+                }
+            }
+            runs.push(CaseRun { lo: c, hi: c, delta, stride: 1 });
+        }
+        Self { runs, specials }
+    }
+}
+
+/// The ranges of code points `char::is_whitespace` holds for, as `str::trim`
+/// uses it, in order.
+pub fn whitespace() -> Vec<(u32, u32)> {
+    let mut ranges: Vec<(u32, u32)> = Vec::new();
+    for c in (0..=char::MAX as u32).filter_map(char::from_u32).filter(|c| c.is_whitespace()) {
+        match ranges.last_mut() {
+            Some((_, hi)) if *hi + 1 == c as u32 => *hi = c as u32,
+            _ => ranges.push((c as u32, c as u32)),
+        }
+    }
+    ranges
+}
@@ -0,0 +1,334 @@
+//! Compiles a linked program to a standalone WebAssembly module.
+//!
+//! The code generation scheme follows the C backend: every computation that
+//! can be jumped to becomes a code block in the function table, driven by the
+//! CK machine in `wasm/runtime.rs`. The module imports only WASI
+//! (`wasi_snapshot_preview1`) and exports `_start` and `memory`, so it runs
+//! as a command under any WASI runtime.
+
+mod encode;
+mod runtime;
+
+use self::{
+    encode::{BlockType::*, ExportKind, Func, Module, ValType::*},
+    runtime::{Data, *},
+};
+use super::{err::CodegenError, prune::prune};
+use crate::library::syntax::*;
+use std::collections::HashMap;
+
+/// Compile-time view of the environment a block runs in.
+#[derive(Clone, Default)]
+struct Scope {
+    locals: Vec<TermV>,
+}
+
+impl Scope {
+    fn bind(&self, var: &TermV) -> Self {
+        let mut locals = self.locals.clone();
+        locals.push(var.clone());
+        Self { locals }
+    }
+}
+
+pub struct WasmEmitter {
+    ctors: HashMap<String, i32>,
+    dtors: HashMap<String, i32>,
+    globals: HashMap<TermV, u32>,
+    global_count: u32,
+    runtime: Vec<Func>,
+    prims: Vec<Func>,
+    blocks: Vec<Func>,
+    data: Data,
+}
+
+impl WasmEmitter {
+    pub fn new() -> Self {
+        let mut data = Data::default();
+        let runtime = runtime::functions(&mut data);
+        let prims = runtime::prims(&mut data);
+        let mut emitter = Self {
+            ctors: HashMap::new(),
+            dtors: HashMap::new(),
+            globals: HashMap::new(),
+            global_count: 0,
+            runtime,
+            prims,
+            blocks: Vec::new(),
+            data,
+        };
+        for ctor in RUNTIME_CTORS {
+            emitter.ctor(ctor);
+        }
+        for dtor in RUNTIME_DTORS {
+            emitter.dtor(dtor);
+        }
+        emitter
+    }
+
+    /// Emits the binary encoding of a WASI command module for the program.
+    pub fn emit(mut self, program: &Program) -> Result<Vec<u8>, CodegenError> {
+        let defines = prune(program);
+        let mut start = Func::new(&[], &[]);
+        start.i32_const(4 * defines.len() as i32).call(Rt::Alloc.idx()).global_set(GLOBALS);
+        for (var, def) in defines {
+            let idx = self.global_count;
+            self.global_count += 1;
+            start.global_get(GLOBALS);
+            self.value(def, &Scope::default(), &mut start)?;
+            start.i32_store(4 * idx);
+            self.globals.insert(var.clone(), idx);
+        }
+        let main = self.block(&program.entry, &Scope::default())?;
+        start.i32_const(main).global_set(PC);
+        start.block(Empty).loop_(Empty);
+        start.global_get(HALTED).br_if(1);
+        start.global_get(PC).call_indirect(0);
+        start.br(0).end().end();
+        let (msg, len) = self.data.string("Error: Program did not exit\n");
+        start.global_get(RET).if_(Empty);
+        start.i32_const(2).i32_const(msg).i32_const(len).call(Rt::Write.idx());
+        start.i32_const(1).call(PROC_EXIT);
+        start.end();
+        start.global_get(EXIT_CODE).call(PROC_EXIT);
+
+        let code_count = (self.prims.len() + self.blocks.len()) as u32;
+        let start_idx = PRIM_BASE + code_count;
+        let heap = (self.data.end() + 7) & !7;
+        let mut funcs = self.runtime;
+        funcs.extend(self.prims);
+        funcs.extend(self.blocks);
+        funcs.push(start);
+        let module = Module {
+            imports: runtime::imports(),
+            funcs,
+            table: (PRIM_BASE..start_idx).collect(),
+            globals: runtime::globals(heap),
+            memory_pages: heap / 65536 + 1,
+            exports: vec![
+                ("memory", ExportKind::Memory, 0),
+                ("_start", ExportKind::Func, start_idx),
+            ],
+            data: (DATA_BASE, self.data.into_bytes()),
+        };
+        Ok(module.encode())
+    }
+
+    fn ctor(&mut self, name: &str) -> i32 {
+        let len = self.ctors.len() as i32;
+        *self.ctors.entry(name.to_owned()).or_insert(len)
+    }
+
+    fn dtor(&mut self, name: &str) -> i32 {
+        let len = self.dtors.len() as i32;
+        *self.dtors.entry(name.to_owned()).or_insert(len)
+    }
+
+    /// Registers a new code block and returns its table index.
+    fn block(&mut self, comp: &SynComp, scope: &Scope) -> Result<i32, CodegenError> {
+        let idx = self.blocks.len();
+        self.blocks.push(Func::default());
+        let mut f = Func::new(&[], &[]);
+        self.comp(comp, scope, &mut f)?;
+        self.blocks[idx] = f;
+        Ok((self.prims.len() + idx) as i32)
+    }
+
+    fn var(&self, var: &TermV, scope: &Scope, f: &mut Func) -> Result<(), CodegenError> {
+        if let Some(idx) = scope.locals.iter().rev().position(|x| x == var) {
+            f.global_get(ENV).i32_const(idx as i32).call(Rt::Lookup.idx());
+        } else if let Some(idx) = self.globals.get(var) {
+            f.global_get(GLOBALS).i32_load(4 * idx);
+        } else {
+            Err(CodegenError::UnboundVar { var: var.clone() })?
+        }
+        Ok(())
+    }
+
+    /// Compiles a value into instructions leaving its address on the stack.
+    fn value(&mut self, value: &SynVal, scope: &Scope, f: &mut Func) -> Result<(), CodegenError> {
+        match value {
+            SynVal::Var(var) => self.var(var, scope, f)?,
+            SynVal::Thunk(Thunk(body)) => {
+                let code = match body.as_ref() {
+                    SynComp::Prim(Prim { name, .. }) => prim(name)?,
+                    body => self.block(body, scope)?,
+                };
+                f.i32_const(code).global_get(ENV).call(Rt::MkThunk.idx());
+            }
+            SynVal::Ctor(Ctor { ctorv, args }) => {
+                let ctor = self.ctor(ctorv.name());
+                let a = f.local(I32);
+                f.i32_const(args.len() as i32).call(Rt::Args.idx()).local_set(a);
+                for (i, arg) in args.iter().enumerate() {
+                    f.local_get(a);
+                    self.value(arg, scope, f)?;
+                    f.i32_store(4 * i as u32);
+                }
+                f.i32_const(ctor).i32_const(args.len() as i32).local_get(a);
+                f.call(Rt::MkCtor.idx());
+            }
+            SynVal::Literal(Literal::Int(i)) => {
+                f.i64_const(*i).call(Rt::MkInt.idx());
+            }
+            SynVal::Literal(Literal::Char(c)) => {
+                f.i32_const(*c as i32).call(Rt::MkChar.idx());
+            }
+            SynVal::Literal(Literal::String(s)) => {
+                let (addr, len) = self.data.string(s);
+                f.i32_const(addr).i32_const(len).call(Rt::MkStr.idx());
+            }
+            SynVal::SemValue(_) => Err(CodegenError::SemanticValue)?,
+            SynVal::Hole(hole) => Err(CodegenError::Hole { hole: hole.clone() })?,
+        }
+        Ok(())
+    }
+
+    /// Compiles a computation into instructions that leave the machine ready
+    /// for its next step.
+    fn comp(&mut self, comp: &SynComp, scope: &Scope, f: &mut Func) -> Result<(), CodegenError> {
+        match comp {
+            SynComp::Ret(Ret(v)) => {
+                self.value(v, scope, f)?;
+                f.call(Rt::Return.idx());
+            }
+            SynComp::Force(Force(v)) => {
+                self.value(v, scope, f)?;
+                f.call(Rt::Force.idx());
+            }
+            SynComp::Let(Let { var, def, body }) => {
+                self.value(def, scope, f)?;
+                f.global_get(ENV).call(Rt::Cons.idx()).global_set(ENV);
+                self.comp(body, &scope.bind(var), f)?;
+            }
+            SynComp::Do(Do { var, comp, body }) => {
+                let kont = self.block(body, &scope.bind(var))?;
+                f.i32_const(kont).call(Rt::PushKont.idx());
+                self.comp(comp, scope, f)?;
+            }
+            SynComp::Rec(Rec { var, body }) => {
+                // the block rebinds the variable each time it is entered
+                let idx = self.blocks.len();
+                self.blocks.push(Func::default());
+                let code = (self.prims.len() + idx) as i32;
+                let mut b = Func::new(&[], &[]);
+                b.i32_const(code).global_get(ENV).call(Rt::MkThunk.idx());
+                b.global_get(ENV).call(Rt::Cons.idx()).global_set(ENV);
+                self.comp(body, &scope.bind(var), &mut b)?;
+                self.blocks[idx] = b;
+                f.i32_const(code).global_set(PC);
+            }
+            SynComp::Match(Match { scrut, arms }) => {
+                let s = f.local(I32);
+                self.value(scrut, scope, f)?;
+                f.local_set(s);
+                for Matcher { ctorv, vars, body } in arms {
+                    let ctor = self.ctor(ctorv.name());
+                    f.local_get(s).i32_load(4).i32_const(ctor).op(encode::I32_EQ).if_(Empty);
+                    let mut scope = scope.clone();
+                    for (i, var) in vars.iter().enumerate() {
+                        f.local_get(s).i32_load(12).i32_load(4 * i as u32);
+                        f.global_get(ENV).call(Rt::Cons.idx()).global_set(ENV);
+                        scope = scope.bind(var);
+                    }
+                    self.comp(body, &scope, f)?;
+                    f.op(encode::RETURN).end();
+                }
+                runtime::panic(f, &mut self.data, "no matching arm");
+            }
+            SynComp::MatchLit(MatchLit { scrut, arms, default }) => {
+                self.value(scrut, scope, f)?;
+                // the unboxed scrutinee, tested against each literal in turn
+                let v = match arms.first() {
+                    Some((Literal::Int(_), _)) => {
+                        let v = f.local(I64);
+                        f.call(Rt::GetInt.idx()).local_set(v);
+                        v
+                    }
+                    Some((Literal::Char(_), _)) => {
+                        let v = f.local(I32);
+                        f.call(Rt::GetChar.idx()).local_set(v);
+                        v
+                    }
+                    Some((Literal::String(_), _)) => {
+                        let v = f.local(I32);
+                        f.call(Rt::GetStr.idx()).local_set(v);
+                        v
+                    }
+                    None => {
+                        f.op(encode::DROP);
+                        0
+                    }
+                };
+                for (lit, body) in arms {
+                    match lit {
+                        Literal::Int(i) => {
+                            f.local_get(v).i64_const(*i).op(encode::I64_EQ);
+                        }
+                        Literal::Char(c) => {
+                            f.local_get(v).i32_const(*c as i32).op(encode::I32_EQ);
+                        }
+                        Literal::String(lit) => {
+                            let (addr, len) = self.data.string(lit);
+                            f.local_get(v).i32_load(4).i32_const(len).op(encode::I32_EQ);
+                            f.if_(Value(I32));
+                            f.local_get(v).i32_load(8).i32_const(addr).i32_const(len);
+                            f.call(Rt::MemEq.idx());
+                            f.else_().i32_const(0).end();
+                        }
+                    }
+                    f.if_(Empty);
+                    self.comp(body, scope, f)?;
+                    f.op(encode::RETURN).end();
+                }
+                self.comp(default, scope, f)?;
+            }
+            SynComp::Comatch(Comatch { arms }) => {
+                let frame = f.local(I32);
+                f.call(Rt::PopDtor.idx()).local_set(frame);
+                for Comatcher { dtorv, vars, body } in arms {
+                    let dtor = self.dtor(dtorv.name());
+                    f.local_get(frame).i32_load(4).i32_const(dtor).op(encode::I32_EQ).if_(Empty);
+                    let mut scope = scope.clone();
+                    for (i, var) in vars.iter().enumerate() {
+                        f.local_get(frame).i32_load(12).i32_load(4 * i as u32);
+                        f.global_get(ENV).call(Rt::Cons.idx()).global_set(ENV);
+                        scope = scope.bind(var);
+                    }
+                    self.comp(body, &scope, f)?;
+                    f.op(encode::RETURN).end();
+                }
+                runtime::panic(f, &mut self.data, "no matching arm");
+            }
+            SynComp::Dtor(Dtor { body, dtorv, args }) => {
+                let dtor = self.dtor(dtorv.name());
+                let a = f.local(I32);
+                f.i32_const(args.len() as i32).call(Rt::Args.idx()).local_set(a);
+                for (i, arg) in args.iter().enumerate() {
+                    f.local_get(a);
+                    self.value(arg, scope, f)?;
+                    f.i32_store(4 * i as u32);
+                }
+                f.i32_const(dtor).i32_const(args.len() as i32).local_get(a);
+                f.call(Rt::PushDtor.idx());
+                self.comp(body, scope, f)?;
+            }
+            SynComp::Handle(_) | SynComp::Perform(_) => {
+                Err(CodegenError::UnsupportedHandler { target: "WebAssembly" })?
+            }
+            SynComp::Prim(Prim { name, .. }) => {
+                f.i32_const(prim(name)?).global_set(PC);
+            }
+            SynComp::Hole(hole) => Err(CodegenError::Hole { hole: hole.clone() })?,
+        }
+        Ok(())
+    }
+}
+
+/// The table index of a builtin.
+fn prim(name: &'static str) -> Result<i32, CodegenError> {
+    match RUNTIME_PRIMS.iter().position(|p| *p == name) {
+        Some(idx) => Ok(idx as i32),
+        None => Err(CodegenError::UnsupportedBuiltin { name, target: "WebAssembly" }),
+    }
+}
@@ -0,0 +1,377 @@
+//! A minimal encoder for the WebAssembly binary format, covering just what the
+//! code generator needs: one memory, one function table, mutable globals,
+//! imported and defined functions, and a single data segment.
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ValType {
+    I32,
+    I64,
+}
+
+impl ValType {
+    fn byte(self) -> u8 {
+        match self {
+            ValType::I32 => 0x7F,
+            ValType::I64 => 0x7E,
+        }
+    }
+}
+
+/// The type of the result of a structured control instruction.
+#[derive(Clone, Copy)]
+pub enum BlockType {
+    Empty,
+    Value(ValType),
+}
+
+// opcodes without immediates, used through `Func::op`
+pub const I32_EQZ: u8 = 0x45;
+pub const I32_EQ: u8 = 0x46;
+pub const I32_NE: u8 = 0x47;
+pub const I32_LT_S: u8 = 0x48;
+pub const I32_LT_U: u8 = 0x49;
+pub const I32_GT_U: u8 = 0x4B;
+pub const I32_GE_S: u8 = 0x4E;
+pub const I64_EQZ: u8 = 0x50;
+pub const I64_EQ: u8 = 0x51;
+pub const I64_NE: u8 = 0x52;
+pub const I64_LT_S: u8 = 0x53;
+pub const I64_LT_U: u8 = 0x54;
+pub const I64_GT_S: u8 = 0x55;
+pub const I64_GT_U: u8 = 0x56;
+pub const I32_ADD: u8 = 0x6A;
+pub const I32_SUB: u8 = 0x6B;
+pub const I32_MUL: u8 = 0x6C;
+pub const I32_AND: u8 = 0x71;
+pub const I32_OR: u8 = 0x72;
+pub const I32_SHL: u8 = 0x74;
+pub const I32_SHR_U: u8 = 0x76;
+pub const I64_ADD: u8 = 0x7C;
+pub const I64_SUB: u8 = 0x7D;
+pub const I64_MUL: u8 = 0x7E;
+pub const I64_DIV_S: u8 = 0x7F;
+pub const I64_DIV_U: u8 = 0x80;
+pub const I64_REM_S: u8 = 0x81;
+pub const I64_REM_U: u8 = 0x82;
+pub const I32_WRAP_I64: u8 = 0xA7;
+pub const I64_EXTEND_I32_U: u8 = 0xAD;
+pub const SELECT: u8 = 0x1B;
+pub const DROP: u8 = 0x1A;
+pub const UNREACHABLE: u8 = 0x00;
+pub const RETURN: u8 = 0x0F;
+
+/// A function under construction; instructions are appended in order.
+#[derive(Clone, Default)]
+pub struct Func {
+    params: Vec<ValType>,
+    results: Vec<ValType>,
+    locals: Vec<ValType>,
+    body: Vec<u8>,
+}
+
+impl Func {
+    pub fn new(params: &[ValType], results: &[ValType]) -> Self {
+        Self { params: params.to_vec(), results: results.to_vec(), ..Default::default() }
+    }
+    /// Declares a fresh local and returns its index.
+    pub fn local(&mut self, ty: ValType) -> u32 {
+        self.locals.push(ty);
+        (self.params.len() + self.locals.len() - 1) as u32
+    }
+
+    pub fn op(&mut self, op: u8) -> &mut Self {
+        self.body.push(op);
+        self
+    }
+    fn op_u32(&mut self, op: u8, imm: u32) -> &mut Self {
+        self.body.push(op);
+        uleb(&mut self.body, imm as u64);
+        self
+    }
+    fn op_block(&mut self, op: u8, bt: BlockType) -> &mut Self {
+        self.body.push(op);
+        match bt {
+            BlockType::Empty => self.body.push(0x40),
+            BlockType::Value(ty) => self.body.push(ty.byte()),
+        }
+        self
+    }
+    fn op_mem(&mut self, op: u8, align: u32, offset: u32) -> &mut Self {
+        self.body.push(op);
+        uleb(&mut self.body, align as u64);
+        uleb(&mut self.body, offset as u64);
+        self
+    }
+
+    pub fn block(&mut self, bt: BlockType) -> &mut Self {
+        self.op_block(0x02, bt)
+    }
+    pub fn loop_(&mut self, bt: BlockType) -> &mut Self {
+        self.op_block(0x03, bt)
+    }
+    pub fn if_(&mut self, bt: BlockType) -> &mut Self {
+        self.op_block(0x04, bt)
+    }
+    pub fn else_(&mut self) -> &mut Self {
+        self.op(0x05)
+    }
+    pub fn end(&mut self) -> &mut Self {
+        self.op(0x0B)
+    }
+    pub fn br(&mut self, depth: u32) -> &mut Self {
+        self.op_u32(0x0C, depth)
+    }
+    pub fn br_if(&mut self, depth: u32) -> &mut Self {
+        self.op_u32(0x0D, depth)
+    }
+    pub fn call(&mut self, func: u32) -> &mut Self {
+        self.op_u32(0x10, func)
+    }
+    pub fn call_indirect(&mut self, ty: u32) -> &mut Self {
+        self.op_u32(0x11, ty);
+        self.body.push(0x00);
+        self
+    }
+
+    pub fn local_get(&mut self, idx: u32) -> &mut Self {
+        self.op_u32(0x20, idx)
+    }
+    pub fn local_set(&mut self, idx: u32) -> &mut Self {
+        self.op_u32(0x21, idx)
+    }
+    pub fn local_tee(&mut self, idx: u32) -> &mut Self {
+        self.op_u32(0x22, idx)
+    }
+    pub fn global_get(&mut self, idx: u32) -> &mut Self {
+        self.op_u32(0x23, idx)
+    }
+    pub fn global_set(&mut self, idx: u32) -> &mut Self {
+        self.op_u32(0x24, idx)
+    }
+
+    pub fn i32_load(&mut self, offset: u32) -> &mut Self {
+        self.op_mem(0x28, 2, offset)
+    }
+    pub fn i64_load(&mut self, offset: u32) -> &mut Self {
+        self.op_mem(0x29, 3, offset)
+    }
+    pub fn i32_load8_u(&mut self, offset: u32) -> &mut Self {
+        self.op_mem(0x2D, 0, offset)
+    }
+    pub fn i32_store(&mut self, offset: u32) -> &mut Self {
+        self.op_mem(0x36, 2, offset)
+    }
+    pub fn i64_store(&mut self, offset: u32) -> &mut Self {
+        self.op_mem(0x37, 3, offset)
+    }
+    pub fn i32_store8(&mut self, offset: u32) -> &mut Self {
+        self.op_mem(0x3A, 0, offset)
+    }
+    pub fn memory_size(&mut self) -> &mut Self {
+        self.body.extend([0x3F, 0x00]);
+        self
+    }
+    pub fn memory_grow(&mut self) -> &mut Self {
+        self.body.extend([0x40, 0x00]);
+        self
+    }
+    /// `memory.copy` from the bulk memory extension: `[dst, src, len] -> []`.
+    pub fn memory_copy(&mut self) -> &mut Self {
+        self.body.extend([0xFC, 0x0A, 0x00, 0x00]);
+        self
+    }
+
+    pub fn i32_const(&mut self, v: i32) -> &mut Self {
+        self.body.push(0x41);
+        sleb(&mut self.body, v as i64);
+        self
+    }
+    pub fn i64_const(&mut self, v: i64) -> &mut Self {
+        self.body.push(0x42);
+        sleb(&mut self.body, v);
+        self
+    }
+}
+
+pub enum ExportKind {
+    Func,
+    Memory,
+}
+
+/// A module ready to be encoded. Function indices count the imports first,
+/// and type `0` is always `[] -> []`, the type of the code in the table.
+#[derive(Default)]
+pub struct Module {
+    pub imports: Vec<(&'static str, &'static str, Func)>,
+    pub funcs: Vec<Func>,
+    pub table: Vec<u32>,
+    pub globals: Vec<(ValType, i64)>,
+    pub memory_pages: u32,
+    pub exports: Vec<(&'static str, ExportKind, u32)>,
+    pub data: (u32, Vec<u8>),
+}
+
+impl Module {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut types: Vec<(Vec<ValType>, Vec<ValType>)> = vec![(vec![], vec![])];
+        let mut type_of = |f: &Func| -> u32 {
+            let sig = (f.params.clone(), f.results.clone());
+            let idx = types.iter().position(|t| *t == sig).unwrap_or_else(|| {
+                types.push(sig);
+                types.len() - 1
+            });
+            idx as u32
+        };
+        let import_types: Vec<_> = self.imports.iter().map(|(_, _, f)| type_of(f)).collect();
+        let func_types: Vec<_> = self.funcs.iter().map(&mut type_of).collect();
+
+        let mut out = b"\0asm\x01\0\0\0".to_vec();
+
+        // types
+        let mut s = Vec::new();
+        uleb(&mut s, types.len() as u64);
+        for (params, results) in &types {
+            s.push(0x60);
+            uleb(&mut s, params.len() as u64);
+            s.extend(params.iter().map(|t| t.byte()));
+            uleb(&mut s, results.len() as u64);
+            s.extend(results.iter().map(|t| t.byte()));
+        }
+        section(&mut out, 1, s);
+
+        // imports
+        let mut s = Vec::new();
+        uleb(&mut s, self.imports.len() as u64);
+        for ((module, name, _), ty) in self.imports.iter().zip(import_types) {
+            name_bytes(&mut s, module);
+            name_bytes(&mut s, name);
+            s.push(0x00);
+            uleb(&mut s, ty as u64);
+        }
+        section(&mut out, 2, s);
+
+        // functions
+        let mut s = Vec::new();
+        uleb(&mut s, func_types.len() as u64);
+        for ty in func_types {
+            uleb(&mut s, ty as u64);
+        }
+        section(&mut out, 3, s);
+
+        // table
+        let mut s = vec![0x01, 0x70, 0x01];
+        uleb(&mut s, self.table.len() as u64);
+        uleb(&mut s, self.table.len() as u64);
+        section(&mut out, 4, s);
+
+        // memory
+        let mut s = vec![0x01, 0x00];
+        uleb(&mut s, self.memory_pages as u64);
+        section(&mut out, 5, s);
+
+        // globals
+        let mut s = Vec::new();
+        uleb(&mut s, self.globals.len() as u64);
+        for (ty, init) in &self.globals {
+            s.push(ty.byte());
+            s.push(0x01);
+            match ty {
+                ValType::I32 => {
+                    s.push(0x41);
+                    sleb(&mut s, *init as i32 as i64);
+                }
+                ValType::I64 => {
+                    s.push(0x42);
+                    sleb(&mut s, *init);
+                }
+            }
+            s.push(0x0B);
+        }
+        section(&mut out, 6, s);
+
+        // exports
+        let mut s = Vec::new();
+        uleb(&mut s, self.exports.len() as u64);
+        for (name, kind, idx) in &self.exports {
+            name_bytes(&mut s, name);
+            s.push(match kind {
+                ExportKind::Func => 0x00,
+                ExportKind::Memory => 0x02,
+            });
+            uleb(&mut s, *idx as u64);
+        }
+        section(&mut out, 7, s);
+
+        // elements
+        let mut s = vec![0x01, 0x00, 0x41, 0x00, 0x0B];
+        uleb(&mut s, self.table.len() as u64);
+        for idx in &self.table {
+            uleb(&mut s, *idx as u64);
+        }
+        section(&mut out, 9, s);
+
+        // code
+        let mut s = Vec::new();
+        uleb(&mut s, self.funcs.len() as u64);
+        for f in &self.funcs {
+            let mut body = Vec::new();
+            uleb(&mut body, f.locals.len() as u64);
+            for ty in &f.locals {
+                body.push(0x01);
+                body.push(ty.byte());
+            }
+            body.extend(&f.body);
+            body.push(0x0B);
+            uleb(&mut s, body.len() as u64);
+            s.extend(body);
+        }
+        section(&mut out, 10, s);
+
+        // data
+        let (offset, bytes) = &self.data;
+        let mut s = vec![0x01, 0x00, 0x41];
+        sleb(&mut s, *offset as i64);
+        s.push(0x0B);
+        uleb(&mut s, bytes.len() as u64);
+        s.extend(bytes);
+        section(&mut out, 11, s);
+
+        out
+    }
+}
+
+fn section(out: &mut Vec<u8>, id: u8, content: Vec<u8>) {
+    out.push(id);
+    uleb(out, content.len() as u64);
+    out.extend(content);
+}
+
+fn name_bytes(out: &mut Vec<u8>, name: &str) {
+    uleb(out, name.len() as u64);
+    out.extend(name.as_bytes());
+}
+
+fn uleb(out: &mut Vec<u8>, mut v: u64) {
+    loop {
+        let byte = (v & 0x7F) as u8;
+        v >>= 7;
+        if v == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn sleb(out: &mut Vec<u8>, mut v: i64) {
+    loop {
+        let byte = (v & 0x7F) as u8;
+        v >>= 7;
+        let done = (v == 0 && byte & 0x40 == 0) || (v == -1 && byte & 0x40 != 0);
+        if done {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
@@ -0,0 +1,974 @@
+//! The WebAssembly runtime, written directly as instructions.
+//!
+//! It mirrors `runtime.c`: values, environments and stack frames are cells in
+//! linear memory handed out by a bump allocator and never reclaimed, and the
+//! machine registers (`pc`, `env`, the stack, ...) are wasm globals. Code
+//! blocks and builtins share the function table, and `pc` holds the table
+//! index of the next one to run. Everything the host provides comes from WASI.
+
+use super::encode::{BlockType::*, ValType::*, *};
+use std::collections::HashMap;
+
+// imported functions, in order
+pub const FD_WRITE: u32 = 0;
+pub const FD_READ: u32 = 1;
+pub const PROC_EXIT: u32 = 2;
+pub const ARGS_SIZES_GET: u32 = 3;
+pub const ARGS_GET: u32 = 4;
+const IMPORT_COUNT: u32 = 5;
+
+pub fn imports() -> Vec<(&'static str, &'static str, Func)> {
+    const WASI: &str = "wasi_snapshot_preview1";
+    vec![
+        (WASI, "fd_write", Func::new(&[I32, I32, I32, I32], &[I32])),
+        (WASI, "fd_read", Func::new(&[I32, I32, I32, I32], &[I32])),
+        (WASI, "proc_exit", Func::new(&[I32], &[])),
+        (WASI, "args_sizes_get", Func::new(&[I32, I32], &[I32])),
+        (WASI, "args_get", Func::new(&[I32, I32], &[I32])),
+    ]
+}
+
+// globals
+pub const HEAP: u32 = 0;
+pub const ENV: u32 = 1;
+pub const STACK: u32 = 2;
+pub const PC: u32 = 3;
+pub const HALTED: u32 = 4;
+pub const EXIT_CODE: u32 = 5;
+pub const RET: u32 = 6;
+/// Address of the table of module definitions.
+pub const GLOBALS: u32 = 7;
+/// Byte length of the last code point decoded by `Utf8Next`.
+const UTF8_LEN: u32 = 8;
+const IN_POS: u32 = 9;
+const IN_LEN: u32 = 10;
+
+pub fn globals(heap: u32) -> Vec<(ValType, i64)> {
+    let mut globals = vec![(I32, 0); 11];
+    globals[HEAP as usize].1 = heap as i64;
+    globals
+}
+
+// memory layout below the data segment
+const IOV: i32 = 0;
+const NIO: i32 = 8;
+const NUMBUF_END: i32 = 48;
+const CHARBUF: i32 = 48;
+const ARGC: i32 = 56;
+const ARGV_SIZE: i32 = 60;
+const INBUF: i32 = 1024;
+const INBUF_SIZE: i32 = 4096;
+pub const DATA_BASE: u32 = 8192;
+
+// value tags; every value is a 16-byte cell starting with its tag
+const TAG_INT: i32 = 0;
+const TAG_STR: i32 = 1;
+const TAG_CHAR: i32 = 2;
+const TAG_CTOR: i32 = 3;
+const TAG_THUNK: i32 = 4;
+
+// frame kinds; a frame is a 24-byte cell whose last field links to the next
+const KONT: i32 = 0;
+const DTOR: i32 = 1;
+
+/// Constructors the runtime builds itself, interned first and in this order.
+pub const RUNTIME_CTORS: [&str; 7] = ["True", "False", "None", "Some", "Pair", "Nil", "Cons"];
+/// Destructors the runtime builds itself, interned first and in this order.
+pub const RUNTIME_DTORS: [&str; 1] = ["arg"];
+const CTOR_TRUE: i32 = 0;
+const CTOR_FALSE: i32 = 1;
+const CTOR_NONE: i32 = 2;
+const CTOR_SOME: i32 = 3;
+const CTOR_PAIR: i32 = 4;
+const CTOR_NIL: i32 = 5;
+const CTOR_CONS: i32 = 6;
+const DTOR_ARG: i32 = 0;
+
+/// The data segment, holding string literals and runtime messages.
+#[derive(Default)]
+pub struct Data {
+    bytes: Vec<u8>,
+    interned: HashMap<Vec<u8>, i32>,
+}
+
+impl Data {
+    /// Returns the address and length of a copy of `s` in the data segment.
+    pub fn string(&mut self, s: &str) -> (i32, i32) {
+        let len = s.len() as i32;
+        if let Some(addr) = self.interned.get(s.as_bytes()) {
+            return (*addr, len);
+        }
+        let addr = self.end() as i32;
+        self.bytes.extend(s.as_bytes());
+        self.interned.insert(s.as_bytes().to_vec(), addr);
+        (addr, len)
+    }
+    pub fn end(&self) -> u32 {
+        DATA_BASE + self.bytes.len() as u32
+    }
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+/// Runtime functions, defined right after the imports in this order.
+#[derive(Clone, Copy)]
+pub enum Rt {
+    AllocBytes,
+    Alloc,
+    Write,
+    Panic,
+    MkInt,
+    MkChar,
+    MkStr,
+    StrCopy,
+    MkCtor,
+    MkThunk,
+    Args,
+    Cons,
+    Lookup,
+    PushKont,
+    PushDtor,
+    PopDtor,
+    Return,
+    Force,
+    Arg,
+    Apply,
+    Halt,
+    GetInt,
+    GetChar,
+    GetStr,
+    MkBool,
+    MkPair,
+    MkSome,
+    MkNone,
+    MemEq,
+    StrLen,
+    Utf8Encode,
+    Utf8Next,
+    CharOffset,
+    SplitPair,
+    ReadByte,
+    Read,
+    IntToStr,
+    StrToInt,
+}
+
+const RT_ALL: [Rt; 38] = {
+    use Rt::*;
+    [
+        AllocBytes,
+        Alloc,
+        Write,
+        Panic,
+        MkInt,
+        MkChar,
+        MkStr,
+        StrCopy,
+        MkCtor,
+        MkThunk,
+        Args,
+        Cons,
+        Lookup,
+        PushKont,
+        PushDtor,
+        PopDtor,
+        Return,
+        Force,
+        Arg,
+        Apply,
+        Halt,
+        GetInt,
+        GetChar,
+        GetStr,
+        MkBool,
+        MkPair,
+        MkSome,
+        MkNone,
+        MemEq,
+        StrLen,
+        Utf8Encode,
+        Utf8Next,
+        CharOffset,
+        SplitPair,
+        ReadByte,
+        Read,
+        IntToStr,
+        StrToInt,
+    ]
+};
+
+impl Rt {
+    pub fn idx(self) -> u32 {
+        IMPORT_COUNT + self as u32
+    }
+}
+
+/// Builtins implemented by the runtime; they follow the runtime functions
+/// and start the function table, in this order.
+pub const RUNTIME_PRIMS: [&str; 25] = [
+    "add",
+    "sub",
+    "mul",
+    "div",
+    "mod",
+    "int_eq",
+    "int_lt",
+    "int_gt",
+    "str_length",
+    "str_append",
+    "str_split_once",
+    "str_split_n",
+    "str_substring",
+    "str_eq",
+    "str_index",
+    "int_to_str",
+    "char_to_str",
+    "char_to_int",
+    "int_to_char",
+    "str_to_int",
+    "write_str",
+    "read_line",
+    "read_till_eof",
+    "exit",
+    "arg_list",
+];
+
+/// Function index of the first builtin.
+pub const PRIM_BASE: u32 = IMPORT_COUNT + RT_ALL.len() as u32;
+
+/// Emits a call to `Panic` with `msg`; the call never returns.
+pub fn panic(f: &mut Func, data: &mut Data, msg: &str) {
+    let (addr, len) = data.string(msg);
+    f.i32_const(addr).i32_const(len).call(Rt::Panic.idx());
+}
+
+/// Builds the runtime functions, in the order of `Rt`.
+pub fn functions(data: &mut Data) -> Vec<Func> {
+    RT_ALL.iter().map(|rt| function(*rt, data)).collect()
+}
+
+fn function(rt: Rt, data: &mut Data) -> Func {
+    use Rt::*;
+    match rt {
+        AllocBytes => {
+            let mut f = Func::new(&[I32], &[I32]);
+            let r = f.local(I32);
+            f.global_get(HEAP).local_tee(r).local_get(0).op(I32_ADD).global_set(HEAP);
+            f.global_get(HEAP).memory_size().i32_const(16).op(I32_SHL).op(I32_GT_U);
+            f.if_(Empty);
+            // grow by enough pages to cover the request
+            f.global_get(HEAP).memory_size().i32_const(16).op(I32_SHL).op(I32_SUB);
+            f.i32_const(16).op(I32_SHR_U).i32_const(1).op(I32_ADD);
+            f.memory_grow().i32_const(-1).op(I32_EQ).if_(Empty);
+            panic(&mut f, data, "out of memory");
+            f.end().end();
+            f.local_get(r);
+            f
+        }
+        Alloc => {
+            let mut f = Func::new(&[I32], &[I32]);
+            f.global_get(HEAP).i32_const(7).op(I32_ADD).i32_const(-8).op(I32_AND).global_set(HEAP);
+            f.local_get(0).call(AllocBytes.idx());
+            f
+        }
+        Write => {
+            // (fd, ptr, len); retries on short writes and gives up on errors
+            let mut f = Func::new(&[I32, I32, I32], &[]);
+            f.block(Empty).loop_(Empty);
+            f.local_get(2).op(I32_EQZ).br_if(1);
+            f.i32_const(IOV).local_get(1).i32_store(0);
+            f.i32_const(IOV).local_get(2).i32_store(4);
+            f.local_get(0).i32_const(IOV).i32_const(1).i32_const(NIO).call(FD_WRITE).br_if(1);
+            f.local_get(1).i32_const(NIO).i32_load(0).op(I32_ADD).local_set(1);
+            f.local_get(2).i32_const(NIO).i32_load(0).op(I32_SUB).local_set(2);
+            f.br(0).end().end();
+            f
+        }
+        Panic => {
+            let mut f = Func::new(&[I32, I32], &[]);
+            let (prefix, prefix_len) = data.string("zydeco runtime panicked: ");
+            let (nl, _) = data.string("\n");
+            f.i32_const(2).i32_const(prefix).i32_const(prefix_len).call(Write.idx());
+            f.i32_const(2).local_get(0).local_get(1).call(Write.idx());
+            f.i32_const(2).i32_const(nl).i32_const(1).call(Write.idx());
+            f.i32_const(101).call(PROC_EXIT).op(UNREACHABLE);
+            f
+        }
+        MkInt => {
+            let mut f = Func::new(&[I64], &[I32]);
+            let v = f.local(I32);
+            f.i32_const(16).call(Alloc.idx()).local_tee(v).i32_const(TAG_INT).i32_store(0);
+            f.local_get(v).local_get(0).i64_store(8);
+            f.local_get(v);
+            f
+        }
+        MkChar => {
+            let mut f = Func::new(&[I32], &[I32]);
+            let v = f.local(I32);
+            f.i32_const(16).call(Alloc.idx()).local_tee(v).i32_const(TAG_CHAR).i32_store(0);
+            f.local_get(v).local_get(0).i32_store(4);
+            f.local_get(v);
+            f
+        }
+        MkStr => {
+            // (ptr, len)
+            let mut f = Func::new(&[I32, I32], &[I32]);
+            let v = f.local(I32);
+            f.i32_const(16).call(Alloc.idx()).local_tee(v).i32_const(TAG_STR).i32_store(0);
+            f.local_get(v).local_get(1).i32_store(4);
+            f.local_get(v).local_get(0).i32_store(8);
+            f.local_get(v);
+            f
+        }
+        StrCopy => {
+            // (ptr, len)
+            let mut f = Func::new(&[I32, I32], &[I32]);
+            let buf = f.local(I32);
+            f.local_get(1).call(AllocBytes.idx()).local_tee(buf);
+            f.local_get(0).local_get(1).memory_copy();
+            f.local_get(buf).local_get(1).call(MkStr.idx());
+            f
+        }
+        MkCtor => {
+            // (ctor, n, args)
+            let mut f = Func::new(&[I32, I32, I32], &[I32]);
+            let v = f.local(I32);
+            f.i32_const(16).call(Alloc.idx()).local_tee(v).i32_const(TAG_CTOR).i32_store(0);
+            f.local_get(v).local_get(0).i32_store(4);
+            f.local_get(v).local_get(1).i32_store(8);
+            f.local_get(v).local_get(2).i32_store(12);
+            f.local_get(v);
+            f
+        }
+        MkThunk => {
+            // (code, env)
+            let mut f = Func::new(&[I32, I32], &[I32]);
+            let v = f.local(I32);
+            f.i32_const(16).call(Alloc.idx()).local_tee(v).i32_const(TAG_THUNK).i32_store(0);
+            f.local_get(v).local_get(0).i32_store(4);
+            f.local_get(v).local_get(1).i32_store(8);
+            f.local_get(v);
+            f
+        }
+        Args => {
+            let mut f = Func::new(&[I32], &[I32]);
+            f.local_get(0).i32_const(4).op(I32_MUL).call(Alloc.idx());
+            f
+        }
+        Cons => {
+            // (v, next)
+            let mut f = Func::new(&[I32, I32], &[I32]);
+            let e = f.local(I32);
+            f.i32_const(8).call(Alloc.idx()).local_tee(e).local_get(0).i32_store(0);
+            f.local_get(e).local_get(1).i32_store(4);
+            f.local_get(e);
+            f
+        }
+        Lookup => {
+            // (env, idx)
+            let mut f = Func::new(&[I32, I32], &[I32]);
+            f.block(Empty).loop_(Empty);
+            f.local_get(1).op(I32_EQZ).br_if(1);
+            f.local_get(0).i32_load(4).local_set(0);
+            f.local_get(1).i32_const(1).op(I32_SUB).local_set(1);
+            f.br(0).end().end();
+            f.local_get(0).i32_load(0);
+            f
+        }
+        PushKont => {
+            // (code); captures the current environment
+            let mut f = Func::new(&[I32], &[]);
+            let fr = f.local(I32);
+            f.i32_const(24).call(Alloc.idx()).local_tee(fr).i32_const(KONT).i32_store(0);
+            f.local_get(fr).local_get(0).i32_store(4);
+            f.local_get(fr).global_get(ENV).i32_store(8);
+            f.local_get(fr).global_get(STACK).i32_store(20);
+            f.local_get(fr).global_set(STACK);
+            f
+        }
+        PushDtor => {
+            // (dtor, n, args)
+            let mut f = Func::new(&[I32, I32, I32], &[]);
+            let fr = f.local(I32);
+            f.i32_const(24).call(Alloc.idx()).local_tee(fr).i32_const(DTOR).i32_store(0);
+            f.local_get(fr).local_get(0).i32_store(4);
+            f.local_get(fr).local_get(1).i32_store(8);
+            f.local_get(fr).local_get(2).i32_store(12);
+            f.local_get(fr).global_get(STACK).i32_store(20);
+            f.local_get(fr).global_set(STACK);
+            f
+        }
+        PopDtor => {
+            let mut f = Func::new(&[], &[I32]);
+            let fr = f.local(I32);
+            f.global_get(STACK).local_tee(fr).op(I32_EQZ);
+            f.local_get(fr).i32_load(0).i32_const(DTOR).op(I32_NE).op(I32_OR).if_(Empty);
+            panic(&mut f, data, "Comatch on non-Dtor");
+            f.end();
+            f.local_get(fr).i32_load(20).global_set(STACK);
+            f.local_get(fr);
+            f
+        }
+        Return => {
+            let mut f = Func::new(&[I32], &[]);
+            let fr = f.local(I32);
+            f.global_get(STACK).local_tee(fr).op(I32_EQZ).if_(Empty);
+            f.i32_const(1).global_set(HALTED).local_get(0).global_set(RET).op(RETURN);
+            f.end();
+            f.local_get(fr).i32_load(0).i32_const(KONT).op(I32_NE).if_(Empty);
+            panic(&mut f, data, "Kont not at stacktop");
+            f.end();
+            f.local_get(fr).i32_load(20).global_set(STACK);
+            f.local_get(0).local_get(fr).i32_load(8).call(Cons.idx()).global_set(ENV);
+            f.local_get(fr).i32_load(4).global_set(PC);
+            f
+        }
+        Force => {
+            let mut f = Func::new(&[I32], &[]);
+            f.local_get(0).i32_load(0).i32_const(TAG_THUNK).op(I32_NE).if_(Empty);
+            panic(&mut f, data, "Force on non-thunk");
+            f.end();
+            f.local_get(0).i32_load(8).global_set(ENV);
+            f.local_get(0).i32_load(4).global_set(PC);
+            f
+        }
+        Arg => {
+            // pops the single argument of the `.arg` frame on top of the stack
+            let mut f = Func::new(&[], &[I32]);
+            let fr = f.local(I32);
+            f.call(PopDtor.idx()).local_tee(fr).i32_load(8).i32_const(1).op(I32_LT_S).if_(Empty);
+            panic(&mut f, data, "empty arg");
+            f.end();
+            f.local_get(fr).i32_load(12).i32_load(0);
+            f
+        }
+        Apply => {
+            // (k, v); applies the thunk `k` to `v`, i.e. `! k v`
+            let mut f = Func::new(&[I32, I32], &[]);
+            let a = f.local(I32);
+            f.i32_const(1).call(Args.idx()).local_tee(a).local_get(1).i32_store(0);
+            f.i32_const(DTOR_ARG).i32_const(1).local_get(a).call(PushDtor.idx());
+            f.local_get(0).call(Force.idx());
+            f
+        }
+        Halt => {
+            let mut f = Func::new(&[I32], &[]);
+            f.i32_const(1).global_set(HALTED).local_get(0).global_set(EXIT_CODE);
+            f
+        }
+        GetInt => {
+            let mut f = Func::new(&[I32], &[I64]);
+            f.local_get(0).i32_load(0).i32_const(TAG_INT).op(I32_NE).if_(Empty);
+            panic(&mut f, data, "expected an integer");
+            f.end();
+            f.local_get(0).i64_load(8);
+            f
+        }
+        GetChar => {
+            let mut f = Func::new(&[I32], &[I32]);
+            f.local_get(0).i32_load(0).i32_const(TAG_CHAR).op(I32_NE).if_(Empty);
+            panic(&mut f, data, "expected a character");
+            f.end();
+            f.local_get(0).i32_load(4);
+            f
+        }
+        GetStr => {
+            let mut f = Func::new(&[I32], &[I32]);
+            f.local_get(0).i32_load(0).i32_const(TAG_STR).op(I32_NE).if_(Empty);
+            panic(&mut f, data, "expected a string");
+            f.end();
+            f.local_get(0);
+            f
+        }
+        MkBool => {
+            let mut f = Func::new(&[I32], &[I32]);
+            f.i32_const(CTOR_TRUE).i32_const(CTOR_FALSE).local_get(0).op(SELECT);
+            f.i32_const(0).i32_const(0).call(MkCtor.idx());
+            f
+        }
+        MkPair => {
+            let mut f = Func::new(&[I32, I32], &[I32]);
+            let a = f.local(I32);
+            f.i32_const(2).call(Args.idx()).local_tee(a).local_get(0).i32_store(0);
+            f.local_get(a).local_get(1).i32_store(4);
+            f.i32_const(CTOR_PAIR).i32_const(2).local_get(a).call(MkCtor.idx());
+            f
+        }
+        MkSome => {
+            let mut f = Func::new(&[I32], &[I32]);
+            let a = f.local(I32);
+            f.i32_const(1).call(Args.idx()).local_tee(a).local_get(0).i32_store(0);
+            f.i32_const(CTOR_SOME).i32_const(1).local_get(a).call(MkCtor.idx());
+            f
+        }
+        MkNone => {
+            let mut f = Func::new(&[], &[I32]);
+            f.i32_const(CTOR_NONE).i32_const(0).i32_const(0).call(MkCtor.idx());
+            f
+        }
+        MemEq => {
+            // (a, b, n)
+            let mut f = Func::new(&[I32, I32, I32], &[I32]);
+            f.block(Empty).loop_(Empty);
+            f.local_get(2).op(I32_EQZ).br_if(1);
+            f.local_get(0).i32_load8_u(0).local_get(1).i32_load8_u(0).op(I32_NE).if_(Empty);
+            f.i32_const(0).op(RETURN);
+            f.end();
+            f.local_get(0).i32_const(1).op(I32_ADD).local_set(0);
+            f.local_get(1).i32_const(1).op(I32_ADD).local_set(1);
+            f.local_get(2).i32_const(1).op(I32_SUB).local_set(2);
+            f.br(0).end().end();
+            f.i32_const(1);
+            f
+        }
+        StrLen => {
+            // length of a NUL-terminated string
+            let mut f = Func::new(&[I32], &[I32]);
+            let n = f.local(I32);
+            f.block(Empty).loop_(Empty);
+            f.local_get(0).local_get(n).op(I32_ADD).i32_load8_u(0).op(I32_EQZ).br_if(1);
+            f.local_get(n).i32_const(1).op(I32_ADD).local_set(n);
+            f.br(0).end().end();
+            f.local_get(n);
+            f
+        }
+        Utf8Encode => {
+            // (c, buf) -> len
+            let mut f = Func::new(&[I32, I32], &[I32]);
+            let lead = |f: &mut Func, prefix: i32, shift: i32| {
+                f.local_get(1).local_get(0).i32_const(shift).op(I32_SHR_U);
+                f.i32_const(prefix).op(I32_OR).i32_store8(0);
+            };
+            let cont = |f: &mut Func, offset: u32, shift: i32| {
+                f.local_get(1).local_get(0).i32_const(shift).op(I32_SHR_U);
+                f.i32_const(0x3F).op(I32_AND).i32_const(0x80).op(I32_OR).i32_store8(offset);
+            };
+            f.local_get(0).i32_const(0x80).op(I32_LT_U).if_(Empty);
+            f.local_get(1).local_get(0).i32_store8(0).i32_const(1).op(RETURN);
+            f.end();
+            f.local_get(0).i32_const(0x800).op(I32_LT_U).if_(Empty);
+            lead(&mut f, 0xC0, 6);
+            cont(&mut f, 1, 0);
+            f.i32_const(2).op(RETURN);
+            f.end();
+            f.local_get(0).i32_const(0x10000).op(I32_LT_U).if_(Empty);
+            lead(&mut f, 0xE0, 12);
+            cont(&mut f, 1, 6);
+            cont(&mut f, 2, 0);
+            f.i32_const(3).op(RETURN);
+            f.end();
+            lead(&mut f, 0xF0, 18);
+            cont(&mut f, 1, 12);
+            cont(&mut f, 2, 6);
+            cont(&mut f, 3, 0);
+            f.i32_const(4);
+            f
+        }
+        Utf8Next => {
+            // decodes the code point at `ptr` and stores its length in `UTF8_LEN`
+            let mut f = Func::new(&[I32], &[I32]);
+            let b0 = f.local(I32);
+            let cont = |f: &mut Func, offset: u32, shift: i32| {
+                f.local_get(0).i32_load8_u(offset).i32_const(0x3F).op(I32_AND);
+                f.i32_const(shift).op(I32_SHL).op(I32_OR);
+            };
+            f.local_get(0).i32_load8_u(0).local_tee(b0).i32_const(0x80).op(I32_LT_U).if_(Empty);
+            f.i32_const(1).global_set(UTF8_LEN).local_get(b0).op(RETURN);
+            f.end();
+            f.local_get(b0).i32_const(0xE0).op(I32_LT_U).if_(Empty);
+            f.i32_const(2).global_set(UTF8_LEN);
+            f.local_get(b0).i32_const(0x1F).op(I32_AND).i32_const(6).op(I32_SHL);
+            cont(&mut f, 1, 0);
+            f.op(RETURN).end();
+            f.local_get(b0).i32_const(0xF0).op(I32_LT_U).if_(Empty);
+            f.i32_const(3).global_set(UTF8_LEN);
+            f.local_get(b0).i32_const(0x0F).op(I32_AND).i32_const(12).op(I32_SHL);
+            cont(&mut f, 1, 6);
+            cont(&mut f, 2, 0);
+            f.op(RETURN).end();
+            f.i32_const(4).global_set(UTF8_LEN);
+            f.local_get(b0).i32_const(0x07).op(I32_AND).i32_const(18).op(I32_SHL);
+            cont(&mut f, 1, 12);
+            cont(&mut f, 2, 6);
+            cont(&mut f, 3, 0);
+            f
+        }
+        CharOffset => {
+            // (s, n): the byte offset of the `n`th code point of `s`, its
+            // length for the one past the end, or -1 if `n` is out of range
+            let mut f = Func::new(&[I32, I64], &[I32]);
+            let i = f.local(I32);
+            let k = f.local(I64);
+            f.block(Empty).loop_(Empty);
+            f.local_get(k).local_get(1).op(I64_EQ).if_(Empty).local_get(i).op(RETURN).end();
+            f.local_get(i).local_get(0).i32_load(4).op(I32_LT_U).op(I32_EQZ).br_if(1);
+            f.local_get(0).i32_load(8).local_get(i).op(I32_ADD).call(Utf8Next.idx()).op(DROP);
+            f.local_get(i).global_get(UTF8_LEN).op(I32_ADD).local_set(i);
+            f.local_get(k).i64_const(1).op(I64_ADD).local_set(k);
+            f.br(0).end().end();
+            f.i32_const(-1);
+            f
+        }
+        SplitPair => {
+            // (s, at, skip)
+            let mut f = Func::new(&[I32, I32, I32], &[I32]);
+            f.local_get(0).i32_load(8).local_get(1).call(StrCopy.idx());
+            f.local_get(0).i32_load(8).local_get(1).op(I32_ADD).local_get(2).op(I32_ADD);
+            f.local_get(0).i32_load(4).local_get(1).op(I32_SUB).local_get(2).op(I32_SUB);
+            f.call(StrCopy.idx()).call(MkPair.idx());
+            f
+        }
+        ReadByte => {
+            // the next byte of stdin, or -1 at the end of input
+            let mut f = Func::new(&[], &[I32]);
+            let n = f.local(I32);
+            f.global_get(IN_POS).global_get(IN_LEN).op(I32_EQ).if_(Empty);
+            f.i32_const(IOV).i32_const(INBUF).i32_store(0);
+            f.i32_const(IOV).i32_const(INBUF_SIZE).i32_store(4);
+            f.i32_const(0).i32_const(IOV).i32_const(1).i32_const(NIO).call(FD_READ);
+            f.if_(Empty).i32_const(-1).op(RETURN).end();
+            f.i32_const(NIO).i32_load(0).local_tee(n).op(I32_EQZ);
+            f.if_(Empty).i32_const(-1).op(RETURN).end();
+            f.i32_const(0).global_set(IN_POS).local_get(n).global_set(IN_LEN);
+            f.end();
+            f.global_get(IN_POS).i32_const(INBUF).op(I32_ADD).i32_load8_u(0);
+            f.global_get(IN_POS).i32_const(1).op(I32_ADD).global_set(IN_POS);
+            f
+        }
+        Read => {
+            // (till_eof); the bytes are laid out contiguously at the top of the heap
+            let mut f = Func::new(&[I32], &[I32]);
+            let start = f.local(I32);
+            let len = f.local(I32);
+            let c = f.local(I32);
+            f.i32_const(0).call(Alloc.idx()).local_set(start);
+            f.block(Empty).loop_(Empty);
+            f.call(ReadByte.idx()).local_tee(c).i32_const(-1).op(I32_EQ).br_if(1);
+            f.i32_const(1).call(AllocBytes.idx()).local_get(c).i32_store8(0);
+            f.local_get(len).i32_const(1).op(I32_ADD).local_set(len);
+            f.local_get(0).op(I32_EQZ).local_get(c).i32_const(b'\n' as i32).op(I32_EQ);
+            f.op(I32_AND).br_if(1);
+            f.br(0).end().end();
+            f.local_get(start).local_get(len).call(MkStr.idx());
+            f
+        }
+        IntToStr => {
+            let mut f = Func::new(&[I64], &[I32]);
+            let p = f.local(I32);
+            let neg = f.local(I32);
+            let u = f.local(I64);
+            f.i32_const(NUMBUF_END).local_set(p);
+            f.local_get(0).i64_const(0).op(I64_LT_S).local_set(neg);
+            f.i64_const(0).local_get(0).op(I64_SUB).local_get(0).local_get(neg).op(SELECT);
+            f.local_set(u);
+            f.loop_(Empty);
+            f.local_get(p).i32_const(1).op(I32_SUB).local_tee(p);
+            f.local_get(u).i64_const(10).op(I64_REM_U).op(I32_WRAP_I64);
+            f.i32_const(b'0' as i32).op(I32_ADD).i32_store8(0);
+            f.local_get(u).i64_const(10).op(I64_DIV_U).local_tee(u).i64_const(0).op(I64_NE);
+            f.br_if(0).end();
+            f.local_get(neg).if_(Empty);
+            f.local_get(p).i32_const(1).op(I32_SUB).local_tee(p);
+            f.i32_const(b'-' as i32).i32_store8(0);
+            f.end();
+            f.local_get(p).i32_const(NUMBUF_END).local_get(p).op(I32_SUB).call(StrCopy.idx());
+            f
+        }
+        StrToInt => {
+            // the integer `s` spells, as an `Option`
+            let mut f = Func::new(&[I32], &[I32]);
+            let p = f.local(I32);
+            let len = f.local(I32);
+            let i = f.local(I32);
+            let neg = f.local(I32);
+            let b = f.local(I32);
+            let acc = f.local(I64);
+            f.local_get(0).i32_load(8).local_set(p);
+            f.local_get(0).i32_load(4).local_set(len);
+            f.local_get(len).if_(Empty);
+            f.local_get(p).i32_load8_u(0).local_tee(b).i32_const(b'-' as i32).op(I32_EQ);
+            f.local_get(b).i32_const(b'+' as i32).op(I32_EQ).op(I32_OR).if_(Empty);
+            f.local_get(b).i32_const(b'-' as i32).op(I32_EQ).local_set(neg);
+            f.i32_const(1).local_set(i);
+            f.end().end();
+            f.local_get(i).local_get(len).op(I32_EQ).if_(Empty);
+            f.call(MkNone.idx()).op(RETURN);
+            f.end();
+            f.block(Empty).loop_(Empty);
+            f.local_get(i).local_get(len).op(I32_EQ).br_if(1);
+            f.local_get(p).local_get(i).op(I32_ADD).i32_load8_u(0);
+            f.i32_const(b'0' as i32).op(I32_SUB).local_tee(b).i32_const(9).op(I32_GT_U);
+            f.if_(Empty);
+            f.call(MkNone.idx()).op(RETURN);
+            f.end();
+            // overflows past `i64::MAX`, or `i64::MIN` when negative
+            f.local_get(acc);
+            f.i64_const(i64::MAX).local_get(neg).op(I64_EXTEND_I32_U).op(I64_ADD);
+            f.local_get(b).op(I64_EXTEND_I32_U).op(I64_SUB).i64_const(10).op(I64_DIV_U);
+            f.op(I64_GT_U).if_(Empty);
+            f.call(MkNone.idx()).op(RETURN);
+            f.end();
+            f.local_get(acc).i64_const(10).op(I64_MUL);
+            f.local_get(b).op(I64_EXTEND_I32_U).op(I64_ADD).local_set(acc);
+            f.local_get(i).i32_const(1).op(I32_ADD).local_set(i);
+            f.br(0).end().end();
+            f.i64_const(0).local_get(acc).op(I64_SUB).local_get(acc).local_get(neg).op(SELECT);
+            f.call(MkInt.idx()).call(MkSome.idx());
+            f
+        }
+    }
+}
+
+/// Builds the builtins, in the order of `RUNTIME_PRIMS`. Each pops its
+/// arguments off the stack like `SynComp::Prim`.
+pub fn prims(data: &mut Data) -> Vec<Func> {
+    RUNTIME_PRIMS.iter().map(|name| prim(name, data)).collect()
+}
+
+fn prim(name: &str, data: &mut Data) -> Func {
+    use Rt::*;
+    let mut f = Func::new(&[], &[]);
+    let int_args = |f: &mut Func| {
+        f.call(Arg.idx()).call(GetInt.idx()).call(Arg.idx()).call(GetInt.idx());
+    };
+    match name {
+        "add" | "sub" | "mul" => {
+            int_args(&mut f);
+            let op = match name {
+                "add" => I64_ADD,
+                "sub" => I64_SUB,
+                _ => I64_MUL,
+            };
+            f.op(op).call(MkInt.idx()).call(Return.idx());
+        }
+        "div" | "mod" => {
+            let b = f.local(I64);
+            int_args(&mut f);
+            f.local_tee(b).op(I64_EQZ).if_(Empty);
+            if name == "div" {
+                panic(&mut f, data, "attempt to divide by zero");
+            } else {
+                panic(&mut f, data, "attempt to calculate the remainder with a divisor of zero");
+            }
+            f.end();
+            let op = if name == "div" { I64_DIV_S } else { I64_REM_S };
+            f.local_get(b).op(op).call(MkInt.idx()).call(Return.idx());
+        }
+        "int_eq" | "int_lt" | "int_gt" => {
+            int_args(&mut f);
+            let op = match name {
+                "int_eq" => I64_EQ,
+                "int_lt" => I64_LT_S,
+                _ => I64_GT_S,
+            };
+            f.op(op).call(MkBool.idx()).call(Return.idx());
+        }
+        "str_length" => {
+            let s = f.local(I32);
+            let i = f.local(I32);
+            let k = f.local(I64);
+            f.call(Arg.idx()).call(GetStr.idx()).local_set(s);
+            f.block(Empty).loop_(Empty);
+            f.local_get(i).local_get(s).i32_load(4).op(I32_LT_U).op(I32_EQZ).br_if(1);
+            f.local_get(s).i32_load(8).local_get(i).op(I32_ADD).call(Utf8Next.idx()).op(DROP);
+            f.local_get(i).global_get(UTF8_LEN).op(I32_ADD).local_set(i);
+            f.local_get(k).i64_const(1).op(I64_ADD).local_set(k);
+            f.br(0).end().end();
+            f.local_get(k).call(MkInt.idx()).call(Return.idx());
+        }
+        "str_append" => {
+            let a = f.local(I32);
+            let b = f.local(I32);
+            let buf = f.local(I32);
+            f.call(Arg.idx()).call(GetStr.idx()).local_set(a);
+            f.call(Arg.idx()).call(GetStr.idx()).local_set(b);
+            f.local_get(a).i32_load(4).local_get(b).i32_load(4).op(I32_ADD);
+            f.call(AllocBytes.idx()).local_tee(buf);
+            f.local_get(a).i32_load(8).local_get(a).i32_load(4).memory_copy();
+            f.local_get(buf).local_get(a).i32_load(4).op(I32_ADD);
+            f.local_get(b).i32_load(8).local_get(b).i32_load(4).memory_copy();
+            f.local_get(buf).local_get(a).i32_load(4).local_get(b).i32_load(4).op(I32_ADD);
+            f.call(MkStr.idx()).call(Return.idx());
+        }
+        "str_split_once" => {
+            let s = f.local(I32);
+            let plen = f.local(I32);
+            let i = f.local(I32);
+            f.call(Arg.idx()).call(GetStr.idx()).local_set(s);
+            f.call(Arg.idx()).call(GetChar.idx()).i32_const(CHARBUF).call(Utf8Encode.idx());
+            f.local_set(plen);
+            f.block(Empty).loop_(Empty);
+            f.local_get(i).local_get(plen).op(I32_ADD).local_get(s).i32_load(4).op(I32_GT_U);
+            f.br_if(1);
+            f.local_get(s).i32_load(8).local_get(i).op(I32_ADD);
+            f.i32_const(CHARBUF).local_get(plen).call(MemEq.idx()).if_(Empty);
+            f.local_get(s).local_get(i).local_get(plen).call(SplitPair.idx());
+            f.call(MkSome.idx()).call(Return.idx()).op(RETURN);
+            f.end();
+            f.local_get(i).i32_const(1).op(I32_ADD).local_set(i);
+            f.br(0).end().end();
+            f.call(MkNone.idx()).call(Return.idx());
+        }
+        "str_split_n" => {
+            let s = f.local(I32);
+            let at = f.local(I32);
+            f.call(Arg.idx()).call(GetStr.idx()).local_tee(s);
+            f.call(Arg.idx()).call(GetInt.idx()).call(CharOffset.idx()).local_tee(at);
+            f.i32_const(0).op(I32_LT_S).if_(Empty);
+            f.call(MkNone.idx()).call(Return.idx()).op(RETURN);
+            f.end();
+            f.local_get(s).local_get(at).i32_const(0).call(SplitPair.idx());
+            f.call(MkSome.idx()).call(Return.idx());
+        }
+        "str_substring" => {
+            let s = f.local(I32);
+            let start = f.local(I32);
+            let end = f.local(I32);
+            // the offset of the code point at the index on top of the stack,
+            // clamped to the string
+            let offset = |f: &mut Func| {
+                let n = f.local(I64);
+                let o = f.local(I32);
+                f.local_set(n).local_get(s);
+                f.i64_const(0).local_get(n).local_get(n).i64_const(0).op(I64_LT_S).op(SELECT);
+                f.call(CharOffset.idx()).local_tee(o);
+                f.local_get(s).i32_load(4).local_get(o).i32_const(0).op(I32_GE_S).op(SELECT);
+            };
+            f.call(Arg.idx()).call(GetStr.idx()).local_set(s);
+            f.call(Arg.idx()).call(GetInt.idx());
+            offset(&mut f);
+            f.local_set(start);
+            f.call(Arg.idx()).call(GetInt.idx());
+            offset(&mut f);
+            f.local_set(end);
+            f.local_get(start).local_get(end).local_get(end).local_get(start).op(I32_LT_S);
+            f.op(SELECT).local_set(end);
+            f.local_get(s).i32_load(8).local_get(start).op(I32_ADD);
+            f.local_get(end).local_get(start).op(I32_SUB).call(StrCopy.idx()).call(Return.idx());
+        }
+        "str_eq" => {
+            let a = f.local(I32);
+            let b = f.local(I32);
+            f.call(Arg.idx()).call(GetStr.idx()).local_set(a);
+            f.call(Arg.idx()).call(GetStr.idx()).local_set(b);
+            f.local_get(a).i32_load(4).local_get(b).i32_load(4).op(I32_EQ);
+            f.if_(Value(I32));
+            f.local_get(a).i32_load(8).local_get(b).i32_load(8).local_get(a).i32_load(4);
+            f.call(MemEq.idx());
+            f.else_().i32_const(0).end();
+            f.call(MkBool.idx()).call(Return.idx());
+        }
+        "str_index" => {
+            let s = f.local(I32);
+            let n = f.local(I64);
+            let i = f.local(I32);
+            let k = f.local(I64);
+            let c = f.local(I32);
+            f.call(Arg.idx()).call(GetStr.idx()).local_set(s);
+            f.call(Arg.idx()).call(GetInt.idx()).local_set(n);
+            f.block(Empty).loop_(Empty);
+            f.local_get(i).local_get(s).i32_load(4).op(I32_LT_U).op(I32_EQZ).br_if(1);
+            f.local_get(s).i32_load(8).local_get(i).op(I32_ADD).call(Utf8Next.idx()).local_set(c);
+            f.local_get(i).global_get(UTF8_LEN).op(I32_ADD).local_set(i);
+            f.local_get(k).local_get(n).op(I64_EQ).if_(Empty);
+            f.local_get(c).call(MkChar.idx()).call(MkSome.idx()).call(Return.idx()).op(RETURN);
+            f.end();
+            f.local_get(k).i64_const(1).op(I64_ADD).local_set(k);
+            f.br(0).end().end();
+            f.call(MkNone.idx()).call(Return.idx());
+        }
+        "int_to_str" => {
+            f.call(Arg.idx()).call(GetInt.idx()).call(IntToStr.idx()).call(Return.idx());
+        }
+        "char_to_str" => {
+            let len = f.local(I32);
+            f.call(Arg.idx()).call(GetChar.idx()).i32_const(CHARBUF).call(Utf8Encode.idx());
+            f.local_set(len);
+            f.i32_const(CHARBUF).local_get(len).call(StrCopy.idx()).call(Return.idx());
+        }
+        "char_to_int" => {
+            f.call(Arg.idx()).call(GetChar.idx());
+            f.op(I64_EXTEND_I32_U).call(MkInt.idx()).call(Return.idx());
+        }
+        "int_to_char" => {
+            let n = f.local(I64);
+            f.call(Arg.idx()).call(GetInt.idx()).local_tee(n).i64_const(0x10FFFF).op(I64_GT_U);
+            f.local_get(n).i64_const(0xD800).op(I64_SUB).i64_const(0x800).op(I64_LT_U);
+            f.op(I32_OR).if_(Empty);
+            f.call(MkNone.idx()).call(Return.idx()).op(RETURN);
+            f.end();
+            f.local_get(n).op(I32_WRAP_I64).call(MkChar.idx()).call(MkSome.idx());
+            f.call(Return.idx());
+        }
+        "str_to_int" => {
+            f.call(Arg.idx()).call(GetStr.idx()).call(StrToInt.idx()).call(Return.idx());
+        }
+        "write_str" => {
+            let s = f.local(I32);
+            let k = f.local(I32);
+            f.call(Arg.idx()).call(GetStr.idx()).local_set(s);
+            f.call(Arg.idx()).local_set(k);
+            f.i32_const(1).local_get(s).i32_load(8).local_get(s).i32_load(4).call(Write.idx());
+            f.local_get(k).call(Force.idx());
+        }
+        "read_line" => {
+            let k = f.local(I32);
+            let line = f.local(I32);
+            f.call(Arg.idx()).local_set(k);
+            f.i32_const(0).call(Read.idx()).local_set(line);
+            // drop the last character, usually the newline
+            let shrink = |f: &mut Func| {
+                f.local_get(line).local_get(line).i32_load(4).i32_const(1).op(I32_SUB);
+                f.i32_store(4);
+            };
+            f.block(Empty).loop_(Empty);
+            f.local_get(line).i32_load(4).op(I32_EQZ).br_if(1);
+            f.local_get(line).i32_load(8).local_get(line).i32_load(4).op(I32_ADD);
+            f.i32_const(1).op(I32_SUB).i32_load8_u(0);
+            f.i32_const(0xC0).op(I32_AND).i32_const(0x80).op(I32_NE).br_if(1);
+            shrink(&mut f);
+            f.br(0).end().end();
+            f.local_get(line).i32_load(4).if_(Empty);
+            shrink(&mut f);
+            f.end();
+            f.local_get(k).local_get(line).call(Apply.idx());
+        }
+        "read_till_eof" => {
+            let k = f.local(I32);
+            f.call(Arg.idx()).local_set(k);
+            f.local_get(k).i32_const(1).call(Read.idx()).call(Apply.idx());
+        }
+        "exit" => {
+            f.call(Arg.idx()).call(GetInt.idx()).op(I32_WRAP_I64).call(Halt.idx());
+        }
+        "arg_list" => {
+            let k = f.local(I32);
+            let argv = f.local(I32);
+            let list = f.local(I32);
+            let i = f.local(I32);
+            let s = f.local(I32);
+            let a = f.local(I32);
+            f.call(Arg.idx()).local_set(k);
+            f.i32_const(ARGC).i32_const(ARGV_SIZE).call(ARGS_SIZES_GET).op(DROP);
+            f.i32_const(ARGC).i32_load(0).call(Args.idx()).local_tee(argv);
+            f.i32_const(ARGV_SIZE).i32_load(0).call(AllocBytes.idx());
+            f.call(ARGS_GET).op(DROP);
+            f.i32_const(CTOR_NIL).i32_const(0).i32_const(0).call(MkCtor.idx()).local_set(list);
+            // skip the program name in `argv[0]`
+            f.i32_const(ARGC).i32_load(0).i32_const(1).op(I32_SUB).local_set(i);
+            f.block(Empty).loop_(Empty);
+            f.local_get(i).i32_const(1).op(I32_GE_S).op(I32_EQZ).br_if(1);
+            f.local_get(argv).local_get(i).i32_const(4).op(I32_MUL).op(I32_ADD).i32_load(0);
+            f.local_tee(s).local_get(s).call(StrLen.idx()).call(MkStr.idx()).local_set(s);
+            f.i32_const(2).call(Args.idx()).local_tee(a).local_get(s).i32_store(0);
+            f.local_get(a).local_get(list).i32_store(4);
+            f.i32_const(CTOR_CONS).i32_const(2).local_get(a).call(MkCtor.idx()).local_set(list);
+            f.local_get(i).i32_const(1).op(I32_SUB).local_set(i);
+            f.br(0).end().end();
+            f.local_get(k).local_get(list).call(Apply.idx());
+        }
+        _ => unreachable!("unknown runtime builtin {}", name),
+    }
+    f
+}
@@ -1,10 +1,14 @@
-use super::syntax::{Thunk as SemThunk, *};
-use crate::{rc, utils::fmt::FmtArgs};
-use im::Vector;
-use std::{
-    io::{BufRead, Write},
-    rc::Rc,
+use super::{
+    host::Host,
+    syntax::{Thunk as SemThunk, *},
+};
+use crate::{
+    rc,
+    syntax::{binder::DtorV, Env, TermHole},
+    utils::{fmt::FmtArgs, span::SpanInfo},
 };
+use im::Vector;
+use std::rc::Rc;
 
 pub trait Eval<'rt>: Sized + FmtArgs {
     type Out;
@@ -26,10 +30,28 @@ pub enum Step<T, Out> {
 }
 
 impl<'rt> Runtime<'rt> {
-    pub fn new(
-        input: &'rt mut dyn BufRead, output: &'rt mut dyn Write, args: &'rt [String],
-    ) -> Self {
-        Runtime { input, output, args, stack: Vector::new(), env: Env::new() }
+    pub fn new(host: &'rt mut dyn Host) -> Self {
+        Runtime { host, stack: Vector::new(), env: Env::new(), prompts: 0, heap: Heap::default() }
+    }
+    /// Reports a runtime error the way the `panic` builtin does, giving the
+    /// exit code 1 the program stops with.
+    pub fn panic(&mut self, msg: &str) -> i32 {
+        self.host.write_str(&format!("Program panicked with message: {}\n", msg)).unwrap();
+        1
+    }
+    /// Pops the frame on top of the stack. Prompts are discarded on the way, as
+    /// a computation that consumes the frames below a prompt has left it: a
+    /// prompt delimits a computation of any type, so a prompted function pops
+    /// its argument from under the prompt. A prompt holds no value, so
+    /// dropping it only ends the delimited part; a later `take_subcont` to it
+    /// panics, as if it had never been pushed.
+    pub fn pop_frame(&mut self) -> Option<SemComp> {
+        loop {
+            match self.stack.pop_back() {
+                Some(SemComp::Prompt(_)) => continue,
+                frame => break frame,
+            }
+        }
     }
 }
 
@@ -45,11 +67,17 @@ impl<'rt> Eval<'rt> for ls::SynVal {
                 Step::Done(super::syntax::Thunk { body, env: runtime.env.clone() }.into())
             }
             ls::SynVal::Ctor(ls::Ctor { ctorv: ctor, args }) => {
-                let args = args.iter().map(|arg| rc!(arg.as_ref().clone().eval(runtime))).collect();
+                let args: Vec<_> =
+                    args.iter().map(|arg| rc!(arg.as_ref().clone().eval(runtime))).collect();
+                // a value holding a hole is as unfinished as the hole
+                if let Some(hole) = hole(args.iter().map(|arg| arg.as_ref())) {
+                    return Step::Done(hole.into());
+                }
                 Step::Done(ls::Ctor { ctorv: ctor, args }.into())
             }
             ls::SynVal::Literal(lit) => Step::Done(lit.into()),
             ls::SynVal::SemValue(sem) => Step::Done(sem),
+            ls::SynVal::Hole(hole) => Step::Done(hole.into()),
         }
     }
 }
@@ -61,21 +89,32 @@ impl<'rt> Eval<'rt> for ls::SynComp {
         match self {
             ls::SynComp::Ret(ls::Ret(v)) => {
                 let v = v.as_ref().clone().eval(runtime);
-                match runtime.stack.pop_back() {
+                if let Some(hole) = hole([&v]) {
+                    return Step::Done(ProgKont::Hole(hole));
+                }
+                match runtime.pop_frame() {
                     Some(SemComp::Kont(comp, env, var)) => {
                         let env = env.update(var, v);
                         runtime.env = env;
                         Step::Step(comp.as_ref().clone())
                     }
+                    Some(SemComp::Handler(handle, env)) => {
+                        let Some((var, body)) = &handle.ret else {
+                            return Step::Step(ls::Ret(rc!(v.into())).into());
+                        };
+                        runtime.env = env.update(var.clone(), v);
+                        Step::Step(body.as_ref().clone())
+                    }
                     None => Step::Done(ProgKont::Ret(v)),
                     _ => panic!("Kont not at stacktop"),
                 }
             }
             ls::SynComp::Force(ls::Force(v)) => {
                 let v = v.as_ref().clone().eval(runtime);
-                let SemVal::Thunk(thunk) = v else {
-                    panic!("Force on non-thunk")
-                };
+                if let Some(hole) = hole([&v]) {
+                    return Step::Done(ProgKont::Hole(hole));
+                }
+                let SemVal::Thunk(thunk) = v else { panic!("Force on non-thunk") };
                 runtime.env = thunk.env;
                 Step::Step(thunk.body.as_ref().clone())
             }
@@ -99,6 +138,9 @@ impl<'rt> Eval<'rt> for ls::SynComp {
             }
             ls::SynComp::Match(ls::Match { scrut, arms }) => {
                 let scrut = scrut.as_ref().clone().eval(runtime);
+                if let Some(hole) = hole([&scrut]) {
+                    return Step::Done(ProgKont::Hole(hole));
+                }
                 let SemVal::Ctor(ls::Ctor { ctorv: ctor, args }) = scrut else {
                     panic!("Match on non-ctor")
                 };
@@ -110,8 +152,21 @@ impl<'rt> Eval<'rt> for ls::SynComp {
                 }
                 Step::Step(body.as_ref().clone())
             }
+            ls::SynComp::MatchLit(ls::MatchLit { scrut, arms, default }) => {
+                let scrut = scrut.as_ref().clone().eval(runtime);
+                if let Some(hole) = hole([&scrut]) {
+                    return Step::Done(ProgKont::Hole(hole));
+                }
+                let SemVal::Literal(lit) = scrut else { panic!("Match on non-literal") };
+                // the arms are sorted by literal when linked
+                let body = match arms.binary_search_by(|(l, _)| l.cmp(&lit)) {
+                    Ok(i) => &arms[i].1,
+                    Err(_) => &default,
+                };
+                Step::Step(body.as_ref().clone())
+            }
             ls::SynComp::Comatch(ls::Comatch { arms }) => {
-                let Some(SemComp::Dtor(dtor, args)) = runtime.stack.pop_back() else {
+                let Some(SemComp::Dtor(dtor, args)) = runtime.pop_frame() else {
                     panic!("Comatch on non-Dtor")
                 };
                 let ls::Comatcher { dtorv: _, vars, body } =
@@ -123,25 +178,90 @@ impl<'rt> Eval<'rt> for ls::SynComp {
                 Step::Step(body.as_ref().clone())
             }
             ls::SynComp::Dtor(ls::Dtor { body, dtorv: dtor, args }) => {
-                let args =
+                let args: Vec<_> =
                     args.iter().map(|arg| Rc::new(arg.as_ref().clone().eval(runtime))).collect();
+                if let Some(hole) = hole(args.iter().map(|arg| arg.as_ref())) {
+                    return Step::Done(ProgKont::Hole(hole));
+                }
                 runtime.stack.push_back(SemComp::Dtor(dtor, args));
                 Step::Step(body.as_ref().clone())
             }
-            ls::SynComp::Prim(ls::Prim { arity, body }) => {
+            ls::SynComp::Handle(handle) => {
+                let body = handle.body.clone();
+                runtime.stack.push_back(SemComp::Handler(rc!(handle), runtime.env.clone()));
+                Step::Step(body.as_ref().clone())
+            }
+            ls::SynComp::Perform(ls::Perform { op, args }) => {
+                let args: Vec<_> =
+                    args.iter().map(|arg| arg.as_ref().clone().eval(runtime)).collect();
+                if let Some(hole) = hole(&args) {
+                    return Step::Done(ProgKont::Hole(hole));
+                }
+                let depth = (runtime.stack.iter().rev()).position(|frame| {
+                    matches!(frame, SemComp::Handler(handle, _)
+                        if handle.arms.iter().any(|arm| arm.op == op))
+                });
+                // only reachable by code the type checker hasn't seen
+                let Some(depth) = depth else {
+                    let msg = format!("unhandled effect operation {}", op.name());
+                    return Step::Done(ProgKont::ExitCode(runtime.panic(&msg)));
+                };
+                // the captured frames include the handler itself, so that it
+                // keeps handling the computation once resumed
+                let idx = runtime.stack.len() - depth - 1;
+                let frames = runtime.stack.split_off(idx);
+                let Some(SemComp::Handler(handle, env)) = frames.front() else { unreachable!() };
+                let ls::Handler { op: _, vars, kont, body } =
+                    handle.arms.iter().find(|arm| arm.op == op).unwrap();
+                let mut env = env.clone();
+                for (var, arg) in vars.iter().zip(args) {
+                    env = env.update(var.clone(), arg);
+                }
+                let resume = ls::Prim { name: "resume", arity: 2, body: rc!(resume) };
+                let frames = rc!(ls::SynVal::SemValue(SubCont(frames.clone()).into()));
+                let dtorv = DtorV::new(format!("arg"), SpanInfo::dummy());
+                let resume = ls::Dtor { body: rc!(resume.into()), dtorv, args: vec![frames] };
+                let resume = SemThunk { body: rc!(resume.into()), env: Env::new() };
+                runtime.env = env.update(kont.clone(), resume.into());
+                Step::Step(body.as_ref().clone())
+            }
+            ls::SynComp::Prim(ls::Prim { name, arity, body }) => {
                 let mut args = Vec::new();
                 for _ in 0..arity {
-                    let Some(SemComp::Dtor(_, arg)) = runtime.stack.pop_back() else {
-                        panic!("Prim on non-Dtor")
+                    let Some(SemComp::Dtor(_, arg)) = runtime.pop_frame() else {
+                        let msg = format!("{} is missing an argument", name);
+                        return Step::Done(ProgKont::ExitCode(runtime.panic(&msg)));
                     };
                     args.push(arg.first().expect("empty arg").as_ref().clone());
                 }
-                match body(args, runtime.input, runtime.output, runtime.args) {
+                match body(args, runtime) {
                     Ok(e) => Step::Step(e),
                     Err(exit_code) => Step::Done(ProgKont::ExitCode(exit_code)),
                 }
             }
+            ls::SynComp::Hole(hole) => Step::Done(ProgKont::Hole(hole)),
+        }
+    }
+}
+
+/// The first hole among values about to be used. A hole bound to a variable
+/// or stored in a thunk goes unnoticed until then.
+fn hole<'a>(vals: impl IntoIterator<Item = &'a SemVal>) -> Option<TermHole> {
+    vals.into_iter().find_map(|v| match v {
+        SemVal::Hole(hole) => Some(hole.clone()),
+        _ => None,
+    })
+}
+
+/// Resumes a computation suspended by an effect operation, reinstating the
+/// captured frames and returning the answer to the operation.
+fn resume(args: Vec<SemVal>, runtime: &mut Runtime) -> Result<ls::SynComp, i32> {
+    match args.as_slice() {
+        [SemVal::SubCont(SubCont(frames)), arg] => {
+            runtime.stack.append(frames.clone());
+            Ok(ls::Ret(rc!(ls::SynVal::SemValue(arg.clone()))).into())
         }
+        _ => Err(runtime.panic("resume applied to something other than a continuation")),
     }
 }
 
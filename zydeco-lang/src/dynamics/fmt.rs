@@ -1,5 +1,8 @@
 use super::syntax::{self as ds, *};
-use crate::utils::fmt::{Args, FmtArgs};
+use crate::utils::{
+    fmt::{Args, FmtArgs},
+    span::SpanView,
+};
 
 impl FmtArgs for ds::Thunk {
     fn fmt_args(&self, fargs: Args) -> String {
@@ -22,11 +25,36 @@ impl FmtArgs for ds::Thunk {
     }
 }
 
+impl FmtArgs for Prompt {
+    fn fmt_args(&self, _fargs: Args) -> String {
+        format!("<prompt #{}>", self.0)
+    }
+}
+
+impl FmtArgs for SubCont {
+    fn fmt_args(&self, _fargs: Args) -> String {
+        format!("<subcont of {} frames>", self.0.len())
+    }
+}
+
+impl FmtArgs for Ref {
+    fn fmt_args(&self, _fargs: Args) -> String {
+        format!("<ref #{}>", self.0)
+    }
+}
+
+impl FmtArgs for Array {
+    fn fmt_args(&self, _fargs: Args) -> String {
+        format!("<array #{}>", self.0)
+    }
+}
+
 impl FmtArgs for ProgKont {
     fn fmt_args(&self, fargs: Args) -> String {
         match self {
             ProgKont::Ret(v) => v.fmt_args(fargs),
             ProgKont::ExitCode(c) => format!("exit({})", c),
+            ProgKont::Hole(hole) => format!("hole {} ({})", hole.fmt(), hole.span()),
         }
     }
 }
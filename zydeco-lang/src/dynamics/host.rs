@@ -0,0 +1,390 @@
+//! The operating system a running program talks to through the builtins.
+//!
+//! [`OsHost`] forwards to the real process, while [`MockHost`] keeps
+//! everything in memory, for running programs in tests and in the browser.
+//!
+//! Paths given to the file system operations are `/`-separated. A host may
+//! confine them to a root directory, in which case neither `..` nor symbolic
+//! links can leave the root, and absolute paths start from it.
+//!
+//! Times are in milliseconds. A host may run on a fake clock, which starts at
+//! a given time and only moves when the program sleeps; together with a fixed
+//! seed for the random numbers, this makes runs reproducible.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::{self, BufRead, Cursor, Read, Write},
+    path::{Component, Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+pub trait Host {
+    /// Writes `s` to the standard output.
+    fn write_str(&mut self, s: &str) -> io::Result<()>;
+    /// Reads a line from the standard input, including the newline if any.
+    fn read_line(&mut self) -> io::Result<String>;
+    /// Reads the rest of the standard input.
+    fn read_to_end(&mut self) -> io::Result<String>;
+    /// The command line arguments of the program.
+    fn args(&self) -> &[String];
+    /// Opens the file at `path`, creating it empty if it doesn't exist and
+    /// keeping its contents otherwise.
+    fn open_file(&mut self, path: &str) -> io::Result<()>;
+    /// Reads the whole file at `path`.
+    fn read_file(&mut self, path: &str) -> io::Result<String>;
+    /// Writes `contents` to the file at `path`, replacing it if it exists.
+    fn write_file(&mut self, path: &str, contents: &str) -> io::Result<()>;
+    /// Appends `contents` to the file at `path`, creating it if needed.
+    fn append_file(&mut self, path: &str, contents: &str) -> io::Result<()>;
+    /// Whether a file or directory exists at `path`.
+    fn exists(&mut self, path: &str) -> io::Result<bool>;
+    /// The names of the entries of the directory at `path`, sorted.
+    fn list_dir(&mut self, path: &str) -> io::Result<Vec<String>>;
+    /// Removes the file at `path`.
+    fn remove_file(&mut self, path: &str) -> io::Result<()>;
+    /// Writes `s` to the standard error.
+    fn write_err(&mut self, s: &str) -> io::Result<()>;
+    /// The value of the environment variable `name`, if set.
+    fn env_var(&mut self, name: &str) -> Option<String>;
+    /// The time since the Unix epoch.
+    fn wall_clock(&mut self) -> i64;
+    /// The time since the host started, which never decreases.
+    fn monotonic_clock(&mut self) -> i64;
+    /// Pauses the program for `ms` milliseconds.
+    fn sleep(&mut self, ms: u64);
+    /// The next number of the pseudo-random sequence.
+    fn random(&mut self) -> u64;
+}
+
+/// The clocks of a host.
+enum Clock {
+    Real {
+        start: Instant,
+    },
+    /// A clock at `start` that has been slept on for `elapsed`.
+    Fake {
+        start: i64,
+        elapsed: i64,
+    },
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock::Fake { start: 0, elapsed: 0 }
+    }
+}
+
+impl Clock {
+    fn wall(&self) -> i64 {
+        match self {
+            Clock::Real { .. } => {
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+                now.as_millis() as i64
+            }
+            Clock::Fake { start, elapsed } => start + elapsed,
+        }
+    }
+    fn monotonic(&self) -> i64 {
+        match self {
+            Clock::Real { start } => start.elapsed().as_millis() as i64,
+            Clock::Fake { start: _, elapsed } => *elapsed,
+        }
+    }
+    fn sleep(&mut self, ms: u64) {
+        match self {
+            Clock::Real { .. } => std::thread::sleep(Duration::from_millis(ms)),
+            Clock::Fake { start: _, elapsed } => *elapsed += ms as i64,
+        }
+    }
+}
+
+/// A SplitMix64 generator.
+#[derive(Default)]
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+}
+
+fn outside(path: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::PermissionDenied, format!("{} is outside of the sandbox", path))
+}
+
+/// Resolves `path` under `root` without leaving it.
+fn confine(root: &Path, path: &str) -> io::Result<PathBuf> {
+    let mut res = root.to_path_buf();
+    let mut depth = 0;
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(name) => {
+                res.push(name);
+                depth += 1;
+            }
+            Component::ParentDir if depth > 0 => {
+                res.pop();
+                depth -= 1;
+            }
+            Component::ParentDir => Err(outside(path))?,
+            Component::RootDir | Component::Prefix(_) => {
+                res = root.to_path_buf();
+                depth = 0;
+            }
+            Component::CurDir => {}
+        }
+    }
+    Ok(res)
+}
+
+/// The host of the running process.
+pub struct OsHost {
+    args: Vec<String>,
+    root: Option<PathBuf>,
+    clock: Clock,
+    rng: Rng,
+}
+
+impl OsHost {
+    pub fn new(args: Vec<String>) -> Self {
+        let clock = Clock::Real { start: Instant::now() };
+        let rng = Rng(clock.wall() as u64);
+        OsHost { args, root: None, clock, rng }
+    }
+    /// Runs on a fake clock starting at `start` milliseconds since the epoch.
+    pub fn fake_clock(mut self, start: i64) -> Self {
+        self.clock = Clock::Fake { start, elapsed: 0 };
+        self
+    }
+    /// Draws the random numbers from the sequence given by `seed`.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.rng = Rng(seed);
+        self
+    }
+    /// Confines the file system operations to the directory `root`.
+    pub fn sandbox(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+    fn path(&self, path: &str) -> io::Result<PathBuf> {
+        let Some(root) = &self.root else { return Ok(PathBuf::from(path)) };
+        let res = confine(root, path)?;
+        // the part of the path that exists, links included, must really be
+        // under the root; what's missing is created in there by the operation
+        let existing =
+            res.ancestors().find(|prefix| prefix.symlink_metadata().is_ok()).unwrap_or(root);
+        if !existing.canonicalize()?.starts_with(root.canonicalize()?) {
+            Err(outside(path))?
+        }
+        Ok(res)
+    }
+}
+
+impl Host for OsHost {
+    fn write_str(&mut self, s: &str) -> io::Result<()> {
+        io::stdout().write_all(s.as_bytes())
+    }
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        io::stdin().lock().read_line(&mut line)?;
+        Ok(line)
+    }
+    fn read_to_end(&mut self) -> io::Result<String> {
+        let mut s = String::new();
+        io::stdin().lock().read_to_string(&mut s)?;
+        Ok(s)
+    }
+    fn args(&self) -> &[String] {
+        &self.args
+    }
+    fn open_file(&mut self, path: &str) -> io::Result<()> {
+        fs::OpenOptions::new().create(true).append(true).open(self.path(path)?).map(|_| ())
+    }
+    fn read_file(&mut self, path: &str) -> io::Result<String> {
+        fs::read_to_string(self.path(path)?)
+    }
+    fn write_file(&mut self, path: &str, contents: &str) -> io::Result<()> {
+        fs::write(self.path(path)?, contents)
+    }
+    fn append_file(&mut self, path: &str, contents: &str) -> io::Result<()> {
+        let mut file = fs::OpenOptions::new().create(true).append(true).open(self.path(path)?)?;
+        file.write_all(contents.as_bytes())
+    }
+    fn exists(&mut self, path: &str) -> io::Result<bool> {
+        self.path(path)?.try_exists()
+    }
+    fn list_dir(&mut self, path: &str) -> io::Result<Vec<String>> {
+        let mut names = Vec::new();
+        for entry in fs::read_dir(self.path(path)?)? {
+            names.push(entry?.file_name().to_string_lossy().into_owned());
+        }
+        names.sort();
+        Ok(names)
+    }
+    fn remove_file(&mut self, path: &str) -> io::Result<()> {
+        fs::remove_file(self.path(path)?)
+    }
+    fn write_err(&mut self, s: &str) -> io::Result<()> {
+        io::stderr().write_all(s.as_bytes())
+    }
+    fn env_var(&mut self, name: &str) -> Option<String> {
+        std::env::var(name).ok()
+    }
+    fn wall_clock(&mut self) -> i64 {
+        self.clock.wall()
+    }
+    fn monotonic_clock(&mut self) -> i64 {
+        self.clock.monotonic()
+    }
+    fn sleep(&mut self, ms: u64) {
+        self.clock.sleep(ms)
+    }
+    fn random(&mut self) -> u64 {
+        self.rng.next()
+    }
+}
+
+/// A host reading its input from a string and collecting its output, with an
+/// in-memory file system rooted at `/`. It runs on a fake clock starting at
+/// the epoch and seeds its random numbers with 0 unless told otherwise.
+#[derive(Default)]
+pub struct MockHost {
+    input: Cursor<String>,
+    output: String,
+    errors: String,
+    args: Vec<String>,
+    files: BTreeMap<PathBuf, String>,
+    env: HashMap<String, String>,
+    clock: Clock,
+    rng: Rng,
+}
+
+impl MockHost {
+    pub fn new(input: impl Into<String>, args: Vec<String>) -> Self {
+        MockHost { input: Cursor::new(input.into()), args, ..Default::default() }
+    }
+    /// Adds a file, along with the directories leading to it.
+    pub fn with_file(mut self, path: &str, contents: impl Into<String>) -> Self {
+        let path = self.path(path).expect("file outside of the mock file system");
+        self.files.insert(path, contents.into());
+        self
+    }
+    /// Sets the environment variable `name`.
+    pub fn with_env(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.env.insert(name.to_string(), value.into());
+        self
+    }
+    /// Starts the clock at `start` milliseconds since the epoch.
+    pub fn with_clock(mut self, start: i64) -> Self {
+        self.clock = Clock::Fake { start, elapsed: 0 };
+        self
+    }
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Rng(seed);
+        self
+    }
+    /// Everything written to the standard output so far.
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+    /// Everything written to the standard error so far.
+    pub fn errors(&self) -> &str {
+        &self.errors
+    }
+    /// The contents of the file at `path`, if any.
+    pub fn file(&self, path: &str) -> Option<&str> {
+        self.files.get(&self.path(path).ok()?).map(String::as_str)
+    }
+    fn path(&self, path: &str) -> io::Result<PathBuf> {
+        confine(Path::new("/"), path)
+    }
+    fn not_found(path: &str) -> io::Error {
+        io::Error::new(io::ErrorKind::NotFound, format!("{} not found", path))
+    }
+    /// Directories exist as long as some file is in them.
+    fn is_dir(&self, dir: &Path) -> bool {
+        dir == Path::new("/") || self.files.keys().any(|file| file.starts_with(dir) && file != dir)
+    }
+}
+
+impl Host for MockHost {
+    fn write_str(&mut self, s: &str) -> io::Result<()> {
+        self.output.push_str(s);
+        Ok(())
+    }
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        self.input.read_line(&mut line)?;
+        Ok(line)
+    }
+    fn read_to_end(&mut self) -> io::Result<String> {
+        let mut s = String::new();
+        self.input.read_to_string(&mut s)?;
+        Ok(s)
+    }
+    fn args(&self) -> &[String] {
+        &self.args
+    }
+    fn open_file(&mut self, path: &str) -> io::Result<()> {
+        let old = self.read_file(path).unwrap_or_default();
+        self.write_file(path, &old)
+    }
+    fn read_file(&mut self, path: &str) -> io::Result<String> {
+        self.files.get(&self.path(path)?).cloned().ok_or_else(|| Self::not_found(path))
+    }
+    fn write_file(&mut self, path: &str, contents: &str) -> io::Result<()> {
+        let file = self.path(path)?;
+        if self.is_dir(&file) {
+            Err(io::Error::new(io::ErrorKind::Other, format!("{} is a directory", path)))?
+        }
+        self.files.insert(file, contents.to_string());
+        Ok(())
+    }
+    fn append_file(&mut self, path: &str, contents: &str) -> io::Result<()> {
+        let old = self.read_file(path).unwrap_or_default();
+        self.write_file(path, &(old + contents))
+    }
+    fn exists(&mut self, path: &str) -> io::Result<bool> {
+        let path = self.path(path)?;
+        Ok(self.files.contains_key(&path) || self.is_dir(&path))
+    }
+    fn list_dir(&mut self, path: &str) -> io::Result<Vec<String>> {
+        let dir = self.path(path)?;
+        if !self.is_dir(&dir) {
+            Err(Self::not_found(path))?
+        }
+        let mut names: Vec<_> = (self.files.keys())
+            .filter_map(|file| file.strip_prefix(&dir).ok()?.iter().next())
+            .map(|name| name.to_string_lossy().into_owned())
+            .collect();
+        names.dedup();
+        Ok(names)
+    }
+    fn remove_file(&mut self, path: &str) -> io::Result<()> {
+        self.files.remove(&self.path(path)?).map(|_| ()).ok_or_else(|| Self::not_found(path))
+    }
+    fn write_err(&mut self, s: &str) -> io::Result<()> {
+        self.errors.push_str(s);
+        Ok(())
+    }
+    fn env_var(&mut self, name: &str) -> Option<String> {
+        self.env.get(name).cloned()
+    }
+    fn wall_clock(&mut self) -> i64 {
+        self.clock.wall()
+    }
+    fn monotonic_clock(&mut self) -> i64 {
+        self.clock.monotonic()
+    }
+    fn sleep(&mut self, ms: u64) {
+        self.clock.sleep(ms)
+    }
+    fn random(&mut self) -> u64 {
+        self.rng.next()
+    }
+}
@@ -0,0 +1,398 @@
+//! Recording the interactions of a program with its host, and replaying them.
+//!
+//! A [`Recorder`] wraps a host and logs every call the builtins make to it,
+//! along with what the host answered. A [`Replayer`] reads such a log back
+//! and answers the same calls from it without touching the real OS, checking
+//! that the program does exactly what it did when recorded.
+//!
+//! The log has one event per line: the name of the operation followed by its
+//! inputs and then its outputs, separated by tabs, with tabs, newlines and
+//! backslashes escaped. The first event holds the command line arguments and
+//! the last one the exit code, or the error the program stopped with instead.
+
+use super::host::Host;
+use std::{
+    fmt,
+    io::{self, Write},
+};
+use thiserror::Error;
+
+/// The first point where a replayed run differs from the recorded one.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Replay diverged at line {line} of the log: expected {expected}, found {found}")]
+pub struct Divergence {
+    pub line: usize,
+    pub expected: String,
+    pub found: String,
+}
+
+/// A logged call to the host.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Event {
+    op: String,
+    fields: Vec<String>,
+}
+
+impl Event {
+    fn new(op: &str, inputs: &[&str], outputs: Vec<String>) -> Self {
+        let fields = inputs.iter().map(|s| s.to_string()).chain(outputs).collect();
+        Event { op: op.to_string(), fields }
+    }
+    fn to_line(&self) -> String {
+        let mut line = escape(&self.op);
+        for field in &self.fields {
+            line.push('\t');
+            line.push_str(&escape(field));
+        }
+        line.push('\n');
+        line
+    }
+    fn parse(line: &str) -> Self {
+        let mut fields = line.split('\t').map(unescape);
+        let op = fields.next().unwrap_or_default();
+        Event { op, fields: fields.collect() }
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.op)?;
+        for field in &self.fields {
+            write!(f, " {:?}", field)?;
+        }
+        Ok(())
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+fn unescape(s: &str) -> String {
+    let mut res = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        res.push(match c {
+            '\\' => match chars.next() {
+                Some('t') => '\t',
+                Some('n') => '\n',
+                Some('r') => '\r',
+                Some(c) => c,
+                None => '\\',
+            },
+            c => c,
+        });
+    }
+    res
+}
+
+/* ------------------------------- Encodings -------------------------------- */
+
+fn encode_result(res: &io::Result<Vec<String>>) -> Vec<String> {
+    match res {
+        Ok(outputs) => std::iter::once("ok".to_string()).chain(outputs.iter().cloned()).collect(),
+        Err(e) => vec!["err".to_string(), e.to_string()],
+    }
+}
+
+fn decode_result(outputs: &[String]) -> io::Result<Vec<String>> {
+    match outputs.split_first() {
+        Some((tag, outputs)) if tag == "ok" => Ok(outputs.to_vec()),
+        Some((tag, [msg])) if tag == "err" => {
+            Err(io::Error::new(io::ErrorKind::Other, msg.clone()))
+        }
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData, "malformed result in the log")),
+    }
+}
+
+fn decode_bool(outputs: Vec<String>) -> io::Result<bool> {
+    match outputs.as_slice() {
+        [b] => b.parse().map_err(|_| io::Error::new(io::ErrorKind::InvalidData, b.clone())),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData, "malformed boolean in the log")),
+    }
+}
+
+fn single(outputs: Vec<String>) -> String {
+    outputs.into_iter().next().unwrap_or_default()
+}
+
+/* -------------------------------- Recorder -------------------------------- */
+
+/// A host logging every interaction with the host it wraps to `log`.
+pub struct Recorder<H, W> {
+    inner: H,
+    log: W,
+    error: Option<io::Error>,
+}
+
+impl<H: Host, W: Write> Recorder<H, W> {
+    pub fn new(inner: H, log: W) -> Self {
+        let args = inner.args().to_vec();
+        let mut recorder = Recorder { inner, log, error: None };
+        recorder.log(Event { op: "args".to_string(), fields: args });
+        recorder
+    }
+    /// Logs the exit code and hands back the host and the log. Fails if any
+    /// event couldn't be written.
+    pub fn exit(self, code: i32) -> io::Result<(H, W)> {
+        self.finish(Event::new("exit", &[&code.to_string()], vec![]))
+    }
+    /// Logs the error the program stopped with instead of exiting, like
+    /// [`Recorder::exit`].
+    pub fn fail(self, error: &str) -> io::Result<(H, W)> {
+        self.finish(Event::new("error", &[error], vec![]))
+    }
+    fn finish(mut self, event: Event) -> io::Result<(H, W)> {
+        self.log(event);
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok((self.inner, self.log)),
+        }
+    }
+    fn log(&mut self, event: Event) {
+        if self.error.is_none() {
+            if let Err(e) = self.log.write_all(event.to_line().as_bytes()) {
+                self.error = Some(e);
+            }
+        }
+    }
+    fn record<T>(
+        &mut self, op: &str, inputs: &[&str], res: io::Result<T>,
+        encode: impl FnOnce(&T) -> Vec<String>,
+    ) -> io::Result<T> {
+        let outputs = encode_result(&res.as_ref().map(encode).map_err(clone_error));
+        self.log(Event::new(op, inputs, outputs));
+        res
+    }
+}
+
+fn clone_error(e: &io::Error) -> io::Error {
+    io::Error::new(e.kind(), e.to_string())
+}
+
+impl<H: Host, W: Write> Host for Recorder<H, W> {
+    fn write_str(&mut self, s: &str) -> io::Result<()> {
+        let res = self.inner.write_str(s);
+        self.record("write_str", &[s], res, |_| vec![])
+    }
+    fn read_line(&mut self) -> io::Result<String> {
+        let res = self.inner.read_line();
+        self.record("read_line", &[], res, |s| vec![s.clone()])
+    }
+    fn read_to_end(&mut self) -> io::Result<String> {
+        let res = self.inner.read_to_end();
+        self.record("read_to_end", &[], res, |s| vec![s.clone()])
+    }
+    fn args(&self) -> &[String] {
+        self.inner.args()
+    }
+    fn open_file(&mut self, path: &str) -> io::Result<()> {
+        let res = self.inner.open_file(path);
+        self.record("open_file", &[path], res, |_| vec![])
+    }
+    fn read_file(&mut self, path: &str) -> io::Result<String> {
+        let res = self.inner.read_file(path);
+        self.record("read_file", &[path], res, |s| vec![s.clone()])
+    }
+    fn write_file(&mut self, path: &str, contents: &str) -> io::Result<()> {
+        let res = self.inner.write_file(path, contents);
+        self.record("write_file", &[path, contents], res, |_| vec![])
+    }
+    fn append_file(&mut self, path: &str, contents: &str) -> io::Result<()> {
+        let res = self.inner.append_file(path, contents);
+        self.record("append_file", &[path, contents], res, |_| vec![])
+    }
+    fn exists(&mut self, path: &str) -> io::Result<bool> {
+        let res = self.inner.exists(path);
+        self.record("exists", &[path], res, |b| vec![b.to_string()])
+    }
+    fn list_dir(&mut self, path: &str) -> io::Result<Vec<String>> {
+        let res = self.inner.list_dir(path);
+        self.record("list_dir", &[path], res, |names| names.clone())
+    }
+    fn remove_file(&mut self, path: &str) -> io::Result<()> {
+        let res = self.inner.remove_file(path);
+        self.record("remove_file", &[path], res, |_| vec![])
+    }
+    fn write_err(&mut self, s: &str) -> io::Result<()> {
+        let res = self.inner.write_err(s);
+        self.record("write_err", &[s], res, |_| vec![])
+    }
+    fn env_var(&mut self, name: &str) -> Option<String> {
+        let res = self.inner.env_var(name);
+        self.log(Event::new("env_var", &[name], res.iter().cloned().collect()));
+        res
+    }
+    fn wall_clock(&mut self) -> i64 {
+        let res = self.inner.wall_clock();
+        self.log(Event::new("wall_clock", &[], vec![res.to_string()]));
+        res
+    }
+    fn monotonic_clock(&mut self) -> i64 {
+        let res = self.inner.monotonic_clock();
+        self.log(Event::new("monotonic_clock", &[], vec![res.to_string()]));
+        res
+    }
+    fn sleep(&mut self, ms: u64) {
+        self.inner.sleep(ms);
+        self.log(Event::new("sleep", &[&ms.to_string()], vec![]));
+    }
+    fn random(&mut self) -> u64 {
+        let res = self.inner.random();
+        self.log(Event::new("random", &[], vec![res.to_string()]));
+        res
+    }
+}
+
+/* -------------------------------- Replayer -------------------------------- */
+
+/// A host answering from a recorded log. The standard output and error are
+/// still written to the wrapped host, so that the replayed run can be watched;
+/// everything else only comes from the log.
+///
+/// After the first divergence the log is no longer consulted: reads see the
+/// end of their input, the file system fails, and the clocks and the random
+/// numbers read zero.
+pub struct Replayer<H> {
+    inner: H,
+    args: Vec<String>,
+    events: std::vec::IntoIter<Event>,
+    line: usize,
+    divergence: Option<Divergence>,
+}
+
+impl<H: Host> Replayer<H> {
+    pub fn new(log: &str, inner: H) -> io::Result<Self> {
+        let mut events = log.lines().map(Event::parse);
+        let args = match events.next() {
+            Some(Event { op, fields }) if op == "args" => fields,
+            _ => {
+                Err(io::Error::new(io::ErrorKind::InvalidData, "the log doesn't start with args"))?
+            }
+        };
+        let events = events.collect::<Vec<_>>().into_iter();
+        Ok(Replayer { inner, args, events, line: 2, divergence: None })
+    }
+    /// Checks that the program exits with the recorded code and that nothing
+    /// recorded is left over, handing back the wrapped host.
+    pub fn exit(self, code: i32) -> Result<H, Divergence> {
+        self.finish("exit", &code.to_string())
+    }
+    /// Checks that the program stops with the recorded error, like
+    /// [`Replayer::exit`].
+    pub fn fail(self, error: &str) -> Result<H, Divergence> {
+        self.finish("error", error)
+    }
+    fn finish(mut self, op: &str, input: &str) -> Result<H, Divergence> {
+        self.replay(op, &[input]);
+        if self.divergence.is_none() {
+            if let Some(event) = self.events.next() {
+                self.diverge(event.to_string(), "the end of the program".to_string());
+            }
+        }
+        match self.divergence {
+            Some(divergence) => Err(divergence),
+            None => Ok(self.inner),
+        }
+    }
+    /// The first divergence so far, if any.
+    pub fn divergence(&self) -> Option<&Divergence> {
+        self.divergence.as_ref()
+    }
+    fn diverge(&mut self, expected: String, found: String) {
+        self.divergence = Some(Divergence { line: self.line, expected, found });
+    }
+    /// The outputs recorded for the call `op` with `inputs`, if the call is
+    /// the one the log expects next.
+    fn replay(&mut self, op: &str, inputs: &[&str]) -> Option<Vec<String>> {
+        if self.divergence.is_some() {
+            return None;
+        }
+        let found = Event::new(op, inputs, vec![]);
+        let Some(mut event) = self.events.next() else {
+            self.diverge("the end of the program".to_string(), found.to_string());
+            return None;
+        };
+        let matches = event.op == op
+            && event.fields.len() >= inputs.len()
+            && event.fields.iter().zip(inputs).all(|(field, input)| field == input);
+        if !matches {
+            event.fields.truncate(inputs.len());
+            self.diverge(event.to_string(), found.to_string());
+            return None;
+        }
+        self.line += 1;
+        Some(event.fields.split_off(inputs.len()))
+    }
+    fn replay_io(&mut self, op: &str, inputs: &[&str]) -> io::Result<Vec<String>> {
+        match self.replay(op, inputs) {
+            Some(outputs) => decode_result(&outputs),
+            None => Err(io::Error::new(io::ErrorKind::Other, "replay diverged")),
+        }
+    }
+    fn replay_int<T: std::str::FromStr + Default>(&mut self, op: &str) -> T {
+        self.replay(op, &[]).and_then(|outputs| single(outputs).parse().ok()).unwrap_or_default()
+    }
+}
+
+impl<H: Host> Host for Replayer<H> {
+    fn write_str(&mut self, s: &str) -> io::Result<()> {
+        self.replay("write_str", &[s]);
+        self.inner.write_str(s)
+    }
+    fn read_line(&mut self) -> io::Result<String> {
+        match self.replay("read_line", &[]) {
+            Some(outputs) => decode_result(&outputs).map(single),
+            None => Ok(String::new()),
+        }
+    }
+    fn read_to_end(&mut self) -> io::Result<String> {
+        match self.replay("read_to_end", &[]) {
+            Some(outputs) => decode_result(&outputs).map(single),
+            None => Ok(String::new()),
+        }
+    }
+    fn args(&self) -> &[String] {
+        &self.args
+    }
+    fn open_file(&mut self, path: &str) -> io::Result<()> {
+        self.replay_io("open_file", &[path]).map(|_| ())
+    }
+    fn read_file(&mut self, path: &str) -> io::Result<String> {
+        self.replay_io("read_file", &[path]).map(single)
+    }
+    fn write_file(&mut self, path: &str, contents: &str) -> io::Result<()> {
+        self.replay_io("write_file", &[path, contents]).map(|_| ())
+    }
+    fn append_file(&mut self, path: &str, contents: &str) -> io::Result<()> {
+        self.replay_io("append_file", &[path, contents]).map(|_| ())
+    }
+    fn exists(&mut self, path: &str) -> io::Result<bool> {
+        self.replay_io("exists", &[path]).and_then(decode_bool)
+    }
+    fn list_dir(&mut self, path: &str) -> io::Result<Vec<String>> {
+        self.replay_io("list_dir", &[path])
+    }
+    fn remove_file(&mut self, path: &str) -> io::Result<()> {
+        self.replay_io("remove_file", &[path]).map(|_| ())
+    }
+    fn write_err(&mut self, s: &str) -> io::Result<()> {
+        self.replay("write_err", &[s]);
+        self.inner.write_err(s)
+    }
+    fn env_var(&mut self, name: &str) -> Option<String> {
+        self.replay("env_var", &[name])?.into_iter().next()
+    }
+    fn wall_clock(&mut self) -> i64 {
+        self.replay_int("wall_clock")
+    }
+    fn monotonic_clock(&mut self) -> i64 {
+        self.replay_int("monotonic_clock")
+    }
+    fn sleep(&mut self, ms: u64) {
+        self.replay("sleep", &[&ms.to_string()]);
+    }
+    fn random(&mut self) -> u64 {
+        self.replay_int("random")
+    }
+}
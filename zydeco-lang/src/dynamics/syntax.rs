@@ -1,10 +1,8 @@
 use crate::syntax::*;
 pub use crate::{library::syntax as ls, syntax::Env, utils::fmt::FmtArgs};
+use super::host::Host;
 use im::Vector;
-use std::{
-    io::{BufRead, Write},
-    rc::Rc,
-};
+use std::rc::Rc;
 use zydeco_derive::{FmtArgs, IntoEnum};
 
 #[derive(Clone)]
@@ -13,11 +11,33 @@ pub struct Thunk {
     pub env: Env<TermV, SemVal>,
 }
 
+/// A delimiter created by `new_prompt`, marking the stack with `push_prompt`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Prompt(pub usize);
+
+/// The slice of the stack captured by `take_subcont`, outermost frame first.
+#[derive(Clone)]
+pub struct SubCont(pub Vector<SemComp>);
+
+/// A mutable cell on the runtime heap, created by `ref_new`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Ref(pub usize);
+
+/// A mutable array on the runtime heap, created by `array_new`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Array(pub usize);
+
 #[derive(IntoEnum, FmtArgs, Clone)]
 pub enum SemVal {
     Thunk(Thunk),
     Ctor(Ctor<CtorV, TV>),
     Literal(Literal),
+    Prompt(Prompt),
+    SubCont(SubCont),
+    Ref(Ref),
+    Array(Array),
+    /// What a hole evaluates to; the program stops once it's used.
+    Hole(TermHole),
 }
 type TV = Rc<SemVal>;
 impl ValueT for SemVal {}
@@ -26,6 +46,8 @@ impl ValueT for SemVal {}
 pub enum ProgKont {
     Ret(SemVal),
     ExitCode(i32),
+    /// The program reached a hole.
+    Hole(TermHole),
 }
 impl ComputationT for ProgKont {}
 
@@ -35,6 +57,9 @@ impl ComputationT for ProgKont {}
 pub enum SemComp {
     Kont(Rc<ls::SynComp>, Env<TermV, SemVal>, TermV),
     Dtor(DtorV, Vec<Rc<SemVal>>),
+    Prompt(Prompt),
+    /// An installed effect handler, with the environment it was installed in.
+    Handler(Rc<ls::Handle<TermV, TermV, ls::RcComp>>, Env<TermV, SemVal>),
 }
 
 use std::fmt;
@@ -50,16 +75,35 @@ impl fmt::Debug for SemComp {
                 dtor,
                 args.into_iter().map(|arg| arg.fmt()).collect::<Vec<_>>().join(", ")
             ),
+            SemComp::Prompt(Prompt(p)) => write!(f, "Prompt(#{})", p),
+            SemComp::Handler(handle, _) => write!(
+                f,
+                "Handler({})",
+                handle.arms.iter().map(|arm| arm.op.to_string()).collect::<Vec<_>>().join(", ")
+            ),
         }
     }
 }
 
 pub struct Runtime<'rt> {
-    pub input: &'rt mut (dyn BufRead),
-    pub output: &'rt mut (dyn Write),
-    pub args: &'rt [String],
+    pub host: &'rt mut dyn Host,
     pub stack: Vector<SemComp>,
     pub env: Env<TermV, SemVal>,
+    /// The number of prompts created so far.
+    pub prompts: usize,
+    /// The cells and arrays created so far.
+    pub heap: Heap,
+}
+
+/// The cells and arrays `Ref` and `Array` index into. They are freed with the
+/// heap rather than one by one, so a cell holding a thunk that refers back to
+/// it doesn't leak once the program is done.
+#[derive(Clone, Default)]
+pub struct Heap {
+    /// The contents of the cells created by `ref_new`.
+    pub refs: Vec<SemVal>,
+    /// The contents of the arrays created by `array_new`.
+    pub arrays: Vec<Vec<SemVal>>,
 }
 
 /* --------------------------------- Module --------------------------------- */
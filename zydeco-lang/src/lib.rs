@@ -3,6 +3,9 @@
 #![allow(clippy::clone_on_copy)]
 #![allow(clippy::mutable_key_type)]
 
+// lets the derive macros refer to this crate by name from within it
+extern crate self as zydeco_lang;
+
 pub mod syntax;
 
 pub mod parse {
@@ -47,6 +50,8 @@ pub mod core {
 pub mod library {
     pub mod syntax;
     pub mod builtins;
+    pub mod err;
+    pub mod convert;
     mod link;
     mod impls;
     mod fmt;
@@ -55,11 +60,21 @@ pub mod library {
 pub mod dynamics {
     pub mod syntax;
     pub mod eval;
+    pub mod host;
+    pub mod replay;
     mod fmt;
 
     pub use eval::Eval;
 }
 
+pub mod codegen {
+    pub mod err;
+    mod prune;
+    mod unicode;
+    pub mod c;
+    pub mod wasm;
+}
+
 pub mod utils {
     pub mod fmt;
     pub mod span;
@@ -1,54 +1,273 @@
-use super::syntax::*;
-use crate::{rc, utils::span::SpanInfo};
-use std::collections::HashMap;
+use super::{err::LinkError, syntax::*};
+use crate::{
+    dynamics::syntax as ds,
+    parse::{err::ParseError, lexer::Lexer, parser::TypeSpanParser},
+    rc,
+    statics::{syntax as ss, Elaboration},
+    utils::span::{FileInfo, Span, SpanHolder},
+};
+use std::{collections::HashMap, path::PathBuf, rc::Rc};
 
-pub(super) struct Builtin {
+/// A function implemented by the host that `extern def` declarations can link
+/// against.
+#[derive(Clone)]
+pub struct Builtin {
     name: &'static str,
     arity: u64,
-    behavior: Box<PrimComp>,
+    ty: Span<ss::Type>,
+    behavior: PrimComp,
 }
 
 impl Builtin {
-    fn new(name: &'static str, arity: u64, behavior: PrimComp) -> Self {
-        Builtin { name, arity, behavior: Box::new(behavior) }
-    }
-    fn gen(self) -> (TermV, SynVal) {
-        let Builtin { name, arity, behavior } = self;
-        (
-            TermV::new(name.to_string(), SpanInfo::dummy()),
-            Thunk(rc!(Prim { arity, body: *behavior }.into())).into(),
-        )
-    }
-    // To add new builtin functions, provide impl and add declaration to std.zydeco
-    pub(super) fn std_library() -> HashMap<TermV, SynVal> {
+    /// A builtin declared in Zydeco with type `ty`, which must be a thunk of a
+    /// computation taking the arguments of the builtin.
+    ///
+    /// The arguments are passed in order; the computation returned runs in
+    /// place of the builtin, and an `Err` exits the program with that code.
+    pub fn try_new<F>(name: &'static str, ty: &str, behavior: F) -> Result<Self, LinkError>
+    where
+        F: Fn(Vec<ds::SemVal>, &mut ds::Runtime) -> Result<SynComp, i32> + 'static,
+    {
+        let ty = Self::parse_type(name, ty)
+            .map_err(|reason| LinkError::MalformedBuiltinType { name: name.to_string(), reason })?;
+        let arity = Self::arity_of(ty.inner_ref())
+            .ok_or_else(|| LinkError::NonThunkBuiltin { name: name.to_string() })?;
+        Ok(Builtin { name, arity, ty, behavior: Rc::new(behavior) })
+    }
+    /// Like [`Builtin::try_new`], for types known to be well-formed, such as
+    /// those of std.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ty` is not a well-formed thunk type.
+    pub fn new<F>(name: &'static str, ty: &str, behavior: F) -> Self
+    where
+        F: Fn(Vec<ds::SemVal>, &mut ds::Runtime) -> Result<SynComp, i32> + 'static,
+    {
+        Self::try_new(name, ty, behavior).unwrap_or_else(|e| panic!("{}", e))
+    }
+    fn parse_type(name: &str, source: &str) -> Result<Span<ss::Type>, String> {
+        let file_info = FileInfo::new(source, Rc::new(PathBuf::from(format!("<{}>", name))));
+        let ty = TypeSpanParser::new()
+            .parse(source, Lexer::new(source))
+            .map_err(|e| format!("{}", ParseError(e, &file_info)))?
+            .span_map(|span| {
+                span.set_info(&file_info);
+            });
+        ty.try_map(Elaboration::elab).map_err(|e| format!("{}", e))
+    }
+    /// The number of arguments of a thunk of (possibly polymorphic) functions;
+    /// type arguments are erased and not counted.
+    fn arity_of(ty: &ss::Type) -> Option<u64> {
+        fn args(ty: &ss::Type) -> u64 {
+            match &ty.synty {
+                ss::SynType::TypeApp(TypeApp { tvar, args: tys }) if tvar.name() == "Fn" => {
+                    1 + args(tys[1].inner_ref())
+                }
+                ss::SynType::Forall(Forall { param: _, ty }) => args(ty.inner_ref()),
+                ss::SynType::Effectful(Effectful { effs: _, ty }) => args(ty.inner_ref()),
+                _ => 0,
+            }
+        }
+        match &ty.synty {
+            ss::SynType::TypeApp(TypeApp { tvar, args: tys }) if tvar.name() == "Thunk" => {
+                Some(args(tys.first()?.inner_ref()))
+            }
+            _ => None,
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+    /// The number of arguments, as given by the type.
+    pub fn arity(&self) -> u64 {
+        self.arity
+    }
+    /// The Zydeco type the builtin is declared with.
+    pub fn ty(&self) -> &Span<ss::Type> {
+        &self.ty
+    }
+    pub(super) fn gen(&self) -> SynVal {
+        let Builtin { name, arity, ty: _, behavior } = self;
+        Thunk(rc!(Prim { name, arity: *arity, body: behavior.clone() }.into())).into()
+    }
+}
+
+/// The builtins a program is linked against, by name.
+#[derive(Clone)]
+pub struct Registry {
+    builtins: HashMap<&'static str, Builtin>,
+}
+
+impl Registry {
+    /// A registry without any builtins, not even those of the standard library.
+    pub fn empty() -> Self {
+        Registry { builtins: HashMap::new() }
+    }
+    /// A registry with the builtins of the standard library.
+    pub fn std() -> Self {
+        let mut registry = Self::empty();
+        for builtin in Self::std_library() {
+            registry.register(builtin);
+        }
+        registry
+    }
+    /// Adds a builtin, replacing any registered under the same name.
+    pub fn register(&mut self, builtin: Builtin) -> &mut Self {
+        self.builtins.insert(builtin.name, builtin);
+        self
+    }
+    pub fn get(&self, name: &str) -> Option<&Builtin> {
+        self.builtins.get(name)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &Builtin> {
+        self.builtins.values()
+    }
+    // To add new builtin functions, provide impl and type here and add the
+    // declaration to std.zydeco; the declared type is checked against this one
+    fn std_library() -> Vec<Builtin> {
         use super::impls::*;
         [
-            Builtin::new("add", 2, add),
-            Builtin::new("sub", 2, sub),
-            Builtin::new("mul", 2, mul),
-            Builtin::new("div", 2, div),
-            Builtin::new("mod", 2, modulo),
-            Builtin::new("int_eq", 2, int_eq),
-            Builtin::new("int_lt", 2, int_lt),
-            Builtin::new("int_gt", 2, int_gt),
-            Builtin::new("str_length", 1, str_length),
-            Builtin::new("str_append", 2, str_append),
-            Builtin::new("str_split_once", 2, str_split_once),
-            Builtin::new("str_split_n", 2, str_split_n),
-            Builtin::new("str_eq", 2, str_eq),
-            Builtin::new("str_index", 2, str_index),
-            Builtin::new("int_to_str", 1, int_to_str),
-            Builtin::new("char_to_str", 1, char_to_str),
-            Builtin::new("char_to_int", 1, char_to_int),
-            Builtin::new("str_to_int", 1, str_to_int),
-            Builtin::new("write_str", 2, write_str),
-            Builtin::new("read_line", 1, read_line),
-            Builtin::new("read_till_eof", 1, read_till_eof),
-            Builtin::new("exit", 1, exit),
-            Builtin::new("arg_list", 1, arg_list),
+            Builtin::new("add", "Thunk(Int -> Int -> Ret Int)", add),
+            Builtin::new("sub", "Thunk(Int -> Int -> Ret Int)", sub),
+            Builtin::new("mul", "Thunk(Int -> Int -> Ret Int)", mul),
+            Builtin::new("div", "Thunk(Int -> Int -> Ret Int)", div),
+            Builtin::new("mod", "Thunk(Int -> Int -> Ret Int)", modulo),
+            Builtin::new("int_eq", "Thunk(Int -> Int -> Ret Bool)", int_eq),
+            Builtin::new("int_lt", "Thunk(Int -> Int -> Ret Bool)", int_lt),
+            Builtin::new("int_gt", "Thunk(Int -> Int -> Ret Bool)", int_gt),
+            Builtin::new("str_length", "Thunk(String -> Ret Int)", str_length),
+            Builtin::new("str_append", "Thunk(String -> String -> Ret String)", str_append),
+            Builtin::new(
+                "str_split_once",
+                "Thunk(String -> Char -> Ret (Option (Pair String String)))",
+                str_split_once,
+            ),
+            Builtin::new(
+                "str_split_n",
+                "Thunk(String -> Int -> Ret (Option (Pair String String)))",
+                str_split_n,
+            ),
+            Builtin::new(
+                "str_substring",
+                "Thunk(String -> Int -> Int -> Ret String)",
+                str_substring,
+            ),
+            Builtin::new("str_eq", "Thunk(String -> String -> Ret Bool)", str_eq),
+            Builtin::new("str_index", "Thunk(String -> Int -> Ret (Option Char))", str_index),
+            Builtin::new("str_find", "Thunk(String -> String -> Ret (Option Int))", str_find),
+            Builtin::new("str_starts_with", "Thunk(String -> String -> Ret Bool)", str_starts_with),
+            Builtin::new("str_ends_with", "Thunk(String -> String -> Ret Bool)", str_ends_with),
+            Builtin::new("str_lt", "Thunk(String -> String -> Ret Bool)", str_lt),
+            Builtin::new(
+                "str_replace",
+                "Thunk(String -> String -> String -> Ret String)",
+                str_replace,
+            ),
+            Builtin::new("str_trim", "Thunk(String -> Ret String)", str_trim),
+            Builtin::new("str_to_upper", "Thunk(String -> Ret String)", str_to_upper),
+            Builtin::new("str_to_lower", "Thunk(String -> Ret String)", str_to_lower),
+            Builtin::new("str_split", "Thunk(String -> String -> Ret (List String))", str_split),
+            Builtin::new("str_join", "Thunk(List String -> String -> Ret String)", str_join),
+            Builtin::new("str_to_list", "Thunk(String -> Ret (List Char))", str_to_list),
+            Builtin::new("list_to_str", "Thunk(List Char -> Ret String)", list_to_str),
+            Builtin::new("int_to_str", "Thunk(Int -> Ret String)", int_to_str),
+            Builtin::new("char_to_str", "Thunk(Char -> Ret String)", char_to_str),
+            Builtin::new("char_to_int", "Thunk(Char -> Ret Int)", char_to_int),
+            Builtin::new("int_to_char", "Thunk(Int -> Ret (Option Char))", int_to_char),
+            Builtin::new("str_to_int", "Thunk(String -> Ret (Option Int))", str_to_int),
+            Builtin::new("write_str", "Thunk(String -> Thunk(OS) -> OS)", write_str),
+            Builtin::new("read_line", "Thunk(Thunk(String -> OS) -> OS)", read_line),
+            Builtin::new("read_till_eof", "Thunk(Thunk(String -> OS) -> OS)", read_till_eof),
+            Builtin::new("exit", "Thunk(Int -> OS)", exit),
+            Builtin::new("arg_list", "Thunk(Thunk(List String -> OS) -> OS)", arg_list),
+            Builtin::new("write_err", "Thunk(String -> Thunk(OS) -> OS)", write_err),
+            Builtin::new("env_var", "Thunk(String -> Thunk(Option String -> OS) -> OS)", env_var),
+            Builtin::new("clock_wall", "Thunk(Thunk(Int -> OS) -> OS)", clock_wall),
+            Builtin::new("clock_monotonic", "Thunk(Thunk(Int -> OS) -> OS)", clock_monotonic),
+            Builtin::new("sleep", "Thunk(Int -> Thunk(OS) -> OS)", sleep),
+            Builtin::new("random_int", "Thunk(Int -> Thunk(Int -> OS) -> OS)", random_int),
+            Builtin::new(
+                "file_open",
+                "Thunk(String -> Thunk(Result Unit String -> OS) -> OS)",
+                file_open,
+            ),
+            Builtin::new(
+                "file_read",
+                "Thunk(String -> Thunk(Result String String -> OS) -> OS)",
+                file_read,
+            ),
+            Builtin::new(
+                "file_write",
+                "Thunk(String -> String -> Thunk(Result Unit String -> OS) -> OS)",
+                file_write,
+            ),
+            Builtin::new(
+                "file_append",
+                "Thunk(String -> String -> Thunk(Result Unit String -> OS) -> OS)",
+                file_append,
+            ),
+            Builtin::new(
+                "file_exists",
+                "Thunk(String -> Thunk(Result Bool String -> OS) -> OS)",
+                file_exists,
+            ),
+            Builtin::new(
+                "file_remove",
+                "Thunk(String -> Thunk(Result Unit String -> OS) -> OS)",
+                file_remove,
+            ),
+            Builtin::new(
+                "dir_list",
+                "Thunk(String -> Thunk(Result (List String) String -> OS) -> OS)",
+                dir_list,
+            ),
+            Builtin::new("new_prompt", "Thunk(forall (B : CType) . Ret (Prompt B))", new_prompt),
+            Builtin::new(
+                "push_prompt",
+                "Thunk(forall (B : CType) . Prompt B -> Thunk B -> B)",
+                push_prompt,
+            ),
+            Builtin::new(
+                "take_subcont",
+                "Thunk(forall (A : CType) (B : CType) . Prompt B -> Thunk(SubCont A B -> B) -> A)",
+                take_subcont,
+            ),
+            Builtin::new(
+                "push_subcont",
+                "Thunk(forall (A : CType) (B : CType) . SubCont A B -> Thunk A -> B)",
+                push_subcont,
+            ),
+            Builtin::new("ref_new", "Thunk(forall (A : VType) . A -> Ret (Ref A))", ref_new),
+            Builtin::new("ref_get", "Thunk(forall (A : VType) . Ref A -> Ret A)", ref_get),
+            Builtin::new("ref_set", "Thunk(forall (A : VType) . Ref A -> A -> Ret Unit)", ref_set),
+            Builtin::new(
+                "array_new",
+                "Thunk(forall (A : VType) . Int -> A -> Ret (Array A))",
+                array_new,
+            ),
+            Builtin::new(
+                "array_get",
+                "Thunk(forall (A : VType) . Array A -> Int -> Ret A)",
+                array_get,
+            ),
+            Builtin::new(
+                "array_set",
+                "Thunk(forall (A : VType) . Array A -> Int -> A -> Ret Unit)",
+                array_set,
+            ),
+            Builtin::new(
+                "array_length",
+                "Thunk(forall (A : VType) . Array A -> Ret Int)",
+                array_length,
+            ),
         ]
-        .into_iter()
-        .map(Builtin::gen)
-        .collect()
+        .into()
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::std()
     }
 }
@@ -0,0 +1,346 @@
+//! Conversions between Rust values and Zydeco runtime values.
+//!
+//! Data types convert by constructor name: `bool` to `Bool`, `Option` to
+//! `Option`, `Result` to `Result`, tuples to `Pair`, `Triple` and `Quadruple`,
+//! `()` to `Unit` and `Vec` to `List`, following the declarations in std.
+//! User-defined types can derive the conversions with
+//! `#[derive(IntoZydeco, FromZydeco)]`, which maps each variant of an enum
+//! (or the struct itself) to the constructor of the same name, and
+//! `#[derive(ZydecoType)]` gives the data type of the same name.
+//!
+//! Integers convert to `Int`, which is an `i64`. Only the Rust integers that
+//! always fit in one convert into it, so `usize` doesn't; an `Int` that
+//! doesn't fit in a Rust integer fails to convert into it.
+
+use super::{err::ConvertError, syntax::*};
+use crate::{
+    dynamics::syntax::SemVal,
+    rc,
+    statics::syntax::Type,
+    utils::{fmt::FmtArgs, span::SpanInfo},
+};
+use std::rc::Rc;
+
+pub use zydeco_derive::{FromZydeco, IntoZydeco, ZydecoType};
+
+pub trait IntoZydeco {
+    fn into_zydeco(self) -> SemVal;
+}
+
+pub trait FromZydeco: Sized {
+    fn from_zydeco(value: &SemVal) -> Result<Self, ConvertError>;
+}
+
+/// The Zydeco value type a Rust type converts to and from.
+pub trait ZydecoType {
+    fn zydeco_type() -> Type;
+}
+
+/// The type built by the type constructor `name` applied to `args`.
+pub fn data_type(name: &'static str, args: Vec<Type>) -> Type {
+    Type::internal(name, args.into_iter().map(|arg| rc!(SpanInfo::dummy().make(arg))).collect())
+}
+
+/// The value built by the constructor `ctor` applied to `args`.
+pub fn ctor(ctor: &str, args: Vec<SemVal>) -> SemVal {
+    let ctorv = CtorV::new(ctor.to_string(), SpanInfo::dummy());
+    Ctor { ctorv, args: args.into_iter().map(|arg| rc!(arg)).collect() }.into()
+}
+
+/// The constructor and arguments of a value of a data type.
+pub fn unctor<'a>(
+    value: &'a SemVal, expected: &str,
+) -> Result<(&'a str, &'a [Rc<SemVal>]), ConvertError> {
+    match value {
+        SemVal::Ctor(Ctor { ctorv, args }) => Ok((ctorv.name(), args)),
+        _ => Err(ConvertError::mismatch(expected, value)),
+    }
+}
+
+impl ConvertError {
+    pub fn mismatch(expected: &str, found: &SemVal) -> Self {
+        ConvertError::Mismatch { expected: expected.to_string(), found: found.fmt() }
+    }
+}
+
+impl IntoZydeco for SemVal {
+    fn into_zydeco(self) -> SemVal {
+        self
+    }
+}
+
+impl FromZydeco for SemVal {
+    fn from_zydeco(value: &SemVal) -> Result<Self, ConvertError> {
+        Ok(value.clone())
+    }
+}
+
+/* --------------------------------- Literal -------------------------------- */
+
+macro_rules! int {
+    ( $ty:ty ) => {
+        impl IntoZydeco for $ty {
+            fn into_zydeco(self) -> SemVal {
+                Literal::Int(i64::from(self)).into()
+            }
+        }
+        from_int!($ty);
+    };
+}
+
+macro_rules! from_int {
+    ( $ty:ty ) => {
+        impl FromZydeco for $ty {
+            fn from_zydeco(value: &SemVal) -> Result<Self, ConvertError> {
+                match value {
+                    SemVal::Literal(Literal::Int(i)) => <$ty>::try_from(*i)
+                        .map_err(|_| ConvertError::mismatch(stringify!($ty), value)),
+                    _ => Err(ConvertError::mismatch("Int", value)),
+                }
+            }
+        }
+        impl ZydecoType for $ty {
+            fn zydeco_type() -> Type {
+                data_type("Int", vec![])
+            }
+        }
+    };
+}
+
+int!(i64);
+int!(i32);
+int!(u32);
+from_int!(usize);
+
+impl IntoZydeco for String {
+    fn into_zydeco(self) -> SemVal {
+        Literal::String(self).into()
+    }
+}
+
+impl IntoZydeco for &str {
+    fn into_zydeco(self) -> SemVal {
+        Literal::String(self.to_string()).into()
+    }
+}
+
+impl FromZydeco for String {
+    fn from_zydeco(value: &SemVal) -> Result<Self, ConvertError> {
+        match value {
+            SemVal::Literal(Literal::String(s)) => Ok(s.clone()),
+            _ => Err(ConvertError::mismatch("String", value)),
+        }
+    }
+}
+
+impl ZydecoType for String {
+    fn zydeco_type() -> Type {
+        data_type("String", vec![])
+    }
+}
+
+impl IntoZydeco for char {
+    fn into_zydeco(self) -> SemVal {
+        Literal::Char(self).into()
+    }
+}
+
+impl FromZydeco for char {
+    fn from_zydeco(value: &SemVal) -> Result<Self, ConvertError> {
+        match value {
+            SemVal::Literal(Literal::Char(c)) => Ok(*c),
+            _ => Err(ConvertError::mismatch("Char", value)),
+        }
+    }
+}
+
+impl ZydecoType for char {
+    fn zydeco_type() -> Type {
+        data_type("Char", vec![])
+    }
+}
+
+/* ---------------------------------- Data ---------------------------------- */
+
+impl IntoZydeco for bool {
+    fn into_zydeco(self) -> SemVal {
+        ctor(if self { "True" } else { "False" }, vec![])
+    }
+}
+
+impl FromZydeco for bool {
+    fn from_zydeco(value: &SemVal) -> Result<Self, ConvertError> {
+        match unctor(value, "Bool")? {
+            ("True", []) => Ok(true),
+            ("False", []) => Ok(false),
+            _ => Err(ConvertError::mismatch("Bool", value)),
+        }
+    }
+}
+
+impl ZydecoType for bool {
+    fn zydeco_type() -> Type {
+        data_type("Bool", vec![])
+    }
+}
+
+impl IntoZydeco for () {
+    fn into_zydeco(self) -> SemVal {
+        ctor("Unit", vec![])
+    }
+}
+
+impl FromZydeco for () {
+    fn from_zydeco(value: &SemVal) -> Result<Self, ConvertError> {
+        match unctor(value, "Unit")? {
+            ("Unit", []) => Ok(()),
+            _ => Err(ConvertError::mismatch("Unit", value)),
+        }
+    }
+}
+
+impl ZydecoType for () {
+    fn zydeco_type() -> Type {
+        data_type("Unit", vec![])
+    }
+}
+
+impl<T: IntoZydeco> IntoZydeco for Option<T> {
+    fn into_zydeco(self) -> SemVal {
+        match self {
+            Some(x) => ctor("Some", vec![x.into_zydeco()]),
+            None => ctor("None", vec![]),
+        }
+    }
+}
+
+impl<T: FromZydeco> FromZydeco for Option<T> {
+    fn from_zydeco(value: &SemVal) -> Result<Self, ConvertError> {
+        match unctor(value, "Option")? {
+            ("Some", [x]) => Ok(Some(T::from_zydeco(x)?)),
+            ("None", []) => Ok(None),
+            _ => Err(ConvertError::mismatch("Option", value)),
+        }
+    }
+}
+
+impl<T: ZydecoType> ZydecoType for Option<T> {
+    fn zydeco_type() -> Type {
+        data_type("Option", vec![T::zydeco_type()])
+    }
+}
+
+impl<T: IntoZydeco, E: IntoZydeco> IntoZydeco for Result<T, E> {
+    fn into_zydeco(self) -> SemVal {
+        match self {
+            Ok(x) => ctor("Ok", vec![x.into_zydeco()]),
+            Err(e) => ctor("Err", vec![e.into_zydeco()]),
+        }
+    }
+}
+
+impl<T: FromZydeco, E: FromZydeco> FromZydeco for Result<T, E> {
+    fn from_zydeco(value: &SemVal) -> Result<Self, ConvertError> {
+        match unctor(value, "Result")? {
+            ("Ok", [x]) => Ok(Ok(T::from_zydeco(x)?)),
+            ("Err", [e]) => Ok(Err(E::from_zydeco(e)?)),
+            _ => Err(ConvertError::mismatch("Result", value)),
+        }
+    }
+}
+
+impl<T: ZydecoType, E: ZydecoType> ZydecoType for Result<T, E> {
+    fn zydeco_type() -> Type {
+        data_type("Result", vec![T::zydeco_type(), E::zydeco_type()])
+    }
+}
+
+macro_rules! tuple {
+    ( $name:literal, $( $a:ident ),* ) => {
+        impl<$( $a: IntoZydeco ),*> IntoZydeco for ($( $a, )*) {
+            #[allow(non_snake_case)]
+            fn into_zydeco(self) -> SemVal {
+                let ($( $a, )*) = self;
+                ctor($name, vec![$( $a.into_zydeco() ),*])
+            }
+        }
+        impl<$( $a: FromZydeco ),*> FromZydeco for ($( $a, )*) {
+            #[allow(non_snake_case)]
+            fn from_zydeco(value: &SemVal) -> Result<Self, ConvertError> {
+                match unctor(value, $name)? {
+                    ($name, [$( $a ),*]) => Ok(($( $a::from_zydeco($a)?, )*)),
+                    _ => Err(ConvertError::mismatch($name, value)),
+                }
+            }
+        }
+        impl<$( $a: ZydecoType ),*> ZydecoType for ($( $a, )*) {
+            fn zydeco_type() -> Type {
+                data_type($name, vec![$( $a::zydeco_type() ),*])
+            }
+        }
+    };
+}
+
+tuple!("Pair", A, B);
+tuple!("Triple", A, B, C);
+tuple!("Quadruple", A, B, C, D);
+
+impl<T: IntoZydeco> IntoZydeco for Vec<T> {
+    fn into_zydeco(self) -> SemVal {
+        let mut list = ctor("Nil", vec![]);
+        for x in self.into_iter().rev() {
+            list = ctor("Cons", vec![x.into_zydeco(), list]);
+        }
+        list
+    }
+}
+
+impl<T: FromZydeco> FromZydeco for Vec<T> {
+    fn from_zydeco(value: &SemVal) -> Result<Self, ConvertError> {
+        let mut res = Vec::new();
+        let mut list = value;
+        loop {
+            match unctor(list, "List")? {
+                ("Nil", []) => break Ok(res),
+                ("Cons", [x, xs]) => {
+                    res.push(T::from_zydeco(x)?);
+                    list = xs;
+                }
+                _ => break Err(ConvertError::mismatch("List", list)),
+            }
+        }
+    }
+}
+
+impl<T: ZydecoType> ZydecoType for Vec<T> {
+    fn zydeco_type() -> Type {
+        data_type("List", vec![T::zydeco_type()])
+    }
+}
+
+/// The arguments of a Zydeco function called from Rust, as a tuple.
+pub trait ZydecoArgs {
+    fn zydeco_types() -> Vec<Type>;
+    fn into_zydeco_args(self) -> Vec<SemVal>;
+}
+
+macro_rules! args {
+    ( $( $a:ident ),* ) => {
+        impl<$( $a: IntoZydeco + ZydecoType ),*> ZydecoArgs for ($( $a, )*) {
+            fn zydeco_types() -> Vec<Type> {
+                vec![$( $a::zydeco_type() ),*]
+            }
+            #[allow(non_snake_case)]
+            fn into_zydeco_args(self) -> Vec<SemVal> {
+                let ($( $a, )*) = self;
+                vec![$( $a.into_zydeco() ),*]
+            }
+        }
+    };
+}
+
+args!();
+args!(A1);
+args!(A1, A2);
+args!(A1, A2, A3);
+args!(A1, A2, A3, A4);
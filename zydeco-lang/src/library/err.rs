@@ -0,0 +1,18 @@
+use crate::{library::syntax::TermV, utils::span::SpanView};
+use thiserror::Error;
+
+#[derive(Error, Clone, Debug)]
+pub enum LinkError {
+    #[error("No implementation found for extern definition {name} ({})", name.span())]
+    MissingImplementation { name: TermV },
+    #[error("The type of builtin {name} is malformed: {reason}")]
+    MalformedBuiltinType { name: String, reason: String },
+    #[error("The type of builtin {name} should be a thunk")]
+    NonThunkBuiltin { name: String },
+}
+
+#[derive(Error, Clone, Debug)]
+pub enum ConvertError {
+    #[error("Expected a value of {expected}, found {found}")]
+    Mismatch { expected: String, found: String },
+}
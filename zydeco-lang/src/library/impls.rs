@@ -1,9 +1,13 @@
-use super::syntax::*;
-use crate::{dynamics::syntax as ds, rc, utils::span::SpanInfo};
-use std::{
-    io::{BufRead, Write},
-    rc::Rc,
+use super::{
+    convert::{FromZydeco, IntoZydeco},
+    syntax::*,
 };
+use crate::{
+    dynamics::syntax::{self as ds, Runtime},
+    rc,
+    utils::span::SpanInfo,
+};
+use std::rc::Rc;
 
 type ZValue = ds::SemVal;
 type ZCompute = SynComp;
@@ -14,9 +18,6 @@ fn ret<E>(value: ZValue) -> Result<ZCompute, E> {
 }
 
 // /* Constructors and Destructors */
-fn ctor(ctor: &str, args: Vec<Rc<ZValue>>) -> ZValue {
-    Ctor { ctorv: CtorV::new(format!("{}", ctor), SpanInfo::dummy()), args }.into()
-}
 fn dtor(body: Rc<ZCompute>, dtor: &str, args: Vec<ZValue>) -> ZCompute {
     let args = args.into_iter().map(|a| rc!(a.into())).collect();
     Dtor { body, dtorv: DtorV::new(format!("{}", dtor), SpanInfo::dummy()), args }.into()
@@ -24,19 +25,13 @@ fn dtor(body: Rc<ZCompute>, dtor: &str, args: Vec<ZValue>) -> ZCompute {
 
 // /* Bool */
 fn bool(b: bool) -> ZValue {
-    let b = match b {
-        true => "True",
-        false => "False",
-    };
-    ctor(b, vec![])
+    b.into_zydeco()
 }
 
 // /* Arithmetic */
 macro_rules! arith {
     ( $name:ident, $op:tt ) => {
-        pub fn $name(
-            args: Vec<ZValue>, _: &mut dyn BufRead, _: &mut dyn Write, _: &[String],
-        ) -> Result<ZCompute, i32> {
+        pub fn $name(args: Vec<ZValue>, _: &mut Runtime) -> Result<ZCompute, i32> {
             match args.as_slice() {
                 [
                     ZValue::Literal(Literal::Int(a)),
@@ -56,9 +51,7 @@ arith!(modulo, %);
 
 macro_rules! intcomp {
     ( $name:ident, $op:tt ) => {
-        pub fn $name(
-            args: Vec<ZValue>, _: &mut dyn BufRead, _: &mut dyn Write, _:&[String],
-        ) -> Result<ZCompute, i32> {
+        pub fn $name(args: Vec<ZValue>, _: &mut Runtime) -> Result<ZCompute, i32> {
             match args.as_slice() {
                 [
                     ZValue::Literal(Literal::Int(a)),
@@ -75,18 +68,21 @@ intcomp!(int_lt, <);
 intcomp!(int_gt, >);
 
 // /* Strings */
-pub fn str_length(
-    args: Vec<ZValue>, _: &mut dyn BufRead, _: &mut dyn Write, _: &[String],
-) -> Result<ZCompute, i32> {
+/// The byte offset of the `n`th code point of `s`, or of its end for the one
+/// past the last.
+fn char_offset(s: &str, n: i64) -> Option<usize> {
+    let n = usize::try_from(n).ok()?;
+    s.char_indices().map(|(i, _)| i).chain([s.len()]).nth(n)
+}
+
+pub fn str_length(args: Vec<ZValue>, _: &mut Runtime) -> Result<ZCompute, i32> {
     match args.as_slice() {
-        [ZValue::Literal(Literal::String(a))] => ret(Literal::Int(a.len() as i64).into()),
+        [ZValue::Literal(Literal::String(a))] => ret(Literal::Int(a.chars().count() as i64).into()),
         _ => unreachable!(""),
     }
 }
 
-pub fn str_append(
-    args: Vec<ZValue>, _: &mut dyn BufRead, _: &mut dyn Write, _: &[String],
-) -> Result<ZCompute, i32> {
+pub fn str_append(args: Vec<ZValue>, _: &mut Runtime) -> Result<ZCompute, i32> {
     match args.as_slice() {
         [ZValue::Literal(Literal::String(a)), ZValue::Literal(Literal::String(b))] => {
             ret(Literal::String(a.to_owned() + b.as_str()).into())
@@ -95,56 +91,37 @@ pub fn str_append(
     }
 }
 
-pub fn str_split_once(
-    args: Vec<ZValue>, _: &mut dyn BufRead, _: &mut dyn Write, _: &[String],
-) -> Result<ZCompute, i32> {
+pub fn str_split_once(args: Vec<ZValue>, _: &mut Runtime) -> Result<ZCompute, i32> {
     match args.as_slice() {
         [ZValue::Literal(Literal::String(s)), ZValue::Literal(Literal::Char(p))] => {
-            match s.split_once(p.to_owned()) {
-                Some((a, b)) => ret(ctor(
-                    "Some",
-                    vec![rc!(ctor(
-                        "Pair",
-                        vec![
-                            rc!(Literal::String(a.to_owned()).into()),
-                            rc!(Literal::String(b.to_owned()).into()),
-                        ],
-                    ))],
-                )),
-                None => ret(ctor("None", vec![])),
-            }
+            ret(s.split_once(p.to_owned()).into_zydeco())
         }
         _ => unreachable!(""),
     }
 }
 
-pub fn str_split_n(
-    args: Vec<ZValue>, _: &mut dyn BufRead, _: &mut dyn Write, _: &[String],
-) -> Result<ZCompute, i32> {
+pub fn str_split_n(args: Vec<ZValue>, _: &mut Runtime) -> Result<ZCompute, i32> {
     match args.as_slice() {
         [ZValue::Literal(Literal::String(s)), ZValue::Literal(Literal::Int(n))] => {
-            if n.is_negative() {
-                return ret(ctor("None", vec![]));
-            }
-            let (a, b) = s.split_at(*n as usize);
-            ret(ctor(
-                "Some",
-                vec![rc!(ctor(
-                    "Pair",
-                    vec![
-                        rc!(Literal::String(a.to_owned()).into()),
-                        rc!(Literal::String(b.to_owned()).into()),
-                    ],
-                ))],
-            ))
+            ret(char_offset(s, *n).map(|i| s.split_at(i)).into_zydeco())
+        }
+        _ => unreachable!(""),
+    }
+}
+
+pub fn str_substring(args: Vec<ZValue>, _: &mut Runtime) -> Result<ZCompute, i32> {
+    match args.as_slice() {
+        [ZValue::Literal(Literal::String(s)), ZValue::Literal(Literal::Int(i)), ZValue::Literal(Literal::Int(j))] =>
+        {
+            let start = char_offset(s, (*i).max(0)).unwrap_or(s.len());
+            let end = char_offset(s, (*j).max(0)).unwrap_or(s.len()).max(start);
+            ret(Literal::String(s[start..end].to_string()).into())
         }
         _ => unreachable!(""),
     }
 }
 
-pub fn str_eq(
-    args: Vec<ZValue>, _: &mut dyn BufRead, _: &mut dyn Write, _: &[String],
-) -> Result<ZCompute, i32> {
+pub fn str_eq(args: Vec<ZValue>, _: &mut Runtime) -> Result<ZCompute, i32> {
     match args.as_slice() {
         [ZValue::Literal(Literal::String(a)), ZValue::Literal(Literal::String(b))] => {
             ret(bool(a == b))
@@ -153,73 +130,130 @@ pub fn str_eq(
     }
 }
 
-pub fn str_index(
-    args: Vec<ZValue>, _: &mut dyn BufRead, _: &mut dyn Write, _: &[String],
-) -> Result<ZCompute, i32> {
+pub fn str_index(args: Vec<ZValue>, _: &mut Runtime) -> Result<ZCompute, i32> {
     match args.as_slice() {
         [ZValue::Literal(Literal::String(a)), ZValue::Literal(Literal::Int(b))] => {
-            ret(Literal::Char(a.chars().nth(*b as usize).unwrap()).into())
+            let c = usize::try_from(*b).ok().and_then(|b| a.chars().nth(b));
+            ret(c.into_zydeco())
         }
         _ => unreachable!(""),
     }
 }
 
-pub fn int_to_str(
-    args: Vec<ZValue>, _: &mut dyn BufRead, _: &mut dyn Write, _: &[String],
-) -> Result<ZCompute, i32> {
+/// Builtins taking one or two strings, given by what they return.
+macro_rules! strop {
+    ( $name:ident, |$a:ident| $e:expr ) => {
+        pub fn $name(args: Vec<ZValue>, _: &mut Runtime) -> Result<ZCompute, i32> {
+            match args.as_slice() {
+                [ZValue::Literal(Literal::String($a))] => ret(($e).into_zydeco()),
+                _ => unreachable!(""),
+            }
+        }
+    };
+    ( $name:ident, |$a:ident, $b:ident| $e:expr ) => {
+        pub fn $name(args: Vec<ZValue>, _: &mut Runtime) -> Result<ZCompute, i32> {
+            match args.as_slice() {
+                [ZValue::Literal(Literal::String($a)), ZValue::Literal(Literal::String($b))] => {
+                    ret(($e).into_zydeco())
+                }
+                _ => unreachable!(""),
+            }
+        }
+    };
+}
+
+strop!(str_find, |s, p| s.find(p.as_str()).map(|i| s[..i].chars().count() as i64));
+strop!(str_starts_with, |s, p| s.starts_with(p.as_str()));
+strop!(str_ends_with, |s, p| s.ends_with(p.as_str()));
+strop!(str_lt, |a, b| a < b);
+strop!(str_trim, |s| s.trim());
+strop!(str_to_upper, |s| s.to_uppercase());
+strop!(str_to_lower, |s| s.to_lowercase());
+strop!(str_to_list, |s| s.chars().collect::<Vec<_>>());
+strop!(str_split, |s, sep| {
+    if sep.is_empty() {
+        s.chars().map(String::from).collect::<Vec<_>>()
+    } else {
+        s.split(sep.as_str()).map(String::from).collect()
+    }
+});
+
+pub fn str_replace(args: Vec<ZValue>, _: &mut Runtime) -> Result<ZCompute, i32> {
+    match args.as_slice() {
+        [ZValue::Literal(Literal::String(s)), ZValue::Literal(Literal::String(from)), ZValue::Literal(Literal::String(to))] => {
+            ret(Literal::String(s.replace(from.as_str(), to)).into())
+        }
+        _ => unreachable!(""),
+    }
+}
+
+pub fn str_join(args: Vec<ZValue>, _: &mut Runtime) -> Result<ZCompute, i32> {
+    match args.as_slice() {
+        [parts, ZValue::Literal(Literal::String(sep))] => {
+            let Ok(parts) = Vec::<String>::from_zydeco(parts) else { unreachable!("") };
+            ret(Literal::String(parts.join(sep)).into())
+        }
+        _ => unreachable!(""),
+    }
+}
+
+pub fn list_to_str(args: Vec<ZValue>, _: &mut Runtime) -> Result<ZCompute, i32> {
+    match args.as_slice() {
+        [chars] => {
+            let Ok(chars) = Vec::<char>::from_zydeco(chars) else { unreachable!("") };
+            ret(Literal::String(chars.into_iter().collect()).into())
+        }
+        _ => unreachable!(""),
+    }
+}
+
+pub fn int_to_str(args: Vec<ZValue>, _: &mut Runtime) -> Result<ZCompute, i32> {
     match args.as_slice() {
         [ZValue::Literal(Literal::Int(a))] => ret(Literal::String(a.to_string()).into()),
         _ => unreachable!(""),
     }
 }
 
-pub fn char_to_str(
-    args: Vec<ZValue>, _: &mut dyn BufRead, _: &mut dyn Write, _: &[String],
-) -> Result<ZCompute, i32> {
+pub fn char_to_str(args: Vec<ZValue>, _: &mut Runtime) -> Result<ZCompute, i32> {
     match args.as_slice() {
         [ZValue::Literal(Literal::Char(a))] => ret(Literal::String(a.to_string()).into()),
         _ => unreachable!(""),
     }
 }
 
-pub fn char_to_int(
-    args: Vec<ZValue>, _: &mut dyn BufRead, _: &mut dyn Write, _: &[String],
-) -> Result<ZCompute, i32> {
+pub fn char_to_int(args: Vec<ZValue>, _: &mut Runtime) -> Result<ZCompute, i32> {
     match args.as_slice() {
-        [ZValue::Literal(Literal::Char(a))] => ret(Literal::Int((*a as u8) as i64).into()),
+        [ZValue::Literal(Literal::Char(a))] => ret(Literal::Int(*a as i64).into()),
         _ => unreachable!(""),
     }
 }
 
-pub fn str_to_int(
-    args: Vec<ZValue>, _: &mut dyn BufRead, _: &mut dyn Write, _: &[String],
-) -> Result<ZCompute, i32> {
+pub fn int_to_char(args: Vec<ZValue>, _: &mut Runtime) -> Result<ZCompute, i32> {
     match args.as_slice() {
-        [ZValue::Literal(Literal::String(s))] => ret(Literal::Int(s.parse().unwrap()).into()),
+        [ZValue::Literal(Literal::Int(a))] => {
+            ret(u32::try_from(*a).ok().and_then(char::from_u32).into_zydeco())
+        }
         _ => unreachable!(""),
     }
 }
 
+strop!(str_to_int, |s| s.parse::<i64>().ok());
+
 // /* IO */
-pub fn write_str(
-    args: Vec<ZValue>, _r: &mut (dyn BufRead), w: &mut (dyn Write), _: &[String],
-) -> Result<ZCompute, i32> {
+pub fn write_str(args: Vec<ZValue>, runtime: &mut Runtime) -> Result<ZCompute, i32> {
     match args.as_slice() {
         [ZValue::Literal(Literal::String(s)), e @ ZValue::Thunk(..)] => {
-            write!(w, "{}", s).unwrap();
+            runtime.host.write_str(s).unwrap();
             Ok(Force(rc!(e.clone().into())).into())
         }
         _ => unreachable!(""),
     }
 }
 
-pub fn read_line(
-    args: Vec<ZValue>, r: &mut (dyn BufRead), _w: &mut (dyn Write), _: &[String],
-) -> Result<ZCompute, i32> {
+pub fn read_line(args: Vec<ZValue>, runtime: &mut Runtime) -> Result<ZCompute, i32> {
     match args.as_slice() {
         [e @ ZValue::Thunk(_)] => {
-            let mut line = String::new();
-            r.read_line(&mut line).unwrap();
+            let mut line = runtime.host.read_line().unwrap();
             line.pop();
             Ok(dtor(
                 rc!(Force(rc!(e.clone().into())).into()),
@@ -231,13 +265,10 @@ pub fn read_line(
     }
 }
 
-pub fn read_till_eof(
-    args: Vec<ZValue>, r: &mut (dyn BufRead), _w: &mut (dyn Write), _: &[String],
-) -> Result<ZCompute, i32> {
+pub fn read_till_eof(args: Vec<ZValue>, runtime: &mut Runtime) -> Result<ZCompute, i32> {
     match args.as_slice() {
         [e @ ZValue::Thunk(_)] => {
-            let mut line = String::new();
-            r.read_to_string(&mut line).unwrap();
+            let line = runtime.host.read_to_end().unwrap();
             Ok(dtor(
                 rc!(Force(rc!(e.clone().into())).into()),
                 "arg",
@@ -248,27 +279,289 @@ pub fn read_till_eof(
     }
 }
 
-pub fn arg_list(
-    args: Vec<ZValue>, _r: &mut (dyn BufRead), _w: &mut (dyn Write), argv: &[String],
-) -> Result<ZCompute, i32> {
+pub fn arg_list(args: Vec<ZValue>, runtime: &mut Runtime) -> Result<ZCompute, i32> {
     match args.as_slice() {
         [k] => {
-            let mut z_arg_list = ctor("Nil", vec![]);
-            for arg in argv.iter().rev() {
-                z_arg_list =
-                    ctor("Cons", vec![rc!(Literal::String(arg.clone()).into()), rc!(z_arg_list)]);
-            }
+            let z_arg_list = runtime.host.args().to_vec().into_zydeco();
             Ok(dtor(rc!(Force(rc!(k.clone().into())).into()), "arg", vec![z_arg_list]))
         }
         _ => unreachable!(""),
     }
 }
 
-pub fn exit(
-    args: Vec<ZValue>, _r: &mut (dyn BufRead), _w: &mut (dyn Write), _: &[String],
-) -> Result<ZCompute, i32> {
+pub fn exit(args: Vec<ZValue>, _: &mut Runtime) -> Result<ZCompute, i32> {
     match args.as_slice() {
         [ZValue::Literal(Literal::Int(a))] => Err(*a as i32),
         _ => unreachable!(""),
     }
 }
+
+/// Reports a runtime error the way `panic` does, exiting with code 1.
+fn panic(msg: String, runtime: &mut Runtime) -> Result<ZCompute, i32> {
+    Err(runtime.panic(&msg))
+}
+
+// /* File System */
+/// Passes the outcome of a file system operation to the continuation `k`.
+fn fs_kont<T: IntoZydeco>(k: &ZValue, res: std::io::Result<T>) -> Result<ZCompute, i32> {
+    let res = res.map_err(|e| e.to_string());
+    Ok(dtor(rc!(Force(rc!(k.clone().into())).into()), "arg", vec![res.into_zydeco()]))
+}
+
+pub fn file_open(args: Vec<ZValue>, runtime: &mut Runtime) -> Result<ZCompute, i32> {
+    match args.as_slice() {
+        [ZValue::Literal(Literal::String(path)), k] => fs_kont(k, runtime.host.open_file(path)),
+        _ => unreachable!(""),
+    }
+}
+
+pub fn file_read(args: Vec<ZValue>, runtime: &mut Runtime) -> Result<ZCompute, i32> {
+    match args.as_slice() {
+        [ZValue::Literal(Literal::String(path)), k] => fs_kont(k, runtime.host.read_file(path)),
+        _ => unreachable!(""),
+    }
+}
+
+pub fn file_write(args: Vec<ZValue>, runtime: &mut Runtime) -> Result<ZCompute, i32> {
+    match args.as_slice() {
+        [ZValue::Literal(Literal::String(path)), ZValue::Literal(Literal::String(s)), k] => {
+            fs_kont(k, runtime.host.write_file(path, s))
+        }
+        _ => unreachable!(""),
+    }
+}
+
+pub fn file_append(args: Vec<ZValue>, runtime: &mut Runtime) -> Result<ZCompute, i32> {
+    match args.as_slice() {
+        [ZValue::Literal(Literal::String(path)), ZValue::Literal(Literal::String(s)), k] => {
+            fs_kont(k, runtime.host.append_file(path, s))
+        }
+        _ => unreachable!(""),
+    }
+}
+
+pub fn file_exists(args: Vec<ZValue>, runtime: &mut Runtime) -> Result<ZCompute, i32> {
+    match args.as_slice() {
+        [ZValue::Literal(Literal::String(path)), k] => fs_kont(k, runtime.host.exists(path)),
+        _ => unreachable!(""),
+    }
+}
+
+pub fn file_remove(args: Vec<ZValue>, runtime: &mut Runtime) -> Result<ZCompute, i32> {
+    match args.as_slice() {
+        [ZValue::Literal(Literal::String(path)), k] => fs_kont(k, runtime.host.remove_file(path)),
+        _ => unreachable!(""),
+    }
+}
+
+pub fn dir_list(args: Vec<ZValue>, runtime: &mut Runtime) -> Result<ZCompute, i32> {
+    match args.as_slice() {
+        [ZValue::Literal(Literal::String(path)), k] => fs_kont(k, runtime.host.list_dir(path)),
+        _ => unreachable!(""),
+    }
+}
+
+// /* Environment */
+pub fn write_err(args: Vec<ZValue>, runtime: &mut Runtime) -> Result<ZCompute, i32> {
+    match args.as_slice() {
+        [ZValue::Literal(Literal::String(s)), e @ ZValue::Thunk(..)] => {
+            runtime.host.write_err(s).unwrap();
+            Ok(Force(rc!(e.clone().into())).into())
+        }
+        _ => unreachable!(""),
+    }
+}
+
+pub fn env_var(args: Vec<ZValue>, runtime: &mut Runtime) -> Result<ZCompute, i32> {
+    match args.as_slice() {
+        [ZValue::Literal(Literal::String(name)), k] => {
+            let value = runtime.host.env_var(name).into_zydeco();
+            Ok(dtor(rc!(Force(rc!(k.clone().into())).into()), "arg", vec![value]))
+        }
+        _ => unreachable!(""),
+    }
+}
+
+pub fn clock_wall(args: Vec<ZValue>, runtime: &mut Runtime) -> Result<ZCompute, i32> {
+    match args.as_slice() {
+        [k] => {
+            let now = Literal::Int(runtime.host.wall_clock()).into();
+            Ok(dtor(rc!(Force(rc!(k.clone().into())).into()), "arg", vec![now]))
+        }
+        _ => unreachable!(""),
+    }
+}
+
+pub fn clock_monotonic(args: Vec<ZValue>, runtime: &mut Runtime) -> Result<ZCompute, i32> {
+    match args.as_slice() {
+        [k] => {
+            let now = Literal::Int(runtime.host.monotonic_clock()).into();
+            Ok(dtor(rc!(Force(rc!(k.clone().into())).into()), "arg", vec![now]))
+        }
+        _ => unreachable!(""),
+    }
+}
+
+pub fn sleep(args: Vec<ZValue>, runtime: &mut Runtime) -> Result<ZCompute, i32> {
+    match args.as_slice() {
+        [ZValue::Literal(Literal::Int(ms)), e @ ZValue::Thunk(..)] => {
+            runtime.host.sleep((*ms).max(0) as u64);
+            Ok(Force(rc!(e.clone().into())).into())
+        }
+        _ => unreachable!(""),
+    }
+}
+
+pub fn random_int(args: Vec<ZValue>, runtime: &mut Runtime) -> Result<ZCompute, i32> {
+    match args.as_slice() {
+        [ZValue::Literal(Literal::Int(bound)), k] => {
+            if *bound <= 0 {
+                return panic(format!("non-positive random bound {}", bound), runtime);
+            }
+            let n = Literal::Int((runtime.host.random() % *bound as u64) as i64).into();
+            Ok(dtor(rc!(Force(rc!(k.clone().into())).into()), "arg", vec![n]))
+        }
+        _ => unreachable!(""),
+    }
+}
+
+// /* Delimited Continuations */
+pub fn new_prompt(_: Vec<ZValue>, runtime: &mut Runtime) -> Result<ZCompute, i32> {
+    let prompt = ds::Prompt(runtime.prompts);
+    runtime.prompts += 1;
+    ret(prompt.into())
+}
+
+pub fn push_prompt(args: Vec<ZValue>, runtime: &mut Runtime) -> Result<ZCompute, i32> {
+    match args.as_slice() {
+        [ZValue::Prompt(p), e @ ZValue::Thunk(_)] => {
+            runtime.stack.push_back(ds::SemComp::Prompt(*p));
+            Ok(Force(rc!(e.clone().into())).into())
+        }
+        _ => unreachable!(""),
+    }
+}
+
+pub fn take_subcont(args: Vec<ZValue>, runtime: &mut Runtime) -> Result<ZCompute, i32> {
+    match args.as_slice() {
+        [ZValue::Prompt(p), f @ ZValue::Thunk(_)] => {
+            let depth = (runtime.stack.iter().rev())
+                .position(|frame| matches!(frame, ds::SemComp::Prompt(q) if q == p));
+            let Some(depth) = depth else {
+                return panic(format!("prompt not found on the stack"), runtime);
+            };
+            let idx = runtime.stack.len() - depth;
+            let frames = runtime.stack.split_off(idx);
+            runtime.stack.pop_back();
+            Ok(dtor(
+                rc!(Force(rc!(f.clone().into())).into()),
+                "arg",
+                vec![ds::SubCont(frames).into()],
+            ))
+        }
+        _ => unreachable!(""),
+    }
+}
+
+pub fn push_subcont(args: Vec<ZValue>, runtime: &mut Runtime) -> Result<ZCompute, i32> {
+    match args.as_slice() {
+        [ZValue::SubCont(ds::SubCont(frames)), e @ ZValue::Thunk(_)] => {
+            runtime.stack.append(frames.clone());
+            Ok(Force(rc!(e.clone().into())).into())
+        }
+        _ => unreachable!(""),
+    }
+}
+
+// /* Mutable State */
+/// The index into an array of the given length, if in bounds.
+fn array_index(i: i64, len: usize) -> Option<usize> {
+    usize::try_from(i).ok().filter(|i| *i < len)
+}
+
+pub fn ref_new(args: Vec<ZValue>, runtime: &mut Runtime) -> Result<ZCompute, i32> {
+    match args.as_slice() {
+        [v] => {
+            runtime.heap.refs.push(v.clone());
+            ret(ds::Ref(runtime.heap.refs.len() - 1).into())
+        }
+        _ => unreachable!(""),
+    }
+}
+
+pub fn ref_get(args: Vec<ZValue>, runtime: &mut Runtime) -> Result<ZCompute, i32> {
+    match args.as_slice() {
+        [ZValue::Ref(ds::Ref(r))] => ret(runtime.heap.refs[*r].clone()),
+        _ => unreachable!(""),
+    }
+}
+
+pub fn ref_set(args: Vec<ZValue>, runtime: &mut Runtime) -> Result<ZCompute, i32> {
+    match args.as_slice() {
+        [ZValue::Ref(ds::Ref(r)), v] => {
+            runtime.heap.refs[*r] = v.clone();
+            ret(().into_zydeco())
+        }
+        _ => unreachable!(""),
+    }
+}
+
+pub fn array_new(args: Vec<ZValue>, runtime: &mut Runtime) -> Result<ZCompute, i32> {
+    match args.as_slice() {
+        [ZValue::Literal(Literal::Int(n)), v] => {
+            let Ok(n) = usize::try_from(*n) else {
+                return panic(format!("negative array length {}", n), runtime);
+            };
+            let mut elems = Vec::new();
+            if elems.try_reserve_exact(n).is_err() {
+                return panic(format!("cannot allocate an array of length {}", n), runtime);
+            }
+            elems.resize(n, v.clone());
+            runtime.heap.arrays.push(elems);
+            ret(ds::Array(runtime.heap.arrays.len() - 1).into())
+        }
+        _ => unreachable!(""),
+    }
+}
+
+pub fn array_get(args: Vec<ZValue>, runtime: &mut Runtime) -> Result<ZCompute, i32> {
+    match args.as_slice() {
+        [ZValue::Array(ds::Array(a)), ZValue::Literal(Literal::Int(i))] => {
+            let len = runtime.heap.arrays[*a].len();
+            let Some(idx) = array_index(*i, len) else {
+                return panic(
+                    format!("array index {} out of bounds for length {}", i, len),
+                    runtime,
+                );
+            };
+            ret(runtime.heap.arrays[*a][idx].clone())
+        }
+        _ => unreachable!(""),
+    }
+}
+
+pub fn array_set(args: Vec<ZValue>, runtime: &mut Runtime) -> Result<ZCompute, i32> {
+    match args.as_slice() {
+        [ZValue::Array(ds::Array(a)), ZValue::Literal(Literal::Int(i)), v] => {
+            let len = runtime.heap.arrays[*a].len();
+            let Some(idx) = array_index(*i, len) else {
+                return panic(
+                    format!("array index {} out of bounds for length {}", i, len),
+                    runtime,
+                );
+            };
+            runtime.heap.arrays[*a][idx] = v.clone();
+            ret(().into_zydeco())
+        }
+        _ => unreachable!(""),
+    }
+}
+
+pub fn array_length(args: Vec<ZValue>, runtime: &mut Runtime) -> Result<ZCompute, i32> {
+    match args.as_slice() {
+        [ZValue::Array(ds::Array(a))] => {
+            ret(Literal::Int(runtime.heap.arrays[*a].len() as i64).into())
+        }
+        _ => unreachable!(""),
+    }
+}
@@ -1,37 +1,40 @@
-use super::{builtins::Builtin, syntax::*};
-use crate::{rc, statics::syntax as ss};
+use super::{builtins::Registry, err::LinkError, impls, syntax::*};
+use crate::{dynamics::syntax as ds, rc, statics::syntax as ss, utils::span::SpanInfo};
 use im::Vector;
+use std::rc::Rc;
 
-impl From<ss::Program> for Program {
-    fn from(p: ss::Program) -> Self {
+impl Program {
+    pub fn link(p: ss::Program, registry: &Registry) -> Result<Self, LinkError> {
         let ss::Program { module, entry } = p;
-        let module = module.inner().into();
+        let module = Module::link(module.inner(), registry)?;
         let entry = entry.inner_ref().into();
-        Self { module, entry }
+        Ok(Self { module, entry })
     }
 }
 
-impl From<ss::Module> for Module {
-    fn from(m: ss::Module) -> Self {
+impl Module {
+    pub fn link(m: ss::Module, registry: &Registry) -> Result<Self, LinkError> {
         let mut define = Vector::new();
-        let std_library = Builtin::std_library();
+        for DeclSymbol { public: _, external: _, inner: Effect { name: _, ops } } in m.effect {
+            for EffectOp { name, tys, ty: _ } in ops {
+                define.push_back((name.clone(), Self::op(name, tys.len())));
+            }
+        }
         for DeclSymbol {
             public: _,
             external: _,
             inner: ss::Define { name: (sym, _ty), def: () },
         } in m.define_ext
         {
-            // Todo: synthesize the arity from the type
-            let def = std_library
-                .get(&sym)
-                .expect("no implementation found for the extern term definition")
-                .clone();
-            define.push_back((sym, def));
+            let Some(builtin) = registry.get(sym.name()) else {
+                Err(LinkError::MissingImplementation { name: sym })?
+            };
+            define.push_back((sym, builtin.gen()));
         }
         for DeclSymbol { public: _, external: _, inner: ss::Define { name, def } } in m.define {
             define.push_back((name, def.inner_ref().into()));
         }
-        Self { name: m.name, define }
+        Ok(Self { name: m.name, define })
     }
 }
 
@@ -47,6 +50,7 @@ impl From<&ss::TermValue> for SynVal {
             }
             ss::TermValue::Literal(l) => l.clone().into(),
             ss::TermValue::Pack(Pack { ty: _, body }) => body.inner_ref().into(),
+            ss::TermValue::Hole(hole) => hole.clone().into(),
         }
     }
 }
@@ -59,6 +63,7 @@ impl From<&ss::TermComputation> for SynComp {
             }
             ss::TermComputation::Ret(Ret(v)) => Ret(rc!(v.inner_ref().into())).into(),
             ss::TermComputation::Force(Force(v)) => Force(rc!(v.inner_ref().into())).into(),
+            ss::TermComputation::Interpolation(string) => string.into(),
             ss::TermComputation::TailGroup(ss::TailGroup { group, body }) => {
                 let mut body: SynComp = body.inner_ref().into();
                 for item in group.into_iter().rev() {
@@ -90,6 +95,22 @@ impl From<&ss::TermComputation> for SynComp {
                     .collect();
                 Match { scrut, arms }.into()
             }
+            ss::TermComputation::MatchLit(MatchLit { scrut, arms, default }) => {
+                let scrut = rc!(scrut.inner_ref().into());
+                let mut arms: Vec<_> = arms
+                    .iter()
+                    .map(|(lit, body)| (lit.clone(), rc!(body.inner_ref().into())))
+                    .collect();
+                // sorted by literal for evaluation to look the arm up, keeping
+                // the first of the arms for the same literal
+                arms.sort_by(|(l1, _), (l2, _)| l1.cmp(l2));
+                arms.dedup_by(|(l1, _), (l2, _)| l1 == l2);
+                let default = rc!(default.inner_ref().into());
+                MatchLit { scrut, arms, default }.into()
+            }
+            ss::TermComputation::NestedMatch(ss::NestedMatch { compiled, .. }) => {
+                compiled.get().expect("match linked before type checking").inner_ref().into()
+            }
             ss::TermComputation::Comatch(Comatch { arms }) => {
                 let arms = arms
                     .iter()
@@ -112,11 +133,111 @@ impl From<&ss::TermComputation> for SynComp {
                 let body = rc!(body.inner_ref().into());
                 Let { var: var.clone(), def: scrut, body }.into()
             }
+            ss::TermComputation::Handle(Handle { body, ret, arms }) => {
+                let body = rc!(body.inner_ref().into());
+                let ret =
+                    ret.as_ref().map(|(var, body)| (var.clone(), rc!(body.inner_ref().into())));
+                let arms = arms
+                    .iter()
+                    .map(|Handler { op, vars, kont, body }| {
+                        let body = rc!(body.inner_ref().into());
+                        Handler { op: op.clone(), vars: vars.clone(), kont: kont.clone(), body }
+                    })
+                    .collect();
+                Handle { body, ret, arms }.into()
+            }
+            ss::TermComputation::Infix(ss::Infix { grouped, .. }) => grouped
+                .get()
+                .expect("infix expression linked before type checking")
+                .inner_ref()
+                .into(),
+            ss::TermComputation::Hole(hole) => hole.clone().into(),
         }
     }
 }
 
+impl From<&ss::Interpolation> for SynComp {
+    /// Formats the splices and joins the pieces left to right, calling the
+    /// builtins directly rather than through any name in scope.
+    fn from(string: &ss::Interpolation) -> Self {
+        let ss::Interpolation { pieces, formats } = string;
+        let formats = formats.get().expect("interpolation linked before type checking");
+        let mut binds: Vec<(TermV, SynComp)> = Vec::new();
+        let mut splices = formats.iter();
+        let mut parts: Vec<RcValue> = Vec::new();
+        for piece in pieces {
+            let part = match piece {
+                ss::Piece::Text(text) => rc!(Literal::String(text.clone()).into()),
+                ss::Piece::Splice(v) => {
+                    let v: RcValue = rc!(v.inner_ref().into());
+                    let format = splices.next().expect("splice without format");
+                    let comp = match format {
+                        ss::Format::Int => apply(builtin("int_to_str", 1, impls::int_to_str), [v]),
+                        ss::Format::Char => {
+                            apply(builtin("char_to_str", 1, impls::char_to_str), [v])
+                        }
+                        ss::Format::String => {
+                            parts.push(v);
+                            continue;
+                        }
+                        ss::Format::Thunk => Force(v).into(),
+                    };
+                    let var = TermV::new(format!("$piece{}", binds.len()), SpanInfo::dummy());
+                    binds.push((var.clone(), comp));
+                    rc!(var.into())
+                }
+            };
+            parts.push(part);
+        }
+        let mut parts = parts.into_iter();
+        let mut joined = parts.next().unwrap_or_else(|| rc!(Literal::String(String::new()).into()));
+        for part in parts {
+            let comp = apply(builtin("str_append", 2, impls::str_append), [joined, part]);
+            let var = TermV::new(format!("$joined{}", binds.len()), SpanInfo::dummy());
+            binds.push((var.clone(), comp));
+            joined = rc!(var.into());
+        }
+        let mut body: SynComp = Ret(joined).into();
+        for (var, comp) in binds.into_iter().rev() {
+            body = Do { var, comp: rc!(comp), body: rc!(body) }.into();
+        }
+        body
+    }
+}
+
+/// The thunk of a builtin, for code the program can't rebind.
+fn builtin(
+    name: &'static str, arity: u64,
+    body: fn(Vec<ds::SemVal>, &mut ds::Runtime) -> Result<SynComp, i32>,
+) -> RcValue {
+    rc!(Thunk(rc!(Prim { name, arity, body: Rc::new(body) }.into())).into())
+}
+
+/// Forces `fun` and applies it to `args` one after another.
+fn apply<const N: usize>(fun: RcValue, args: [RcValue; N]) -> SynComp {
+    let mut body: SynComp = Force(fun).into();
+    for arg in args {
+        let dtorv = DtorV::new(format!("arg"), SpanInfo::dummy());
+        body = Dtor { body: rc!(body), dtorv, args: vec![arg] }.into();
+    }
+    body
+}
+
 impl Module {
+    /// The thunk performing an effect operation once all its arguments are
+    /// supplied.
+    fn op(op: TermV, arity: usize) -> SynVal {
+        let vars: Vec<_> =
+            (0..arity).map(|i| TermV::new(format!("x{}", i), SpanInfo::dummy())).collect();
+        let args = vars.iter().map(|var| rc!(var.clone().into())).collect();
+        let mut body: SynComp = Perform { op, args }.into();
+        for var in vars.into_iter().rev() {
+            let dtorv = DtorV::new(format!("arg"), SpanInfo::dummy());
+            body = Comatch { arms: vec![Comatcher { dtorv, vars: vec![var], body: rc!(body) }] }
+                .into();
+        }
+        Thunk(rc!(body)).into()
+    }
     pub fn pure(name: Option<String>) -> Self {
         Self { name, define: Vector::new() }
     }
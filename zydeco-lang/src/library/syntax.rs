@@ -1,10 +1,7 @@
 use crate::dynamics::syntax as ds;
 pub use crate::syntax::*;
 use im::Vector;
-use std::{
-    io::{BufRead, Write},
-    rc::Rc,
-};
+use std::rc::Rc;
 use zydeco_derive::{FmtArgs, IntoEnum};
 
 /* ---------------------------------- Term ---------------------------------- */
@@ -16,15 +13,16 @@ pub enum SynVal {
     Ctor(Ctor<CtorV, RcValue>),
     Literal(Literal),
     SemValue(ds::SemVal),
+    Hole(TermHole),
 }
-type RcValue = Rc<SynVal>;
+pub(crate) type RcValue = Rc<SynVal>;
 impl ValueT for SynVal {}
 
-pub type PrimComp =
-    fn(Vec<ds::SemVal>, &mut (dyn BufRead), &mut (dyn Write), &[String]) -> Result<SynComp, i32>;
+pub type PrimComp = Rc<dyn Fn(Vec<ds::SemVal>, &mut ds::Runtime) -> Result<SynComp, i32>>;
 
 #[derive(Clone)]
 pub struct Prim {
+    pub name: &'static str,
     pub arity: u64,
     pub body: PrimComp,
 }
@@ -37,11 +35,15 @@ pub enum SynComp {
     Do(Do<TermV, RcComp, RcComp>),
     Rec(Rec<TermV, RcComp>),
     Match(Match<CtorV, TermV, RcValue, RcComp>),
+    MatchLit(MatchLit<RcValue, RcComp>),
     Comatch(Comatch<DtorV, TermV, RcComp>),
     Dtor(Dtor<RcComp, DtorV, RcValue>),
+    Handle(Handle<TermV, TermV, RcComp>),
+    Perform(Perform<TermV, RcValue>),
     Prim(Prim),
+    Hole(TermHole),
 }
-type RcComp = Rc<SynComp>;
+pub(crate) type RcComp = Rc<SynComp>;
 impl ComputationT for SynComp {}
 
 #[derive(IntoEnum, FmtArgs, Clone)]
@@ -1,46 +1,115 @@
+use super::{lexer::Tok, syntax::*};
+use crate::prelude::*;
+use logos::Logos;
+
 /// Expand escape characters in a string literal, converting the source code
 /// representation to the text it represents. The `idx0` argument should be the
 /// position in the input stream of the first character of `text`, the position
 /// after the opening double-quote.
 /// https://github.com/lalrpop/lalrpop/blob/d09a1433d181de12fd1ebfc89c2ecaeed734943c/lalrpop/src/tok/mod.rs#L749
-pub fn apply_string_escapes(code: &str) -> String {
+pub fn apply_string_escapes(code: &str) -> Result<String, &'static str> {
     if !code.contains('\\') {
-        code.into()
+        Ok(code.into())
     } else {
-        let mut iter = code.char_indices();
+        let mut iter = code.chars();
         let mut text = String::new();
-        while let Some((_, mut ch)) = iter.next() {
+        while let Some(ch) = iter.next() {
             if ch == '\\' {
                 // The parser should never have accepted an ill-formed string
                 // literal, so we know it can't end in a backslash.
-                let (_, next_ch) = iter.next().unwrap();
-                ch = match next_ch {
-                    '\\' | '\"' => next_ch,
-                    'n' => '\n',
-                    'r' => '\r',
-                    't' => '\t',
-                    _ => next_ch,
+                text.push(apply_escape(&mut iter)?);
+            } else {
+                text.push(ch);
+            }
+        }
+        Ok(text)
+    }
+}
+
+/// Expand escape characters in a string literal like `apply_string_escapes`,
+/// and split it at the `${x}` splices it has, if any. The `idx0` argument is
+/// the position in the input stream of the first character of `code`.
+pub fn apply_string_interpolation(code: &str, idx0: usize) -> Result<TermValue, &'static str> {
+    if !code.contains("${") {
+        return Ok(Literal::String(apply_string_escapes(code)?).into());
+    }
+    let mut iter = code.chars();
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let mut start = idx0;
+    while let Some(ch) = iter.next() {
+        let pos = idx0 + code.len() - iter.as_str().len() - ch.len_utf8();
+        match ch {
+            '\\' => text.push(apply_escape(&mut iter)?),
+            '$' if iter.as_str().starts_with('{') => {
+                let (name, _) = iter.as_str()[1..]
+                    .split_once('}')
+                    .ok_or("Unclosed splice; expected `}` after `${`")?;
+                let ident = name.trim();
+                let mut lexer = Tok::lexer(ident);
+                let (Some(Ok(Tok::LowerIdent(_))), None) = (lexer.next(), lexer.next()) else {
+                    Err("Invalid splice; expected a variable name in `${...}`")?
+                };
+                if !text.is_empty() {
+                    parts.push(SpanInfo::new(start, pos).make(std::mem::take(&mut text)).into());
                 }
+                // skip the brace, the name and the closing brace
+                iter.nth(name.chars().count() + 1);
+                let offset = pos + 2 + name.len() - name.trim_start().len();
+                let info = SpanInfo::new(offset, offset + ident.len());
+                let ident = info.make(ident.to_string());
+                parts.push(NameRef { path: vec![], ident, info }.into());
+                start = idx0 + code.len() - iter.as_str().len();
             }
-            text.push(ch);
+            ch => text.push(ch),
         }
-        text
     }
+    if parts.is_empty() {
+        // every `${` was escaped
+        return Ok(Literal::String(text).into());
+    }
+    if !text.is_empty() {
+        parts.push(SpanInfo::new(start, idx0 + code.len()).make(text).into());
+    }
+    Ok(Interpolation(parts).into())
 }
 
-pub fn apply_char_escapes(code: &str) -> char {
+pub fn apply_char_escapes(code: &str) -> Result<char, &'static str> {
     let mut iter = code.chars();
     // remove the quotes
     iter.next();
     iter.next_back();
     match iter.next().unwrap() {
-        '\\' => match iter.next() {
-            Some('n') => '\n',
-            Some('r') => '\r',
-            Some('t') => '\t',
-            Some('\'') => '\'',
-            _ => '\\', //Error?
-        },
-        c => c,
+        '\\' => apply_escape(&mut iter),
+        c => Ok(c),
     }
 }
+
+/// Expands the escape sequence following a backslash, including `\u{...}`
+/// with one to six hex digits naming a Unicode scalar value.
+fn apply_escape(iter: &mut std::str::Chars) -> Result<char, &'static str> {
+    let invalid = "Invalid unicode escape; expected \\u{...} naming a Unicode scalar value";
+    Ok(match iter.next() {
+        Some('n') => '\n',
+        Some('r') => '\r',
+        Some('t') => '\t',
+        Some('0') => '\0',
+        Some('u') => {
+            let rest = iter.as_str();
+            let hex = (rest.strip_prefix('{'))
+                .and_then(|rest| rest.split_once('}'))
+                .map(|(hex, _)| hex)
+                .filter(|hex| {
+                    (1..=6).contains(&hex.len()) && hex.chars().all(|c| c.is_ascii_hexdigit())
+                })
+                .ok_or(invalid)?;
+            let code = u32::from_str_radix(hex, 16).map_err(|_| invalid)?;
+            let c = char::from_u32(code).ok_or(invalid)?;
+            // skip the braces and the digits
+            iter.nth(hex.len() + 1);
+            c
+        }
+        Some(c) => c,
+        None => '\\',
+    })
+}
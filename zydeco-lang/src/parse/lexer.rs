@@ -11,9 +11,15 @@ use std::fmt::{Debug, Display};
 pub enum Tok<'input> {
     #[regex(r"[A-Z]([a-zA-Z0-9_]|'|\?|\+|\*|-|=|~)*")]
     UpperIdent(&'input str),
-    #[regex(r"([_a-z]|\?|\*|=)([a-zA-Z0-9_]|'|\?|\+|\*|-|=|~)*")]
+    #[regex(r"([_a-z]|\*|=)([a-zA-Z0-9_]|'|\?|\+|\*|-|=|~)*")]
     #[regex(r"(\+|\-)([a-zA-Z_]|'|\?|\+|\*|-|=|~)*")]
+    #[regex(r"(<|>|/|%|\^|&)(<|>|/|%|\^|&|\+|\*|=|~)*")]
     LowerIdent(&'input str),
+    /// A term left to be written.
+    #[regex(r"\?([a-zA-Z0-9_]|'|\?|\+|\*|-|=|~)*")]
+    TermHole(&'input str),
+    #[token("_", priority = 10)]
+    Underscore,
 
     #[token("pub")]
     Pub,
@@ -34,11 +40,19 @@ pub enum Tok<'input> {
     Codata,
     #[token("alias")]
     Alias,
+    #[token("effect")]
+    Effect,
     #[token("def")]
     #[token("define")]
     Define,
     #[token("main")]
     Main,
+    #[token("infix")]
+    Infix,
+    #[token("infixl")]
+    InfixL,
+    #[token("infixr")]
+    InfixR,
     #[token("let")]
     Let,
     #[token("in")]
@@ -63,12 +77,18 @@ pub enum Tok<'input> {
     At,
     #[token("pack")]
     Pack,
+    #[token("handle")]
+    Handle,
+    #[token("with")]
+    With,
 
     #[regex(r"[\+-]?[0-9]+")]
     NumLit(&'input str),
     #[regex(r#""[^"\\]*(?:\\.[^"\\]*)*""#)]
     StrLit(&'input str),
-    #[regex(r#"'([ -~]|\\[nrt'|(\\)])'"#)]
+    // any printable character but `'` and `\`, spelled out as ranges since
+    // negated classes don't match multibyte characters here
+    #[regex(r#"'([ -&(-\[\]-~\u{80}-\u{10FFFF}]|\\[^u\n\r]|\\u\{[^}']*\})'"#)]
     CharLit(&'input str),
     #[token("VType")]
     VType,
@@ -103,6 +123,8 @@ pub enum Tok<'input> {
     Dot,
     #[token("..")]
     DotDot,
+    #[token("...")]
+    Ellipsis,
     #[token("->")]
     Arrow,
     #[token("<-")]
@@ -116,6 +138,8 @@ impl<'input> Display for Tok<'input> {
         match self {
             Tok::UpperIdent(s) => write!(f, "UpperIdentifier({})", s),
             Tok::LowerIdent(s) => write!(f, "LowerIdentifier({})", s),
+            Tok::TermHole(s) => write!(f, "TermHole({})", s),
+            Tok::Underscore => write!(f, "_"),
             Tok::Where => write!(f, "where"),
             Tok::End => write!(f, "end"),
             Tok::Pub => write!(f, "pub"),
@@ -125,8 +149,12 @@ impl<'input> Display for Tok<'input> {
             Tok::Data => write!(f, "data"),
             Tok::Codata => write!(f, "codata"),
             Tok::Alias => write!(f, "alias"),
+            Tok::Effect => write!(f, "effect"),
             Tok::Define => write!(f, "define"),
             Tok::Main => write!(f, "main"),
+            Tok::Infix => write!(f, "infix"),
+            Tok::InfixL => write!(f, "infixl"),
+            Tok::InfixR => write!(f, "infixr"),
             Tok::Let => write!(f, "let"),
             Tok::In => write!(f, "in"),
             Tok::Do => write!(f, "do"),
@@ -139,6 +167,8 @@ impl<'input> Display for Tok<'input> {
             Tok::Exists => write!(f, "Exists"),
             Tok::At => write!(f, "@"),
             Tok::Pack => write!(f, "exists"),
+            Tok::Handle => write!(f, "handle"),
+            Tok::With => write!(f, "with"),
             Tok::NumLit(s) => write!(f, "NumLiteral({})", s),
             Tok::StrLit(s) => write!(f, "StrLiteral({})", s),
             Tok::CharLit(s) => write!(f, "CharLiteral({})", s),
@@ -158,6 +188,7 @@ impl<'input> Display for Tok<'input> {
             Tok::Branch => write!(f, "|"),
             Tok::Dot => write!(f, "."),
             Tok::DotDot => write!(f, ".."),
+            Tok::Ellipsis => write!(f, "..."),
             Tok::Arrow => write!(f, "->"),
             Tok::Assign => write!(f, "<-"),
             Tok::Hole => write!(f, "_?"),
@@ -14,6 +14,58 @@ pub enum Pattern {
     TermPattern(TermPattern),
 }
 
+/// The `_` pattern, which matches anything and binds nothing.
+#[derive(SpanHolder, Clone, Debug)]
+pub struct Wildcard;
+
+/// A constructor applied to patterns of its arguments.
+#[derive(SpanHolder, Clone, Debug)]
+pub struct CtorPattern {
+    pub ctorv: CtorV,
+    pub args: Vec<Span<MatchPattern>>,
+}
+
+/// A destructor with names for its arguments, one step of a copattern.
+#[derive(SpanHolder, Clone, Debug)]
+pub struct DtorPattern {
+    pub dtorv: DtorV,
+    pub vars: Vec<NameDef>,
+}
+
+/// A tuple pattern `(a, b, c)`.
+#[derive(SpanHolder, Clone, Debug)]
+pub struct TuplePattern(pub Vec<Span<MatchPattern>>);
+
+/// The `]` ending a list literal or pattern.
+#[derive(SpanHolder, Clone, Debug)]
+pub struct ListEnd;
+
+#[derive(SpanHolder, Clone, Debug)]
+pub struct ListPatternCons {
+    pub head: Box<Span<MatchPattern>>,
+    pub tail: Box<Span<ListPattern>>,
+}
+
+/// A list pattern `[a, b, ...rest]`, from the element it's at onwards; without
+/// a rest pattern it matches lists of exactly the elements given.
+#[derive(IntoEnum, SpanHolder, Clone, Debug)]
+pub enum ListPattern {
+    Nil(ListEnd),
+    Cons(ListPatternCons),
+    Rest(Box<Span<MatchPattern>>),
+}
+
+/// A pattern of a `match` arm, where constructor patterns may nest.
+#[derive(IntoEnum, SpanHolder, Clone, Debug)]
+pub enum MatchPattern {
+    Ctor(CtorPattern),
+    Literal(Literal),
+    Tuple(TuplePattern),
+    List(ListPattern),
+    Var(NameDef),
+    Wildcard(Wildcard),
+}
+
 /* ---------------------------------- Kind ---------------------------------- */
 
 pub use crate::syntax::{KindBase, TypeArity};
@@ -38,6 +90,7 @@ pub enum Type {
     Arrow(Arrow<BoxType>),
     Forall(Forall<Vec<TypePattern>, BoxType>),
     Exists(Exists<Vec<TypePattern>, BoxType>),
+    Effectful(Effectful<NameRef, BoxType>),
     Hole(Hole),
 }
 pub type BoxType = Box<Span<Type>>;
@@ -45,6 +98,35 @@ impl TypeT for Type {}
 
 /* ---------------------------------- Term ---------------------------------- */
 
+/// A tuple `(a, b, c)`, built by the constructor of the std tuple type of its
+/// size.
+#[derive(SpanHolder, Clone, Debug)]
+pub struct Tuple(pub Vec<Span<TermValue>>);
+
+#[derive(SpanHolder, Clone, Debug)]
+pub struct ListLitCons {
+    pub head: BoxValue,
+    pub tail: Box<Span<ListLit>>,
+}
+
+/// A list literal `[a, b, c]`, from the element it's at onwards.
+#[derive(IntoEnum, SpanHolder, Clone, Debug)]
+pub enum ListLit {
+    Nil(ListEnd),
+    Cons(ListLitCons),
+}
+
+/// A piece of an interpolated string, either text or a `${x}` splice.
+#[derive(IntoEnum, SpanHolder, Clone, Debug)]
+pub enum InterpPart {
+    Text(Span<String>),
+    Var(NameRef),
+}
+
+/// A string literal `"x = ${x}"` with values spliced in.
+#[derive(SpanHolder, Clone, Debug)]
+pub struct Interpolation(pub Vec<InterpPart>);
+
 #[derive(IntoEnum, SpanHolder, Clone, Debug)]
 pub enum TermValue {
     TermAnn(Annotation<BoxValue, Span<Type>>),
@@ -52,7 +134,11 @@ pub enum TermValue {
     Thunk(Thunk<BoxComp>),
     Ctor(Ctor<CtorV, Span<TermValue>>),
     Literal(Literal),
+    Interpolation(Interpolation),
+    Tuple(Span<Tuple>),
+    List(Span<ListLit>),
     Pack(Pack<BoxType, BoxValue>),
+    Hole(TermHole),
 }
 pub type BoxValue = Box<Span<TermValue>>;
 impl ValueT for TermValue {}
@@ -72,6 +158,37 @@ pub struct Let {
     pub body: BoxComp,
 }
 
+#[derive(SpanHolder, Clone, Debug)]
+pub struct Match {
+    pub scrut: BoxValue,
+    pub arms: Vec<Matcher>,
+}
+#[derive(SpanHolder, Clone, Debug)]
+pub struct Matcher {
+    pub pat: Span<MatchPattern>,
+    pub body: Span<TermComputation>,
+}
+
+/// Values joined by operators, `x + y * z`, grouped by the fixities declared
+/// for the operators.
+#[derive(SpanHolder, Clone, Debug)]
+pub struct Infix {
+    pub head: BoxValue,
+    pub tail: Vec<(Span<String>, Span<TermValue>)>,
+}
+
+#[derive(SpanHolder, Clone, Debug)]
+pub struct Comatch {
+    pub arms: Vec<Comatcher>,
+}
+/// An arm of a `comatch`, observed by the sequence of destructors in its
+/// copattern.
+#[derive(SpanHolder, Clone, Debug)]
+pub struct Comatcher {
+    pub copat: Vec<DtorPattern>,
+    pub body: Span<TermComputation>,
+}
+
 #[derive(IntoEnum, SpanHolder, Clone, Debug)]
 pub enum TermComputation {
     TermAnn(Annotation<BoxComp, Span<Type>>),
@@ -80,13 +197,16 @@ pub enum TermComputation {
     Let(Let),
     Do(Do<TermPattern, BoxComp, BoxComp>),
     Rec(Rec<TermPattern, BoxComp>),
-    Match(Match<CtorV, NameDef, BoxValue, Span<TermComputation>>),
+    Match(Match),
     Abs(Abs<Vec<Pattern>, BoxComp>),
     App(App<BoxComp, BoxValue>),
-    Comatch(Comatch<DtorV, NameDef, Span<TermComputation>>),
+    Comatch(Comatch),
     Dtor(Dtor<BoxComp, DtorV, Span<TermValue>>),
     TyAppTerm(App<BoxComp, BoxType>),
     MatchPack(MatchPack<BoxValue, NameDef, NameDef, BoxComp>),
+    Handle(Handle<NameRef, NameDef, BoxComp>),
+    Infix(Infix),
+    Hole(TermHole),
 }
 pub type BoxComp = Box<Span<TermComputation>>;
 impl ComputationT for TermComputation {}
@@ -124,6 +244,33 @@ pub enum UseDef {
 #[derive(SpanHolder, Clone, Debug)]
 pub struct Define(pub GenLet);
 
+/// How an operator groups with the operators of the same precedence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Assoc {
+    Left,
+    Right,
+    Non,
+}
+
+/// An operator declaration `infixl 6 + = add`, making `x + y` apply `add`.
+#[derive(Clone, Debug)]
+pub struct Fixity {
+    pub assoc: Assoc,
+    pub prec: i64,
+    pub op: Span<String>,
+    pub def: NameRef,
+}
+
+impl SpanHolder for Fixity {
+    fn span_map_mut<F>(&mut self, f: F)
+    where
+        F: Fn(&mut SpanInfo) + Clone,
+    {
+        self.op.span_map_mut(f.clone());
+        self.def.span_map_mut(f);
+    }
+}
+
 #[derive(SpanHolder, Clone, Debug)]
 pub struct Main {
     pub entry: Span<TermComputation>,
@@ -136,7 +283,9 @@ pub enum Declaration {
     Data(Data<NameDef, Option<Span<Kind>>, CtorV, Span<Type>>),
     Codata(Codata<NameDef, Option<Span<Kind>>, DtorV, Span<Type>>),
     Alias(Alias<NameDef, Option<Span<Kind>>, BoxType>),
+    Effect(Effect<NameDef, NameDef, Span<Type>>),
     Define(Define),
+    Fixity(Fixity),
     Main(Main),
 }
 
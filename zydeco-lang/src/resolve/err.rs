@@ -9,6 +9,8 @@ pub enum NameResolveError {
     DuplicateCtorDeclaration { name: CtorV },
     #[error("{name} declared multiple times")]
     DuplicateDtorDeclaration { name: DtorV },
+    #[error("{name} declared multiple times")]
+    DuplicateOpDeclaration { name: TermV },
     #[error("{name} declared with neither type signature nor binding")]
     EmptyDeclaration { name: String },
     #[error("{name} declared as external but has implementation")]
@@ -19,6 +21,10 @@ pub enum NameResolveError {
     UnboundTypeVariable { tvar: TypeV },
     #[error("Unbound term variable {var}")]
     UnboundTermVariable { var: TermV },
+    #[error("Unbound effect {eff}")]
+    UnboundEffect { eff: TypeV },
+    #[error("Unknown effect operation {op}")]
+    UnknownOperation { op: TermV },
     #[error("Unknown constructor. In {context}, no constructor named {ctor} is found.")]
     UnknownConstructor { context: String, ctor: CtorV },
     #[error("Unknown destructor. In {context}, no destructor named {dtor} is found.")]
@@ -6,6 +6,14 @@ use crate::{
     utils::span::{Span, SpanInfo, SpanView},
 };
 use im::vector;
+use std::collections::HashSet;
+
+mod copattern;
+mod infix;
+mod interpolation;
+mod pattern;
+
+use interpolation::Binds;
 
 pub trait Elaboration<T>: Sized {
     type Error;
@@ -69,15 +77,27 @@ impl Elaboration<ps::Kind> for Kind {
                         params.insert(0, k.try_map(Elaboration::elab)?);
                         Ok(TypeArity { params, kd }.into())
                     }
+                    Kind::Hole(_) => unreachable!(),
                 }
             }
         }
     }
 }
 
+/// Elaborates the kind of a type parameter, which is left as a hole to be
+/// inferred when omitted.
+fn elab_kind_opt(
+    tvar: &NameDef, kd: Option<Span<ps::Kind>>,
+) -> Result<Span<Kind>, TyckErrorItem> {
+    match kd {
+        Some(kd) => kd.try_map(Elaboration::elab),
+        None => Ok(tvar.span().make(Hole.into())),
+    }
+}
+
 fn desugar_gen_let(
     rec: bool, fun: bool, (var, ty): (NameDef, Option<Span<ps::Type>>), params: Vec<ps::Pattern>,
-    def: Option<Box<Span<ps::Term>>>,
+    def: Option<Box<Span<ps::Term>>>, binds: Option<&mut Binds>,
 ) -> Result<(TermV, RcType, Option<RcValue>), TyckErrorItem> {
     let name = var.clone().into();
     let ty_rc = {
@@ -90,9 +110,16 @@ fn desugar_gen_let(
     let Some(def) = def else {
         return Ok((name, ty_rc, None));
     };
+    let def = Box::new((*def).map(|def| match def {
+        // a hole left for the body of a function is a computation
+        ps::Term::Value(ps::TermValue::Hole(hole)) if rec || fun => {
+            ps::Term::Computation(hole.into())
+        }
+        def => def,
+    }));
     match (rec, fun, def.inner) {
         (false, false, ps::Term::Value(value)) => {
-            Ok((name, ty_rc, Some(rc!(def.info.make(Elaboration::elab(value)?)))))
+            Ok((name, ty_rc, Some(rc!(def.info.make(elab_value(value, binds)?)))))
         }
         (_, _, ps::Term::Value(_)) => Err(TyckErrorItem::KindMismatch {
             context: format!("desugaring let"),
@@ -121,12 +148,7 @@ fn desugar_gen_let(
             for param in params.iter().rev() {
                 match param {
                     ps::Pattern::TypePattern((tvar, kd_param)) => {
-                        let Some(kd_dom) = kd_param else {
-                            Err(TyckErrorItem::NeedAnnotation {
-                                content: format!("gen let type variable elabrotion")
-                            })?
-                        };
-                        let kd_dom = kd_dom.clone().try_map(Elaboration::elab)?;
+                        let kd_dom = elab_kind_opt(tvar, kd_param.clone())?;
                         ty = rc!(tvar
                             .span()
                             .make(Forall { param: (tvar.into(), kd_dom.clone()), ty }.into()))
@@ -148,6 +170,18 @@ fn desugar_gen_let(
     }
 }
 
+/// The constructors of the std tuple types, by their number of elements from 2.
+const TUPLES: [&str; 3] = ["Pair", "Triple", "Quadruple"];
+
+/// The constructor of the std tuple type with as many elements as `elems`,
+/// spanning the tuple at `info`.
+fn tuple_ctor<T>(elems: &[Span<T>], info: &SpanInfo) -> Result<CtorV, TyckErrorItem> {
+    let Some(name) = elems.len().checked_sub(2).and_then(|i| TUPLES.get(i)) else {
+        Err(TyckErrorItem::TupleSize { size: elems.len(), info: info.clone() })?
+    };
+    Ok(CtorV::new(name.to_string(), info.clone()))
+}
+
 fn desugar_fn(
     ps::Abs { param, body }: ps::Abs<Vec<ps::Pattern>, ps::BoxComp>,
 ) -> Result<TermComputation, TyckErrorItem> {
@@ -155,7 +189,7 @@ fn desugar_fn(
         (var, ty): (NameDef, Option<Span<ps::Type>>), body: RcComp,
     ) -> Result<TermComputation, TyckErrorItem> {
         let mut body = Comatch {
-            arms: vec![ps::Comatcher {
+            arms: vec![Comatcher {
                 dtorv: DtorV::new(format!("arg"), SpanInfo::dummy()),
                 vars: vec![var.into()],
                 body,
@@ -216,10 +250,8 @@ impl Elaboration<ps::Type> for Type {
             ps::Type::Forall(ps::Forall { param: params, ty: t }) => {
                 let mut t = t.try_map(Elaboration::elab)?;
                 for (tvar, kd) in params.into_iter().rev() {
-                    let kd = kd.ok_or_else(|| TyckErrorItem::NeedAnnotation {
-                        content: format!("forall type variable elabrotion"),
-                    })?;
-                    let param = Elaboration::elab((tvar, kd))?;
+                    let kd = elab_kind_opt(&tvar, kd)?;
+                    let param = (tvar.into(), kd);
                     t = t.span().clone().make(Forall { param, ty: rc!(t) }.into())
                 }
                 t.inner
@@ -227,14 +259,22 @@ impl Elaboration<ps::Type> for Type {
             ps::Type::Exists(ps::Exists { param: params, ty: t }) => {
                 let mut t = t.try_map(Elaboration::elab)?;
                 for (tvar, kd) in params.into_iter().rev() {
-                    let kd = kd.ok_or_else(|| TyckErrorItem::NeedAnnotation {
-                        content: format!("exists type variable elabrotion"),
-                    })?;
-                    let param = Elaboration::elab((tvar, kd))?;
+                    let kd = elab_kind_opt(&tvar, kd)?;
+                    let param = (tvar.into(), kd);
                     t = t.span().clone().make(Exists { param, ty: rc!(t) }.into())
                 }
                 t.inner
             }
+            ps::Type::Effectful(ps::Effectful { effs, ty }) => {
+                let ty = ty.try_map(Elaboration::elab)?;
+                // an empty annotation performs no effects
+                if effs.is_empty() {
+                    ty.inner
+                } else {
+                    let effs = effs.into_iter().map(Into::into).collect();
+                    Effectful { effs, ty: rc!(ty) }.into()
+                }
+            }
             ps::Type::Hole(ps::Hole) => Hole.into(),
         })
     }
@@ -243,175 +283,250 @@ impl Elaboration<ps::Type> for Type {
 impl Elaboration<ps::TermValue> for TermValue {
     type Error = TyckErrorItem;
     fn elab(value: ps::TermValue) -> Result<Self, TyckErrorItem> {
-        Ok(match value {
-            ps::TermValue::TermAnn(Annotation { term: body, ty }) => Annotation {
-                term: rc!(body.try_map(Elaboration::elab)?),
-                ty: rc!(ty.try_map(Elaboration::elab)?),
-            }
-            .into(),
-            ps::TermValue::Var(x) => TermV::from(x).into(),
-            ps::TermValue::Thunk(Thunk(body)) => {
-                let span = body.span().clone();
-                let body = Thunk(rc!((body).try_map(Elaboration::elab)?)).into();
-                Annotation {
-                    term: rc!(span.make(body)),
-                    ty: rc!(span.make(Type::make_thunk(rc!(span.make(Hole.into()))))),
-                }
-                .into()
-            }
-            ps::TermValue::Ctor(Ctor { ctorv: ctor, args }) => Ctor {
-                ctorv: ctor,
-                args: Vec::<_>::elab(args)?.into_iter().map(|arg| rc!(arg)).collect(),
-            }
-            .into(),
-            ps::TermValue::Literal(t) => t.into(),
-            ps::TermValue::Pack(ps::Pack { ty, body }) => {
-                let ty = ty.try_map(Elaboration::elab)?;
-                let body = body.try_map(Elaboration::elab)?;
-                Pack { ty: rc!(ty), body: rc!(body) }.into()
-            }
-        })
+        elab_value(value, None)
     }
 }
 
+/// Elaborates a value, collecting in `binds` the computations its interpolated
+/// strings need run first; outside of computations there's nowhere to run
+/// them, and `binds` is `None`.
+fn elab_value(
+    value: ps::TermValue, binds: Option<&mut Binds>,
+) -> Result<TermValue, TyckErrorItem> {
+    Ok(match value {
+        ps::TermValue::TermAnn(Annotation { term: body, ty }) => Annotation {
+            term: rc_value(*body, binds)?,
+            ty: rc!(ty.try_map(Elaboration::elab)?),
+        }
+        .into(),
+        ps::TermValue::Var(x) => TermV::from(x).into(),
+        ps::TermValue::Thunk(Thunk(body)) => {
+            let span = body.span().clone();
+            let body = Thunk(rc!((body).try_map(Elaboration::elab)?)).into();
+            Annotation {
+                term: rc!(span.make(body)),
+                ty: rc!(span.make(Type::make_thunk(rc!(span.make(Hole.into()))))),
+            }
+            .into()
+        }
+        ps::TermValue::Ctor(Ctor { ctorv: ctor, args }) => {
+            Ctor { ctorv: ctor, args: rc_values(args, binds)? }.into()
+        }
+        ps::TermValue::Literal(t) => t.into(),
+        ps::TermValue::Interpolation(t) => interpolation::elab(t, binds)?,
+        ps::TermValue::Tuple(Span { inner: ps::Tuple(elems), info }) => {
+            Ctor { ctorv: tuple_ctor(&elems, &info)?, args: rc_values(elems, binds)? }.into()
+        }
+        ps::TermValue::List(list) => list_lit(list, binds)?,
+        ps::TermValue::Pack(ps::Pack { ty, body }) => {
+            let ty = ty.try_map(Elaboration::elab)?;
+            Pack { ty: rc!(ty), body: rc_value(*body, binds)? }.into()
+        }
+        ps::TermValue::Hole(hole) => hole.into(),
+    })
+}
+
+/// Elaborates a value with its span, see `elab_value`.
+fn rc_value(
+    value: Span<ps::TermValue>, binds: Option<&mut Binds>,
+) -> Result<RcValue, TyckErrorItem> {
+    Ok(rc!(value.try_map(|value| elab_value(value, binds))?))
+}
+
+/// Elaborates values one after another, see `elab_value`.
+fn rc_values(
+    values: Vec<Span<ps::TermValue>>, mut binds: Option<&mut Binds>,
+) -> Result<Vec<RcValue>, TyckErrorItem> {
+    values.into_iter().map(|value| rc_value(value, binds.as_deref_mut())).collect()
+}
+
+/// Builds a list literal from the constructors of `List`; an element is where
+/// its `Cons` is, and the rest of the literal where its `Nil` is.
+fn list_lit(
+    Span { inner, info }: Span<ps::ListLit>, mut binds: Option<&mut Binds>,
+) -> Result<TermValue, TyckErrorItem> {
+    Ok(match inner {
+        ps::ListLit::Nil(ps::ListEnd) => {
+            Ctor { ctorv: CtorV::new(format!("Nil"), info), args: vec![] }.into()
+        }
+        ps::ListLit::Cons(ps::ListLitCons { head, tail }) => {
+            let ctorv = CtorV::new(format!("Cons"), head.span().clone());
+            let tail_info = tail.span().clone();
+            let head = rc_value(*head, binds.as_deref_mut())?;
+            let args = vec![head, rc!(tail_info.make(list_lit(*tail, binds)?))];
+            Ctor { ctorv, args }.into()
+        }
+    })
+}
+
 impl Elaboration<ps::TermComputation> for TermComputation {
     type Error = TyckErrorItem;
     fn elab(comp: ps::TermComputation) -> Result<Self, TyckErrorItem> {
-        Ok(match comp {
-            ps::TermComputation::TermAnn(Annotation { term: body, ty }) => Annotation {
-                term: rc!(body.try_map(Elaboration::elab)?),
-                ty: rc!(ty.try_map(Elaboration::elab)?),
-            }
-            .into(),
-            ps::TermComputation::Ret(Ret(body)) => {
-                let span = body.span().clone();
-                let body: TermComputation = Ret(rc!((body).try_map(Elaboration::elab)?)).into();
-                Annotation {
-                    term: rc!(span.make(body)),
-                    ty: rc!(span.make(Type::make_ret(rc!(span.make(Hole.into()))))),
-                }
-                .into()
-            }
-            ps::TermComputation::Force(Force(body)) => {
-                Force(rc!((body).try_map(Elaboration::elab)?)).into()
-            }
-            ps::TermComputation::Let(ps::Let {
-                gen: ps::GenLet { rec, fun, name, params, def },
-                body,
-            }) => {
-                let (var, ty, def) = desugar_gen_let(rec, fun, name, params, def)?;
-                let Some(def) = def else {
-                    Err(NameResolveError::EmptyDeclaration { name: var.name().to_string() })?
-                };
-                let mut def = def;
-                let span = def.span().clone();
-                def = rc!(span.make(ps::Annotation { term: def, ty }.into()));
-                let body: Span<TermComputation> = body.try_map(Elaboration::elab)?;
-                let item = Let { var, def, body: () }.into();
-                if let TermComputation::TailGroup(TailGroup { mut group, body }) = body.inner {
-                    group.push_front(item);
-                    TailGroup { group, body }.into()
-                } else {
-                    TailGroup { group: vector![item], body: rc!(body) }.into()
-                }
-            }
-            ps::TermComputation::Do(ps::Do { var: (var, ty), comp, body }) => {
-                let var = TermV::from(var);
-                let mut comp = rc!((comp).try_map(Elaboration::elab)?);
-                if let Some(ty) = ty {
-                    comp = rc!(comp.info.clone().make(
-                        Annotation { term: comp, ty: rc!(ty.try_map(Elaboration::elab)?) }.into(),
-                    ));
-                }
-                let body = body.try_map(Elaboration::elab)?;
-                let item = Do { var, comp, body: () }.into();
-                if let TermComputation::TailGroup(TailGroup { mut group, body }) = body.inner {
-                    group.push_front(item);
-                    TailGroup { group, body }.into()
-                } else {
-                    TailGroup { group: vector![item], body: rc!(body) }.into()
-                }
-            }
-            ps::TermComputation::Rec(Rec { var: (var, ty), body }) => {
-                let var = TermV::from(var);
-                let body = rc!((body).try_map(Elaboration::elab)?);
-                let span = body.span().clone();
-                let mut body: TermComputation = Rec { var, body }.into();
-                if let Some(ty) = ty {
-                    let ty: Span<Type> = ty.try_map(Elaboration::elab)?;
-                    let ty_ = ty.inner.clone();
-                    let SynType::TypeApp(ty_app) = ty.inner.synty else {
-                        Err(TyckErrorItem::TypeExpected {
-                            context: format!("elaborating recursion"),
-                            expected: format!("{{a}}"),
-                            found: ty_
-                        })?
-                    };
-                    let Some(ty) = ty_app.elim_thunk_syntax() else {
-                        Err(TyckErrorItem::TypeExpected {
-                            context: format!("elaborating recursion"),
-                            expected: format!("{{a}}"),
-                            found: ty_
-                        })?
-                    };
-                    body = Annotation { term: rc!(span.make(body)), ty: rc!(span.make(ty)) }.into();
-                }
-                body
-            }
-            ps::TermComputation::Match(ps::Match { scrut, arms }) => {
-                let scrut = rc!((scrut).try_map(Elaboration::elab)?);
-                let arms = arms
-                    .into_iter()
-                    .map(|arm| {
-                        let ps::Matcher { ctorv, vars, body } = arm;
-                        let vars = vars.into_iter().map(Into::into).collect();
-                        let body = rc!((body).try_map(Elaboration::elab)?);
-                        Ok(Matcher { ctorv, vars, body })
-                    })
-                    .collect::<Result<_, TyckErrorItem>>()?;
-                Match { scrut, arms }.into()
-            }
-            ps::TermComputation::Abs(t) => desugar_fn(t)?,
-            ps::TermComputation::App(ps::App { body, arg }) => {
-                let fun = rc!((body).try_map(Elaboration::elab)?);
-                let arg = arg.try_map(Elaboration::elab)?;
-                Dtor {
-                    body: fun,
-                    dtorv: DtorV::new(format!("arg"), SpanInfo::dummy()),
-                    args: vec![rc!(arg)],
-                }
-                .into()
+        let mut binds = Binds::new();
+        let comp = elab_comp(comp, &mut binds)?;
+        Ok(interpolation::bind_all(binds, comp))
+    }
+}
+
+fn elab_comp(
+    comp: ps::TermComputation, binds: &mut Binds,
+) -> Result<TermComputation, TyckErrorItem> {
+    Ok(match comp {
+        ps::TermComputation::TermAnn(Annotation { term: body, ty }) => Annotation {
+            term: rc!(body.try_map(Elaboration::elab)?),
+            ty: rc!(ty.try_map(Elaboration::elab)?),
+        }
+        .into(),
+        ps::TermComputation::Ret(Ret(body)) => {
+            let span = body.span().clone();
+            let body: TermComputation = Ret(rc_value(*body, Some(binds))?).into();
+            Annotation {
+                term: rc!(span.make(body)),
+                ty: rc!(span.make(Type::make_ret(rc!(span.make(Hole.into()))))),
             }
-            ps::TermComputation::Comatch(ps::Comatch { arms }) => {
-                let arms = arms
-                    .into_iter()
-                    .map(|arm| {
-                        let ps::Comatcher { dtorv, vars, body } = arm;
-                        let vars = vars.into_iter().map(Into::into).collect();
-                        let body = rc!((body).try_map(Elaboration::elab)?);
-                        Ok(Comatcher { dtorv, vars, body })
-                    })
-                    .collect::<Result<Vec<_>, TyckErrorItem>>()?;
-                Comatch { arms }.into()
+            .into()
+        }
+        ps::TermComputation::Force(Force(body)) => Force(rc_value(*body, Some(binds))?).into(),
+        ps::TermComputation::Let(ps::Let {
+            gen: ps::GenLet { rec, fun, name, params, def },
+            body,
+        }) => {
+            let (var, ty, def) = desugar_gen_let(rec, fun, name, params, def, Some(binds))?;
+            let Some(def) = def else {
+                Err(NameResolveError::EmptyDeclaration { name: var.name().to_string() })?
+            };
+            let mut def = def;
+            let span = def.span().clone();
+            def = rc!(span.make(ps::Annotation { term: def, ty }.into()));
+            let body: Span<TermComputation> = body.try_map(Elaboration::elab)?;
+            let item = Let { var, def, body: () }.into();
+            if let TermComputation::TailGroup(TailGroup { mut group, body }) = body.inner {
+                group.push_front(item);
+                TailGroup { group, body }.into()
+            } else {
+                TailGroup { group: vector![item], body: rc!(body) }.into()
             }
-            ps::TermComputation::Dtor(ps::Dtor { body, dtorv, args }) => {
-                let body = rc!((body).try_map(Elaboration::elab)?);
-                let args = Vec::<_>::elab(args)?.into_iter().map(|arg| rc!(arg)).collect();
-                Dtor { body, dtorv, args }.into()
+        }
+        ps::TermComputation::Do(ps::Do { var: (var, ty), comp, body }) => {
+            let var = TermV::from(var);
+            let mut comp = rc!((comp).try_map(Elaboration::elab)?);
+            if let Some(ty) = ty {
+                comp = rc!(comp.info.clone().make(
+                    Annotation { term: comp, ty: rc!(ty.try_map(Elaboration::elab)?) }.into(),
+                ));
             }
-            ps::TermComputation::TyAppTerm(ps::App { body, arg }) => {
-                let body = rc!((body).try_map(Elaboration::elab)?);
-                let arg = rc!(arg.try_map(Elaboration::elab)?);
-                App { body, arg }.into()
+            let body = body.try_map(Elaboration::elab)?;
+            let item = Do { var, comp, body: () }.into();
+            if let TermComputation::TailGroup(TailGroup { mut group, body }) = body.inner {
+                group.push_front(item);
+                TailGroup { group, body }.into()
+            } else {
+                TailGroup { group: vector![item], body: rc!(body) }.into()
             }
-            ps::TermComputation::MatchPack(ps::MatchPack { scrut, tvar, var, body }) => {
-                let tvar = tvar.into();
-                let var = var.into();
-                let scrut = rc!((scrut).try_map(Elaboration::elab)?);
+        }
+        ps::TermComputation::Rec(rec) => elab_rec(rec)?,
+        ps::TermComputation::Match(ps::Match { scrut, arms }) => {
+            let scrut = rc_value(*scrut, Some(binds))?;
+            let arms = arms
+                .into_iter()
+                .map(|arm| {
+                    let ps::Matcher { pat, body } = arm;
+                    let body = rc!((body).try_map(Elaboration::elab)?);
+                    Ok((pat, body))
+                })
+                .collect::<Result<_, TyckErrorItem>>()?;
+            pattern::elab(scrut, arms)?
+        }
+        ps::TermComputation::Abs(t) => desugar_fn(t)?,
+        ps::TermComputation::App(ps::App { body, arg }) => {
+            let fun = rc!((body).try_map(Elaboration::elab)?);
+            let arg = rc_value(*arg, Some(binds))?;
+            Dtor { body: fun, dtorv: DtorV::new(format!("arg"), SpanInfo::dummy()), args: vec![arg] }
+            .into()
+        }
+        ps::TermComputation::Comatch(ps::Comatch { arms }) => {
+            let arms = arms
+                .into_iter()
+                .map(|arm| {
+                    let ps::Comatcher { copat, body } = arm;
+                    let body = rc!((body).try_map(Elaboration::elab)?);
+                    Ok((copat, body))
+                })
+                .collect::<Result<_, TyckErrorItem>>()?;
+            copattern::compile(arms)?
+        }
+        ps::TermComputation::Dtor(ps::Dtor { body, dtorv, args }) => {
+            let body = rc!((body).try_map(Elaboration::elab)?);
+            let args = rc_values(args, Some(binds))?;
+            Dtor { body, dtorv, args }.into()
+        }
+        ps::TermComputation::TyAppTerm(ps::App { body, arg }) => {
+            let body = rc!((body).try_map(Elaboration::elab)?);
+            let arg = rc!(arg.try_map(Elaboration::elab)?);
+            App { body, arg }.into()
+        }
+        ps::TermComputation::MatchPack(ps::MatchPack { scrut, tvar, var, body }) => {
+            let tvar = tvar.into();
+            let var = var.into();
+            let scrut = rc_value(*scrut, Some(binds))?;
+            let body = rc!((body).try_map(Elaboration::elab)?);
+            MatchPack { scrut, tvar, var, body }.into()
+        }
+        ps::TermComputation::Handle(handle) => Handle::elab(handle)?.into(),
+        ps::TermComputation::Infix(infix) => infix::elab(infix, binds)?,
+        ps::TermComputation::Hole(hole) => hole.into(),
+    })
+}
+
+/// Elaborates a `rec`, whose annotation has to be a thunk type.
+fn elab_rec(
+    Rec { var: (var, ty), body }: Rec<ps::TermPattern, ps::BoxComp>,
+) -> Result<TermComputation, TyckErrorItem> {
+    let var = TermV::from(var);
+    let body = rc!((body).try_map(Elaboration::elab)?);
+    let span = body.span().clone();
+    let mut body: TermComputation = Rec { var, body }.into();
+    if let Some(ty) = ty {
+        let ty: Span<Type> = ty.try_map(Elaboration::elab)?;
+        let ty_ = ty.inner.clone();
+        let SynType::TypeApp(ty_app) = ty.inner.synty else {
+            Err(TyckErrorItem::TypeExpected {
+                context: format!("elaborating recursion"),
+                expected: format!("{{a}}"),
+                found: ty_
+            })?
+        };
+        let Some(ty) = ty_app.elim_thunk_syntax() else {
+            Err(TyckErrorItem::TypeExpected {
+                context: format!("elaborating recursion"),
+                expected: format!("{{a}}"),
+                found: ty_
+            })?
+        };
+        body = Annotation { term: rc!(span.make(body)), ty: rc!(span.make(ty)) }.into();
+    }
+    Ok(body)
+}
+
+impl Elaboration<ps::Handle<NameRef, NameDef, ps::BoxComp>> for Handle<TermV, TermV, RcComp> {
+    type Error = TyckErrorItem;
+    fn elab(handle: ps::Handle<NameRef, NameDef, ps::BoxComp>) -> Result<Self, TyckErrorItem> {
+        let ps::Handle { body, ret, arms } = handle;
+        let body = rc!((body).try_map(Elaboration::elab)?);
+        let ret = match ret {
+            Some((var, body)) => Some((var.into(), rc!((body).try_map(Elaboration::elab)?))),
+            None => None,
+        };
+        let arms = arms
+            .into_iter()
+            .map(|arm| {
+                let ps::Handler { op, vars, kont, body } = arm;
+                let vars = vars.into_iter().map(Into::into).collect();
                 let body = rc!((body).try_map(Elaboration::elab)?);
-                MatchPack { scrut, tvar, var, body }.into()
-            }
-        })
+                Ok(Handler { op: op.into(), vars, kont: kont.into(), body })
+            })
+            .collect::<Result<_, TyckErrorItem>>()?;
+        Ok(Handle { body, ret, arms })
     }
 }
 
@@ -439,18 +554,11 @@ impl Elaboration<ps::Data<NameDef, Option<Span<ps::Kind>>, CtorV, Span<ps::Type>
     ) -> Result<Self, TyckErrorItem> {
         let params = params
             .into_iter()
-            .map(|(tvar, kd)| {
-                Ok((
-                    tvar,
-                    kd.ok_or_else(|| TyckErrorItem::NeedAnnotation {
-                        content: format!("elaborating data type"),
-                    })?,
-                ))
-            })
+            .map(|(tvar, kd)| Ok((tvar.clone().into(), elab_kind_opt(&tvar, kd)?)))
             .collect::<Result<_, TyckErrorItem>>()?;
         Ok(Self {
             name: name.into(),
-            params: Elaboration::elab(params)?,
+            params,
             ctors: Elaboration::elab(ctors)?,
         })
     }
@@ -480,18 +588,11 @@ impl Elaboration<ps::Codata<NameDef, Option<Span<ps::Kind>>, DtorV, Span<ps::Typ
     ) -> Result<Self, TyckErrorItem> {
         let params = params
             .into_iter()
-            .map(|(tvar, kd)| {
-                Ok((
-                    tvar,
-                    kd.ok_or_else(|| TyckErrorItem::NeedAnnotation {
-                        content: format!("elaborating codata type"),
-                    })?,
-                ))
-            })
+            .map(|(tvar, kd)| Ok((tvar.clone().into(), elab_kind_opt(&tvar, kd)?)))
             .collect::<Result<_, TyckErrorItem>>()?;
         Ok(Self {
             name: name.into(),
-            params: Elaboration::elab(params)?,
+            params,
             dtors: Elaboration::elab(dtors)?,
         })
     }
@@ -514,27 +615,36 @@ impl Elaboration<ps::Alias<NameDef, Option<Span<ps::Kind>>, ps::BoxType>> for pr
     ) -> Result<Self, TyckErrorItem> {
         let params = params
             .into_iter()
-            .map(|(tvar, kd)| {
-                Ok((
-                    tvar,
-                    kd.ok_or_else(|| TyckErrorItem::NeedAnnotation {
-                        content: format!("elaborating alias type"),
-                    })?,
-                ))
-            })
+            .map(|(tvar, kd)| Ok((tvar.clone().into(), elab_kind_opt(&tvar, kd)?)))
             .collect::<Result<_, TyckErrorItem>>()?;
         Ok(Self {
             name: name.into(),
-            params: Elaboration::elab(params)?,
+            params,
             ty: rc!(ty.try_map(Elaboration::elab)?),
         })
     }
 }
 
+impl Elaboration<ps::Effect<NameDef, NameDef, Span<ps::Type>>> for prelude::Effect {
+    type Error = TyckErrorItem;
+    fn elab(
+        Effect { name, ops }: ps::Effect<NameDef, NameDef, Span<ps::Type>>,
+    ) -> Result<Self, TyckErrorItem> {
+        let ops = ops
+            .into_iter()
+            .map(|EffectOp { name, tys, ty }| {
+                let tys = Vec::<_>::elab(tys)?.into_iter().map(|ty| rc!(ty)).collect();
+                Ok(EffectOp { name: name.into(), tys, ty: rc!(ty.try_map(Elaboration::elab)?) })
+            })
+            .collect::<Result<_, TyckErrorItem>>()?;
+        Ok(Self { name: name.into(), ops })
+    }
+}
+
 impl Elaboration<ps::Module> for Module {
     type Error = TyckErrorItem;
     fn elab(ps::Module { name, declarations }: ps::Module) -> Result<Self, TyckErrorItem> {
-        let mut module: Module = Elaboration::elab(ps::TopLevel { declarations })?;
+        let mut module = elab_declarations(declarations)?;
         module.name = name.map(|name| name.ident.inner);
         Ok(module)
     }
@@ -543,64 +653,92 @@ impl Elaboration<ps::Module> for Module {
 impl Elaboration<ps::TopLevel> for Module {
     type Error = TyckErrorItem;
     fn elab(ps::TopLevel { declarations }: ps::TopLevel) -> Result<Self, TyckErrorItem> {
-        let mut data = Vec::new();
-        let mut codata = Vec::new();
-        let mut alias = Vec::new();
-        let mut define = Vec::new();
-        let mut define_ext = Vec::new();
-        for declaration in declarations {
-            let DeclSymbol { public, external, inner } = declaration;
-            match inner {
-                ps::Declaration::Module(m) => {
-                    let Module {
-                        name: _,
-                        data: ds,
-                        codata: cs,
-                        alias: aliases,
-                        define: defs,
-                        define_ext: defexts,
-                    } = Elaboration::elab(m)?;
-                    data.extend(ds);
-                    codata.extend(cs);
-                    alias.extend(aliases);
-                    define.extend(defs);
-                    define_ext.extend(defexts);
-                }
-                ps::Declaration::UseDef(_d) => {}
-                ps::Declaration::Data(d) => {
-                    data.push(DeclSymbol { public, external, inner: Elaboration::elab(d)? })
-                }
-                ps::Declaration::Codata(d) => {
-                    codata.push(DeclSymbol { public, external, inner: Elaboration::elab(d)? })
-                }
-                ps::Declaration::Alias(d) => {
-                    alias.push(DeclSymbol { public, external, inner: Elaboration::elab(d)? })
-                }
-                ps::Declaration::Define(d) => {
-                    let ps::Define(ps::GenLet { rec, fun, name, params, def }) = d;
-                    let (name, ty, te) = desugar_gen_let(rec, fun, name, params, def)?;
-                    if external {
-                        define_ext.push(DeclSymbol {
-                            public,
-                            external,
-                            inner: Define { name: (name, ty), def: () },
-                        })
-                    } else {
-                        let term = te.ok_or_else(|| NameResolveError::EmptyDeclaration {
-                            name: name.name().to_string(),
-                        })?;
-                        let span = term.span().clone();
-                        let def = rc!(span.make(Annotation { term, ty }.into()));
-                        define.push(DeclSymbol { public, external, inner: Define { name, def } })
-                    }
+        elab_declarations(declarations)
+    }
+}
+
+fn elab_declarations(
+    declarations: Vec<DeclSymbol<ps::Declaration>>,
+) -> Result<Module, TyckErrorItem> {
+    let mut data = Vec::new();
+    let mut codata = Vec::new();
+    let mut alias = Vec::new();
+    let mut effect = Vec::new();
+    let mut fixity = Vec::new();
+    let mut define = Vec::new();
+    let mut define_ext = Vec::new();
+    let mut ops = HashSet::new();
+    for declaration in declarations {
+        let DeclSymbol { public, external, inner } = declaration;
+        match inner {
+            ps::Declaration::Module(m) => {
+                let Module {
+                    name: _,
+                    data: ds,
+                    codata: cs,
+                    alias: aliases,
+                    effect: effs,
+                    fixity: fixs,
+                    define: defs,
+                    define_ext: defexts,
+                } = Elaboration::elab(m)?;
+                data.extend(ds);
+                codata.extend(cs);
+                alias.extend(aliases);
+                effect.extend(effs);
+                fixity.extend(fixs);
+                define.extend(defs);
+                define_ext.extend(defexts);
+            }
+            ps::Declaration::UseDef(_d) => {}
+            ps::Declaration::Data(d) => {
+                data.push(DeclSymbol { public, external, inner: Elaboration::elab(d)? })
+            }
+            ps::Declaration::Codata(d) => {
+                codata.push(DeclSymbol { public, external, inner: Elaboration::elab(d)? })
+            }
+            ps::Declaration::Alias(d) => {
+                alias.push(DeclSymbol { public, external, inner: Elaboration::elab(d)? })
+            }
+            ps::Declaration::Effect(d) => {
+                effect.push(DeclSymbol { public, external, inner: Elaboration::elab(d)? })
+            }
+            ps::Declaration::Define(d) => {
+                let ps::Define(ps::GenLet { rec, fun, name, params, def }) = d;
+                let (name, ty, te) = desugar_gen_let(rec, fun, name, params, def, None)?;
+                if external {
+                    define_ext.push(DeclSymbol {
+                        public,
+                        external,
+                        inner: Define { name: (name, ty), def: () },
+                    })
+                } else {
+                    let term = te.ok_or_else(|| NameResolveError::EmptyDeclaration {
+                        name: name.name().to_string(),
+                    })?;
+                    let span = term.span().clone();
+                    let def = rc!(span.make(Annotation { term, ty }.into()));
+                    define.push(DeclSymbol { public, external, inner: Define { name, def } })
                 }
-                ps::Declaration::Main(ps::Main { entry: _ }) => {
-                    Err(TyckErrorItem::MainEntryInModule)?
+            }
+            ps::Declaration::Fixity(d) => {
+                let (fix, def) = infix::declare(d);
+                // an operator is declared once per module, and shadows those
+                // of other modules
+                if ops.contains(fix.op.inner_ref()) {
+                    let name = TermV::new(fix.op.inner_clone(), fix.op.span().clone());
+                    Err(NameResolveError::DuplicateOpDeclaration { name })?
                 }
+                ops.insert(fix.op.inner_clone());
+                fixity.push(DeclSymbol { public, external, inner: fix });
+                define.push(DeclSymbol { public, external, inner: def });
+            }
+            ps::Declaration::Main(ps::Main { entry: _ }) => {
+                Err(TyckErrorItem::MainEntryInModule)?
             }
         }
-        Ok(Self { name: None, data, codata, alias, define, define_ext })
     }
+    Ok(Module { name: None, data, codata, alias, effect, fixity, define, define_ext })
 }
 
 impl Elaboration<ps::TopLevel> for Program {
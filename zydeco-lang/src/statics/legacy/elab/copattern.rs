@@ -0,0 +1,120 @@
+//! Compiles the copatterns of `comatch` arms, sequences of destructors
+//! observing a computation, into the flat comatches of the core syntax. Arms
+//! are grouped by their first destructor, and each group observes the rest of
+//! its copatterns in a comatch of its own. Type checking makes sure every
+//! observation of a codata type has an arm; an observation more than one arm
+//! handles is rejected here.
+
+use super::*;
+use std::collections::VecDeque;
+
+/// An arm with the destructors of its copattern not observed yet, and the
+/// variables the ones observed bound.
+struct Row {
+    copat: VecDeque<ps::DtorPattern>,
+    binds: Vec<(TermV, RcValue)>,
+    body: RcComp,
+}
+
+/// The rows observing a destructor next, each with the names it gives the
+/// arguments.
+type Group = (DtorV, Vec<(Vec<TermV>, Row)>);
+
+struct Compiler {
+    fresh: usize,
+}
+
+/// Compiles a `comatch` with the copatterns of `arms` into nested flat
+/// comatches.
+pub(super) fn compile(
+    arms: Vec<(Vec<ps::DtorPattern>, RcComp)>,
+) -> Result<TermComputation, TyckErrorItem> {
+    let rows = arms
+        .into_iter()
+        .map(|(copat, body)| Row { copat: copat.into(), binds: Vec::new(), body })
+        .collect();
+    Compiler { fresh: 0 }.compile(rows, &[])
+}
+
+impl Compiler {
+    fn fresh(&mut self, info: &SpanInfo) -> TermV {
+        self.fresh += 1;
+        TermV::new(format!("$copat{}", self.fresh), info.clone())
+    }
+
+    /// Compiles the `rows` observed after the destructors in `path`.
+    fn compile(
+        &mut self, rows: Vec<Row>, path: &[DtorV],
+    ) -> Result<TermComputation, TyckErrorItem> {
+        let mut groups: Vec<Group> = Vec::new();
+        for mut row in rows {
+            let ps::DtorPattern { dtorv, vars } =
+                row.copat.pop_front().expect("copattern without destructors");
+            let vars: Vec<TermV> = vars.into_iter().map(Into::into).collect();
+            match groups.iter_mut().find(|(first, _)| *first == dtorv) {
+                Some((_, group)) => {
+                    let expected = group[0].0.len();
+                    if vars.len() != expected {
+                        Err(TyckErrorItem::ArityMismatch {
+                            context: format!("the copattern of `{}` ({})", dtorv, dtorv.span()),
+                            expected,
+                            found: vars.len(),
+                        })?
+                    }
+                    // an arm that ends at the destructor handles every
+                    // observation the others do
+                    if row.copat.is_empty() || group.iter().any(|(_, row)| row.copat.is_empty()) {
+                        let path = path.iter().chain([&dtorv]);
+                        Err(TyckErrorItem::OverlappingCopatterns {
+                            path: path
+                                .map(|dtorv| format!(".{}", dtorv))
+                                .collect::<Vec<_>>()
+                                .join(" "),
+                            info: dtorv.span().clone(),
+                        })?
+                    }
+                    group.push((vars, row));
+                }
+                None => groups.push((dtorv, vec![(vars, row)])),
+            }
+        }
+        let mut arms = Vec::new();
+        for (dtorv, group) in groups {
+            let arity = group[0].0.len();
+            // an argument every arm names alike, and that doesn't shadow a
+            // variable to be bound later, is bound to that name directly
+            let vars: Vec<TermV> = (0..arity)
+                .map(|i| {
+                    let name = &group[0].0[i];
+                    let alike = group.iter().all(|(vars, row)| {
+                        &vars[i] == name && row.binds.iter().all(|(var, _)| var != name)
+                    });
+                    if alike {
+                        name.clone()
+                    } else {
+                        self.fresh(dtorv.span())
+                    }
+                })
+                .collect();
+            let mut rows = Vec::new();
+            for (names, mut row) in group {
+                for (name, var) in names.into_iter().zip(&vars) {
+                    if &name != var {
+                        row.binds.push((name, rc!(dtorv.span().make(var.clone().into()))));
+                    }
+                }
+                rows.push(row);
+            }
+            let body = if rows[0].copat.is_empty() {
+                let Row { binds, body, .. } = rows.pop().unwrap();
+                TailGroup::bind(binds, body)
+            } else {
+                let mut path = path.to_vec();
+                path.push(dtorv.clone());
+                rc!(dtorv.span().make(self.compile(rows, &path)?))
+            };
+            arms.push(Comatcher { dtorv, vars, body });
+        }
+        Ok(Comatch { arms }.into())
+    }
+}
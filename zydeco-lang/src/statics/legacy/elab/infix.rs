@@ -0,0 +1,34 @@
+//! Elaborates infix expressions `x + y * z`. An operator declaration binds
+//! its definition to the operator in parentheses, `(+)`, which no variable of
+//! the program can be named, so an operator applies the definition it was
+//! declared with wherever it's used, even where `+` is bound by the program.
+//! Grouping the operators by their fixities is left to type checking, which
+//! knows the declarations in scope.
+
+use super::*;
+use std::{cell::OnceCell, rc::Rc};
+
+/// Elaborates an infix expression, leaving its operators ungrouped.
+pub(super) fn elab(
+    ps::Infix { head, tail }: ps::Infix, binds: &mut Binds,
+) -> Result<TermComputation, TyckErrorItem> {
+    let head = rc_value(*head, Some(binds))?;
+    let tail = tail
+        .into_iter()
+        .map(|(op, operand)| Ok((op, rc_value(operand, Some(binds))?)))
+        .collect::<Result<_, TyckErrorItem>>()?;
+    Ok(Infix { head, tail, grouped: Rc::new(OnceCell::new()) }.into())
+}
+
+/// Elaborates an operator declaration into its fixity and the definition the
+/// operator applies, bound to `Fixity::var`.
+pub(super) fn declare(
+    ps::Fixity { assoc, prec, op, def }: ps::Fixity,
+) -> (Fixity, Define<TermV, RcValue>) {
+    let info = op.info.clone();
+    let term: TermValue = TermV::from(def).into();
+    let ty = rc!(info.make(Hole.into()));
+    let def = rc!(info.make(Annotation { term: rc!(info.make(term)), ty }.into()));
+    let name = Fixity::var(&op);
+    (Fixity { op, assoc, prec }, Define { name, def })
+}
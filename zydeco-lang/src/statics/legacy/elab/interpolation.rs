@@ -0,0 +1,52 @@
+//! Elaborates interpolated strings `"x = ${x}"`. Values are elaborated inside
+//! the computation they belong to, so the string is built by a `do` binding
+//! around that computation. How each splice is formatted is up to type
+//! checking, see `Interpolation`.
+
+use super::*;
+use std::{cell::OnceCell, rc::Rc};
+
+/// The computations the values of a computation need run before it, with the
+/// variables the values refer to them by.
+pub(super) type Binds = Vec<(TermV, RcComp)>;
+
+/// Binds the strings interpolated in the values of the elaborated `comp`
+/// before running it.
+pub(super) fn bind_all(binds: Binds, comp: TermComputation) -> TermComputation {
+    let Some((_, last)) = binds.last() else { return comp };
+    let span = last.span().clone();
+    let group = binds.into_iter().map(|(var, comp)| Do { var, comp, body: () }.into()).collect();
+    TailGroup { group, body: rc!(span.make(comp)) }.into()
+}
+
+fn bind(binds: &mut Binds, comp: TermComputation, info: &SpanInfo) -> RcValue {
+    let var = TermV::new(format!("$str{}", binds.len()), info.clone());
+    binds.push((var.clone(), rc!(info.make(comp))));
+    rc!(info.make(var.into()))
+}
+
+/// Elaborates an interpolated string into a variable bound to the text in
+/// `binds`, which are `None` outside of computations.
+pub(super) fn elab(
+    ps::Interpolation(parts): ps::Interpolation, binds: Option<&mut Binds>,
+) -> Result<TermValue, TyckErrorItem> {
+    let info = match &parts[0] {
+        ps::InterpPart::Text(text) => text.info.clone(),
+        ps::InterpPart::Var(x) => x.info.clone(),
+    };
+    let Some(binds) = binds else {
+        Err(TyckErrorItem::InterpolationOutsideComputation { info })?
+    };
+    let pieces = (parts.into_iter())
+        .map(|part| match part {
+            ps::InterpPart::Text(text) => Piece::Text(text.inner),
+            ps::InterpPart::Var(x) => {
+                let info = x.info.clone();
+                let x: TermValue = TermV::from(x).into();
+                Piece::Splice(rc!(info.make(x)))
+            }
+        })
+        .collect();
+    let string = Interpolation { pieces, formats: Rc::new(OnceCell::new()) };
+    Ok(bind(binds, string.into(), &info).inner_ref().clone())
+}
@@ -0,0 +1,63 @@
+//! Elaborates the nested patterns of `match` arms, spelling out tuple and
+//! list patterns as the constructors they stand for. Compiling the arms into
+//! flat matches needs the data types matched, so it's left to type checking.
+
+use super::*;
+use std::{cell::OnceCell, rc::Rc};
+
+fn pattern(pat: &Span<ps::MatchPattern>, vars: &mut Vec<TermV>) -> Result<Pattern, TyckErrorItem> {
+    Ok(match pat.inner_ref() {
+        ps::MatchPattern::Ctor(ps::CtorPattern { ctorv, args }) => Pattern::Ctor(
+            ctorv.clone(),
+            args.iter().map(|arg| pattern(arg, vars)).collect::<Result<_, _>>()?,
+        ),
+        ps::MatchPattern::Literal(lit) => Pattern::Lit(lit.clone()),
+        ps::MatchPattern::Tuple(ps::TuplePattern(elems)) => Pattern::Ctor(
+            tuple_ctor(elems, pat.span())?,
+            elems.iter().map(|elem| pattern(elem, vars)).collect::<Result<_, _>>()?,
+        ),
+        ps::MatchPattern::List(list) => list_pattern(list, pat.span(), vars)?,
+        ps::MatchPattern::Var(var) => {
+            let var = TermV::from(var);
+            if vars.contains(&var) {
+                Err(TyckErrorItem::DuplicatePatternVar { var: var.clone() })?
+            }
+            vars.push(var.clone());
+            Pattern::Any(Some(var))
+        }
+        ps::MatchPattern::Wildcard(ps::Wildcard) => Pattern::Any(None),
+    })
+}
+
+/// The pattern of the constructors of `List` a list pattern at `info` stands
+/// for, spanned like those of list literals.
+fn list_pattern(
+    list: &ps::ListPattern, info: &SpanInfo, vars: &mut Vec<TermV>,
+) -> Result<Pattern, TyckErrorItem> {
+    Ok(match list {
+        ps::ListPattern::Nil(ps::ListEnd) => {
+            Pattern::Ctor(CtorV::new(format!("Nil"), info.clone()), Vec::new())
+        }
+        ps::ListPattern::Cons(ps::ListPatternCons { head, tail }) => Pattern::Ctor(
+            CtorV::new(format!("Cons"), head.span().clone()),
+            vec![pattern(head, vars)?, list_pattern(tail.inner_ref(), tail.span(), vars)?],
+        ),
+        ps::ListPattern::Rest(rest) => pattern(rest, vars)?,
+    })
+}
+
+/// Elaborates a `match` on `scrut`, whose arms type checking compiles.
+pub(super) fn elab(
+    scrut: RcValue, arms: Vec<(Span<ps::MatchPattern>, RcComp)>,
+) -> Result<TermComputation, TyckErrorItem> {
+    // nothing tells which data type an empty match is on; it's left to type
+    // checking that there are no constructors
+    if arms.is_empty() {
+        return Ok(Match { scrut, arms: Vec::new() }.into());
+    }
+    let arms = arms
+        .into_iter()
+        .map(|(pat, body)| Ok((pat.span().make(pattern(&pat, &mut Vec::new())?), body)))
+        .collect::<Result<_, TyckErrorItem>>()?;
+    Ok(NestedMatch { scrut, arms, compiled: Rc::new(OnceCell::new()) }.into())
+}
@@ -24,6 +24,8 @@ impl fmt::Display for TyckError {
 pub enum TyckErrorItem {
     #[error("Unbound variable {var}")]
     UnboundVar { var: TermV },
+    #[error("No builtin found for extern definition {name}")]
+    UnknownExtern { name: TermV },
     #[error("Kind mismatch. In {context}, expected {}, but got {}", .expected.fmt(), .found.fmt())]
     KindMismatch { context: String, expected: Kind, found: Kind },
     #[error("Type mismatch. In {context}, expected {}, but got {}", .expected.fmt(), .found.fmt())]
@@ -38,8 +40,30 @@ pub enum TyckErrorItem {
     Subsumption { sort: &'static str },
     #[error("Inconsistent matchers.\nUnexpected:\n{unexpected:?}Missing:\n{missing:?}")]
     InconsistentMatchers { unexpected: Vec<CtorV>, missing: Vec<CtorV> },
+    #[error("Match is missing a case for {missing}")]
+    NonExhaustiveMatch { missing: String },
+    #[error("Match arm can never be reached")]
+    RedundantMatchArm,
+    #[error("Constructor {ctor} is not one of data type {data}")]
+    ForeignCtor { ctor: CtorV, data: TypeV },
+    #[error("Variable {var} is bound more than once in a pattern ({})", .var.span())]
+    DuplicatePatternVar { var: TermV },
+    #[error("No tuple type has {size} elements ({info})")]
+    TupleSize { size: usize, info: SpanInfo },
+    #[error("No fixity is declared for the operator `{op}`")]
+    UnknownOperator { op: String },
+    #[error("Operators `{left}` and `{right}` don't associate; bind one with `do`")]
+    NonAssociativeOperators { left: String, right: String },
+    #[error("String interpolation is only allowed in computations ({info})")]
+    InterpolationOutsideComputation { info: SpanInfo },
+    #[error("Observation {path} is handled by more than one arm ({info})")]
+    OverlappingCopatterns { path: String, info: SpanInfo },
     #[error("Inconsistent comatchers.\nUnexpected:\n{unexpected:?}, Missing:\n{missing:?}")]
     InconsistentComatchers { unexpected: Vec<DtorV>, missing: Vec<DtorV> },
+    #[error("Inconsistent handlers.\nUnexpected:\n{unexpected:?}, Missing:\n{missing:?}")]
+    InconsistentHandlers { unexpected: Vec<TermV>, missing: Vec<TermV> },
+    #[error("Effect {effect} is performed where it is not handled")]
+    UnhandledEffect { effect: TypeV },
     #[error("Inconsistent branches. Expected: {tys:?}")]
     InconsistentBranches { tys: Vec<Type> },
     #[error(transparent)]
@@ -89,3 +113,23 @@ impl fmt::Display for Frame {
         Ok(())
     }
 }
+
+/// A hole met in type checking, with the type it's checked against and the
+/// local variables in scope there.
+#[derive(Clone, Debug)]
+pub struct Goal {
+    pub hole: TermHole,
+    pub ty: Type,
+    pub locals: Vec<(TermV, Type)>,
+}
+
+impl fmt::Display for Goal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Goal {} : {}", self.hole.fmt(), self.ty.fmt())?;
+        writeln!(f, "\t({})", self.hole.span())?;
+        for (var, ty) in &self.locals {
+            writeln!(f, "\t{} : {}", var, ty.fmt())?;
+        }
+        Ok(())
+    }
+}
@@ -33,6 +33,68 @@ impl FmtArgs for TailGroup {
     }
 }
 
+impl FmtArgs for Pattern {
+    fn fmt_args(&self, fargs: Args) -> String {
+        match self {
+            Pattern::Any(Some(var)) => var.fmt_args(fargs),
+            Pattern::Any(None) => format!("_"),
+            Pattern::Ctor(ctorv, args) => {
+                let args: Vec<_> = args.iter().map(|arg| arg.fmt_args(fargs)).collect();
+                format!("{}({})", ctorv.fmt_args(fargs), args.join(", "))
+            }
+            Pattern::Lit(lit) => lit.fmt_args(fargs),
+        }
+    }
+}
+
+impl FmtArgs for NestedMatch {
+    fn fmt_args(&self, fargs: Args) -> String {
+        let NestedMatch { scrut, arms, compiled: _ } = self;
+        let mut s = String::new();
+        s += "match ";
+        s += &scrut.fmt_args(fargs);
+        for (pat, body) in arms {
+            s += &fargs.br_indent();
+            s += "| ";
+            s += &pat.fmt_args(fargs);
+            s += " -> ";
+            {
+                let fargs = fargs.indent();
+                s += &fargs.br_indent();
+                s += &body.fmt_args(fargs);
+            }
+        }
+        s += &fargs.br_indent();
+        s += "end";
+        s
+    }
+}
+
+impl FmtArgs for Interpolation {
+    fn fmt_args(&self, fargs: Args) -> String {
+        let Interpolation { pieces, formats: _ } = self;
+        let mut s = String::from("\"");
+        for piece in pieces {
+            match piece {
+                Piece::Text(text) => s += &text.escape_debug().to_string(),
+                Piece::Splice(v) => s += &format!("${{{}}}", v.fmt_args(fargs)),
+            }
+        }
+        s + "\""
+    }
+}
+
+impl FmtArgs for Infix {
+    fn fmt_args(&self, fargs: Args) -> String {
+        let Infix { head, tail, grouped: _ } = self;
+        let mut s = head.fmt_args(fargs);
+        for (op, operand) in tail {
+            s += &format!(" {} {}", op.inner_ref(), operand.fmt_args(fargs));
+        }
+        s
+    }
+}
+
 impl FmtArgs for Abs<(TypeV, Option<Span<Kind>>), RcComp> {
     fn fmt_args(&self, fargs: Args) -> String {
         let Abs { param, body } = self;
@@ -50,7 +112,7 @@ impl FmtArgs for App<RcComp, RcType> {
 impl FmtArgs for Module {
     fn fmt_args(&self, args: Args) -> String {
         let mut s = String::new();
-        let Module { name, data, codata, alias, define, define_ext } = self;
+        let Module { name, data, codata, alias, effect, fixity, define, define_ext } = self;
         if let Some(name) = name {
             s += &format!("module {} where", name);
             s += &args.br_indent();
@@ -67,6 +129,22 @@ impl FmtArgs for Module {
             s += &d.fmt_args(args);
             s += &args.br_indent();
         }
+        for d in effect {
+            s += &d.fmt_args(args);
+            s += &args.br_indent();
+        }
+        for DeclSymbol { public, external: _, inner: Fixity { op, assoc, prec } } in fixity {
+            if *public {
+                s += &format!("pub ");
+            }
+            let assoc = match assoc {
+                Assoc::Left => "infixl",
+                Assoc::Right => "infixr",
+                Assoc::Non => "infix",
+            };
+            s += &format!("{} {} {} end", assoc, prec, op.inner_ref());
+            s += &args.br_indent();
+        }
         for DeclSymbol { public, external: _, inner: Define { name: (var, ty), def: () } } in
             define_ext
         {
@@ -1,9 +1,9 @@
-use crate::prelude::*;
+use crate::{prelude::*, rc};
 use im::Vector;
-use std::rc::Rc;
+use std::{cell::OnceCell, rc::Rc};
 use zydeco_derive::{FmtArgs, IntoEnum};
 
-pub use crate::syntax::*;
+pub use crate::{parse::syntax::Assoc, syntax::*};
 
 /* ---------------------------------- Kind ---------------------------------- */
 
@@ -13,6 +13,8 @@ pub use crate::syntax::{KindBase, TypeArity};
 pub enum Kind {
     Base(KindBase),
     TypeArity(TypeArity<Span<Kind>, BoxKind>),
+    /// A kind left out, to be inferred.
+    Hole(Hole),
 }
 pub type BoxKind = Box<Span<Kind>>;
 impl KindT for Kind {}
@@ -26,6 +28,7 @@ pub enum SynType {
     TypeApp(TypeApp<TypeV, RcType>),
     Forall(Forall<(TypeV, Span<Kind>), RcType>),
     Exists(Exists<(TypeV, Span<Kind>), RcType>),
+    Effectful(Effectful<TypeV, RcType>),
     AbstVar(AbstVar),
     Hole(Hole),
 }
@@ -49,6 +52,7 @@ macro_rules! impl_from {
 impl_from!(TypeApp<TypeV, RcType>);
 impl_from!(Forall<(TypeV, Span<Kind>), RcType>);
 impl_from!(Exists<(TypeV, Span<Kind>), RcType>);
+impl_from!(Effectful<TypeV, RcType>);
 impl_from!(AbstVar);
 impl_from!(Hole);
 impl From<TypeV> for Type {
@@ -67,6 +71,7 @@ pub enum TermValue {
     Ctor(Ctor<CtorV, RcValue>),
     Literal(Literal),
     Pack(Pack<RcType, RcValue>),
+    Hole(TermHole),
 }
 pub type RcValue = Rc<Span<TermValue>>;
 impl ValueT for TermValue {}
@@ -83,23 +88,119 @@ pub struct TailGroup {
     pub body: RcComp,
 }
 
+impl TailGroup {
+    /// Binds each of `binds` with a `let` before `body`, leaving out the
+    /// variables bound to themselves.
+    pub fn bind(binds: Vec<(TermV, RcValue)>, body: RcComp) -> RcComp {
+        let group: Vector<TailTerm> = binds
+            .into_iter()
+            .filter(|(var, def)| !matches!(def.inner_ref(), TermValue::Var(v) if v == var))
+            .map(|(var, def)| Let { var, def, body: () }.into())
+            .collect();
+        if group.is_empty() {
+            return body;
+        }
+        let span = body.span().clone();
+        rc!(span.make(TailGroup { group, body }.into()))
+    }
+}
+
+/// A pattern of a `match` arm.
+#[derive(Clone, Debug)]
+pub enum Pattern {
+    /// Matches anything, naming it if there's a variable.
+    Any(Option<TermV>),
+    Ctor(CtorV, Vec<Pattern>),
+    Lit(Literal),
+}
+
+/// A `match` whose arms have nested patterns. Only type checking knows the
+/// data types of the values matched, so it's the one compiling the arms into
+/// the flat matches that run.
+#[derive(Clone, Debug)]
+pub struct NestedMatch {
+    pub scrut: RcValue,
+    pub arms: Vec<(Span<Pattern>, RcComp)>,
+    /// The flat matches, once type checked.
+    pub compiled: Rc<OnceCell<RcComp>>,
+}
+
+/// An infix expression `x + y * z`, where each operator applies the definition
+/// named after it. Only type checking knows the fixities declared, so it's the
+/// one grouping the operands into the applications that run.
+#[derive(Clone, Debug)]
+pub struct Infix {
+    pub head: RcValue,
+    pub tail: Vec<(Span<String>, RcValue)>,
+    /// The applications, once type checked.
+    pub grouped: Rc<OnceCell<RcComp>>,
+}
+
+/// A piece of an interpolated string.
+#[derive(Clone, Debug)]
+pub enum Piece {
+    Text(String),
+    Splice(RcValue),
+}
+
+/// How a value spliced into a string is written out: `Int`, `Char` and
+/// `String` values by the std builtins, and a `Thunk(Ret String)` by forcing
+/// it.
+#[derive(Clone, Copy, Debug)]
+pub enum Format {
+    Int,
+    Char,
+    String,
+    Thunk,
+}
+
+/// An interpolated string `"x = ${x}"`. A splice is formatted by its type,
+/// so type checking chooses the formats, and linking joins the pieces with
+/// the std builtins themselves, which no variable of the program can stand in
+/// for.
+#[derive(Clone, Debug)]
+pub struct Interpolation {
+    pub pieces: Vec<Piece>,
+    /// The format of each splice in order, once type checked.
+    pub formats: Rc<OnceCell<Vec<Format>>>,
+}
+
 #[derive(IntoEnum, FmtArgs, Clone, Debug)]
 pub enum TermComputation {
     Annotation(Annotation<RcComp, RcType>),
     Ret(Ret<RcValue>),
     Force(Force<RcValue>),
+    Interpolation(Interpolation),
     TailGroup(TailGroup),
     Rec(Rec<TermV, RcComp>),
     Match(Match<CtorV, TermV, RcValue, RcComp>),
+    MatchLit(MatchLit<RcValue, RcComp>),
+    NestedMatch(NestedMatch),
     Comatch(Comatch<DtorV, TermV, RcComp>),
     Dtor(Dtor<RcComp, DtorV, RcValue>),
     TyAbsTerm(Abs<(TypeV, Option<Span<Kind>>), RcComp>),
     TyAppTerm(App<RcComp, RcType>),
     MatchPack(MatchPack<RcValue, TypeV, TermV, RcComp>),
+    Handle(Handle<TermV, TermV, RcComp>),
+    Infix(Infix),
+    Hole(TermHole),
 }
 pub type RcComp = Rc<Span<TermComputation>>;
 impl ComputationT for TermComputation {}
 
+impl TermComputation {
+    /// Forces `fun` and applies it to `args` one after another, like `! fun a b`.
+    pub fn force_app(fun: TermV, args: Vec<RcValue>, info: &SpanInfo) -> Self {
+        let fun: TermValue = fun.into();
+        let mut body: TermComputation = Force(rc!(info.make(fun))).into();
+        for arg in args {
+            let dtorv = DtorV::new(format!("arg"), SpanInfo::dummy());
+            body = Dtor { body: rc!(info.make(body)), dtorv, args: vec![arg] }.into();
+        }
+        body
+    }
+}
+
 #[derive(IntoEnum, FmtArgs, Clone, Debug)]
 pub enum Term {
     Value(TermValue),
@@ -108,12 +209,30 @@ pub enum Term {
 
 /* --------------------------------- Module --------------------------------- */
 
+/// How an operator groups; it applies the definition bound to `Fixity::var`.
+#[derive(Clone, Debug)]
+pub struct Fixity {
+    pub op: Span<String>,
+    pub assoc: Assoc,
+    pub prec: i64,
+}
+
+impl Fixity {
+    /// The variable an operator's definition is bound to, the operator in
+    /// parentheses, which the lexer never reads as an identifier.
+    pub fn var(op: &Span<String>) -> TermV {
+        TermV::new(format!("({})", op.inner_ref()), op.span().clone())
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Module {
     pub name: Option<String>,
     pub data: Vec<DeclSymbol<prelude::Data>>,
     pub codata: Vec<DeclSymbol<prelude::Codata>>,
     pub alias: Vec<DeclSymbol<prelude::Alias>>,
+    pub effect: Vec<DeclSymbol<prelude::Effect>>,
+    pub fixity: Vec<DeclSymbol<Fixity>>,
     pub define: Vec<DeclSymbol<Define<TermV, RcValue>>>,
     pub define_ext: Vec<DeclSymbol<Define<(TermV, RcType), ()>>>,
 }
@@ -129,4 +248,5 @@ pub mod prelude {
     pub type Data = super::Data<TypeV, Span<Kind>, CtorV, RcType>;
     pub type Codata = super::Codata<TypeV, Span<Kind>, DtorV, RcType>;
     pub type Alias = super::Alias<TypeV, Span<Kind>, RcType>;
+    pub type Effect = super::Effect<TypeV, TermV, RcType>;
 }
@@ -3,12 +3,17 @@ mod value;
 mod computation;
 mod module;
 mod lub;
+mod kind;
+mod meta;
+mod pattern;
+mod infix;
 
 use super::{
-    err::{Frame, Trace, TyckError, TyckErrorItem},
+    err::{Frame, Goal, Trace, TyckError, TyckErrorItem},
     syntax::*,
 };
 use crate::{
+    library::builtins::Registry,
     rc,
     resolve::err::NameResolveError,
     syntax::Env,
@@ -17,7 +22,7 @@ use crate::{
         span::{Span, SpanInfo, SpanView},
     },
 };
-use std::collections::HashSet;
+use std::{cell::RefCell, collections::HashSet, rc::Rc};
 use TyckErrorItem::*;
 
 pub trait CtxT {
@@ -38,12 +43,74 @@ pub struct Ctx {
     pub data_env: im::HashMap<TypeV, prelude::Data>,
     pub codata_env: im::HashMap<TypeV, prelude::Codata>,
     pub alias_env: im::HashMap<TypeV, prelude::Alias>,
+    pub effect_env: im::HashMap<TypeV, prelude::Effect>,
+    /// The effect each operation belongs to.
+    pub op_env: im::HashMap<TermV, TypeV>,
+    /// How the operators declared group; behind an `Rc` as contexts are
+    /// cloned at every step and this rarely changes.
+    pub fixities: Rc<im::HashMap<String, Fixity>>,
+    /// The effects the computation under check may perform.
+    pub effects: im::HashSet<TypeV>,
+    /// Recursive computations whose type is still being determined; calls to
+    /// them are left to be checked once it is.
+    pub recs: im::HashSet<TermV>,
+    /// The top-level definitions, with where each is defined; any other
+    /// variable in scope is local.
+    pub globals: im::HashMap<TermV, SpanInfo>,
+    /// The holes met so far, shared by every context derived from this one.
+    pub goals: Rc<RefCell<Vec<Goal>>>,
+    /// The types of the builtins `extern def` declarations can refer to.
+    pub builtins: im::HashMap<TermV, Span<Type>>,
     pub trace: Trace,
 }
 
 mod ctx {
     use super::*;
     impl Ctx {
+        /// A context in which `extern def` declarations are checked against
+        /// the builtins of `registry`.
+        pub fn new(registry: &Registry) -> Self {
+            let builtins = (registry.iter())
+                .map(|builtin| {
+                    (
+                        TermV::new(builtin.name().to_string(), SpanInfo::dummy()),
+                        builtin.ty().clone(),
+                    )
+                })
+                .collect();
+            Ctx { builtins, ..Default::default() }
+        }
+        /// Records the goal of a hole, replacing the one recorded by an earlier
+        /// pass over the same term.
+        pub(super) fn goal(&self, hole: &TermHole, ty: Type) -> Result<(), TyckError> {
+            let span = hole.span();
+            let ty = self.resolve_alias(ty, span)?;
+            let mut locals = Vec::new();
+            for (var, ty) in self.term_ctx.iter() {
+                // the values patterns are matched against are named with `$`,
+                // which no variable of the program can be
+                if var.name().starts_with('$') {
+                    continue;
+                }
+                if self.globals.get(var) != Some(var.span()) {
+                    locals.push((var.clone(), self.resolve_alias(ty.clone(), span)?));
+                }
+            }
+            locals.sort_by_key(|(var, _)| var.span().start());
+            let goal = Goal { hole: hole.clone(), ty, locals };
+            let mut goals = self.goals.borrow_mut();
+            match goals.iter_mut().find(|goal| goal.hole.span() == span) {
+                Some(old) => *old = goal,
+                None => goals.push(goal),
+            }
+            Ok(())
+        }
+        /// Binds `var` to a value of type `ty`, shadowing the variable of the
+        /// same name in scope, a recursive one included.
+        pub(super) fn bind(&mut self, var: TermV, ty: Type) {
+            self.recs.remove(&var);
+            self.term_ctx.insert(var, ty);
+        }
         pub(super) fn fresh(&mut self, kd: Kind) -> AbstVar {
             self.abst_ctx.push_back(kd);
             AbstVar(self.abst_ctx.len() - 1)
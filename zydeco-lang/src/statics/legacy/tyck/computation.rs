@@ -1,6 +1,6 @@
 use std::collections::{HashMap, HashSet};
 
-use super::*;
+use super::{meta::*, *};
 
 impl TypeCheck for Span<TermComputation> {
     type Ctx = Ctx;
@@ -17,11 +17,21 @@ impl TypeCheck for Span<TermComputation> {
         let span = self.span();
         Ok(match self.inner_ref() {
             TermComputation::Annotation(Annotation { term, ty }) => {
+                let ty = ctx.infer_kinds(ty, &KindBase::CType.into())?;
                 ty.ana(KindBase::CType.into(), ctx.clone())?;
-                Step::AnaMode((ctx, term), ty.inner_clone())
+                let ty = ctx.resolve_alias(ty.inner_clone(), span)?;
+                if let SynType::Effectful(_) = ty.resolve()? {
+                    // the annotated computation is run right here
+                    let ty = term.ana(ty, ctx.clone())?;
+                    Step::Done(ctx.perform(ty, span)?)
+                } else {
+                    Step::AnaMode((ctx, term), ty)
+                }
             }
-            TermComputation::Ret(_) => {
-                Err(ctx.err(span, NeedAnnotation { content: format!("ret") }))?
+            TermComputation::Ret(Ret(v)) => {
+                let ty = v.syn(ctx.clone())?;
+                span.make(ty.clone()).ana(KindBase::VType.into(), ctx)?;
+                Step::Done(Type::make_ret(rc!(span.make(ty))))
             }
             TermComputation::Force(Force(v)) => {
                 let ty_val = v.syn(ctx.clone())?;
@@ -35,8 +45,18 @@ impl TypeCheck for Span<TermComputation> {
                         },
                     )
                 })?;
-                span.make(ty_body.to_owned()).ana(KindBase::CType.into(), ctx)?;
-                Step::Done(ty_body)
+                span.make(ty_body.to_owned()).ana(KindBase::CType.into(), ctx.clone())?;
+                Step::Done(ctx.perform(ty_body, span)?)
+            }
+            TermComputation::Interpolation(Interpolation { pieces, formats }) => {
+                let mut splices = Vec::new();
+                for piece in pieces {
+                    if let Piece::Splice(v) = piece {
+                        splices.push(v.format(ctx.clone())?);
+                    }
+                }
+                formats.get_or_init(|| splices);
+                Step::Done(Type::make_ret(rc!(span.make(Type::internal("String", vec![])))))
             }
             TermComputation::TailGroup(TailGroup { group, body }) => {
                 for item in group {
@@ -44,10 +64,10 @@ impl TypeCheck for Span<TermComputation> {
                         TailTerm::Let(Let { var, def, body: () }) => {
                             let ty_def = def.syn(ctx.clone())?;
                             span.make(ty_def.clone()).ana(KindBase::VType.into(), ctx.clone())?;
-                            ctx.term_ctx.insert(var.to_owned(), ty_def);
+                            ctx.bind(var.to_owned(), ty_def);
                         }
                         TailTerm::Do(Do { var, comp, body: () }) => {
-                            let ty_comp = comp.syn(ctx.clone())?;
+                            let ty_comp = comp.syn_run(ctx.clone())?;
                             span.make(ty_comp.clone()).ana(KindBase::CType.into(), ctx.clone())?;
                             let ty_val =
                                 ty_comp.clone().elim_ret(ctx.clone(), span).ok_or_else(|| {
@@ -60,14 +80,26 @@ impl TypeCheck for Span<TermComputation> {
                                         },
                                     )
                                 })?;
-                            ctx.term_ctx.insert(var.to_owned(), ty_val);
+                            ctx.bind(var.to_owned(), ty_val);
                         }
                     }
                 }
                 Step::SynMode((ctx, body))
             }
-            TermComputation::Rec(Rec { var: _, body: _ }) => {
-                Err(ctx.err(span, NeedAnnotation { content: format!("rec") }))?
+            TermComputation::Rec(Rec { var, body }) => {
+                // the body determines its own type, with the recursive calls in
+                // it left unchecked; they are checked against that type after
+                let ty = {
+                    let mut ctx = ctx.clone();
+                    let ty_var = Type::make_thunk(rc!(span.make(Hole.into())));
+                    ctx.bind(var.to_owned(), ty_var);
+                    ctx.recs.insert(var.to_owned());
+                    body.syn(ctx)?
+                };
+                bool_test(!ty.has_hole(), || {
+                    ctx.err(span, NeedAnnotation { content: format!("rec") })
+                })?;
+                Step::AnaMode((ctx, self), ty)
             }
             TermComputation::Match(Match { scrut, arms }) => {
                 let ty_scrut = scrut.syn(ctx.clone())?;
@@ -98,7 +130,7 @@ impl TypeCheck for Span<TermComputation> {
                     let tys = tys.into_iter().map(|ty| ty.inner_clone().subst(diff.clone(), &ctx));
                     let mut ctx = ctx.clone();
                     for (var, ty) in vars.iter().zip(tys) {
-                        ctx.term_ctx.insert(var.to_owned(), ty?);
+                        ctx.bind(var.to_owned(), ty?);
                     }
                     let ty = body.syn(ctx.clone())?;
                     let span = body.span();
@@ -127,73 +159,48 @@ impl TypeCheck for Span<TermComputation> {
                 };
                 Step::Done(ty)
             }
-            TermComputation::Comatch(_) => {
-                Err(ctx.err(span, NeedAnnotation { content: format!("comatch") }))?
+            TermComputation::NestedMatch(m) => {
+                let ty_scrut = m.scrut.syn(ctx.clone())?;
+                let comp = m.compile(ty_scrut, &ctx)?;
+                Step::SynMode((ctx, comp))
             }
-            TermComputation::Dtor(Dtor { body, dtorv: dtor, args }) => {
-                let ty_body = body.syn(ctx.clone())?;
-                let (Codata { name, params, dtors }, ty_args) =
-                    ctx.resolve_codata(ty_body, span)?;
-                // arity check on codata type
-                let diff = Env::init(&params, &ty_args, || {
-                    ctx.err(
-                        span,
-                        ArityMismatch {
-                            context: format!("codata type `{}` instiantiation", name),
-                            expected: params.len(),
-                            found: ty_args.len(),
-                        },
-                    )
-                })?;
-                let CodataBr { dtorv: _, tys, ty } = dtors
-                    .into_iter()
-                    .find(|CodataBr { dtorv, tys: _, ty: _ }| dtorv == dtor)
-                    .ok_or_else(|| {
-                        ctx.err(
-                            body.span(),
-                            NameResolveError::UnknownDestructor {
-                                context: format!("codata type `{}`", name),
-                                dtor: dtor.clone(),
-                            }
-                            .into(),
-                        )
-                    })?;
-                bool_test(args.len() == tys.len(), || {
-                    ctx.err(
-                        span,
-                        ArityMismatch {
-                            context: format!("dtor"),
-                            expected: tys.len(),
-                            found: args.len(),
-                        },
-                    )
-                })?;
-                for (arg, ty) in args.iter().zip(tys.iter()) {
-                    arg.ana(ty.inner_clone().subst(diff.clone(), &ctx)?, ctx.clone())?;
+            TermComputation::Infix(infix) => {
+                let comp = infix.group(&ctx)?;
+                Step::SynMode((ctx, comp))
+            }
+            TermComputation::MatchLit(MatchLit { scrut, arms, default }) => {
+                let ty_scrut = scrut.syn(ctx.clone())?;
+                for (lit, _) in arms {
+                    span.make(TermValue::from(lit.clone())).ana(ty_scrut.clone(), ctx.clone())?;
+                }
+                let mut ty_arms = Vec::new();
+                for body in arms.iter().map(|(_, body)| body).chain([default]) {
+                    let ty = body.syn(ctx.clone())?;
+                    let span = body.span();
+                    span.make(ty.clone()).ana(KindBase::CType.into(), ctx.clone())?;
+                    ty_arms.push(ty);
+                }
+                // branch consistency check
+                let mut ty_opt: Option<Type> = None;
+                for ty in &ty_arms {
+                    if let Some(ty_opt) = &ty_opt {
+                        ty_opt.clone().lub(ty.clone(), ctx.clone(), span).map_err(|_| {
+                            ctx.err(span, InconsistentBranches { tys: ty_arms.clone() })
+                        })?;
+                    } else {
+                        ty_opt = Some(ty.clone());
+                    }
                 }
-                Step::Done(ty.inner_clone().subst(diff, &ctx)?)
+                Step::Done(ty_opt.expect("match on a literal has a default arm"))
+            }
+            TermComputation::Comatch(_) => {
+                Err(ctx.err(span, NeedAnnotation { content: format!("comatch") }))?
             }
             TermComputation::TyAbsTerm(_) => {
                 Err(ctx.err(span, NeedAnnotation { content: format!("typabs") }))?
             }
-            TermComputation::TyAppTerm(App { body, arg }) => {
-                let ty_body = body.syn(ctx.clone())?;
-                let ty_body = ctx.resolve_alias(ty_body, span)?;
-                let SynType::Forall(Forall { param: (param, kd), ty }) = ty_body.resolve()? else {
-                    Err(ctx.err(span, TypeExpected {
-                        context: format!("term-typ-application"),
-                        expected: format!("forall"),
-                        found: ty_body,
-                    }))?
-                };
-                arg.ana(kd.inner_clone(), ctx.clone())?;
-                let diff = Env::init(&[(param, kd)], &[arg.clone()], || {
-                    ctx.err(
-                        span,
-                        ArityMismatch { context: format!("typapp"), expected: 1, found: 1 },
-                    )
-                })?;
-                Step::Done(ty.inner_clone().subst(diff, &ctx)?)
+            TermComputation::Dtor(_) | TermComputation::TyAppTerm(_) => {
+                Step::Done(self.tyck_spine(Hole.into(), ctx)?)
             }
             TermComputation::MatchPack(MatchPack { scrut, tvar, var, body }) => {
                 let ty_scrut = scrut.syn(ctx.clone())?;
@@ -208,11 +215,18 @@ impl TypeCheck for Span<TermComputation> {
                 ctx.type_ctx.insert(tvar.clone(), kd.inner_clone());
                 let ty =
                     ty.inner_clone().subst(Env::from_iter([(param, tvar.clone().into())]), &ctx)?;
-                ctx.term_ctx.insert(var.clone(), ty);
+                ctx.bind(var.clone(), ty);
                 let ty_body = body.syn(ctx.clone())?;
                 span.make(ty_body.clone()).ana(KindBase::CType.into(), ctx)?;
                 Step::Done(ty_body)
             }
+            TermComputation::Handle(handle) => {
+                Step::Done(span.make(handle).tyck_handle(Hole.into(), ctx)?)
+            }
+            TermComputation::Hole(hole) => {
+                ctx.goal(hole, Hole.into())?;
+                Step::Done(Hole.into())
+            }
         })
     }
     fn ana_step(
@@ -231,8 +245,15 @@ impl TypeCheck for Span<TermComputation> {
             return Ok(Step::SynMode((ctx, self)));
         }
         span.make(typ.clone()).ana(KindBase::CType.into(), ctx.clone())?;
+        if let SynType::Effectful(Effectful { effs, ty }) = typ_syn {
+            // the computation may perform the annotated effects
+            ctx.effects.extend(effs.iter().cloned());
+            let ty = self.ana(ty.inner_clone(), ctx)?;
+            return Ok(Step::Done(Effectful { effs, ty: rc!(span.make(ty)) }.into()));
+        }
         Ok(match self.inner_ref() {
             TermComputation::Annotation(Annotation { term, ty }) => {
+                let ty = ctx.infer_kinds(ty, &KindBase::CType.into())?;
                 let ty_lub = Type::lub(ty.inner_clone(), typ, ctx.clone(), span)?;
                 Step::AnaMode((ctx, term), ty_lub)
             }
@@ -252,8 +273,16 @@ impl TypeCheck for Span<TermComputation> {
                 Step::Done(typ_lub)
             }
             TermComputation::Force(Force(v)) => {
-                v.ana(Type::make_thunk(rc!(span.make(typ.clone()))), ctx)?;
-                Step::Done(typ)
+                if let TermValue::Var(_) = v.inner_ref() {
+                    // the thunk's type is known, including the effects it performs
+                    // and the type arguments the expected type determines
+                    let typ_syn = self.tyck_spine(typ.clone(), ctx.clone())?;
+                    Step::Done(Type::lub(typ, typ_syn, ctx.clone(), span)?)
+                } else {
+                    let ty = ctx.latent(typ.clone(), span);
+                    v.ana(Type::make_thunk(rc!(span.make(ty))), ctx)?;
+                    Step::Done(typ)
+                }
             }
             TermComputation::TailGroup(TailGroup { group, body }) => {
                 for item in group {
@@ -261,10 +290,10 @@ impl TypeCheck for Span<TermComputation> {
                         TailTerm::Let(Let { var, def, body: () }) => {
                             let ty_def = def.syn(ctx.clone())?;
                             span.make(ty_def.clone()).ana(KindBase::VType.into(), ctx.clone())?;
-                            ctx.term_ctx.insert(var.to_owned(), ty_def);
+                            ctx.bind(var.to_owned(), ty_def);
                         }
                         TailTerm::Do(Do { var, comp, body: () }) => {
-                            let ty_comp = comp.syn(ctx.clone())?;
+                            let ty_comp = comp.syn_run(ctx.clone())?;
                             span.make(ty_comp.clone()).ana(KindBase::CType.into(), ctx.clone())?;
                             let ty_val =
                                 ty_comp.clone().elim_ret(ctx.clone(), span).ok_or_else(|| {
@@ -277,14 +306,15 @@ impl TypeCheck for Span<TermComputation> {
                                         },
                                     )
                                 })?;
-                            ctx.term_ctx.insert(var.to_owned(), ty_val);
+                            ctx.bind(var.to_owned(), ty_val);
                         }
                     }
                 }
                 Step::AnaMode((ctx, body), typ)
             }
             TermComputation::Rec(Rec { var, body }) => {
-                ctx.term_ctx.insert(var.to_owned(), Type::make_thunk(rc!(span.make(typ.clone()))));
+                let ty = ctx.latent(typ.clone(), span);
+                ctx.bind(var.to_owned(), Type::make_thunk(rc!(span.make(ty))));
                 Step::AnaMode((ctx, body), typ)
             }
             TermComputation::Match(Match { scrut, arms }) => {
@@ -315,7 +345,7 @@ impl TypeCheck for Span<TermComputation> {
                     let tys = tys.into_iter().map(|ty| ty.inner_clone().subst(diff.clone(), &ctx));
                     let mut ctx = ctx.clone();
                     for (var, ty) in vars.iter().zip(tys) {
-                        ctx.term_ctx.insert(var.to_owned(), ty?);
+                        ctx.bind(var.to_owned(), ty?);
                     }
                     body.ana(typ.clone(), ctx.clone())?;
                 }
@@ -326,9 +356,38 @@ impl TypeCheck for Span<TermComputation> {
                 })?;
                 Step::Done(typ)
             }
+            TermComputation::NestedMatch(m) => {
+                let ty_scrut = m.scrut.syn(ctx.clone())?;
+                let comp = m.compile(ty_scrut, &ctx)?;
+                Step::AnaMode((ctx, comp), typ)
+            }
+            TermComputation::Infix(infix) => {
+                let comp = infix.group(&ctx)?;
+                Step::AnaMode((ctx, comp), typ)
+            }
+            TermComputation::MatchLit(MatchLit { scrut, arms, default }) => {
+                let ty_scrut = scrut.syn(ctx.clone())?;
+                for (lit, body) in arms {
+                    span.make(TermValue::from(lit.clone())).ana(ty_scrut.clone(), ctx.clone())?;
+                    body.ana(typ.clone(), ctx.clone())?;
+                }
+                default.ana(typ.clone(), ctx.clone())?;
+                Step::Done(typ)
+            }
             TermComputation::Comatch(Comatch { arms }) => {
                 let (Codata { name, params, dtors }, ty_args) =
                     ctx.resolve_codata(typ.clone(), span)?;
+                // type arguments left as holes are determined by the arms
+                let mut metas = Metas::default();
+                let ty_args: Vec<_> = (ty_args.into_iter())
+                    .zip(params.iter())
+                    .map(|(arg, (param, kd))| match arg.inner_ref().synty {
+                        SynType::Hole(_) => {
+                            rc!(arg.span().make(metas.fresh(param.clone(), kd.clone())))
+                        }
+                        _ => arg,
+                    })
+                    .collect();
                 // arity check on codata type
                 let diff = Env::init(&params, &ty_args, || {
                     ctx.err(
@@ -352,20 +411,26 @@ impl TypeCheck for Span<TermComputation> {
                         continue;
                     };
                     dtorv_set_arm.insert(dtor.to_owned());
-                    let tys = tys.into_iter().map(|ty| ty.inner_clone().subst(diff.clone(), &ctx));
+                    let tys = tys.into_iter().map(|ty| {
+                        ty.inner_clone().subst(diff.clone(), &ctx)?.subst(metas.expected(), &ctx)
+                    });
                     let ty = ty.inner_clone().subst(diff.clone(), &ctx)?;
                     let mut ctx = ctx.clone();
                     for (var, ty) in vars.iter().zip(tys) {
-                        ctx.term_ctx.insert(var.to_owned(), ty?);
+                        ctx.bind(var.to_owned(), ty?);
                     }
-                    body.ana(ty, ctx)?;
+                    let found = body.ana(ty.clone().subst(metas.expected(), &ctx)?, ctx.clone())?;
+                    let found = found.subst(ctx.type_env.clone(), &ctx)?;
+                    metas.unify(ty, found, &ctx, span)?;
                 }
                 let dtorv_set_coda: HashSet<DtorV> = dtors.keys().cloned().collect();
                 let missing: Vec<_> = dtorv_set_coda.difference(&dtorv_set_arm).cloned().collect();
                 bool_test(unexpected.is_empty() && missing.is_empty(), || {
                     ctx.err(span, InconsistentComatchers { unexpected, missing })
                 })?;
-                Step::Done(typ)
+                let ty = Type::from(TypeApp { tvar: name, args: ty_args })
+                    .subst(metas.expected(), &ctx)?;
+                Step::Done(Type::lub(typ, ty, ctx.clone(), span)?)
             }
             TermComputation::TyAbsTerm(Abs { param: (tvar_, kd_), body }) => {
                 let SynType::Forall(Forall { param: (tvar, kd), ty }) = &typ_syn else {
@@ -386,9 +451,23 @@ impl TypeCheck for Span<TermComputation> {
                 body.ana(ty.inner_clone(), ctx)?;
                 Step::Done(typ)
             }
-            TermComputation::Dtor(_)
-            | TermComputation::TyAppTerm(_)
-            | TermComputation::MatchPack(_) => {
+            TermComputation::Handle(handle) => {
+                Step::Done(span.make(handle).tyck_handle(typ, ctx)?)
+            }
+            TermComputation::Interpolation(_) => {
+                let typ_syn = self.syn(ctx.clone())?;
+                Step::Done(Type::lub(typ, typ_syn, ctx, span)?)
+            }
+            TermComputation::Dtor(_) | TermComputation::TyAppTerm(_) => {
+                // the expected type may determine type arguments left implicit
+                let typ_syn = self.tyck_spine(typ.clone(), ctx.clone())?;
+                Step::Done(Type::lub(typ, typ_syn, ctx, span)?)
+            }
+            TermComputation::Hole(hole) => {
+                ctx.goal(hole, typ.clone())?;
+                Step::Done(typ)
+            }
+            TermComputation::MatchPack(_) => {
                 // subsumption
                 let typ_syn = self.syn(ctx.clone())?;
                 // println!("{} /\\ {}", typ.fmt(), typ_syn.fmt());
@@ -398,3 +477,263 @@ impl TypeCheck for Span<TermComputation> {
         })
     }
 }
+
+impl Span<TermValue> {
+    /// Chooses the format of a value spliced into a string by its type.
+    fn format(&self, ctx: Ctx) -> Result<Format, TyckError> {
+        let span = self.span();
+        let ty = self.syn(ctx.clone())?;
+        let string = || rc!(span.make(Type::internal("String", vec![])));
+        let formats = [
+            (Format::Int, Type::internal("Int", vec![])),
+            (Format::Char, Type::internal("Char", vec![])),
+            (Format::String, Type::internal("String", vec![])),
+            (Format::Thunk, Type::make_thunk(rc!(span.make(Type::make_ret(string()))))),
+        ];
+        let format = (formats.into_iter())
+            .find(|(_, shown)| ty.clone().lub(shown.clone(), ctx.clone(), span).is_ok());
+        let Some((format, _)) = format else {
+            Err(ctx.err(
+                span,
+                TypeExpected {
+                    context: format!("string interpolation"),
+                    expected: format!("Int, Char, String or Thunk(Ret String)"),
+                    found: ty,
+                },
+            ))?
+        };
+        Ok(format)
+    }
+}
+
+impl Span<&Handle<TermV, TermV, RcComp>> {
+    /// Checks a handler against the type of its result, which may be a hole.
+    fn tyck_handle(&self, mut typ: Type, ctx: Ctx) -> Result<Type, TyckError> {
+        let span = self.span();
+        let Handle { body, ret, arms } = self.inner_ref();
+        // the operations handled determine the effect; a handler with only a
+        // return clause handles no effect, and its body performs the ones
+        // around it
+        let (handled, ops) = match arms.first() {
+            Some(Handler { op, .. }) => {
+                let Some(eff) = ctx.op_env.get(op) else {
+                    Err(ctx.err(
+                        op.span(),
+                        NameResolveError::UnknownOperation { op: op.clone() }.into(),
+                    ))?
+                };
+                let Effect { name, ops } = ctx.effect_env[eff].clone();
+                (Some(name), ops)
+            }
+            None => (None, Vec::new()),
+        };
+        let ty_body = {
+            let mut ctx = ctx.clone();
+            ctx.effects.extend(handled);
+            body.ana(Type::make_ret(rc!(span.make(Hole.into()))), ctx)?
+        };
+        let ty_val = ty_body.clone().elim_ret(ctx.clone(), span).ok_or_else(|| {
+            ctx.err(
+                span,
+                TypeExpected {
+                    context: format!("handle"),
+                    expected: format!("Ret _?"),
+                    found: ty_body.clone(),
+                },
+            )
+        })?;
+        typ = match ret {
+            Some((var, body)) => {
+                let mut ctx = ctx.clone();
+                ctx.bind(var.to_owned(), ty_val);
+                body.ana(typ, ctx)?
+            }
+            None => Type::lub(typ, Type::make_ret(rc!(span.make(ty_val))), ctx.clone(), span)?,
+        };
+        let ops: HashMap<_, _> =
+            ops.into_iter().map(|EffectOp { name, tys, ty }| (name, (tys, ty))).collect();
+        let mut unexpected = Vec::new();
+        let mut op_set_arm: HashSet<TermV> = HashSet::new();
+        for Handler { op, vars, kont, body } in arms {
+            let Some((tys, ty)) = ops.get(op) else {
+                unexpected.push(op.to_owned());
+                continue;
+            };
+            op_set_arm.insert(op.to_owned());
+            bool_test(vars.len() == tys.len(), || {
+                ctx.err(
+                    body.span(),
+                    ArityMismatch {
+                        context: format!("handler of `{}`", op),
+                        expected: tys.len(),
+                        found: vars.len(),
+                    },
+                )
+            })?;
+            let mut ctx = ctx.clone();
+            for (var, ty) in vars.iter().zip(tys) {
+                ctx.bind(var.to_owned(), ty.inner_clone());
+            }
+            // resuming the continuation runs the rest of the handled computation
+            // under the same handler
+            let ty_kont = Type::internal("Fn", vec![ty.clone(), rc!(span.make(typ.clone()))]);
+            let ty_kont = ctx.latent(ty_kont, span);
+            ctx.bind(kont.to_owned(), Type::make_thunk(rc!(span.make(ty_kont))));
+            body.ana(typ.clone(), ctx)?;
+        }
+        let op_set_eff: HashSet<TermV> = ops.keys().cloned().collect();
+        let missing: Vec<_> = op_set_eff.difference(&op_set_arm).cloned().collect();
+        bool_test(unexpected.is_empty() && missing.is_empty(), || {
+            ctx.err(span, InconsistentHandlers { unexpected, missing })
+        })?;
+        Ok(typ)
+    }
+}
+
+/// An elimination applied to the head of an application spine.
+enum Elim<'a> {
+    Dtor(&'a Dtor<RcComp, DtorV, RcValue>, &'a SpanInfo),
+    TyApp(&'a RcType, &'a SpanInfo),
+}
+
+impl Span<TermComputation> {
+    /// Synthesizes the type of a computation run by a `do`, inferring the type
+    /// arguments of a polymorphic one.
+    fn syn_run(&self, ctx: Ctx) -> Result<Type, TyckError> {
+        let ty = self.syn(ctx.clone())?;
+        match ctx.resolve_alias(ty.clone(), self.span())?.resolve()? {
+            SynType::Forall(_) => self.tyck_spine(Hole.into(), ctx),
+            _ => Ok(ty),
+        }
+    }
+    /// Checks an application spine, a head followed by destructors and type
+    /// applications, against a type that may be a hole. The binder of a
+    /// `forall` met by a destructor is instantiated with a type argument
+    /// inferred from the arguments and the expected type.
+    fn tyck_spine(&self, typ: Type, ctx: Ctx) -> Result<Type, TyckError> {
+        let span = self.span();
+        let mut spine = Vec::new();
+        let mut head = self;
+        loop {
+            match head.inner_ref() {
+                TermComputation::Dtor(dtor) => {
+                    spine.push(Elim::Dtor(dtor, head.span()));
+                    head = &dtor.body;
+                }
+                TermComputation::TyAppTerm(App { body, arg }) => {
+                    spine.push(Elim::TyApp(arg, head.span()));
+                    head = body;
+                }
+                _ => break,
+            }
+        }
+        if let TermComputation::Force(Force(v)) = head.inner_ref() {
+            if let TermValue::Var(x) = v.inner_ref() {
+                if ctx.recs.contains(x) {
+                    // a recursive call, checked once the type of `x` is known
+                    return Ok(Hole.into());
+                }
+            }
+        }
+        let mut ty = head.syn(ctx.clone())?;
+        let mut metas = Metas::default();
+        let mut deferred = Vec::new();
+        for elim in spine.into_iter().rev() {
+            ty = ctx.resolve_alias(ty.subst(metas.solved(), &ctx)?, span)?;
+            match elim {
+                Elim::TyApp(arg, span) => {
+                    let SynType::Forall(Forall { param: (param, kd), ty: ty_body }) = ty.resolve()? else {
+                        Err(ctx.err(span, TypeExpected {
+                            context: format!("term-typ-application"),
+                            expected: format!("forall"),
+                            found: ty,
+                        }))?
+                    };
+                    arg.ana(kd.inner_clone(), ctx.clone())?;
+                    // resolve the argument here so that its type variables can't be
+                    // captured by the binders left in the body
+                    let arg =
+                        rc!(arg.span().make(arg.inner_clone().subst(ctx.type_env.clone(), &ctx)?));
+                    let diff = Env::init(&[(param, kd)], &[arg], || {
+                        ctx.err(
+                            span,
+                            ArityMismatch { context: format!("typapp"), expected: 1, found: 1 },
+                        )
+                    })?;
+                    ty = ctx.perform(ty_body.inner_clone().subst(diff, &ctx)?, span)?;
+                }
+                Elim::Dtor(Dtor { body, dtorv: dtor, args }, span) => {
+                    while let SynType::Forall(forall) = ty.resolve()? {
+                        ty = ctx.resolve_alias(metas.instantiate(forall, &ctx)?, span)?;
+                    }
+                    if let SynType::TypeApp(TypeApp { tvar, .. }) = ty.resolve()? {
+                        if tvar.name().starts_with('?') {
+                            // the codata type is yet to be determined
+                            Err(metas.unsolved(&ctx, span))?
+                        }
+                    }
+                    let (Codata { name, params, dtors }, ty_args) = ctx.resolve_codata(ty, span)?;
+                    // arity check on codata type
+                    let diff = Env::init(&params, &ty_args, || {
+                        ctx.err(
+                            span,
+                            ArityMismatch {
+                                context: format!("codata type `{}` instiantiation", name),
+                                expected: params.len(),
+                                found: ty_args.len(),
+                            },
+                        )
+                    })?;
+                    let CodataBr { dtorv: _, tys, ty: ty_res } = dtors
+                        .into_iter()
+                        .find(|CodataBr { dtorv, tys: _, ty: _ }| dtorv == dtor)
+                        .ok_or_else(|| {
+                            ctx.err(
+                                body.span(),
+                                NameResolveError::UnknownDestructor {
+                                    context: format!("codata type `{}`", name),
+                                    dtor: dtor.clone(),
+                                }
+                                .into(),
+                            )
+                        })?;
+                    bool_test(args.len() == tys.len(), || {
+                        ctx.err(
+                            span,
+                            ArityMismatch {
+                                context: format!("dtor"),
+                                expected: tys.len(),
+                                found: args.len(),
+                            },
+                        )
+                    })?;
+                    for (arg, ty) in args.iter().zip(tys.iter()) {
+                        let pat = ty.inner_clone().subst(diff.clone(), &ctx)?;
+                        match metas.check_arg(arg, pat.clone(), &ctx, span) {
+                            Ok(()) => {}
+                            // the argument may need a type argument determined by
+                            // the arguments after it
+                            Err(_) if !metas.is_solved() => deferred.push((arg, pat, span)),
+                            Err(err) => Err(err)?,
+                        }
+                    }
+                    ty = ctx.perform(ty_res.inner_clone().subst(diff, &ctx)?, span)?;
+                }
+            }
+        }
+        ty = metas.run(ty, typ, &ctx, span)?;
+        for (arg, pat, span) in deferred {
+            if let Err(err) = metas.check_arg(arg, pat, &ctx, span) {
+                Err(if metas.is_solved() { err } else { metas.unsolved(&ctx, span) })?
+            }
+        }
+        if !metas.is_solved() {
+            Err(metas.unsolved(&ctx, span))?
+        }
+        for Meta { sol, kd, param, .. } in &metas.0 {
+            let sol = sol.clone().unwrap();
+            param.span().make(sol).ana(kd.inner_clone(), ctx.clone())?;
+        }
+        ty.subst(metas.solved(), &ctx)
+    }
+}
@@ -0,0 +1,77 @@
+//! Groups the operators of an infix expression `x + y * z` by the fixities
+//! declared for them. Operands are values, so every application but the last
+//! one is bound by a `do`, and the last one is the computation itself.
+
+use super::*;
+
+struct Grouping<'a> {
+    operands: Vec<RcValue>,
+    operators: Vec<(&'a Span<String>, &'a Fixity)>,
+    binds: Vec<(TermV, RcComp)>,
+}
+
+impl Grouping<'_> {
+    /// Applies the operator on top of the stack to the last two operands.
+    fn reduce(&mut self) {
+        let (op, _) = self.operators.pop().expect("no operator to apply");
+        let rhs = self.operands.pop().expect("operator without operands");
+        let lhs = self.operands.pop().expect("operator without operands");
+        let fun = Fixity::var(op);
+        let comp = TermComputation::force_app(fun, vec![lhs, rhs], op.span());
+        let var = TermV::new(format!("$op{}", self.binds.len()), op.span().clone());
+        self.operands.push(rc!(op.span().make(var.clone().into())));
+        self.binds.push((var, rc!(op.span().make(comp))));
+    }
+}
+
+impl Infix {
+    /// Groups the operators, applying those of higher precedence first. The
+    /// applications are type checked in place of the expression, and kept for
+    /// linking.
+    pub(super) fn group(&self, ctx: &Ctx) -> Result<&RcComp, TyckError> {
+        let Infix { head, tail, grouped } = self;
+        let mut grouping =
+            Grouping { operands: vec![head.clone()], operators: Vec::new(), binds: Vec::new() };
+        for (op, operand) in tail {
+            let Some(fixity) = ctx.fixities.get(op.inner_ref()) else {
+                Err(ctx.err(op.span(), UnknownOperator { op: op.inner_clone() }))?
+            };
+            while let Some((top, above)) = grouping.operators.last() {
+                let applies_first = match above.prec.cmp(&fixity.prec) {
+                    std::cmp::Ordering::Greater => true,
+                    std::cmp::Ordering::Less => false,
+                    std::cmp::Ordering::Equal => match (above.assoc, fixity.assoc) {
+                        (Assoc::Left, Assoc::Left) => true,
+                        (Assoc::Right, Assoc::Right) => false,
+                        _ => Err(ctx.err(
+                            op.span(),
+                            NonAssociativeOperators {
+                                left: top.inner_clone(),
+                                right: op.inner_clone(),
+                            },
+                        ))?,
+                    },
+                };
+                if !applies_first {
+                    break;
+                }
+                grouping.reduce();
+            }
+            grouping.operators.push((op, fixity));
+            grouping.operands.push(operand.clone());
+        }
+        while !grouping.operators.is_empty() {
+            grouping.reduce();
+        }
+        let mut binds = grouping.binds;
+        let (_, body) = binds.pop().expect("infix expression without operators");
+        let comp = if binds.is_empty() {
+            body
+        } else {
+            let span = body.span().clone();
+            let group = binds.into_iter().map(|(var, comp)| Do { var, comp, body: () }.into());
+            rc!(span.make(TailGroup { group: group.collect(), body }.into()))
+        };
+        Ok(grouped.get_or_init(|| comp))
+    }
+}
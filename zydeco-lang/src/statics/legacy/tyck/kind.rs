@@ -0,0 +1,410 @@
+use super::*;
+use std::collections::{HashMap, VecDeque};
+
+/// A kind under inference, with meta variables standing for the kinds left
+/// out.
+#[derive(Clone, Debug)]
+enum IKind {
+    Meta(usize),
+    Base(KindBase),
+    Arity(Vec<IKind>, Box<IKind>),
+}
+
+/// A kind left out: the type variable it belongs to, if any, and its solution
+/// along with the use site it was solved at.
+struct KindMeta {
+    tvar: Option<TypeV>,
+    sol: Option<(IKind, SpanInfo)>,
+}
+
+type Scope = im::HashMap<TypeV, IKind>;
+
+/// Infers the kinds left out of type parameters by unification. Kinds no use
+/// determines default to `VType`. Mismatches that don't involve an inferred
+/// kind are left for the kind checker to report.
+struct KindInfer<'a> {
+    ctx: &'a Ctx,
+    /// the kinds of the type constructors declared in the module under inference
+    decls: HashMap<TypeV, IKind>,
+    metas: Vec<KindMeta>,
+    /// the meta variables of the binders whose kinds were left out, in the
+    /// order they are met
+    holes: VecDeque<usize>,
+}
+
+impl<'a> KindInfer<'a> {
+    fn new(ctx: &'a Ctx) -> Self {
+        KindInfer { ctx, decls: HashMap::new(), metas: Vec::new(), holes: VecDeque::new() }
+    }
+    fn fresh(&mut self, tvar: Option<&TypeV>) -> IKind {
+        self.metas.push(KindMeta { tvar: tvar.cloned(), sol: None });
+        IKind::Meta(self.metas.len() - 1)
+    }
+    fn from_kind(&mut self, kd: &Kind) -> IKind {
+        match kd {
+            Kind::Base(kd) => IKind::Base(*kd),
+            Kind::TypeArity(TypeArity { params, kd }) if params.is_empty() => {
+                self.from_kind(kd.inner_ref())
+            }
+            Kind::TypeArity(TypeArity { params, kd }) => IKind::Arity(
+                params.iter().map(|kd| self.from_kind(kd.inner_ref())).collect(),
+                Box::new(self.from_kind(kd.inner_ref())),
+            ),
+            Kind::Hole(_) => self.fresh(None),
+        }
+    }
+    /// The kind of a binder, a fresh meta variable if it was left out.
+    fn binder(&mut self, tvar: &TypeV, kd: &Span<Kind>) -> IKind {
+        if let Kind::Hole(_) = kd.inner_ref() {
+            let kd = self.fresh(Some(tvar));
+            let IKind::Meta(meta) = kd else { unreachable!() };
+            self.holes.push_back(meta);
+            kd
+        } else {
+            self.from_kind(kd.inner_ref())
+        }
+    }
+    /// Follows the solutions of the meta variable at the head of a kind.
+    fn shallow(&self, mut kd: IKind) -> IKind {
+        while let IKind::Meta(meta) = kd {
+            match &self.metas[meta].sol {
+                Some((sol, _)) => kd = sol.clone(),
+                None => break,
+            }
+        }
+        kd
+    }
+    fn occurs(&self, meta: usize, kd: &IKind) -> bool {
+        match self.shallow(kd.clone()) {
+            IKind::Meta(m) => m == meta,
+            IKind::Base(_) => false,
+            IKind::Arity(params, kd) => {
+                params.iter().any(|kd| self.occurs(meta, kd)) || self.occurs(meta, &kd)
+            }
+        }
+    }
+    fn unify(&mut self, lhs: &IKind, rhs: &IKind, span: &SpanInfo) -> bool {
+        match (self.shallow(lhs.clone()), self.shallow(rhs.clone())) {
+            (IKind::Meta(l), IKind::Meta(r)) if l == r => true,
+            (IKind::Meta(meta), kd) | (kd, IKind::Meta(meta)) => {
+                if self.occurs(meta, &kd) {
+                    return false;
+                }
+                self.metas[meta].sol = Some((kd, span.clone()));
+                true
+            }
+            (IKind::Base(l), IKind::Base(r)) => l == r,
+            (IKind::Arity(ls, l), IKind::Arity(rs, r)) => {
+                ls.len() == rs.len()
+                    && ls.iter().zip(rs.iter()).all(|(l, r)| self.unify(l, r, span))
+                    && self.unify(&l, &r, span)
+            }
+            (IKind::Base(_), IKind::Arity(..)) | (IKind::Arity(..), IKind::Base(_)) => false,
+        }
+    }
+    /// The meta variables a kind mentions, following their solutions.
+    fn metas_of(&self, kd: &IKind, out: &mut Vec<usize>) {
+        match kd {
+            IKind::Meta(meta) => {
+                out.push(*meta);
+                if let Some((sol, _)) = &self.metas[*meta].sol {
+                    self.metas_of(sol, out);
+                }
+            }
+            IKind::Base(_) => {}
+            IKind::Arity(params, kd) => {
+                params.iter().for_each(|kd| self.metas_of(kd, out));
+                self.metas_of(kd, out);
+            }
+        }
+    }
+    fn to_kind(&self, kd: &IKind, span: &SpanInfo) -> Kind {
+        match self.shallow(kd.clone()) {
+            IKind::Meta(_) => Hole.into(),
+            IKind::Base(kd) => kd.into(),
+            IKind::Arity(params, kd) => TypeArity {
+                params: params.iter().map(|kd| span.make(self.to_kind(kd, span))).collect(),
+                kd: Box::new(span.make(self.to_kind(&kd, span))),
+            }
+            .into(),
+        }
+    }
+    /// Unifies the kind expected at a use site with the kind found there.
+    fn expect(
+        &mut self, expected: &IKind, found: &IKind, span: &SpanInfo,
+    ) -> Result<(), TyckError> {
+        let mut metas = Vec::new();
+        self.metas_of(expected, &mut metas);
+        self.metas_of(found, &mut metas);
+        if metas.is_empty() || self.unify(expected, found, span) {
+            return Ok(());
+        }
+        // name the type variable whose earlier use conflicts with this one
+        let context = (metas.iter())
+            .find_map(|meta| {
+                let KindMeta { tvar, sol } = &self.metas[*meta];
+                let (tvar, (sol, site)) = (tvar.as_ref()?, sol.as_ref()?);
+                Some(format!(
+                    "the kind of `{}`, already {} from its use at {}",
+                    tvar,
+                    self.to_kind(sol, site).fmt(),
+                    site
+                ))
+            })
+            .unwrap_or_else(|| format!("kind inference"));
+        Err(self.ctx.err(
+            span,
+            KindMismatch {
+                context,
+                expected: self.to_kind(expected, span),
+                found: self.to_kind(found, span),
+            },
+        ))
+    }
+    fn lookup(&mut self, tvar: &TypeV, scope: &Scope) -> Option<IKind> {
+        if let Some(kd) = scope.get(tvar) {
+            return Some(kd.clone());
+        }
+        if let Some(ty) = self.ctx.type_env.get(tvar) {
+            let SynType::AbstVar(AbstVar(abst)) = ty.synty else { return None };
+            let kd = self.ctx.abst_ctx[abst].clone();
+            return Some(self.from_kind(&kd));
+        }
+        if let Some(kd) = self.decls.get(tvar) {
+            return Some(kd.clone());
+        }
+        let kd = self.ctx.type_ctx.get(tvar)?.clone();
+        Some(self.from_kind(&kd))
+    }
+    fn infer(&mut self, ty: &Span<Type>, scope: &Scope) -> Result<IKind, TyckError> {
+        let span = ty.span();
+        match &ty.inner_ref().synty {
+            SynType::TypeApp(TypeApp { tvar, args }) => {
+                let kd = match self.lookup(tvar, scope) {
+                    Some(kd) if args.is_empty() => return Ok(kd),
+                    Some(kd) => self.shallow(kd),
+                    // unbound; left for the kind checker to report
+                    None => self.fresh(None),
+                };
+                let kd = match kd {
+                    // a type variable applied to arguments is a type constructor
+                    IKind::Meta(meta) => {
+                        let params = args.iter().map(|_| self.fresh(None)).collect();
+                        let kd = IKind::Arity(params, Box::new(self.fresh(None)));
+                        self.metas[meta].sol = Some((kd.clone(), span.clone()));
+                        kd
+                    }
+                    kd => kd,
+                };
+                match kd {
+                    IKind::Arity(params, kd) if params.len() == args.len() => {
+                        for (arg, param) in args.iter().zip(params.iter()) {
+                            self.check(arg, param, scope)?;
+                        }
+                        Ok(*kd)
+                    }
+                    _ => {
+                        for arg in args {
+                            self.infer(arg, scope)?;
+                        }
+                        Ok(self.fresh(None))
+                    }
+                }
+            }
+            SynType::Forall(Forall { param: (tvar, kd), ty }) => {
+                let kd = self.binder(tvar, kd);
+                self.check(ty, &IKind::Base(KindBase::CType), &scope.update(tvar.clone(), kd))?;
+                Ok(IKind::Base(KindBase::CType))
+            }
+            SynType::Exists(Exists { param: (tvar, kd), ty }) => {
+                let kd = self.binder(tvar, kd);
+                self.check(ty, &IKind::Base(KindBase::VType), &scope.update(tvar.clone(), kd))?;
+                Ok(IKind::Base(KindBase::VType))
+            }
+            SynType::Effectful(Effectful { effs: _, ty }) => {
+                self.check(ty, &IKind::Base(KindBase::CType), scope)?;
+                Ok(IKind::Base(KindBase::CType))
+            }
+            SynType::AbstVar(AbstVar(abst)) => {
+                let kd = self.ctx.abst_ctx[*abst].clone();
+                Ok(self.from_kind(&kd))
+            }
+            SynType::Hole(_) => Ok(self.fresh(None)),
+        }
+    }
+    fn check(&mut self, ty: &Span<Type>, kd: &IKind, scope: &Scope) -> Result<(), TyckError> {
+        let found = self.infer(ty, scope)?;
+        self.expect(kd, &found, ty.span())
+    }
+    /// Solves the meta variables no use determines.
+    fn default(&mut self) {
+        for meta in 0..self.metas.len() {
+            if self.metas[meta].sol.is_none() {
+                self.metas[meta].sol = Some((IKind::Base(KindBase::VType), SpanInfo::dummy()));
+            }
+        }
+    }
+    /// Fills in the kind of a binder, in the order `binder` met them.
+    fn fill_binder(&mut self, kd: &Span<Kind>) -> Span<Kind> {
+        if let Kind::Hole(_) = kd.inner_ref() {
+            let meta = self.holes.pop_front().expect("a kind inferred for every hole");
+            kd.span().make(self.to_kind(&IKind::Meta(meta), kd.span()))
+        } else {
+            kd.clone()
+        }
+    }
+    /// Fills in the kinds of the binders in a type, in the order `infer` met
+    /// them.
+    fn fill(&mut self, ty: &RcType) -> RcType {
+        if self.holes.is_empty() {
+            return ty.clone();
+        }
+        let span = ty.span();
+        let ty: Type = match &ty.inner_ref().synty {
+            SynType::TypeApp(TypeApp { tvar, args }) => {
+                let args = args.iter().map(|arg| self.fill(arg)).collect();
+                TypeApp { tvar: tvar.clone(), args }.into()
+            }
+            SynType::Forall(Forall { param: (tvar, kd), ty }) => {
+                let kd = self.fill_binder(kd);
+                Forall { param: (tvar.clone(), kd), ty: self.fill(ty) }.into()
+            }
+            SynType::Exists(Exists { param: (tvar, kd), ty }) => {
+                let kd = self.fill_binder(kd);
+                Exists { param: (tvar.clone(), kd), ty: self.fill(ty) }.into()
+            }
+            SynType::Effectful(Effectful { effs, ty }) => {
+                Effectful { effs: effs.clone(), ty: self.fill(ty) }.into()
+            }
+            SynType::AbstVar(_) | SynType::Hole(_) => return ty.clone(),
+        };
+        rc!(span.make(ty))
+    }
+}
+
+impl Ctx {
+    /// Infers the kinds left out of the type parameters of the declarations in
+    /// a module and of the binders in their types.
+    pub(super) fn infer_kinds_module(&self, module: &mut Module) -> Result<(), TyckError> {
+        let mut infer = KindInfer::new(self);
+        let vtype = IKind::Base(KindBase::VType);
+        let ctype = IKind::Base(KindBase::CType);
+        let mut scopes = Vec::new();
+        let mut params = |infer: &mut KindInfer, ps: &[(TypeV, Span<Kind>)]| {
+            let kds: Vec<_> = ps.iter().map(|(tvar, kd)| infer.binder(tvar, kd)).collect();
+            let scope: Scope = ps.iter().map(|(tvar, _)| tvar.clone()).zip(kds.clone()).collect();
+            scopes.push(scope);
+            kds
+        };
+        let arity = |kds: Vec<IKind>, kd: IKind| {
+            if kds.is_empty() {
+                kd
+            } else {
+                IKind::Arity(kds, Box::new(kd))
+            }
+        };
+        // declare the type constructors, which may refer to each other
+        for DeclSymbol { inner: data, .. } in &module.data {
+            let kds = params(&mut infer, &data.params);
+            infer.decls.insert(data.name.clone(), arity(kds, vtype.clone()));
+        }
+        for DeclSymbol { inner: coda, .. } in &module.codata {
+            let kds = params(&mut infer, &coda.params);
+            infer.decls.insert(coda.name.clone(), arity(kds, ctype.clone()));
+        }
+        let mut alias_kds = Vec::new();
+        for DeclSymbol { inner: alias, .. } in &module.alias {
+            let kds = params(&mut infer, &alias.params);
+            let kd = infer.fresh(None);
+            alias_kds.push(kd.clone());
+            infer.decls.insert(alias.name.clone(), arity(kds, kd));
+        }
+        // constrain them by their uses
+        let mut scopes = scopes.into_iter();
+        for DeclSymbol { inner: data, .. } in &module.data {
+            let scope = scopes.next().unwrap();
+            for DataBr { ctorv: _, tys } in &data.ctors {
+                for ty in tys {
+                    infer.check(ty, &vtype, &scope)?;
+                }
+            }
+        }
+        for DeclSymbol { inner: coda, .. } in &module.codata {
+            let scope = scopes.next().unwrap();
+            for CodataBr { dtorv: _, tys, ty } in &coda.dtors {
+                for ty in tys {
+                    infer.check(ty, &vtype, &scope)?;
+                }
+                infer.check(ty, &ctype, &scope)?;
+            }
+        }
+        for (DeclSymbol { inner: alias, .. }, kd) in module.alias.iter().zip(alias_kds) {
+            let scope = scopes.next().unwrap();
+            infer.check(&alias.ty, &kd, &scope)?;
+        }
+        for DeclSymbol { inner: eff, .. } in &module.effect {
+            for EffectOp { name: _, tys, ty } in &eff.ops {
+                for ty in tys.iter().chain([ty]) {
+                    infer.check(ty, &vtype, &Scope::new())?;
+                }
+            }
+        }
+        if infer.holes.is_empty() {
+            return Ok(());
+        }
+        infer.default();
+        // fill in the kinds, in the order they were met
+        let fill_params = |infer: &mut KindInfer, ps: &mut Vec<(TypeV, Span<Kind>)>| {
+            for (_, kd) in ps.iter_mut() {
+                *kd = infer.fill_binder(kd);
+            }
+        };
+        for DeclSymbol { inner: data, .. } in &mut module.data {
+            fill_params(&mut infer, &mut data.params);
+        }
+        for DeclSymbol { inner: coda, .. } in &mut module.codata {
+            fill_params(&mut infer, &mut coda.params);
+        }
+        for DeclSymbol { inner: alias, .. } in &mut module.alias {
+            fill_params(&mut infer, &mut alias.params);
+        }
+        for DeclSymbol { inner: data, .. } in &mut module.data {
+            for DataBr { ctorv: _, tys } in &mut data.ctors {
+                for ty in tys.iter_mut() {
+                    *ty = infer.fill(ty);
+                }
+            }
+        }
+        for DeclSymbol { inner: coda, .. } in &mut module.codata {
+            for CodataBr { dtorv: _, tys, ty } in &mut coda.dtors {
+                for ty in tys.iter_mut() {
+                    *ty = infer.fill(ty);
+                }
+                *ty = infer.fill(ty);
+            }
+        }
+        for DeclSymbol { inner: alias, .. } in &mut module.alias {
+            alias.ty = infer.fill(&alias.ty);
+        }
+        for DeclSymbol { inner: eff, .. } in &mut module.effect {
+            for EffectOp { name: _, tys, ty } in &mut eff.ops {
+                for ty in tys.iter_mut() {
+                    *ty = infer.fill(ty);
+                }
+                *ty = infer.fill(ty);
+            }
+        }
+        Ok(())
+    }
+    /// Infers the kinds left out of the binders in a type of the given kind.
+    pub(super) fn infer_kinds(&self, ty: &RcType, kd: &Kind) -> Result<RcType, TyckError> {
+        let mut infer = KindInfer::new(self);
+        let kd = infer.from_kind(kd);
+        infer.check(ty, &kd, &Scope::new())?;
+        if infer.holes.is_empty() {
+            return Ok(ty.clone());
+        }
+        infer.default();
+        Ok(infer.fill(ty))
+    }
+}
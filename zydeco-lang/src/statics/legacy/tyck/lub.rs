@@ -19,7 +19,7 @@ impl<T> Lub for Seal<T> {
 impl Kind {
     fn normalize(self) -> Self {
         match self {
-            Kind::Base(_) => self,
+            Kind::Base(_) | Kind::Hole(_) => self,
             Kind::TypeArity(TypeArity { params, kd }) => {
                 if params.is_empty() {
                     kd.inner()
@@ -39,6 +39,8 @@ impl Lub for Kind {
         let lhs = lhs.normalize();
         let rhs = rhs.normalize();
         match (lhs.clone(), rhs.clone()) {
+            (Kind::Hole(_), _) => Ok(rhs),
+            (_, Kind::Hole(_)) => Ok(lhs),
             (Kind::Base(lhs), Kind::Base(rhs)) => {
                 bool_test(lhs == rhs, || {
                     ctx.err(
@@ -150,6 +152,16 @@ impl Lub for Type {
                 // Ok(Exists { param: lhs.param.clone(), ty: rc!(lhs.ty.span().make(ty)) }.into())
                 Ok(lhs)
             }
+            (
+                SynType::Effectful(Effectful { effs, ty }),
+                SynType::Effectful(Effectful { effs: effs_, ty: ty_ }),
+            ) => {
+                let lhs_effs: HashSet<_> = effs.iter().collect();
+                let rhs_effs: HashSet<_> = effs_.iter().collect();
+                bool_test(lhs_effs == rhs_effs, err)?;
+                let ty = ty.inner_clone().lub(ty_.inner_clone(), ctx, span)?;
+                Ok(Effectful { effs, ty: rc!(ty_.span().make(ty)) }.into())
+            }
             (SynType::AbstVar(lhs), SynType::AbstVar(rhs)) => {
                 bool_test(lhs == rhs, err)?;
                 Ok(lhs.into())
@@ -157,6 +169,7 @@ impl Lub for Type {
             (SynType::TypeApp(_), _)
             | (SynType::Forall(_), _)
             | (SynType::Exists(_), _)
+            | (SynType::Effectful(_), _)
             | (SynType::AbstVar(_), _) => Err(err()),
         }
     }
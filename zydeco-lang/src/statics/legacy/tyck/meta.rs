@@ -0,0 +1,143 @@
+use super::*;
+
+/// A type argument left implicit in an application, to be determined by the
+/// types of the arguments or the type the application is checked against.
+pub(super) struct Meta {
+    pub var: TypeV,
+    pub param: TypeV,
+    pub kd: Span<Kind>,
+    pub sol: Option<Type>,
+}
+
+#[derive(Default)]
+pub(super) struct Metas(pub Vec<Meta>);
+
+impl Metas {
+    /// Instantiates the binder of a `forall` with a fresh meta variable.
+    pub(super) fn instantiate(
+        &mut self, Forall { param: (param, kd), ty }: Forall<(TypeV, Span<Kind>), RcType>,
+        ctx: &Ctx,
+    ) -> Result<Type, TyckError> {
+        let var = self.fresh(param.clone(), kd);
+        ty.inner_clone().subst(Env::from_iter([(param, var)]), ctx)
+    }
+    /// Matches the type of a computation about to be run against the type
+    /// expected of it, instantiating its binders with fresh meta variables
+    /// unless it's expected to stay polymorphic.
+    pub(super) fn run(
+        &mut self, mut ty: Type, typ: Type, ctx: &Ctx, span: &SpanInfo,
+    ) -> Result<Type, TyckError> {
+        let typ = ctx.resolve_alias(typ.subst(ctx.type_env.clone(), ctx)?, span)?;
+        if !matches!(typ.resolve()?, SynType::Forall(_)) {
+            ty = ctx.resolve_alias(ty.subst(self.solved(), ctx)?, span)?;
+            while let SynType::Forall(forall) = ty.resolve()? {
+                ty = ctx.resolve_alias(self.instantiate(forall, ctx)?, span)?;
+            }
+        }
+        self.unify(ty.clone(), typ, ctx, span)?;
+        Ok(ty)
+    }
+    /// A fresh meta variable standing for the type argument `param`.
+    pub(super) fn fresh(&mut self, param: TypeV, kd: Span<Kind>) -> Type {
+        // `?` can't start a type variable in the surface syntax, so the meta
+        // variable can't capture anything
+        let var = TypeV::new(format!("?{}", self.0.len()), param.span().clone());
+        self.0.push(Meta { var: var.clone(), param, kd, sol: None });
+        var.into()
+    }
+    /// The solutions so far.
+    pub(super) fn solved(&self) -> Env<TypeV, Type> {
+        Env::from_iter(
+            (self.0.iter()).filter_map(|Meta { var, sol, .. }| Some((var.clone(), sol.clone()?))),
+        )
+    }
+    /// The solutions so far, with holes for the meta variables still unsolved.
+    pub(super) fn expected(&self) -> Env<TypeV, Type> {
+        Env::from_iter(
+            (self.0.iter())
+                .map(|Meta { var, sol, .. }| (var.clone(), sol.clone().unwrap_or(Hole.into()))),
+        )
+    }
+    /// A solution is partial while it still has holes in it.
+    fn is_unsolved(meta: &Meta) -> bool {
+        meta.sol.as_ref().is_none_or(|sol| sol.has_hole())
+    }
+    pub(super) fn is_solved(&self) -> bool {
+        !self.0.iter().any(Self::is_unsolved)
+    }
+    /// Reports the first type argument that couldn't be determined.
+    pub(super) fn unsolved(&self, ctx: &Ctx, span: &SpanInfo) -> TyckError {
+        let Meta { param, .. } = self
+            .0
+            .iter()
+            .find(|meta| Self::is_unsolved(meta))
+            .expect("some meta variable is unsolved");
+        ctx.err(
+            span,
+            NeedAnnotation {
+                content: format!(
+                    "type argument `{}` of this application; pass it explicitly with `@(..)`",
+                    param
+                ),
+            },
+        )
+    }
+    /// Matches a type mentioning meta variables against a type found for it,
+    /// solving the meta variables it meets. Any other mismatch has already
+    /// been reported when the type was found.
+    pub(super) fn unify(
+        &mut self, pat: Type, found: Type, ctx: &Ctx, span: &SpanInfo,
+    ) -> Result<(), TyckError> {
+        let pat = ctx.resolve_alias(pat, span)?;
+        let found = ctx.resolve_alias(found, span)?;
+        match (pat.resolve()?, found.resolve()?) {
+            (_, SynType::Hole(_)) => {}
+            (SynType::TypeApp(TypeApp { tvar, args }), _)
+                if args.is_empty() && self.0.iter().any(|meta| meta.var == tvar) =>
+            {
+                let meta = self.0.iter_mut().find(|meta| meta.var == tvar).unwrap();
+                meta.sol = Some(match meta.sol.take() {
+                    Some(sol) => sol.lub(found, ctx.clone(), span)?,
+                    None => found,
+                });
+            }
+            (SynType::TypeApp(lhs), SynType::TypeApp(rhs))
+                if lhs.tvar == rhs.tvar && lhs.args.len() == rhs.args.len() =>
+            {
+                for (lhs, rhs) in lhs.args.iter().zip(rhs.args.iter()) {
+                    self.unify(lhs.inner_clone(), rhs.inner_clone(), ctx, span)?;
+                }
+            }
+            (SynType::Effectful(lhs), SynType::Effectful(rhs)) => {
+                self.unify(lhs.ty.inner_clone(), rhs.ty.inner_clone(), ctx, span)?;
+            }
+            // binders are left alone; their meta variables must be solved
+            // elsewhere
+            _ => {}
+        }
+        Ok(())
+    }
+    /// Checks an argument against a parameter type mentioning meta variables.
+    pub(super) fn check_arg(
+        &mut self, arg: &RcValue, pat: Type, ctx: &Ctx, span: &SpanInfo,
+    ) -> Result<(), TyckError> {
+        let found = arg.ana(pat.clone().subst(self.expected(), ctx)?, ctx.clone())?;
+        let found = found.subst(ctx.type_env.clone(), ctx)?;
+        self.unify(pat, found, ctx, span)
+    }
+}
+
+impl Type {
+    pub(super) fn has_hole(&self) -> bool {
+        match &self.synty {
+            SynType::TypeApp(TypeApp { args, .. }) => {
+                args.iter().any(|arg| arg.inner_ref().has_hole())
+            }
+            SynType::Forall(Forall { ty, .. })
+            | SynType::Exists(Exists { ty, .. })
+            | SynType::Effectful(Effectful { ty, .. }) => ty.inner_ref().has_hole(),
+            SynType::AbstVar(_) => false,
+            SynType::Hole(_) => true,
+        }
+    }
+}
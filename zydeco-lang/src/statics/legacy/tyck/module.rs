@@ -105,13 +105,41 @@ impl TypeCheck for Span<&prelude::Alias> {
     }
 }
 
+impl Span<&prelude::Effect> {
+    /// Checks an operation and gives the type it is bound at, a thunk of a
+    /// function performing the effect.
+    fn op_type(&self, op: &EffectOp<TermV, RcType>, ctx: &Ctx) -> Result<Type, TyckError> {
+        let eff = self.inner_ref();
+        let EffectOp { name, tys, ty } = op;
+        if eff.ops.iter().filter(|op| &op.name == name).count() > 1 {
+            Err(ctx.err(
+                name.span(),
+                NameResolveError::DuplicateOpDeclaration { name: name.clone() }.into(),
+            ))?;
+        }
+        for ty in tys {
+            ty.ana(KindBase::VType.into(), ctx.clone())?;
+        }
+        ty.ana(KindBase::VType.into(), ctx.clone())?;
+        let span = name.span();
+        let mut ty_op = Type::make_ret(ty.clone());
+        for ty in tys.iter().rev() {
+            ty_op = Type::internal("Fn", vec![ty.clone(), rc!(span.make(ty_op))]);
+        }
+        let ty_op = Effectful { effs: vec![eff.name.clone()], ty: rc!(span.make(ty_op)) };
+        Ok(Type::make_thunk(rc!(span.make(ty_op.into()))))
+    }
+}
+
 impl TypeCheck for Span<Module> {
     type Ctx = Ctx;
     type Out = Seal<Ctx>;
     fn syn_step(
         &self, mut ctx: Self::Ctx,
     ) -> Result<Step<(Self::Ctx, &Self), Self::Out>, TyckError> {
-        let Module { name: _, data, codata, alias, define, define_ext } = self.inner_ref();
+        let mut module = self.inner_clone();
+        ctx.infer_kinds_module(&mut module)?;
+        let Module { name: _, data, codata, alias, effect, fixity, define, define_ext } = &module;
         // register data type, codata type and type alias declarations in the type context
         for DeclSymbol { inner: data, .. } in data {
             let res = ctx.type_ctx.insert(data.name.clone(), data.type_arity());
@@ -153,11 +181,61 @@ impl TypeCheck for Span<Module> {
             coda.name.span().make(coda).syn(ctx.clone())?;
             ctx.codata_env.insert(coda.name.clone(), coda.clone());
         }
+        // register effect declarations and their operations
+        for DeclSymbol { inner: eff, .. } in effect {
+            let span = eff.name.span();
+            if ctx.effect_env.contains_key(&eff.name) {
+                Err(ctx.err(
+                    span,
+                    NameResolveError::DuplicateTypeDeclaration { name: eff.name.clone() }.into(),
+                ))?;
+            }
+            ctx.effect_env.insert(eff.name.clone(), eff.clone());
+            for op in eff.ops.iter() {
+                // operations are named globally, and handlers pick them by name
+                if ctx.op_env.contains_key(&op.name) {
+                    Err(ctx.err(
+                        op.name.span(),
+                        NameResolveError::DuplicateOpDeclaration { name: op.name.clone() }.into(),
+                    ))?;
+                }
+                let ty_op = span.make(eff).op_type(op, &ctx)?;
+                ctx.op_env.insert(op.name.clone(), eff.name.clone());
+                ctx.bind(op.name.clone(), ty_op);
+                ctx.globals.insert(op.name.clone(), op.name.span().clone());
+            }
+        }
         for DeclSymbol { inner: Define { name: (var, ty), def: () }, .. } in define_ext {
-            ctx.term_ctx.insert(var.clone(), ty.inner_clone());
+            let ty = &ctx.infer_kinds(ty, &KindBase::VType.into())?;
+            // the declared type must agree with the builtin implementing it
+            let Some(builtin) = ctx.builtins.get(var).cloned() else {
+                Err(ctx.err(var.span(), UnknownExtern { name: var.clone() }))?
+            };
+            let mismatch = || {
+                ctx.err(
+                    ty.span(),
+                    TypeMismatch {
+                        context: format!("extern definition {}", var),
+                        expected: builtin.inner_clone(),
+                        found: ty.inner_clone(),
+                    },
+                )
+            };
+            Lub::lub(builtin.inner_clone(), ty.inner_clone(), ctx.clone(), ty.span())
+                .map_err(|_| mismatch())?;
+            ctx.bind(var.clone(), ty.inner_clone());
+            ctx.globals.insert(var.clone(), var.span().clone());
         }
-        // register term declarations in the term context
+        // register term declarations in the term context; an operator
+        // declaration takes effect along with the definition it applies, which
+        // come in the same order, so it shadows earlier ones like definitions do
+        let mut fixity = fixity.iter().peekable();
         for DeclSymbol { inner: Define { name, def }, external, .. } in define {
+            if let Some(DeclSymbol { inner: fix, .. }) =
+                fixity.next_if(|DeclSymbol { inner: fix, .. }| Fixity::var(&fix.op) == *name)
+            {
+                Rc::make_mut(&mut ctx.fixities).insert(fix.op.inner_clone(), fix.clone());
+            }
             bool_test(!external, || {
                 ctx.err(
                     name.span(),
@@ -167,7 +245,8 @@ impl TypeCheck for Span<Module> {
             let ty_def = def.syn(ctx.clone())?;
             let span = name.span();
             span.make(ty_def.clone()).ana(KindBase::VType.into(), ctx.clone())?;
-            ctx.term_ctx.insert(name.clone(), ty_def);
+            ctx.bind(name.clone(), ty_def);
+            ctx.globals.insert(name.clone(), name.span().clone());
         }
         Ok(Step::Done(Seal(ctx)))
     }
@@ -0,0 +1,392 @@
+//! Compiles the nested patterns of `match` arms into flat matches, by
+//! specializing a matrix with a row of patterns for each arm, one column at a
+//! time (Maranget, "Compiling Pattern Matching to Good Decision Trees"). Arms
+//! that can never be reached and values no arm matches are found on the same
+//! matrix ("Warnings for Pattern Matching"). A column of literals compiles to
+//! a match on a literal, whose default arm takes the rows matching anything
+//! there. The constructors a column tests for are those of the data type of
+//! the values in it, as found by type checking.
+
+use super::*;
+use std::fmt;
+
+/// A value that no arm matches, with `_` standing for any value.
+#[derive(Clone)]
+enum Example {
+    Any,
+    Ctor(CtorV, Vec<Example>),
+    Lit(Literal),
+}
+
+impl fmt::Display for Example {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Example::Any => write!(f, "_"),
+            Example::Ctor(ctorv, args) => {
+                write!(f, "{}(", ctorv)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ")")
+            }
+            Example::Lit(lit) => write!(f, "{}", lit.fmt()),
+        }
+    }
+}
+
+/// An arm in the matrix, with the variables its patterns bound so far.
+#[derive(Clone)]
+struct Row {
+    pats: Vec<Pattern>,
+    binds: Vec<(TermV, RcValue)>,
+    body: RcComp,
+}
+
+/// The constructors of a data type, with the types of their arguments.
+type Signature = Vec<(CtorV, Vec<Type>)>;
+
+struct Compiler<'a> {
+    ctx: &'a Ctx,
+    /// Where the `match` is, for the errors and the matches it compiles to.
+    info: SpanInfo,
+    fresh: usize,
+}
+
+impl NestedMatch {
+    /// Compiles the match on a value of type `ty` into flat matches, rejecting
+    /// arms that can never be reached and values that no arm matches. The flat
+    /// matches are type checked in place of the match, and kept for linking.
+    pub(super) fn compile(&self, ty: Type, ctx: &Ctx) -> Result<&RcComp, TyckError> {
+        let NestedMatch { scrut, arms, compiled } = self;
+        let mut compiler = Compiler { ctx, info: scrut.span().clone(), fresh: 0 };
+        let mut rows: Vec<Row> = Vec::new();
+        for (pat, body) in arms {
+            compiler.check(pat.inner_ref(), &ty, pat.span())?;
+            let pats = vec![pat.inner_clone()];
+            let matrix: Vec<_> = rows.iter().map(|row| row.pats.clone()).collect();
+            if compiler.useful(&matrix, &pats, std::slice::from_ref(&ty))?.is_none() {
+                Err(ctx.err(pat.span(), RedundantMatchArm))?
+            }
+            rows.push(Row { pats, binds: Vec::new(), body: body.clone() });
+        }
+        let matrix: Vec<_> = rows.iter().map(|row| row.pats.clone()).collect();
+        if let Some(mut example) =
+            compiler.useful(&matrix, &[Pattern::Any(None)], std::slice::from_ref(&ty))?
+        {
+            let missing = example.remove(0).to_string();
+            Err(ctx.err(&compiler.info, NonExhaustiveMatch { missing }))?
+        }
+        let comp = compiler.compile(vec![scrut.clone()], vec![ty], rows)?;
+        Ok(compiled.get_or_init(|| comp))
+    }
+}
+
+impl Compiler<'_> {
+    /// The constructors of the data type `ty`, and its name.
+    fn signature(&self, ty: &Type, span: &SpanInfo) -> Result<(TypeV, Signature), TyckError> {
+        let ctx = self.ctx;
+        let (Data { name, params, ctors }, args) = ctx.resolve_data(ty.clone(), span)?;
+        let diff = Env::init(&params, &args, || {
+            ctx.err(
+                span,
+                ArityMismatch {
+                    context: format!("data type `{}` instantiation", name),
+                    expected: params.len(),
+                    found: args.len(),
+                },
+            )
+        })?;
+        let sig = ctors
+            .into_iter()
+            .map(|DataBr { ctorv, tys }| {
+                let tys = tys.iter().map(|ty| ty.inner_clone().subst(diff.clone(), ctx));
+                Ok((ctorv, tys.collect::<Result<_, TyckError>>()?))
+            })
+            .collect::<Result<_, TyckError>>()?;
+        Ok((name, sig))
+    }
+
+    /// Checks that `pat` can match values of type `ty`: its constructors are
+    /// those of the data type, given as many arguments as they take, and its
+    /// literals are of the type.
+    fn check(&self, pat: &Pattern, ty: &Type, span: &SpanInfo) -> Result<(), TyckError> {
+        match pat {
+            Pattern::Any(_) => {}
+            Pattern::Lit(lit) => {
+                span.make(TermValue::from(lit.clone())).ana(ty.clone(), self.ctx.clone())?;
+            }
+            Pattern::Ctor(ctorv, args) => {
+                let (data, sig) = self.signature(ty, span)?;
+                let Some((_, tys)) = sig.iter().find(|(ctor, _)| ctor == ctorv) else {
+                    Err(self.ctx.err(span, ForeignCtor { ctor: ctorv.clone(), data }))?
+                };
+                if tys.len() != args.len() {
+                    Err(self.ctx.err(
+                        span,
+                        ArityMismatch {
+                            context: format!("the pattern of `{}`", ctorv),
+                            expected: tys.len(),
+                            found: args.len(),
+                        },
+                    ))?
+                }
+                for (arg, ty) in args.iter().zip(tys) {
+                    self.check(arg, ty, span)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The constructors the first column of `rows` tests for.
+    fn heads(rows: &[Vec<Pattern>]) -> Vec<CtorV> {
+        let mut heads: Vec<CtorV> = Vec::new();
+        for row in rows {
+            if let Pattern::Ctor(ctorv, _) = &row[0] {
+                if !heads.contains(ctorv) {
+                    heads.push(ctorv.clone());
+                }
+            }
+        }
+        heads
+    }
+
+    /// The rows that match `ctor` in the first column, with the column
+    /// replaced by the patterns of its arguments.
+    fn specialize(rows: &[Vec<Pattern>], ctor: &CtorV, arity: usize) -> Vec<Vec<Pattern>> {
+        let mut specialized = Vec::new();
+        for row in rows {
+            let args = match &row[0] {
+                Pattern::Ctor(ctorv, args) if ctorv == ctor => args.clone(),
+                Pattern::Ctor(..) | Pattern::Lit(_) => continue,
+                Pattern::Any(_) => vec![Pattern::Any(None); arity],
+            };
+            specialized.push(args.into_iter().chain(row[1..].iter().cloned()).collect());
+        }
+        specialized
+    }
+
+    /// A literal that none of `rows` tests for in the first column, which
+    /// holds literals.
+    fn uncovered(rows: &[Vec<Pattern>]) -> Literal {
+        let lits: Vec<_> = (rows.iter())
+            .filter_map(|row| match &row[0] {
+                Pattern::Lit(lit) => Some(lit),
+                _ => None,
+            })
+            .collect();
+        let uncovered = |lit: &Literal| !lits.contains(&lit);
+        match lits[0] {
+            Literal::Int(_) => (0..).map(Literal::Int).find(uncovered),
+            Literal::String(_) => (0..).map(|n| Literal::String("a".repeat(n))).find(uncovered),
+            Literal::Char(_) => ('a'..=char::MAX).map(Literal::Char).find(uncovered),
+        }
+        .expect("finitely many literals")
+    }
+
+    /// Values matched by `pats` but by none of `rows`, if there are any; the
+    /// values of each column are of the type in `tys`.
+    fn useful(
+        &self, rows: &[Vec<Pattern>], pats: &[Pattern], tys: &[Type],
+    ) -> Result<Option<Vec<Example>>, TyckError> {
+        let (Some((head, pats)), Some((ty, tys))) = (pats.split_first(), tys.split_first()) else {
+            return Ok(rows.is_empty().then(Vec::new));
+        };
+        // rebuilds the constructor from the examples of its arguments
+        let rebuild = |ctorv: &CtorV, arity: usize, mut examples: Vec<Example>| {
+            let args = examples.drain(..arity).collect();
+            examples.insert(0, Example::Ctor(ctorv.clone(), args));
+            examples
+        };
+        match head {
+            Pattern::Lit(lit) => {
+                let rows: Vec<_> = rows
+                    .iter()
+                    .filter(|row| match &row[0] {
+                        Pattern::Lit(other) => other == lit,
+                        _ => true,
+                    })
+                    .map(|row| row[1..].to_vec())
+                    .collect();
+                Ok(self.useful(&rows, pats, tys)?.map(|mut examples| {
+                    examples.insert(0, Example::Lit(lit.clone()));
+                    examples
+                }))
+            }
+            Pattern::Ctor(ctorv, args) => {
+                let (_, sig) = self.signature(ty, &self.info)?;
+                let (_, arg_tys) = (sig.into_iter())
+                    .find(|(ctor, _)| ctor == ctorv)
+                    .expect("pattern checked against the data type");
+                let rows = Self::specialize(rows, ctorv, args.len());
+                let pats: Vec<_> = args.iter().chain(pats).cloned().collect();
+                let tys: Vec<_> = arg_tys.into_iter().chain(tys.iter().cloned()).collect();
+                Ok(self
+                    .useful(&rows, &pats, &tys)?
+                    .map(|examples| rebuild(ctorv, args.len(), examples)))
+            }
+            Pattern::Any(_) => {
+                // literals never cover all the values of their type
+                let literals = rows.iter().any(|row| matches!(row[0], Pattern::Lit(_)));
+                let heads = if literals { Vec::new() } else { Self::heads(rows) };
+                let sig =
+                    if heads.is_empty() { Vec::new() } else { self.signature(ty, &self.info)?.1 };
+                let missing = sig.iter().find(|(ctor, _)| !heads.contains(ctor));
+                if heads.is_empty() || missing.is_some() {
+                    let first = match missing {
+                        Some((ctorv, tys)) => {
+                            Example::Ctor(ctorv.clone(), vec![Example::Any; tys.len()])
+                        }
+                        None if literals => Example::Lit(Self::uncovered(rows)),
+                        None => Example::Any,
+                    };
+                    // a value the first column doesn't test for goes to the
+                    // rows matching anything there
+                    let rows: Vec<_> = rows
+                        .iter()
+                        .filter(|row| matches!(row[0], Pattern::Any(_)))
+                        .map(|row| row[1..].to_vec())
+                        .collect();
+                    return Ok(self.useful(&rows, pats, tys)?.map(|mut examples| {
+                        examples.insert(0, first);
+                        examples
+                    }));
+                }
+                for (ctorv, arg_tys) in &sig {
+                    let rows = Self::specialize(rows, ctorv, arg_tys.len());
+                    let pats: Vec<_> = vec![Pattern::Any(None); arg_tys.len()]
+                        .into_iter()
+                        .chain(pats.iter().cloned())
+                        .collect();
+                    let tys: Vec<_> = arg_tys.iter().chain(tys).cloned().collect();
+                    if let Some(examples) = self.useful(&rows, &pats, &tys)? {
+                        return Ok(Some(rebuild(ctorv, arg_tys.len(), examples)));
+                    }
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    fn fresh(&mut self) -> TermV {
+        self.fresh += 1;
+        TermV::new(format!("$pat{}", self.fresh), self.info.clone())
+    }
+
+    /// Compiles the matrix of `rows`, whose columns match the values of
+    /// `occs` of the types in `tys`; every value reaching it is matched by
+    /// some row.
+    fn compile(
+        &mut self, occs: Vec<RcValue>, tys: Vec<Type>, rows: Vec<Row>,
+    ) -> Result<RcComp, TyckError> {
+        let first = rows.first().expect("matrix of an exhaustive match runs out of rows");
+        let Some(col) = first.pats.iter().position(|pat| !matches!(pat, Pattern::Any(_))) else {
+            // the first row matches anything left
+            let Row { pats, mut binds, body } = rows.into_iter().next().unwrap();
+            for (pat, occ) in pats.into_iter().zip(occs) {
+                if let Pattern::Any(Some(var)) = pat {
+                    binds.push((var, occ));
+                }
+            }
+            return Ok(TailGroup::bind(binds, body));
+        };
+        if rows.iter().any(|row| matches!(row.pats[col], Pattern::Lit(_))) {
+            return self.compile_literals(occs, tys, rows, col);
+        }
+        let (_, sig) = self.signature(&tys[col], &self.info)?;
+        let occ = occs[col].clone();
+        let mut arms = Vec::new();
+        for (ctorv, arg_tys) in sig {
+            let arity = arg_tys.len();
+            let mut branch = Vec::new();
+            for row in &rows {
+                let mut pats = row.pats.clone();
+                let mut binds = row.binds.clone();
+                let args = match pats.remove(col) {
+                    Pattern::Ctor(ctor, args) if ctor == ctorv => args,
+                    Pattern::Ctor(..) | Pattern::Lit(_) => continue,
+                    Pattern::Any(var) => {
+                        if let Some(var) = var {
+                            binds.push((var, occ.clone()));
+                        }
+                        vec![Pattern::Any(None); arity]
+                    }
+                };
+                let pats = args.into_iter().chain(pats).collect();
+                branch.push(Row { pats, binds, body: row.body.clone() });
+            }
+            // an argument every row names alike is bound to that name directly
+            let vars: Vec<TermV> = (0..arity)
+                .map(|i| {
+                    let mut names = branch.iter().map(|row| match &row.pats[i] {
+                        Pattern::Any(Some(var)) => Some(var.clone()),
+                        _ => None,
+                    });
+                    let name = names.next().flatten();
+                    match name {
+                        Some(name) if names.all(|other| other.as_ref() == Some(&name)) => name,
+                        _ => self.fresh(),
+                    }
+                })
+                .collect();
+            let args = vars.iter().map(|var| rc!(self.info.make(var.clone().into())));
+            let occs =
+                args.chain(occs[..col].iter().cloned()).chain(occs[col + 1..].iter().cloned());
+            let tys = arg_tys
+                .into_iter()
+                .chain(tys[..col].iter().cloned())
+                .chain(tys[col + 1..].iter().cloned());
+            let body = self.compile(occs.collect(), tys.collect(), branch)?;
+            arms.push(Matcher { ctorv, vars, body });
+        }
+        Ok(rc!(self.info.make(Match { scrut: occ, arms }.into())))
+    }
+
+    /// Compiles the matrix of `rows` on the literals in column `col`, with the
+    /// rows matching anything there as the default.
+    fn compile_literals(
+        &mut self, occs: Vec<RcValue>, tys: Vec<Type>, rows: Vec<Row>, col: usize,
+    ) -> Result<RcComp, TyckError> {
+        let occ = occs[col].clone();
+        let occs: Vec<_> = occs[..col].iter().chain(&occs[col + 1..]).cloned().collect();
+        let tys: Vec<_> = tys[..col].iter().chain(&tys[col + 1..]).cloned().collect();
+        // the rows left once the column matched `lit`, or anything else if
+        // there's no literal
+        let branch = |lit: Option<&Literal>| {
+            let mut branch = Vec::new();
+            for row in &rows {
+                let mut pats = row.pats.clone();
+                let mut binds = row.binds.clone();
+                match pats.remove(col) {
+                    Pattern::Lit(other) if Some(&other) == lit => {}
+                    Pattern::Lit(_) | Pattern::Ctor(..) => continue,
+                    Pattern::Any(var) => {
+                        if let Some(var) = var {
+                            binds.push((var, occ.clone()));
+                        }
+                    }
+                }
+                branch.push(Row { pats, binds, body: row.body.clone() });
+            }
+            branch
+        };
+        let mut lits: Vec<Literal> = Vec::new();
+        for row in &rows {
+            if let Pattern::Lit(lit) = &row.pats[col] {
+                if !lits.contains(lit) {
+                    lits.push(lit.clone());
+                }
+            }
+        }
+        let mut arms = Vec::new();
+        for lit in lits {
+            let body = self.compile(occs.clone(), tys.clone(), branch(Some(&lit)))?;
+            arms.push((lit, body));
+        }
+        let default = self.compile(occs, tys, branch(None))?;
+        Ok(rc!(self.info.make(MatchLit { scrut: occ, arms, default }.into())))
+    }
+}
@@ -95,6 +95,30 @@ impl Ctx {
             })?;
         Ok((codata, args))
     }
+    /// Runs a computation of the given type: checks that the effects it
+    /// performs are allowed here, and strips them off.
+    pub(super) fn perform(&self, mut typ: Type, span: &SpanInfo) -> Result<Type, TyckError> {
+        typ = self.resolve_alias(typ, span)?;
+        while let SynType::Effectful(Effectful { effs, ty }) = typ.resolve()? {
+            for effect in effs {
+                bool_test(self.effects.contains(&effect), || {
+                    self.err(span, UnhandledEffect { effect: effect.clone() })
+                })?;
+            }
+            typ = self.resolve_alias(ty.inner_clone(), span)?;
+        }
+        Ok(typ)
+    }
+    /// Annotates a computation type with the effects allowed here, for a
+    /// computation delayed in a thunk.
+    pub(super) fn latent(&self, typ: Type, span: &SpanInfo) -> Type {
+        if self.effects.is_empty() {
+            return typ;
+        }
+        let mut effs: Vec<_> = self.effects.iter().cloned().collect();
+        effs.sort_by(|a, b| a.name().cmp(b.name()));
+        Effectful { effs, ty: rc!(span.make(typ)) }.into()
+    }
     pub(super) fn resolve_alias(&self, mut typ: Type, span: &SpanInfo) -> Result<Type, TyckError> {
         while let SynType::TypeApp(TypeApp { ref tvar, ref args }) = typ.resolve()? {
             if let Some(Alias { name, params, ty }) = self.alias_env.get(tvar) {
@@ -146,8 +170,14 @@ impl TypeCheck for Span<Type> {
                     ))?
                 };
                 let (params, kd) = match kd {
+                    // a type constructor on its own, passed to a higher-kinded parameter
+                    Kind::TypeArity(_) if app.args.is_empty() => return Ok(Step::Done(kd.clone())),
                     Kind::TypeArity(TypeArity { params, kd }) => (params.clone(), kd.inner_clone()),
                     Kind::Base(kd) => (vec![], kd.clone().into()),
+                    Kind::Hole(_) => Err(ctx.err(
+                        span,
+                        NeedAnnotation { content: format!("the kind of `{}`", tvar) },
+                    ))?,
                 };
                 bool_test(app.args.len() == params.len(), || {
                     ctx.err(
@@ -174,6 +204,15 @@ impl TypeCheck for Span<Type> {
                 ty.ana(KindBase::VType.into(), ctx)?;
                 Ok(Step::Done(KindBase::VType.into()))
             }
+            SynType::Effectful(Effectful { effs, ty }) => {
+                for eff in effs {
+                    if !ctx.effect_env.contains_key(&eff) {
+                        Err(ctx.err(span, NameResolveError::UnboundEffect { eff }.into()))?
+                    }
+                }
+                ty.ana(KindBase::CType.into(), ctx)?;
+                Ok(Step::Done(KindBase::CType.into()))
+            }
             SynType::AbstVar(AbstVar(abs)) => Ok(Step::Done(ctx.abst_ctx[abs].clone())),
             SynType::Hole(_) => Err(ctx.err(span, NeedAnnotation { content: format!("hole") }))?,
         }
@@ -193,7 +232,11 @@ impl TypeCheck for Span<Type> {
         let ty_syn = ty.resolve()?;
         match ty_syn {
             SynType::Hole(_) => Ok(Step::Done(kd)),
-            SynType::TypeApp(_) | SynType::Forall(_) | SynType::Exists(_) | SynType::AbstVar(_) => {
+            SynType::TypeApp(_)
+            | SynType::Forall(_)
+            | SynType::Exists(_)
+            | SynType::Effectful(_)
+            | SynType::AbstVar(_) => {
                 let kd_syn = self.syn(ctx.clone())?;
                 let kd = kd_syn.lub(kd, ctx, span)?;
                 Ok(Step::Done(kd))
@@ -209,17 +252,25 @@ impl Type {
         match typ_syn {
             SynType::TypeApp(TypeApp { tvar, mut args }) => {
                 if let Some(ty) = diff.get(&tvar) {
-                    bool_test(args.is_empty(), || {
-                        ctx.err(
+                    if args.is_empty() {
+                        return Ok(ty.clone());
+                    }
+                    // a higher-kinded type variable is applied to the arguments
+                    // by appending them to the type constructor it stands for
+                    let SynType::TypeApp(TypeApp { tvar: tvar_, args: mut args_ }) = ty.resolve()? else {
+                        Err(ctx.err(
                             tvar.span(),
                             ArityMismatch {
                                 context: format!("type variable `{}`", tvar),
                                 expected: 0,
                                 found: args.len(),
                             },
-                        )
-                    })?;
-                    Ok(ty.clone())
+                        ))?
+                    };
+                    for arg in args {
+                        args_.push(arg.try_map_rc(|ty| ty.clone().subst(diff.clone(), ctx))?);
+                    }
+                    Ok(Type { synty: TypeApp { tvar: tvar_, args: args_ }.into() })
                 } else {
                     for arg in args.iter_mut() {
                         *arg = arg.try_map_rc(|ty| ty.clone().subst(diff.clone(), ctx))?;
@@ -247,6 +298,10 @@ impl Type {
                     .into(),
                 })
             }
+            SynType::Effectful(Effectful { effs, ty }) => Ok(Type {
+                synty: Effectful { effs, ty: ty.try_map_rc(|ty| ty.clone().subst(diff, ctx))? }
+                    .into(),
+            }),
             SynType::AbstVar(_) | SynType::Hole(_) => Ok(typ),
         }
     }
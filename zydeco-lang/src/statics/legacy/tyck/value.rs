@@ -1,4 +1,4 @@
-use super::*;
+use super::{meta::*, *};
 
 impl TypeCheck for Span<&Literal> {
     type Ctx = ();
@@ -27,18 +27,47 @@ impl TypeCheck for Span<TermValue> {
         let span = self.span();
         Ok(match self.inner_ref() {
             TermValue::Annotation(Annotation { term, ty }) => {
+                let ty = ctx.infer_kinds(ty, &KindBase::VType.into())?;
                 ty.ana(KindBase::VType.into(), ctx.clone())?;
                 Step::AnaMode((ctx, term), ty.inner_clone())
             }
             TermValue::Var(x) => Step::Done(
                 ctx.term_ctx.get(x).cloned().ok_or(ctx.err(span, UnboundVar { var: x.clone() }))?,
             ),
-            TermValue::Thunk(_) => {
-                Err(ctx.err(span, NeedAnnotation { content: format!("thunk") }))?
+            TermValue::Thunk(Thunk(c)) => {
+                let mut ctx_comp = ctx.clone();
+                ctx_comp.effects.clear();
+                let ty = c.syn(ctx_comp)?;
+                bool_test(!ty.has_hole(), || {
+                    ctx.err(span, NeedAnnotation { content: format!("thunk") })
+                })?;
+                Step::Done(Type::make_thunk(rc!(span.make(ty))))
+            }
+            TermValue::Ctor(Ctor { ctorv: ctor, args: _ }) => {
+                // a constructor declared by a single data type determines it,
+                // and the arguments its type arguments
+                let mut datas = (ctx.data_env.iter()).filter(|(_, Data { ctors, .. })| {
+                    ctors.iter().any(|DataBr { ctorv, .. }| ctorv == ctor)
+                });
+                let (Some((name, Data { params, .. })), None) = (datas.next(), datas.next()) else {
+                    Err(ctx.err(span, NeedAnnotation { content: format!("ctor") }))?
+                };
+                let args = (params.iter())
+                    .map(|(param, _)| rc!(param.span().make(Hole.into())))
+                    .collect();
+                let typ = Type::from(TypeApp { tvar: name.clone(), args });
+                let ty = self.ana(typ, ctx.clone())?;
+                bool_test(!ty.has_hole(), || {
+                    ctx.err(span, NeedAnnotation { content: format!("ctor") })
+                })?;
+                Step::Done(ty)
             }
-            TermValue::Ctor(_) => Err(ctx.err(span, NeedAnnotation { content: format!("ctor") }))?,
             TermValue::Literal(l) => Step::Done(span.make(l).syn(())?),
             TermValue::Pack(_) => Err(ctx.err(span, NeedAnnotation { content: format!("pack") }))?,
+            TermValue::Hole(hole) => {
+                ctx.goal(hole, Hole.into())?;
+                Step::Done(Hole.into())
+            }
         })
     }
     fn ana_step(
@@ -59,6 +88,7 @@ impl TypeCheck for Span<TermValue> {
         span.make(typ.clone()).ana(KindBase::VType.into(), ctx.clone())?;
         Ok(match self.inner_ref() {
             TermValue::Annotation(Annotation { term, ty }) => {
+                let ty = ctx.infer_kinds(ty, &KindBase::VType.into())?;
                 let ty_lub = Type::lub(ty.inner_clone(), typ, ctx.clone(), span)?;
                 Step::AnaMode((ctx, term), ty_lub)
             }
@@ -73,13 +103,28 @@ impl TypeCheck for Span<TermValue> {
                         },
                     )
                 })?;
-                let ty = Type::make_thunk(rc!(span.make(c.ana(typ_comp, ctx.clone())?)));
+                // a thunk performs no effects until forced, and then only those
+                // its type declares
+                let mut ctx_comp = ctx.clone();
+                ctx_comp.effects.clear();
+                let ty = Type::make_thunk(rc!(span.make(c.ana(typ_comp, ctx_comp)?)));
                 let typ_lub = Type::lub(ty, typ, ctx.clone(), span)?;
                 Step::Done(typ_lub)
             }
             TermValue::Ctor(Ctor { ctorv: ctor, args }) => {
                 let (Data { name, params, ctors }, ty_args) =
                     ctx.resolve_data(typ.clone(), span)?;
+                // type arguments left as holes are determined by the arguments
+                let mut metas = Metas::default();
+                let ty_args: Vec<_> = (ty_args.into_iter())
+                    .zip(params.iter())
+                    .map(|(arg, (param, kd))| match arg.inner_ref().synty {
+                        SynType::Hole(_) => {
+                            rc!(arg.span().make(metas.fresh(param.clone(), kd.clone())))
+                        }
+                        _ => arg,
+                    })
+                    .collect();
                 let diff = Env::init(&params, &ty_args, || {
                     ctx.err(
                         span,
@@ -113,9 +158,12 @@ impl TypeCheck for Span<TermValue> {
                     )
                 })?;
                 for (arg, ty) in args.iter().zip(tys.iter()) {
-                    arg.ana(ty.inner_clone().subst(diff.clone(), &ctx)?, ctx.clone())?;
+                    let ty = ty.inner_clone().subst(diff.clone(), &ctx)?;
+                    metas.check_arg(arg, ty, &ctx, span)?;
                 }
-                Step::Done(typ)
+                let ty = Type::from(TypeApp { tvar: name, args: ty_args })
+                    .subst(metas.expected(), &ctx)?;
+                Step::Done(Type::lub(typ, ty, ctx.clone(), span)?)
             }
             TermValue::Pack(Pack { ty, body }) => {
                 let SynType::Exists(
@@ -134,6 +182,10 @@ impl TypeCheck for Span<TermValue> {
                 body.ana(ty_body, ctx)?;
                 Step::Done(typ)
             }
+            TermValue::Hole(hole) => {
+                ctx.goal(hole, typ.clone())?;
+                Step::Done(typ)
+            }
             TermValue::Var(_) | TermValue::Literal(_) => {
                 // subsumption
                 let typ_syn = self.syn(ctx.clone())?;
@@ -7,6 +7,7 @@ pub use env::*;
 mod fmt;
 mod span;
 
+use crate::utils::span::Span;
 use zydeco_derive::{IntoEnum, SpanHolder};
 
 /* ---------------------------------- Sort ---------------------------------- */
@@ -54,6 +55,11 @@ pub struct Annotation<Term, Type> {
 #[derive(SpanHolder, Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Hole;
 
+/// A term left to be written, `?name` or `_`. It checks against any type, and
+/// stops the program once reached.
+#[derive(SpanHolder, Clone, Debug)]
+pub struct TermHole(pub Span<String>);
+
 /* ---------------------------------- Meta ---------------------------------- */
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -107,6 +113,19 @@ pub struct CodataBr<D: DtorT, Ty: TypeT> {
     pub ty: Ty,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Effect<TyV: TyVarT, TeV: VarT, Ty: TypeT> {
+    pub name: TyV,
+    pub ops: Vec<EffectOp<TeV, Ty>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EffectOp<TeV: VarT, Ty: TypeT> {
+    pub name: TeV,
+    pub tys: Vec<Ty>,
+    pub ty: Ty,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Alias<TyV: TyVarT, Kd: KindT, Ty: TypeT> {
     pub name: TyV,
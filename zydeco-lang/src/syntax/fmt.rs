@@ -46,6 +46,12 @@ impl FmtArgs for Hole {
     }
 }
 
+impl FmtArgs for TermHole {
+    fn fmt_args(&self, _fargs: Args) -> String {
+        self.0.inner.clone()
+    }
+}
+
 impl<In, Out> FmtArgs for Arrow<In, Out>
 where
     In: FmtArgs,
@@ -131,6 +137,18 @@ where
     }
 }
 
+impl<Eff, Ty> FmtArgs for Effectful<Eff, Ty>
+where
+    Eff: FmtArgs,
+    Ty: TypeT + FmtArgs,
+{
+    fn fmt_args(&self, fargs: Args) -> String {
+        let Effectful { effs, ty } = self;
+        let effs = effs.into_iter().map(|eff| eff.fmt_args(fargs)).collect::<Vec<_>>();
+        format!("{{{}}} {}", effs.join(", "), ty.fmt_args(fargs))
+    }
+}
+
 impl<B> FmtArgs for Thunk<B>
 where
     B: ComputationT + FmtArgs,
@@ -297,6 +315,34 @@ where
     }
 }
 
+impl<A, B> FmtArgs for MatchLit<A, B>
+where
+    A: ValueT + FmtArgs,
+    B: ComputationT + FmtArgs,
+{
+    fn fmt_args(&self, fargs: Args) -> String {
+        let MatchLit { scrut, arms, default } = self;
+        let mut s = String::new();
+        s += "match ";
+        s += &scrut.fmt_args(fargs);
+        let arms = arms.iter().map(|(lit, body)| (lit.fmt_args(fargs), body));
+        for (pat, body) in arms.chain([(format!("_"), default)]) {
+            s += &fargs.br_indent();
+            s += "| ";
+            s += &pat;
+            s += " -> ";
+            {
+                let fargs = fargs.indent();
+                s += &fargs.br_indent();
+                s += &body.fmt_args(fargs);
+            }
+        }
+        s += &fargs.br_indent();
+        s += "end";
+        s
+    }
+}
+
 impl<D, TeV, B> FmtArgs for Comatch<D, TeV, B>
 where
     D: DtorT + FmtArgs,
@@ -364,6 +410,67 @@ where
     }
 }
 
+impl<Op, TeV, B> FmtArgs for Handle<Op, TeV, B>
+where
+    Op: FmtArgs,
+    TeV: VarT + FmtArgs,
+    B: ComputationT + FmtArgs,
+{
+    fn fmt_args(&self, fargs: Args) -> String {
+        let Handle { body, ret, arms } = self;
+        let mut s = String::new();
+        s += "handle ";
+        s += &body.fmt_args(fargs);
+        s += " with";
+        if let Some((var, body)) = ret {
+            s += &fargs.br_indent();
+            s += "| ret ";
+            s += &var.fmt_args(fargs);
+            s += " -> ";
+            {
+                let fargs = fargs.indent();
+                s += &fargs.br_indent();
+                s += &body.fmt_args(fargs);
+            }
+        }
+        for Handler { op, vars, kont, body } in arms {
+            s += &fargs.br_indent();
+            s += "| ";
+            s += &op.fmt_args(fargs);
+            s += "(";
+            s += &vars.into_iter().map(|var| var.fmt_args(fargs)).collect::<Vec<_>>().join(", ");
+            s += ") ";
+            s += &kont.fmt_args(fargs);
+            s += " -> ";
+            {
+                let fargs = fargs.indent();
+                s += &fargs.br_indent();
+                s += &body.fmt_args(fargs);
+            }
+        }
+        s += &fargs.br_indent();
+        s += "end";
+        s
+    }
+}
+
+impl<Op, A> FmtArgs for Perform<Op, A>
+where
+    Op: FmtArgs,
+    A: ValueT + FmtArgs,
+{
+    fn fmt_args(&self, fargs: Args) -> String {
+        let Perform { op, args } = self;
+        let mut s = String::new();
+        s += "perform ";
+        s += &op.fmt_args(fargs);
+        s += "(";
+        s += &args.into_iter().map(|arg| arg.fmt_args(fargs)).collect::<Vec<_>>().join(", ");
+        s += ")";
+        s
+    }
+}
+
 impl<T> FmtArgs for DeclSymbol<T>
 where
     T: FmtArgs,
@@ -480,6 +587,49 @@ where
     }
 }
 
+impl<TyV, TeV, Ty> FmtArgs for Effect<TyV, TeV, Ty>
+where
+    TyV: TyVarT + FmtArgs,
+    TeV: VarT + FmtArgs,
+    Ty: TypeT + FmtArgs,
+{
+    fn fmt_args(&self, fargs: Args) -> String {
+        let Effect { name, ops } = self;
+        let mut s = String::new();
+        s += "effect ";
+        s += &name.fmt_args(fargs);
+        s += " where ";
+        {
+            let fargs = fargs.indent();
+            for op in ops {
+                s += &fargs.br_indent();
+                s += &op.fmt_args(fargs);
+            }
+        }
+        s += &fargs.br_indent();
+        s += "end";
+        s
+    }
+}
+
+impl<TeV, Ty> FmtArgs for EffectOp<TeV, Ty>
+where
+    TeV: VarT + FmtArgs,
+    Ty: TypeT + FmtArgs,
+{
+    fn fmt_args(&self, fargs: Args) -> String {
+        let EffectOp { name, tys, ty } = self;
+        let mut s = String::new();
+        s += "| ";
+        s += &name.fmt_args(fargs);
+        s += "(";
+        s += &tys.into_iter().map(|ty| ty.fmt_args(fargs)).collect::<Vec<_>>().join(", ");
+        s += ") : ";
+        s += &ty.fmt_args(fargs);
+        s
+    }
+}
+
 impl<TyV, Kd, Ty> FmtArgs for Alias<TyV, Kd, Ty>
 where
     TyV: TyVarT + FmtArgs,
@@ -17,6 +17,12 @@ impl SpanHolder for String {
     }
 }
 
+impl SpanView for TermHole {
+    fn span(&self) -> &SpanInfo {
+        self.0.span()
+    }
+}
+
 impl<In, Out> SpanHolder for Arrow<In, Out>
 where
     In: SpanHolder,
@@ -92,6 +98,21 @@ where
     }
 }
 
+impl<Eff, Ty> SpanHolder for Effectful<Eff, Ty>
+where
+    Eff: SpanHolder,
+    Ty: TypeT + SpanHolder,
+{
+    fn span_map_mut<F>(&mut self, f: F)
+    where
+        F: Fn(&mut SpanInfo) + Clone,
+    {
+        let Effectful { effs, ty } = self;
+        effs.span_map_mut(f.clone());
+        ty.span_map_mut(f);
+    }
+}
+
 impl<B> SpanHolder for Thunk<B>
 where
     B: ComputationT + SpanHolder,
@@ -294,6 +315,28 @@ where
     }
 }
 
+impl<Op, TeV, B> SpanHolder for Handle<Op, TeV, B>
+where
+    Op: SpanHolder,
+    TeV: VarT + SpanHolder,
+    B: ComputationT + SpanHolder,
+{
+    fn span_map_mut<F>(&mut self, f: F)
+    where
+        F: Fn(&mut SpanInfo) + Clone,
+    {
+        let Handle { body, ret, arms } = self;
+        body.span_map_mut(f.clone());
+        ret.span_map_mut(f.clone());
+        for Handler { op, vars, kont, body } in arms {
+            op.span_map_mut(f.clone());
+            vars.span_map_mut(f.clone());
+            kont.span_map_mut(f.clone());
+            body.span_map_mut(f.clone());
+        }
+    }
+}
+
 impl<TyV, Kd, C, T> SpanHolder for Data<TyV, Kd, C, T>
 where
     TyV: TyVarT + SpanHolder,
@@ -337,6 +380,26 @@ where
     }
 }
 
+impl<TyV, TeV, T> SpanHolder for Effect<TyV, TeV, T>
+where
+    TyV: TyVarT + SpanHolder,
+    TeV: VarT + SpanHolder,
+    T: TypeT + SpanHolder,
+{
+    fn span_map_mut<F>(&mut self, f: F)
+    where
+        F: Fn(&mut SpanInfo) + Clone,
+    {
+        let Effect { name, ops } = self;
+        name.span_map_mut(f.clone());
+        for EffectOp { name, tys, ty } in ops {
+            name.span_map_mut(f.clone());
+            tys.span_map_mut(f.clone());
+            ty.span_map_mut(f.clone());
+        }
+    }
+}
+
 impl<TyV, Kd, Ty> SpanHolder for Alias<TyV, Kd, Ty>
 where
     TyV: TyVarT + SpanHolder,
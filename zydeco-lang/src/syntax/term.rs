@@ -35,12 +35,19 @@ pub struct Exists<TyV: TyVarT, Ty: TypeT> {
     pub ty: Ty,
 }
 
+/// A computation type annotated with the effects it may perform when run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Effectful<Eff, Ty: TypeT> {
+    pub effs: Vec<Eff>,
+    pub ty: Ty,
+}
+
 /* --------------------------------- Values --------------------------------- */
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Thunk<B: ComputationT>(pub B);
 
-#[derive(IntoEnum, Clone, Debug, PartialEq, Eq)]
+#[derive(IntoEnum, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Literal {
     Int(i64),
     String(String),
@@ -100,6 +107,15 @@ pub struct Matcher<C: CtorT, TeV: VarT, B: ComputationT> {
     pub body: B,
 }
 
+/// A match on a literal, which takes the default arm when no arm's literal is
+/// the value matched.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MatchLit<A: ValueT, B: ComputationT> {
+    pub scrut: A,
+    pub arms: Vec<(Literal, B)>,
+    pub default: B,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Comatch<D: DtorT, TeV: VarT, B: ComputationT> {
     pub arms: Vec<Comatcher<D, TeV, B>>,
@@ -125,3 +141,24 @@ pub struct MatchPack<A: ValueT, TyV: TyVarT, TeV: VarT, B: ComputationT> {
     pub var: TeV,
     pub body: B,
 }
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Handle<Op, TeV: VarT, B: ComputationT> {
+    pub body: B,
+    /// The return clause; returning the value unchanged if absent.
+    pub ret: Option<(TeV, B)>,
+    pub arms: Vec<Handler<Op, TeV, B>>,
+}
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Handler<Op, TeV: VarT, B: ComputationT> {
+    pub op: Op,
+    pub vars: Vec<TeV>,
+    pub kont: TeV,
+    pub body: B,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Perform<Op, A: ValueT> {
+    pub op: Op,
+    pub args: Vec<A>,
+}
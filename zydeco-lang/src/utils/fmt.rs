@@ -37,10 +37,9 @@ pub trait FmtArgs {
     }
     fn fmt_truncate(&self, n: usize) -> String {
         let s = self.fmt_no_br();
-        if s.len() > n {
-            format!("{}...", &s[..n].trim())
-        } else {
-            s
+        match s.char_indices().nth(n) {
+            Some((i, _)) => format!("{}...", s[..i].trim()),
+            None => s,
         }
     }
 }
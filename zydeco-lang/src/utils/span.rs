@@ -68,6 +68,10 @@ impl SpanInfo {
     pub fn is_dummy(&self) -> bool {
         self.span1 == (0, 0) && self.span2.get().is_none() && self.path.get().is_none()
     }
+    /// The offset in the source the span starts at.
+    pub fn start(&self) -> usize {
+        self.span1.0
+    }
     pub fn make<T>(&self, inner: T) -> Span<T> {
         Span { inner, info: self.clone() }
     }
@@ -1,6 +1,18 @@
 use crate::{
-    dynamics::{eval::Eval, syntax as ds},
-    library::syntax as ls,
+    codegen::{
+        c::{self, CEmitter},
+        wasm::WasmEmitter,
+    },
+    dynamics::{
+        eval::Eval,
+        host::{Host, MockHost, OsHost},
+        syntax as ds,
+    },
+    library::{
+        builtins::Registry,
+        convert::{FromZydeco, ZydecoArgs, ZydecoType},
+        syntax as ls,
+    },
     parse::{
         err::ParseError,
         lexer::Lexer,
@@ -8,12 +20,20 @@ use crate::{
         syntax as ps,
     },
     prelude::*,
-    statics::{syntax as ss, Ctx, Elaboration, Seal, TypeCheck},
-    syntax::Env,
-    utils::span::FileInfo,
+    rc,
+    statics::{err::Goal, legacy::tyck::Lub, syntax as ss, Ctx, Elaboration, Seal, TypeCheck},
+    syntax::{DeclSymbol, Env},
+    utils::{
+        fmt::FmtArgs,
+        span::{FileInfo, SpanInfo, SpanView},
+    },
 };
 pub use ds::ProgKont;
-use std::{path::PathBuf, rc::Rc};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
 pub struct Zydeco;
 
@@ -57,45 +77,83 @@ impl ZydecoFile {
         let p = Elaboration::elab(p).map_err(|e| format!("{}", e))?;
         Ok(p)
     }
-    pub fn tyck(m: Span<ss::Program>) -> Result<(), String> {
-        m.syn(Ctx::default()).map_err(|e| format!("{}", e))?;
-        Ok(())
+    pub fn tyck(m: Span<ss::Program>) -> Result<Vec<Goal>, String> {
+        Self::tyck_with(m, &Registry::std())
+    }
+    /// Type checks the program, with `extern def` declarations checked against
+    /// the builtins of `registry`, and returns the goals of its holes in the
+    /// order they appear.
+    pub fn tyck_with(m: Span<ss::Program>, registry: &Registry) -> Result<Vec<Goal>, String> {
+        let ctx = Ctx::new(registry);
+        m.syn(ctx.clone()).map_err(|e| format!("{}", e))?;
+        let mut goals = ctx.goals.take();
+        goals.sort_by_key(|goal| goal.hole.span().start());
+        Ok(goals)
     }
     pub fn link(m: ss::Program) -> Result<ls::Program, String> {
-        let m: ls::Program = m.into();
-        Ok(m)
+        Self::link_with(m, &Registry::std())
+    }
+    /// Links the program against the builtins of `registry`, which every
+    /// `extern def` must find its implementation in.
+    pub fn link_with(m: ss::Program, registry: &Registry) -> Result<ls::Program, String> {
+        ls::Program::link(m, registry).map_err(|e| format!("{}", e))
     }
     pub fn eval_os(p: ls::Program, args: &[String]) -> ds::Program {
-        let mut input = std::io::stdin().lock();
-        let mut output = std::io::stdout();
-        Self::eval_virtual_os(p, &mut input, &mut output, args)
-    }
-    pub fn eval_virtual_os(
-        p: ls::Program, r: &mut dyn std::io::BufRead, w: &mut dyn std::io::Write, args: &[String],
-    ) -> ds::Program {
-        let mut runtime = ds::Runtime::new(r, w, args);
+        Self::eval_host(p, &mut OsHost::new(args.to_vec()))
+    }
+    /// Runs the program on `input` and `args` without touching the real OS,
+    /// returning the output along with the result.
+    pub fn eval_virtual_os(p: ls::Program, input: &str, args: &[String]) -> (ds::Program, String) {
+        let mut host = MockHost::new(input, args.to_vec());
+        let m = Self::eval_host(p, &mut host);
+        (m, host.output().to_string())
+    }
+    pub fn eval_host(p: ls::Program, host: &mut dyn Host) -> ds::Program {
+        let mut runtime = ds::Runtime::new(host);
         let m = ls::Program::eval(p, &mut runtime);
         m
     }
+    pub fn emit_c(p: &ls::Program) -> Result<String, String> {
+        CEmitter::new().emit(p).map_err(|e| format!("{}", e))
+    }
+    /// Compiles the program into the executable `exe`, keeping the generated
+    /// C source next to it.
+    pub fn build_c(p: &ls::Program, exe: &Path) -> Result<PathBuf, String> {
+        let src = Self::emit_c(p)?;
+        let c_path = exe.with_extension("c");
+        std::fs::write(&c_path, src).map_err(|e| format!("{}", e))?;
+        c::compile(&c_path, exe).map_err(|e| format!("{}", e))?;
+        Ok(c_path)
+    }
+    pub fn emit_wasm(p: &ls::Program) -> Result<Vec<u8>, String> {
+        WasmEmitter::new().emit(p).map_err(|e| format!("{}", e))
+    }
+    /// Compiles the program into the WASI command module `out`.
+    pub fn build_wasm(p: &ls::Program, out: &Path) -> Result<(), String> {
+        let module = Self::emit_wasm(p)?;
+        std::fs::write(out, module).map_err(|e| format!("{}", e))
+    }
 }
 
 #[derive(Clone)]
 pub struct ZydecoExpr {
     pub ctx: Ctx,
     pub env: Env<ls::TermV, ds::SemVal>,
+    /// The cells and arrays the values in `env` may refer to.
+    pub heap: ds::Heap,
 }
 
 impl ZydecoExpr {
     pub fn new() -> Self {
         let std = Zydeco::std().unwrap();
         let std: Span<ss::Module> = Elaboration::elab(std).unwrap();
-        let Seal(ctx) = std.syn(Ctx::default()).expect("std import failed");
-        let std: ls::Module = std.inner.into();
-        let mut input = std::io::empty();
-        let mut output = std::io::sink();
-        let mut runtime = ds::Runtime::new(&mut input, &mut output, &[]);
+        let registry = Registry::std();
+        let Seal(ctx) = std.syn(Ctx::new(&registry)).expect("std import failed");
+        let std = ls::Module::link(std.inner, &registry).expect("std link failed");
+        let mut host = MockHost::default();
+        let mut runtime = ds::Runtime::new(&mut host);
         std.eval(&mut runtime);
-        Self { ctx, env: runtime.env }
+        Self { ctx, env: runtime.env, heap: runtime.heap }
     }
     pub fn parse(source: &str) -> Result<Span<ps::Term>, String> {
         TermSpanParser::new().parse(source, Lexer::new(source)).map_err(|e| e.to_string())
@@ -107,6 +165,12 @@ impl ZydecoExpr {
     pub fn tyck(&self, t: Span<ss::Term>) -> Result<ss::Type, String> {
         t.syn_term(self.ctx.clone()).map_err(|e| format!("{}", e))
     }
+    /// Takes the goals of the holes met since the last call.
+    pub fn goals(&self) -> Vec<Goal> {
+        let mut goals = self.ctx.goals.take();
+        goals.sort_by_key(|goal| goal.hole.span().start());
+        goals
+    }
     pub fn link_value(val: &ss::TermValue) -> ls::SynVal {
         val.into()
     }
@@ -114,32 +178,177 @@ impl ZydecoExpr {
         comp.into()
     }
     pub fn eval_value(&mut self, val: ls::SynVal) -> ds::SemVal {
-        let mut input = std::io::empty();
-        let mut output = std::io::sink();
-        let mut runtime = ds::Runtime::new(&mut input, &mut output, &[]);
+        let mut host = MockHost::default();
+        let mut runtime = ds::Runtime::new(&mut host);
         runtime.env = self.env.clone();
-        val.eval(&mut runtime)
+        runtime.heap = self.heap.clone();
+        let val = val.eval(&mut runtime);
+        self.heap = runtime.heap;
+        val
     }
     pub fn eval_ret_computation(&mut self, comp: ls::SynComp) -> ds::ProgKont {
-        let mut input = std::io::empty();
-        let mut output = std::io::sink();
-        let mut runtime = ds::Runtime::new(&mut input, &mut output, &[]);
+        let mut host = MockHost::default();
+        let mut runtime = ds::Runtime::new(&mut host);
         runtime.env = self.env.clone();
+        runtime.heap = self.heap.clone();
         let m = ls::Program::eval(
             ls::Program { module: ls::Module::pure(None), entry: comp },
             &mut runtime,
         );
         self.env = runtime.env;
+        self.heap = runtime.heap;
         m.entry
     }
     pub fn eval_os(&mut self, comp: ls::SynComp, args: &[String]) -> ds::Program {
-        let mut input = std::io::stdin().lock();
-        let mut output = std::io::stdout();
+        let mut host = OsHost::new(args.to_vec());
         let p = ls::Program { module: ls::Module::pure(None), entry: comp };
-        let mut runtime = ds::Runtime::new(&mut input, &mut output, args);
+        let mut runtime = ds::Runtime::new(&mut host);
         runtime.env = self.env.clone();
+        runtime.heap = self.heap.clone();
         let m = ls::Program::eval(p, &mut runtime);
         self.env = runtime.env;
+        self.heap = runtime.heap;
         m
     }
 }
+
+/// A loaded and typechecked program whose public definitions can be called
+/// from Rust.
+#[derive(Clone)]
+pub struct Engine {
+    pub ctx: Ctx,
+    pub env: Env<ls::TermV, ds::SemVal>,
+    /// Public definitions by qualified name, e.g. `Std.Identity.id`, with
+    /// their types and values. Each is the one defined at that path, even if a
+    /// later definition of the same name shadows it in `ctx` and `env`.
+    names: HashMap<String, (ss::Type, ds::SemVal)>,
+}
+
+impl Engine {
+    /// Loads the files in `paths` along with std, linked against `registry`.
+    pub fn load(paths: Vec<PathBuf>, registry: &Registry) -> Result<Self, String> {
+        Self::new(ZydecoFile::parse(paths)?, registry)
+    }
+    /// Loads the declarations of `top`, which must not have a `main` entry.
+    pub fn new(top: Span<ps::TopLevel>, registry: &Registry) -> Result<Self, String> {
+        let mut paths = Vec::new();
+        Self::collect_names(&mut paths, "", &top.inner.declarations);
+        // the binder of a definition tells which path it's defined at
+        let path = |var: &ls::TermV| {
+            paths.iter().find(|(binder, _)| binder == var.span()).map(|(_, path)| path.clone())
+        };
+        let module: Span<ss::Module> = Elaboration::elab(top).map_err(|e| format!("{}", e))?;
+        // definitions are checked one at a time, so that each one's type is
+        // known before a later one of the same name shadows it
+        let mut rest = module.clone();
+        let define_ext = std::mem::take(&mut rest.inner.define_ext);
+        let define = std::mem::take(&mut rest.inner.define);
+        let mut fixity = std::mem::take(&mut rest.inner.fixity).into_iter().peekable();
+        let Seal(mut ctx) = rest.syn(Ctx::new(registry)).map_err(|e| format!("{}", e))?;
+        let single = |define_ext, define, fixity| ss::Module {
+            name: None,
+            data: Vec::new(),
+            codata: Vec::new(),
+            alias: Vec::new(),
+            effect: Vec::new(),
+            fixity,
+            define,
+            define_ext,
+        };
+        let mut types = HashMap::new();
+        let singletons = (define_ext.into_iter())
+            .map(|d| (d.inner.name.0.clone(), single(vec![d], Vec::new(), Vec::new())))
+            .chain(define.into_iter().map(|d| {
+                let fixity = fixity
+                    .next_if(|DeclSymbol { inner: fix, .. }| {
+                        ss::Fixity::var(&fix.op) == d.inner.name
+                    })
+                    .into_iter()
+                    .collect();
+                (d.inner.name.clone(), single(Vec::new(), vec![d], fixity))
+            }));
+        for (var, single) in singletons {
+            let Seal(next) = module.span().make(single).syn(ctx).map_err(|e| format!("{}", e))?;
+            ctx = next;
+            if let Some(path) = path(&var) {
+                types.insert(path, ctx.term_ctx.get(&var).cloned().expect("definition checked"));
+            }
+        }
+        let module = ls::Module::link(module.inner, registry).map_err(|e| format!("{}", e))?;
+        let mut host = MockHost::default();
+        let mut runtime = ds::Runtime::new(&mut host);
+        let mut names = HashMap::new();
+        for (var, def) in module.define {
+            let value = def.eval(&mut runtime);
+            if let Some(path) = path(&var) {
+                let ty = types.remove(&path).expect("public definition checked");
+                names.insert(path, (ty, value.clone()));
+            }
+            runtime.env = runtime.env.update(var, value);
+        }
+        Ok(Self { ctx, env: runtime.env, names })
+    }
+    /// Collects the binders of the public definitions in `declarations`,
+    /// along with their qualified names.
+    fn collect_names(
+        paths: &mut Vec<(SpanInfo, String)>, path: &str,
+        declarations: &[DeclSymbol<ps::Declaration>],
+    ) {
+        for DeclSymbol { public, external: _, inner } in declarations {
+            match inner {
+                ps::Declaration::Module(ps::Module { name: Some(name), declarations }) => {
+                    let path = format!("{}{}.", path, name.ident.inner);
+                    Self::collect_names(paths, &path, declarations)
+                }
+                ps::Declaration::Module(ps::Module { name: None, declarations }) => {
+                    Self::collect_names(paths, path, declarations)
+                }
+                ps::Declaration::Define(ps::Define(ps::GenLet { name: (name, _), .. }))
+                    if *public =>
+                {
+                    let binder = name.ident.span().clone();
+                    paths.push((binder, format!("{}{}", path, name.ident.inner)));
+                }
+                _ => {}
+            }
+        }
+    }
+    fn definition(&self, name: &str) -> Result<&(ss::Type, ds::SemVal), String> {
+        self.names.get(name).ok_or_else(|| format!("No public definition {}", name))
+    }
+    /// Looks up the public definition `name`, given by its qualified name.
+    pub fn lookup(&self, name: &str) -> Option<&ds::SemVal> {
+        Some(&self.names.get(name)?.1)
+    }
+    /// Applies the public definition `name` to `args` and converts the value
+    /// it returns. The definition must have type `Thunk(A1 -> ... -> Ret R)`,
+    /// where `A1, ...` and `R` are the Zydeco types of the Rust arguments and
+    /// result. The call talks to the OS through `host`.
+    pub fn call<A: ZydecoArgs, R: FromZydeco + ZydecoType>(
+        &self, name: &str, args: A, host: &mut dyn Host,
+    ) -> Result<R, String> {
+        let (actual, value) = self.definition(name)?;
+        let mut expected = ss::Type::make_ret(rc!(SpanInfo::dummy().make(R::zydeco_type())));
+        for ty in A::zydeco_types().into_iter().rev() {
+            let (ty, rest) = (SpanInfo::dummy().make(ty), SpanInfo::dummy().make(expected));
+            expected = ss::Type::internal("Fn", vec![rc!(ty), rc!(rest)]);
+        }
+        let expected = ss::Type::make_thunk(rc!(SpanInfo::dummy().make(expected)));
+        Lub::lub(expected.clone(), actual.clone(), self.ctx.clone(), &SpanInfo::dummy()).map_err(
+            |_| format!("{} has type {}, expected {}", name, actual.fmt(), expected.fmt()),
+        )?;
+        let mut runtime = ds::Runtime::new(host);
+        runtime.env = self.env.clone();
+        // the first argument goes on top of the stack
+        for arg in args.into_zydeco_args().into_iter().rev() {
+            let dtorv = ls::DtorV::new(format!("arg"), SpanInfo::dummy());
+            runtime.stack.push_back(ds::SemComp::Dtor(dtorv, vec![rc!(arg)]));
+        }
+        let entry: ls::SynComp = ls::Force(rc!(ls::SynVal::SemValue(value.clone()))).into();
+        match entry.eval(&mut runtime) {
+            ProgKont::Ret(value) => R::from_zydeco(&value).map_err(|e| format!("{}", e)),
+            ProgKont::ExitCode(code) => Err(format!("{} exited with code {}", name, code)),
+            ProgKont::Hole(hole) => Err(format!("{} reached {}", name, ProgKont::Hole(hole).fmt())),
+        }
+    }
+}
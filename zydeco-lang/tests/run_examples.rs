@@ -8,10 +8,35 @@
  *   are not executed
  *
  * - io/ holds tests that need custom I/O mocking to execute.
+ *
+ * - panic/ holds tests that must panic at runtime, with exit code 1 and the
+ *   message given by the test. Those the type checker rejects are run
+ *   unchecked, without std.
+ *
+ * The programs under non-zero-exit-code/ and io/ are also compiled to C
+ * and to WebAssembly, and the results checked against the interpreter.
  */
 
-use std::path::PathBuf;
-use zydeco_lang::{dynamics::syntax as ds, prelude::*, statics::syntax as ss, zydeco::ZydecoFile};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+use zydeco_lang::{
+    dynamics::{
+        host::{Host, MockHost, OsHost},
+        replay::{Recorder, Replayer},
+        syntax as ds,
+    },
+    library::{
+        builtins::{Builtin, Registry},
+        convert::{FromZydeco, IntoZydeco, ZydecoType},
+        syntax as ls,
+    },
+    prelude::*,
+    statics::syntax as ss,
+    zydeco::{Engine, ZydecoFile},
+};
 
 fn wrapper<T>(r: Result<T, String>) {
     match r {
@@ -21,6 +46,12 @@ fn wrapper<T>(r: Result<T, String>) {
 }
 
 fn till_check(base: &str, f: &[&str]) -> Result<Span<ss::Program>, String> {
+    till_check_with(base, f, &Registry::std())
+}
+
+fn till_check_with(
+    base: &str, f: &[&str], registry: &Registry,
+) -> Result<Span<ss::Program>, String> {
     let path = PathBuf::from(base);
     let paths = f
         .iter()
@@ -32,7 +63,7 @@ fn till_check(base: &str, f: &[&str]) -> Result<Span<ss::Program>, String> {
         .collect();
     let m = ZydecoFile::parse(paths)?;
     let m = ZydecoFile::elab(m)?;
-    ZydecoFile::tyck(m.clone())?;
+    ZydecoFile::tyck_with(m.clone(), registry)?;
 
     Ok(m)
 }
@@ -43,10 +74,8 @@ fn test_template(parent: &'static str, run: bool, f: &[&str]) -> Result<(), Stri
     if run {
         let m = ZydecoFile::link(m.inner)?;
 
-        let mut input = std::io::empty();
-        let mut output = std::io::sink();
-        let ds::ProgKont::ExitCode(exit_code) =
-        ZydecoFile::eval_virtual_os(m, &mut input, &mut output, &[]).entry else {
+        let ds::ProgKont::ExitCode(exit_code) = ZydecoFile::eval_virtual_os(m, "", &[]).0.entry
+        else {
             Err("Expected ExitCode".to_string())?
         };
         if exit_code != 0 {
@@ -70,6 +99,7 @@ fn doc_test(f: &[&str], run: bool) -> Result<(), String> {
     test_template("../docs/spell", run, f)
 }
 
+#[derive(Default)]
 struct IOMatch {
     args: Vec<String>,
     input: String,
@@ -80,24 +110,209 @@ fn io_test(f: &[&str], iomatch: &IOMatch) -> Result<(), String> {
     let m = till_check("tests/io", f)?;
     let m = ZydecoFile::link(m.inner)?;
 
-    let mut input = std::io::Cursor::new(iomatch.input.as_str());
-    let mut output: Vec<u8> = Vec::new();
-    let args = iomatch.args.as_slice();
-
-    let ds::ProgKont::ExitCode(exit_code) =
-        ZydecoFile::eval_virtual_os(m, &mut input, &mut output, args).entry else {
-            Err("Expected ExitCode".to_string())?
-        };
+    let (m, output) = ZydecoFile::eval_virtual_os(m, &iomatch.input, &iomatch.args);
+    let ds::ProgKont::ExitCode(exit_code) = m.entry else { Err("Expected ExitCode".to_string())? };
     if exit_code != 0 {
         Err(format!("Non-zero exit code: {}", exit_code))?
     }
 
-    let s = std::str::from_utf8(&output).unwrap();
+    let s = output.as_str();
     assert_eq!(iomatch.correct_answer, s, "Output is not correct.");
 
     Ok(())
 }
 
+/// Runs the linked program in the interpreter, returning its exit code and output.
+fn interpret(m: &ls::Program, iomatch: &IOMatch) -> Result<(i32, String), String> {
+    let (p, output) = ZydecoFile::eval_virtual_os(m.clone(), &iomatch.input, &iomatch.args);
+    let ds::ProgKont::ExitCode(exit_code) = p.entry else { Err("Expected ExitCode".to_string())? };
+    Ok((exit_code, output))
+}
+
+/// Runs a compiled program with the test's input and arguments, requiring the
+/// same exit code and output as the interpreter.
+fn run_compiled(
+    cmd: &mut Command, iomatch: &IOMatch, expected: (i32, String),
+) -> Result<(), String> {
+    let mut child = cmd
+        .args(&iomatch.args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    child.stdin.take().unwrap().write_all(iomatch.input.as_bytes()).map_err(|e| e.to_string())?;
+    let compiled = child.wait_with_output().map_err(|e| e.to_string())?;
+
+    let (exit_code, output) = expected;
+    assert_eq!(Some(exit_code), compiled.status.code(), "Exit codes differ.");
+    assert_eq!(output, std::str::from_utf8(&compiled.stdout).unwrap(), "Outputs differ.");
+    Ok(())
+}
+
+/// Runs the program, requiring it to panic with `message`.
+fn panic_test(f: &[&str], message: &str) -> Result<(), String> {
+    let m = till_check("tests/panic", f)?;
+    let m = ZydecoFile::link(m.inner)?;
+    let (exit_code, output) = interpret(&m, &IOMatch::default())?;
+    assert_eq!(1, exit_code, "Exit code is not 1.");
+    assert_eq!(format!("Program panicked with message: {}\n", message), output);
+    Ok(())
+}
+
+/// Parses, elaborates and links a program on its own, without std and without
+/// type checking it.
+fn till_link_unchecked(f: &str) -> Result<ls::Program, String> {
+    let path = PathBuf::from("tests/panic").join(f);
+    let source = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let m = ZydecoFile::elab(ZydecoFile::parse_src(&source, path)?)?;
+    ZydecoFile::link(m.inner)
+}
+
+/// Runs a program the type checker rejects, requiring it to panic with
+/// `message`.
+fn panic_unchecked_test(f: &str, message: &str) -> Result<(), String> {
+    let m = till_link_unchecked(f)?;
+    let (exit_code, output) = interpret(&m, &IOMatch::default())?;
+    assert_eq!(1, exit_code, "Exit code is not 1.");
+    assert_eq!(format!("Program panicked with message: {}\n", message), output);
+    Ok(())
+}
+
+fn build_dir(target: &str) -> Result<PathBuf, String> {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(target);
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+/// Runs the program in the interpreter and as a native executable built by
+/// the C backend, requiring the same exit code and output from both.
+fn c_test(parent: &str, f: &[&str], iomatch: &IOMatch) -> Result<(), String> {
+    let m = till_check(parent, f)?;
+    let m = ZydecoFile::link(m.inner)?;
+    c_compare(&m, &f.join("+"), iomatch)
+}
+
+/// Builds the linked program as the executable `name` and compares it with
+/// the interpreter.
+fn c_compare(m: &ls::Program, name: &str, iomatch: &IOMatch) -> Result<(), String> {
+    let expected = interpret(m, iomatch)?;
+    let exe = build_dir("c")?.join(name.replace('.', "_"));
+    ZydecoFile::build_c(m, &exe)?;
+    run_compiled(&mut Command::new(&exe), iomatch, expected)
+}
+
+fn c_batch_test(f: &[&str]) -> Result<(), String> {
+    c_test("tests/nonzero-exit-code", f, &IOMatch::default())
+}
+
+fn c_io_test(f: &[&str], iomatch: &IOMatch) -> Result<(), String> {
+    c_test("tests/io", f, iomatch)
+}
+
+fn c_panic_test(f: &[&str]) -> Result<(), String> {
+    c_test("tests/panic", f, &IOMatch::default())
+}
+
+fn c_panic_unchecked_test(f: &str) -> Result<(), String> {
+    c_compare(&till_link_unchecked(f)?, f, &IOMatch::default())
+}
+
+/// Builds a program of tests/host with the C backend, returning the executable.
+fn c_host_build(f: &str) -> Result<PathBuf, String> {
+    let m = till_check("tests/host", &[f])?;
+    let m = ZydecoFile::link(m.inner)?;
+    let exe = build_dir("c")?.join(format!("host_{}", f.replace('.', "_")));
+    ZydecoFile::build_c(&m, &exe)?;
+    Ok(exe)
+}
+
+/// Runs a WASI module with node's `wasi` module.
+const NODE_WASI_LAUNCHER: &str = r#"
+const { readFileSync } = require("fs");
+const { WASI } = require("wasi");
+const args = process.argv.slice(2);
+const wasi = new WASI({ version: "preview1", args, env: {}, returnOnExit: true });
+WebAssembly.instantiate(readFileSync(args[0]), wasi.getImportObject()).then(({ instance }) => {
+    process.exitCode = wasi.start(instance);
+});
+"#;
+
+/// A command running the module under the first WASI runtime found, trying
+/// `wasmtime` and then `node`.
+fn wasm_runner(module: &Path) -> Result<Option<Command>, String> {
+    let found = |cmd: &str| Command::new(cmd).arg("--version").output().is_ok();
+    if found("wasmtime") {
+        let mut cmd = Command::new("wasmtime");
+        cmd.arg(module);
+        Ok(Some(cmd))
+    } else if found("node") {
+        let launcher = build_dir("wasm")?.join("launcher.js");
+        std::fs::write(&launcher, NODE_WASI_LAUNCHER).map_err(|e| e.to_string())?;
+        let mut cmd = Command::new("node");
+        cmd.arg("--no-warnings").arg(launcher).arg(module);
+        Ok(Some(cmd))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Runs the program in the interpreter and as a WebAssembly module built by
+/// the wasm backend, requiring the same exit code and output from both.
+/// Fails when no WASI runtime is installed, unless `ZYDECO_SKIP_WASM` is set
+/// to skip running the module.
+fn wasm_test(parent: &str, f: &[&str], iomatch: &IOMatch) -> Result<(), String> {
+    let m = till_check(parent, f)?;
+    let m = ZydecoFile::link(m.inner)?;
+    let expected = interpret(&m, iomatch)?;
+
+    let module = build_dir("wasm")?.join(f.join("+").replace('.', "_")).with_extension("wasm");
+    ZydecoFile::build_wasm(&m, &module)?;
+    let Some(mut cmd) = wasm_runner(&module)? else {
+        if std::env::var_os("ZYDECO_SKIP_WASM").is_some() {
+            eprintln!("ZYDECO_SKIP_WASM is set, skipping {}", module.display());
+            return Ok(());
+        }
+        Err("No WASI runtime found; install wasmtime or node, or set ZYDECO_SKIP_WASM")?
+    };
+    run_compiled(&mut cmd, iomatch, expected)
+}
+
+/// Requires the WebAssembly backend to reject the linked program with `error`.
+fn wasm_reject(m: &ls::Program, error: &str) -> Result<(), String> {
+    match ZydecoFile::emit_wasm(m) {
+        Ok(_) => Err("The WebAssembly backend accepted the program".to_string()),
+        Err(e) => {
+            assert_eq!(error, e);
+            Ok(())
+        }
+    }
+}
+
+fn wasm_unsupported_test(parent: &str, f: &[&str], error: &str) -> Result<(), String> {
+    let m = till_check(parent, f)?;
+    wasm_reject(&ZydecoFile::link(m.inner)?, error)
+}
+
+fn wasm_unsupported_batch_test(f: &[&str], error: &str) -> Result<(), String> {
+    wasm_unsupported_test("tests/nonzero-exit-code", f, error)
+}
+
+fn wasm_unsupported_panic_test(f: &[&str], error: &str) -> Result<(), String> {
+    wasm_unsupported_test("tests/panic", f, error)
+}
+
+fn wasm_unsupported_unchecked_test(f: &str, error: &str) -> Result<(), String> {
+    wasm_reject(&till_link_unchecked(f)?, error)
+}
+
+fn wasm_batch_test(f: &[&str]) -> Result<(), String> {
+    wasm_test("tests/nonzero-exit-code", f, &IOMatch::default())
+}
+
+fn wasm_io_test(f: &[&str], iomatch: &IOMatch) -> Result<(), String> {
+    wasm_test("tests/io", f, iomatch)
+}
+
 macro_rules! mk_test {
     ($test_sort:ident, $test_name:ident, $file_name:expr, $($rest:expr),*) => {
         #[test]
@@ -120,6 +335,8 @@ mod chk_tests {
     mk_test!(check_test, iota, &["iota.zy"]);
     mk_test!(check_test, alias, &["alias.zy"]);
     mk_test!(check_test, bigmac, &["bigmac.zy"]);
+    mk_test!(check_test, holes, &["holes.zy"]);
+    mk_test!(check_test, non_ascii_match, &["non-ascii-match.zy"]);
 }
 mod batch_tests {
     // Note: to use rust-analyzer's debug feature on tests, you can replace
@@ -138,16 +355,38 @@ mod batch_tests {
     mk_test!(batch_test, ifz, &["ifz.zy"]);
     mk_test!(batch_test, add, &["add.zy"]);
     mk_test!(batch_test, regex, &["regex.zy"]);
+    mk_test!(batch_test, unicode, &["unicode.zy"]);
+    mk_test!(batch_test, strings, &["strings.zy"]);
+    mk_test!(batch_test, parse_int, &["parse-int.zy"]);
     mk_test!(batch_test, listm, &["listm.zydeco"]);
     mk_test!(batch_test, fn_opt, &["fn-opt.zy"]);
     mk_test!(batch_test, abort, &["abort.zy"]);
     mk_test!(batch_test, choice, &["choice.zy"]);
     mk_test!(batch_test, forall, &["forall.zy"]);
+    mk_test!(batch_test, infer_tyapp, &["infer-tyapp.zy"]);
+    mk_test!(batch_test, nullary_tyapp, &["nullary-tyapp.zy"]);
+    mk_test!(batch_test, synth, &["synth.zy"]);
+    mk_test!(batch_test, nested_patterns, &["nested-patterns.zy"]);
+    mk_test!(batch_test, shared_ctors, &["shared-ctors.zy"]);
+    mk_test!(batch_test, literal_patterns, &["literal-patterns.zy"]);
+    mk_test!(batch_test, copatterns, &["copatterns.zy"]);
+    mk_test!(batch_test, list_tuple_literals, &["list-tuple-literals.zy"]);
+    mk_test!(batch_test, interpolation, &["interpolation.zy"]);
+    mk_test!(batch_test, operators, &["operators.zy"]);
+    mk_test!(batch_test, operator_shadowing, &["operator-shadowing.zy"]);
+    mk_test!(batch_test, kind_inference, &["kind-inference.zy"]);
     mk_test!(batch_test, exists, &["exists.zy"]);
     mk_test!(batch_test, partial_ann, &["partial-annotation.zy"]);
     mk_test!(batch_test, oo, &["oo.zydeco"]);
     mk_test!(batch_test, ret, &["ret.zydeco"]);
     mk_test!(batch_test, hash, &["hash.zy"]);
+    mk_test!(batch_test, generator, &["generator.zy"]);
+    mk_test!(batch_test, exception, &["exception.zy"]);
+    mk_test!(batch_test, state_effect, &["state-effect.zy"]);
+    mk_test!(batch_test, exception_effect, &["exception-effect.zy"]);
+    mk_test!(batch_test, sieve, &["sieve.zy"]);
+    mk_test!(batch_test, landins_knot, &["landins-knot.zy"]);
+    mk_test!(batch_test, prompt_function, &["prompt-function.zy"]);
 }
 mod io_tests {
     use super::*;
@@ -184,6 +423,337 @@ mod io_tests {
         }
     );
 }
+mod c_tests {
+    use super::*;
+    mk_test!(c_batch_test, defunctionalization, &["defunctionalization.zydeco"]);
+    mk_test!(c_batch_test, dpa, &["deterministic-pushdown-automaton.zydeco"]);
+    mk_test!(c_batch_test, interpreter, &["interpreter.zydeco"]);
+    mk_test!(c_batch_test, list, &["list.zydeco"]);
+    mk_test!(c_batch_test, y, &["Y.zydeco"]);
+    mk_test!(c_batch_test, unit, &["unit.zy"]);
+    mk_test!(c_batch_test, num, &["num.zy"]);
+    mk_test!(c_batch_test, eo_data, &["even-odd-data.zy"]);
+    mk_test!(c_batch_test, eo_rec, &["even-old-rec.zy"]);
+    mk_test!(c_batch_test, eo_coda, &["even-odd-codata.zy"]);
+    mk_test!(c_batch_test, ifz, &["ifz.zy"]);
+    mk_test!(c_batch_test, add, &["add.zy"]);
+    mk_test!(c_batch_test, regex, &["regex.zy"]);
+    mk_test!(c_batch_test, unicode, &["unicode.zy"]);
+    mk_test!(c_batch_test, parse_int, &["parse-int.zy"]);
+    mk_test!(c_batch_test, listm, &["listm.zydeco"]);
+    mk_test!(c_batch_test, fn_opt, &["fn-opt.zy"]);
+    mk_test!(c_batch_test, abort, &["abort.zy"]);
+    mk_test!(c_batch_test, choice, &["choice.zy"]);
+    mk_test!(c_batch_test, forall, &["forall.zy"]);
+    mk_test!(c_batch_test, infer_tyapp, &["infer-tyapp.zy"]);
+    mk_test!(c_batch_test, synth, &["synth.zy"]);
+    mk_test!(c_batch_test, nested_patterns, &["nested-patterns.zy"]);
+    mk_test!(c_batch_test, shared_ctors, &["shared-ctors.zy"]);
+    mk_test!(c_batch_test, literal_patterns, &["literal-patterns.zy"]);
+    mk_test!(c_batch_test, copatterns, &["copatterns.zy"]);
+    mk_test!(c_batch_test, list_tuple_literals, &["list-tuple-literals.zy"]);
+    mk_test!(c_batch_test, interpolation, &["interpolation.zy"]);
+    mk_test!(c_batch_test, operators, &["operators.zy"]);
+    mk_test!(c_batch_test, operator_shadowing, &["operator-shadowing.zy"]);
+    mk_test!(c_batch_test, kind_inference, &["kind-inference.zy"]);
+    mk_test!(c_batch_test, exists, &["exists.zy"]);
+    mk_test!(c_batch_test, partial_ann, &["partial-annotation.zy"]);
+    mk_test!(c_batch_test, oo, &["oo.zydeco"]);
+    mk_test!(c_batch_test, ret, &["ret.zydeco"]);
+    mk_test!(c_batch_test, hash, &["hash.zy"]);
+    mk_test!(c_batch_test, strings, &["strings.zy"]);
+    mk_test!(c_batch_test, nullary_tyapp, &["nullary-tyapp.zy"]);
+    mk_test!(c_batch_test, generator, &["generator.zy"]);
+    mk_test!(c_batch_test, exception, &["exception.zy"]);
+    mk_test!(c_batch_test, state_effect, &["state-effect.zy"]);
+    mk_test!(c_batch_test, exception_effect, &["exception-effect.zy"]);
+    mk_test!(c_batch_test, sieve, &["sieve.zy"]);
+    mk_test!(c_batch_test, landins_knot, &["landins-knot.zy"]);
+    mk_test!(c_batch_test, prompt_function, &["prompt-function.zy"]);
+    mk_test!(c_panic_test, array_get_out_of_bounds, &["array-get-out-of-bounds.zy"]);
+    mk_test!(c_panic_test, array_set_out_of_bounds, &["array-set-out-of-bounds.zy"]);
+    mk_test!(c_panic_test, array_negative_length, &["array-negative-length.zy"]);
+    mk_test!(c_panic_test, array_too_long, &["array-too-long.zy"]);
+    mk_test!(c_panic_test, prompt_not_found, &["prompt-not-found.zy"]);
+    mk_test!(c_panic_test, prompt_left, &["prompt-left.zy"]);
+    mk_test!(c_panic_unchecked_test, unhandled_operation, "unhandled-operation.zy");
+    mk_test!(c_panic_unchecked_test, resume_without_argument, "resume-without-argument.zy");
+
+    #[test]
+    fn host_system() {
+        let m = till_check("tests/host", &["system.zy"]).unwrap();
+        let m = ZydecoFile::link(m.inner).unwrap();
+        let mut host =
+            MockHost::default().with_env("USER", "zydeco").with_clock(1000).with_seed(42);
+        let ds::ProgKont::ExitCode(exit_code) = ZydecoFile::eval_host(m, &mut host).entry else {
+            panic!("{}", host.output())
+        };
+        let compiled = Command::new(c_host_build("system.zy").unwrap())
+            .args(["--zy-seed=42", "--zy-fake-clock=1000"])
+            .env("USER", "zydeco")
+            .env_remove("MISSING")
+            .output()
+            .unwrap();
+        assert_eq!(Some(exit_code), compiled.status.code(), "Exit codes differ.");
+        assert_eq!(host.output(), std::str::from_utf8(&compiled.stdout).unwrap());
+        assert_eq!(host.errors(), std::str::from_utf8(&compiled.stderr).unwrap());
+    }
+
+    #[test]
+    fn host_files() {
+        let root = build_dir("c").unwrap().join("sandbox");
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("data")).unwrap();
+        std::fs::write(root.join("data/in.txt"), "hello").unwrap();
+        let compiled = Command::new(c_host_build("files.zy").unwrap())
+            .arg(format!("--zy-sandbox={}", root.display()))
+            .output()
+            .unwrap();
+        let output = std::str::from_utf8(&compiled.stdout).unwrap();
+        assert_eq!(Some(0), compiled.status.code(), "{}", output);
+        // unlike the mock host's, a real directory outlives its last file
+        assert_eq!(output, "data\n../secret is outside of the sandbox\n");
+        assert_eq!(std::fs::read_to_string(root.join("out.txt")).unwrap(), "hello more");
+        assert!(!root.join("data/in.txt").exists());
+        // nor can a link lead out of it
+        let outside = build_dir("c").unwrap().join("outside");
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(outside.join("in.txt"), "hello").unwrap();
+        std::fs::remove_dir_all(root.join("data")).unwrap();
+        std::os::unix::fs::symlink(&outside, root.join("data")).unwrap();
+        let compiled = Command::new(c_host_build("files.zy").unwrap())
+            .arg(format!("--zy-sandbox={}", root.display()))
+            .output()
+            .unwrap();
+        let output = std::str::from_utf8(&compiled.stdout).unwrap();
+        assert_ne!(Some(0), compiled.status.code(), "{}", output);
+        assert!(output.contains("data/in.txt is outside of the sandbox"), "{}", output);
+        assert_eq!(std::fs::read_to_string(outside.join("in.txt")).unwrap(), "hello");
+    }
+    mk_test!(
+        c_io_test,
+        echo_once,
+        &["echo_once.zydeco"],
+        &IOMatch { input: "hello\n".to_string(), ..Default::default() }
+    );
+    mk_test!(
+        c_io_test,
+        print_args,
+        &["print_args.zydeco"],
+        &IOMatch { args: vec!["hello".to_string(), "world".to_string()], ..Default::default() }
+    );
+    mk_test!(
+        c_io_test,
+        print_list,
+        &["print_list.zydeco"],
+        &IOMatch { input: "hello\n".to_string(), ..Default::default() }
+    );
+}
+mod wasm_tests {
+    use super::*;
+    mk_test!(wasm_batch_test, defunctionalization, &["defunctionalization.zydeco"]);
+    mk_test!(wasm_batch_test, dpa, &["deterministic-pushdown-automaton.zydeco"]);
+    mk_test!(wasm_batch_test, interpreter, &["interpreter.zydeco"]);
+    mk_test!(wasm_batch_test, list, &["list.zydeco"]);
+    mk_test!(wasm_batch_test, y, &["Y.zydeco"]);
+    mk_test!(wasm_batch_test, unit, &["unit.zy"]);
+    mk_test!(wasm_batch_test, num, &["num.zy"]);
+    mk_test!(wasm_batch_test, eo_data, &["even-odd-data.zy"]);
+    mk_test!(wasm_batch_test, eo_rec, &["even-old-rec.zy"]);
+    mk_test!(wasm_batch_test, eo_coda, &["even-odd-codata.zy"]);
+    mk_test!(wasm_batch_test, ifz, &["ifz.zy"]);
+    mk_test!(wasm_batch_test, add, &["add.zy"]);
+    mk_test!(wasm_batch_test, regex, &["regex.zy"]);
+    mk_test!(wasm_batch_test, unicode, &["unicode.zy"]);
+    mk_test!(wasm_batch_test, parse_int, &["parse-int.zy"]);
+    mk_test!(wasm_batch_test, listm, &["listm.zydeco"]);
+    mk_test!(wasm_batch_test, fn_opt, &["fn-opt.zy"]);
+    mk_test!(wasm_batch_test, abort, &["abort.zy"]);
+    mk_test!(wasm_batch_test, choice, &["choice.zy"]);
+    mk_test!(wasm_batch_test, forall, &["forall.zy"]);
+    mk_test!(wasm_batch_test, infer_tyapp, &["infer-tyapp.zy"]);
+    mk_test!(wasm_batch_test, synth, &["synth.zy"]);
+    mk_test!(wasm_batch_test, nested_patterns, &["nested-patterns.zy"]);
+    mk_test!(wasm_batch_test, shared_ctors, &["shared-ctors.zy"]);
+    mk_test!(wasm_batch_test, literal_patterns, &["literal-patterns.zy"]);
+    mk_test!(wasm_batch_test, copatterns, &["copatterns.zy"]);
+    mk_test!(wasm_batch_test, list_tuple_literals, &["list-tuple-literals.zy"]);
+    mk_test!(wasm_batch_test, interpolation, &["interpolation.zy"]);
+    mk_test!(wasm_batch_test, operators, &["operators.zy"]);
+    mk_test!(wasm_batch_test, operator_shadowing, &["operator-shadowing.zy"]);
+    mk_test!(wasm_batch_test, kind_inference, &["kind-inference.zy"]);
+    mk_test!(wasm_batch_test, exists, &["exists.zy"]);
+    mk_test!(wasm_batch_test, partial_ann, &["partial-annotation.zy"]);
+    mk_test!(wasm_batch_test, oo, &["oo.zydeco"]);
+    mk_test!(wasm_batch_test, ret, &["ret.zydeco"]);
+    mk_test!(wasm_batch_test, hash, &["hash.zy"]);
+    mk_test!(
+        wasm_unsupported_batch_test,
+        strings,
+        &["strings.zy"],
+        "Builtin str_find is not supported by the WebAssembly backend"
+    );
+    mk_test!(
+        wasm_unsupported_batch_test,
+        nullary_tyapp,
+        &["nullary-tyapp.zy"],
+        "Builtin new_prompt is not supported by the WebAssembly backend"
+    );
+    mk_test!(
+        wasm_unsupported_batch_test,
+        generator,
+        &["generator.zy"],
+        "Builtin new_prompt is not supported by the WebAssembly backend"
+    );
+    mk_test!(
+        wasm_unsupported_batch_test,
+        exception,
+        &["exception.zy"],
+        "Builtin new_prompt is not supported by the WebAssembly backend"
+    );
+    mk_test!(
+        wasm_unsupported_batch_test,
+        state_effect,
+        &["state-effect.zy"],
+        "Effect handlers are not supported by the WebAssembly backend"
+    );
+    mk_test!(
+        wasm_unsupported_batch_test,
+        exception_effect,
+        &["exception-effect.zy"],
+        "Effect handlers are not supported by the WebAssembly backend"
+    );
+    mk_test!(
+        wasm_unsupported_batch_test,
+        sieve,
+        &["sieve.zy"],
+        "Builtin ref_new is not supported by the WebAssembly backend"
+    );
+    mk_test!(
+        wasm_unsupported_batch_test,
+        landins_knot,
+        &["landins-knot.zy"],
+        "Builtin ref_new is not supported by the WebAssembly backend"
+    );
+    mk_test!(
+        wasm_unsupported_batch_test,
+        prompt_function,
+        &["prompt-function.zy"],
+        "Builtin new_prompt is not supported by the WebAssembly backend"
+    );
+    mk_test!(
+        wasm_unsupported_panic_test,
+        array_get_out_of_bounds,
+        &["array-get-out-of-bounds.zy"],
+        "Builtin array_new is not supported by the WebAssembly backend"
+    );
+    mk_test!(
+        wasm_unsupported_panic_test,
+        array_set_out_of_bounds,
+        &["array-set-out-of-bounds.zy"],
+        "Builtin array_new is not supported by the WebAssembly backend"
+    );
+    mk_test!(
+        wasm_unsupported_panic_test,
+        array_negative_length,
+        &["array-negative-length.zy"],
+        "Builtin array_new is not supported by the WebAssembly backend"
+    );
+    mk_test!(
+        wasm_unsupported_panic_test,
+        array_too_long,
+        &["array-too-long.zy"],
+        "Builtin array_new is not supported by the WebAssembly backend"
+    );
+    mk_test!(
+        wasm_unsupported_panic_test,
+        prompt_not_found,
+        &["prompt-not-found.zy"],
+        "Builtin new_prompt is not supported by the WebAssembly backend"
+    );
+    mk_test!(
+        wasm_unsupported_panic_test,
+        prompt_left,
+        &["prompt-left.zy"],
+        "Builtin new_prompt is not supported by the WebAssembly backend"
+    );
+    mk_test!(
+        wasm_unsupported_unchecked_test,
+        unhandled_operation,
+        "unhandled-operation.zy",
+        "Effect handlers are not supported by the WebAssembly backend"
+    );
+    mk_test!(
+        wasm_unsupported_unchecked_test,
+        resume_without_argument,
+        "resume-without-argument.zy",
+        "Effect handlers are not supported by the WebAssembly backend"
+    );
+    mk_test!(
+        wasm_io_test,
+        echo_once,
+        &["echo_once.zydeco"],
+        &IOMatch { input: "hello\n".to_string(), ..Default::default() }
+    );
+    mk_test!(
+        wasm_io_test,
+        print_args,
+        &["print_args.zydeco"],
+        &IOMatch { args: vec!["hello".to_string(), "world".to_string()], ..Default::default() }
+    );
+    mk_test!(
+        wasm_io_test,
+        print_list,
+        &["print_list.zydeco"],
+        &IOMatch { input: "hello\n".to_string(), ..Default::default() }
+    );
+}
+mod panic_tests {
+    use super::*;
+    mk_test!(
+        panic_test,
+        prompt_not_found,
+        &["prompt-not-found.zy"],
+        "prompt not found on the stack"
+    );
+    mk_test!(panic_test, prompt_left, &["prompt-left.zy"], "prompt not found on the stack");
+    mk_test!(
+        panic_test,
+        array_get_out_of_bounds,
+        &["array-get-out-of-bounds.zy"],
+        "array index 3 out of bounds for length 3"
+    );
+    mk_test!(
+        panic_test,
+        array_set_out_of_bounds,
+        &["array-set-out-of-bounds.zy"],
+        "array index -1 out of bounds for length 3"
+    );
+    mk_test!(
+        panic_test,
+        array_negative_length,
+        &["array-negative-length.zy"],
+        "negative array length -2"
+    );
+    mk_test!(
+        panic_test,
+        array_too_long,
+        &["array-too-long.zy"],
+        "cannot allocate an array of length 9223372036854775807"
+    );
+    mk_test!(
+        panic_unchecked_test,
+        unhandled_operation,
+        "unhandled-operation.zy",
+        "unhandled effect operation raise"
+    );
+    mk_test!(
+        panic_unchecked_test,
+        resume_without_argument,
+        "resume-without-argument.zy",
+        "resume is missing an argument"
+    );
+}
 mod doc_tests {
     use super::*;
     mk_test!(doc_test, toplevel, &["0-toplevel.zy"], true);
@@ -193,7 +763,404 @@ mod doc_tests {
     mk_test!(doc_test, y_combinator, &["4-y-combinator.zy"], true);
     mk_test!(doc_test, mutual_rec, &["5-mutual-rec.zy"], true);
     mk_test!(doc_test, cps, &["6-cps.zy"], true);
-    mk_test!(doc_test, call_by_need, &["7-call-by-need.zy"], false);
+    mk_test!(doc_test, call_by_need, &["7-call-by-need.zy"], true);
+}
+
+/// Runs the program linked against `registry`, requiring exit code 0.
+fn host_test(f: &[&str], registry: &Registry) -> Result<(), String> {
+    let m = till_check_with("tests/host", f, registry)?;
+    let m = ZydecoFile::link_with(m.inner, registry)?;
+    let (exit_code, _) = interpret(&m, &IOMatch::default())?;
+    if exit_code != 0 {
+        Err(format!("Non-zero exit code: {}", exit_code))?
+    }
+    Ok(())
 }
 
-mod custom_tests {}
+mod custom_tests {
+    use super::*;
+    use std::{cell::Cell, rc::Rc};
+
+    #[test]
+    fn host_builtins() {
+        let ticks = Rc::new(Cell::new(0));
+        let reported = Rc::new(Cell::new(None));
+        let mut registry = Registry::std();
+        registry
+            .register(Builtin::new("tick", "Thunk(Ret Int)", {
+                let ticks = ticks.clone();
+                move |_, _| {
+                    ticks.set(ticks.get() + 1);
+                    Ok(ls::Ret(Rc::new(ls::Literal::Int(ticks.get()).into())).into())
+                }
+            }))
+            .register(Builtin::new("report", "Thunk(Int -> Ret Unit)", {
+                let reported = reported.clone();
+                move |args, _| {
+                    let [ds::SemVal::Literal(ls::Literal::Int(i))] = args.as_slice() else {
+                        unreachable!()
+                    };
+                    reported.set(Some(*i));
+                    let unit = ls::Ctor {
+                        ctorv: ls::CtorV::new("Unit".to_string(), SpanInfo::dummy()),
+                        args: vec![],
+                    };
+                    Ok(ls::Ret(Rc::new(ds::SemVal::from(unit).into())).into())
+                }
+            }));
+        wrapper(host_test(&["counter.zy"], &registry));
+        assert_eq!(ticks.get(), 2);
+        assert_eq!(reported.get(), Some(3));
+    }
+
+    #[derive(IntoZydeco, FromZydeco, ZydecoType, Debug, PartialEq)]
+    enum Shape {
+        Circle(i64),
+        Rect { w: i64, h: i64 },
+    }
+
+    #[test]
+    fn host_conversions() {
+        let mut registry = Registry::std();
+        registry
+            .register(Builtin::new("area", "Thunk(Shape -> Ret Int)", |args, _| {
+                let area = match Shape::from_zydeco(&args[0]).unwrap() {
+                    Shape::Circle(r) => 3 * r * r,
+                    Shape::Rect { w, h } => w * h,
+                };
+                Ok(ls::Ret(Rc::new(area.into_zydeco().into())).into())
+            }))
+            .register(Builtin::new("square", "Thunk(Int -> Ret Shape)", |args, _| {
+                let w = i64::from_zydeco(&args[0]).unwrap();
+                Ok(ls::Ret(Rc::new(Shape::Rect { w, h: w }.into_zydeco().into())).into())
+            }));
+        wrapper(host_test(&["shape.zy"], &registry));
+
+        let v = vec![Some((1, "one".to_string())), None];
+        assert_eq!(Vec::<Option<(i64, String)>>::from_zydeco(&v.clone().into_zydeco()).unwrap(), v);
+        let q = (1, 'a', (true, ()), vec![2]);
+        assert_eq!(
+            <(i64, char, (bool, ()), Vec<i64>)>::from_zydeco(&q.clone().into_zydeco()).unwrap(),
+            q
+        );
+        let e = Shape::from_zydeco(&true.into_zydeco()).unwrap_err();
+        assert_eq!(e.to_string(), "Expected a value of Shape, found True()");
+    }
+
+    #[test]
+    fn tyapp_undetermined() {
+        let Err(e) = till_check("tests/check-only", &["undetermined-tyapp.zy"]) else {
+            panic!("checked with an undetermined type argument")
+        };
+        assert!(e.contains("Need annotation for type argument `A`"), "{}", e);
+        assert!(e.contains("undetermined-tyapp.zy:5:11"), "{}", e);
+    }
+
+    #[test]
+    fn nullary_tyapp_undetermined() {
+        let Err(e) = till_check("tests/check-only", &["undetermined-nullary.zy"]) else {
+            panic!("ran a polymorphic computation with an undetermined type argument")
+        };
+        assert!(e.contains("Need annotation for type argument `A`"), "{}", e);
+        assert!(e.contains("undetermined-nullary.zy:9:12"), "{}", e);
+    }
+
+    #[test]
+    fn rec_undetermined() {
+        let Err(e) = till_check("tests/check-only", &["undetermined-rec.zy"]) else {
+            panic!("checked a recursion of undetermined type")
+        };
+        assert!(e.contains("Need annotation for rec"), "{}", e);
+        assert!(e.contains("undetermined-rec.zy:4:14"), "{}", e);
+    }
+
+    #[test]
+    fn holes_report() {
+        let m = ZydecoFile::parse(vec!["tests/check-only/holes.zy".into()]).unwrap();
+        let m = ZydecoFile::elab(m).unwrap();
+        let goals = ZydecoFile::tyck(m).unwrap();
+        let goals: Vec<_> = goals.iter().map(|goal| goal.to_string()).collect();
+        assert_eq!(goals.len(), 2, "{:?}", goals);
+        assert!(goals[0].starts_with("Goal ?offset : Int\n"), "{}", goals[0]);
+        assert!(goals[0].contains("holes.zy:7:38"), "{}", goals[0]);
+        assert!(goals[0].ends_with("\tfactor : Int\n\tn : Int\n"), "{}", goals[0]);
+        assert!(goals[1].starts_with("Goal _ : Int\n"), "{}", goals[1]);
+        assert!(goals[1].ends_with("\tn : Int\n\tx : Int\n"), "{}", goals[1]);
+    }
+
+    #[test]
+    fn holes_reached() {
+        let m = till_check("tests/check-only", &["holes.zy"]).unwrap();
+        let m = ZydecoFile::link(m.inner).unwrap();
+        let (m, output) = ZydecoFile::eval_virtual_os(m, "", &[]);
+        assert_eq!(output, "before the hole\n");
+        let ds::ProgKont::Hole(hole) = m.entry else { panic!("didn't stop at the hole") };
+        assert_eq!(hole.fmt(), "?offset");
+    }
+
+    #[test]
+    fn match_non_exhaustive() {
+        let Err(e) = till_check("tests/check-only", &["non-exhaustive.zy"]) else {
+            panic!("checked a match missing a case")
+        };
+        assert!(e.contains("Match is missing a case for Cons(_, Nil())"), "{}", e);
+        assert!(e.contains("non-exhaustive.zy:4:9"), "{}", e);
+    }
+
+    #[test]
+    fn match_literal_default() {
+        let Err(e) = till_check("tests/check-only", &["literal-default.zy"]) else {
+            panic!("checked a match on literals without a default arm")
+        };
+        assert!(e.contains("Match is missing a case for 2"), "{}", e);
+        assert!(e.contains("literal-default.zy:4:9"), "{}", e);
+    }
+
+    #[test]
+    fn match_literal_nested() {
+        let Err(e) = till_check("tests/check-only", &["literal-nested.zy"]) else {
+            panic!("checked a match missing a literal")
+        };
+        assert!(e.contains("Match is missing a case for Some(\"aa\")"), "{}", e);
+        assert!(e.contains("literal-nested.zy:4:9"), "{}", e);
+    }
+
+    #[test]
+    fn interpolation_no_formatting() {
+        let Err(e) = till_check("tests/check-only", &["interpolation-no-formatting.zy"]) else {
+            panic!("checked a string interpolating a Bool")
+        };
+        assert!(
+            e.contains("In string interpolation, expected Int, Char, String or Thunk(Ret String)"),
+            "{}",
+            e
+        );
+        assert!(e.contains("interpolation-no-formatting.zy:5:22"), "{}", e);
+    }
+
+    #[test]
+    fn non_associative_operators() {
+        let Err(e) = till_check("tests/check-only", &["non-associative-operators.zy"]) else {
+            panic!("checked a chain of non-associative operators")
+        };
+        assert!(e.contains("Operators `==` and `==` don't associate"), "{}", e);
+        assert!(e.contains("non-associative-operators.zy:5:18"), "{}", e);
+    }
+
+    #[test]
+    fn rebound_operator() {
+        if let Err(e) = till_check("tests/check-only", &["rebound-operator.zy"]) {
+            panic!("a local `+` rebound the operator: {}", e)
+        }
+    }
+
+    #[test]
+    fn duplicate_operator() {
+        let Err(e) = till_check("tests/check-only", &["duplicate-operator.zy"]) else {
+            panic!("checked an operator declared twice in a module")
+        };
+        assert!(e.contains("<+ declared multiple times"), "{}", e);
+    }
+
+    #[test]
+    fn effect_duplicate_operation() {
+        let Err(e) = till_check("tests/check-only", &["duplicate-operation.zy"]) else {
+            panic!("checked two effects sharing an operation")
+        };
+        assert!(e.contains("get declared multiple times"), "{}", e);
+        assert!(e.contains("duplicate-operation.zy:9:5"), "{}", e);
+    }
+
+    #[test]
+    fn comatch_overlapping_copatterns() {
+        let Err(e) = till_check("tests/check-only", &["overlapping-copatterns.zy"]) else {
+            panic!("checked a comatch handling an observation twice")
+        };
+        assert!(e.contains("Observation .tail is handled by more than one arm"), "{}", e);
+        assert!(e.contains("overlapping-copatterns.zy:11:6"), "{}", e);
+    }
+
+    #[test]
+    fn match_redundant_arm() {
+        let Err(e) = till_check("tests/check-only", &["redundant-arm.zy"]) else {
+            panic!("checked a match with an arm never reached")
+        };
+        assert!(e.contains("Match arm can never be reached"), "{}", e);
+        assert!(e.contains("redundant-arm.zy:8:5"), "{}", e);
+    }
+
+    #[test]
+    fn kind_conflict() {
+        let Err(e) = till_check("tests/check-only", &["kind-conflict.zy"]) else {
+            panic!("checked with conflicting kinds")
+        };
+        assert!(e.contains("the kind of `A`, already VType from its use at"), "{}", e);
+        assert!(e.contains("kind-conflict.zy:5:9"), "{}", e);
+        assert!(e.contains("kind-conflict.zy:5:18"), "{}", e);
+    }
+
+    #[test]
+    fn host_builtin_missing() {
+        let Err(e) = till_check("tests/host", &["counter.zy"]) else {
+            panic!("checked without a builtin for `tick`")
+        };
+        assert!(e.contains("No builtin found for extern definition tick"), "{}", e);
+        let mut registry = Registry::std();
+        registry
+            .register(Builtin::new("tick", "Thunk(Ret Int)", |_, _| unreachable!()))
+            .register(Builtin::new("report", "Thunk(Int -> Ret Unit)", |_, _| unreachable!()));
+        let m = till_check_with("tests/host", &["counter.zy"], &registry).unwrap();
+        let Err(e) = ZydecoFile::link_with(m.inner, &Registry::std()) else {
+            panic!("linked without an implementation for `tick`")
+        };
+        assert!(e.contains("No implementation found for extern definition tick"), "{}", e);
+    }
+
+    #[test]
+    fn host_builtin_mismatch() {
+        let report = Builtin::new("report", "Thunk(String -> Ret Unit)", |_, _| unreachable!());
+        assert_eq!(report.arity(), 1);
+        let mut registry = Registry::std();
+        registry
+            .register(Builtin::new("tick", "Thunk(Ret Int)", |_, _| unreachable!()))
+            .register(report);
+        let Err(e) = till_check_with("tests/host", &["counter.zy"], &registry) else {
+            panic!("checked against a builtin of a different type")
+        };
+        assert!(e.contains("In extern definition report"), "{}", e);
+    }
+
+    #[test]
+    fn host_builtin_malformed_type() {
+        let Err(e) = Builtin::try_new("tick", "Thunk(Ret", |_, _| unreachable!()) else {
+            panic!("built a builtin with a malformed type")
+        };
+        assert!(e.to_string().contains("The type of builtin tick is malformed"), "{}", e);
+        let Err(e) = Builtin::try_new("tick", "Int", |_, _| unreachable!()) else {
+            panic!("built a builtin with a non-thunk type")
+        };
+        assert!(e.to_string().contains("The type of builtin tick should be a thunk"), "{}", e);
+    }
+
+    #[test]
+    fn engine_call() {
+        let path = PathBuf::from("tests/host/engine.zy");
+        let engine = Engine::load(vec![path], &Registry::std()).unwrap();
+        let mut host = MockHost::default();
+        let mut call =
+            |name, shape| -> Result<i64, String> { engine.call(name, (shape,), &mut host) };
+        assert_eq!(call("Geometry.area", Shape::Circle(2)), Ok(12));
+        assert_eq!(call("Geometry.area", Shape::Rect { w: 2, h: 5 }), Ok(10));
+        let square: Shape = engine.call("Geometry.square", (4,), &mut host).unwrap();
+        assert_eq!(square, Shape::Rect { w: 4, h: 4 });
+        let sum: i64 = engine.call("sum", (vec![1, 2, 3],), &mut host).unwrap();
+        assert_eq!(sum, 6);
+        let t: (bool, String, i64) =
+            engine.call("rotate", ((1, true, "one".to_string()),), &mut host).unwrap();
+        assert_eq!(t, (true, "one".to_string(), 1));
+        let e = engine.call::<_, i64>("Geometry.perimeter", (1, 2), &mut host);
+        assert_eq!(e.unwrap_err(), "No public definition Geometry.perimeter");
+        let e = engine.call::<_, bool>("sum", (vec![1],), &mut host).unwrap_err();
+        assert!(e.starts_with("sum has type"), "{}", e);
+        let e = engine.call::<_, usize>("sum", (vec![-1],), &mut host).unwrap_err();
+        assert_eq!(e, "Expected a value of usize, found -1");
+        let e = engine.call::<_, u32>("sum", (vec![i64::MAX],), &mut host).unwrap_err();
+        assert_eq!(e, format!("Expected a value of u32, found {}", i64::MAX));
+    }
+
+    #[test]
+    fn engine_same_name() {
+        let path = PathBuf::from("tests/host/same-name.zy");
+        let engine = Engine::load(vec![path], &Registry::std()).unwrap();
+        let mut host = MockHost::default();
+        assert_eq!(engine.call("Inc.step", (1,), &mut host), Ok(2));
+        assert_eq!(engine.call("Scale.step", (1,), &mut host), Ok(10));
+        assert_eq!(engine.call("Std.Arith.add", (2, 3), &mut host), Ok(5));
+        assert_eq!(engine.call("add", (2, 3), &mut host), Ok(6));
+    }
+
+    #[test]
+    fn host_files() {
+        let m = till_check("tests/host", &["files.zy"]).unwrap();
+        let m = ZydecoFile::link(m.inner).unwrap();
+        let mut host = MockHost::default().with_file("data/in.txt", "hello");
+        let ds::ProgKont::ExitCode(0) = ZydecoFile::eval_host(m, &mut host).entry else {
+            panic!("{}", host.output())
+        };
+        assert_eq!(host.output(), "out.txt\n../secret is outside of the sandbox\n");
+        assert_eq!(host.file("/out.txt"), Some("hello more"));
+        assert_eq!(host.file("data/in.txt"), None);
+    }
+
+    #[test]
+    fn host_system() {
+        let m = till_check("tests/host", &["system.zy"]).unwrap();
+        let run = |seed| {
+            let m = ZydecoFile::link(m.inner.clone()).unwrap();
+            let mut host =
+                MockHost::default().with_env("USER", "zydeco").with_clock(1000).with_seed(seed);
+            let ds::ProgKont::ExitCode(0) = ZydecoFile::eval_host(m, &mut host).entry else {
+                panic!("{}", host.output())
+            };
+            assert_eq!(host.errors(), "starting");
+            host.output().to_string()
+        };
+        let out = run(42);
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(lines[..4], ["zydeco", "unset", "250", "250"]);
+        assert!((0..6).contains(&lines[4].parse::<i64>().unwrap()));
+        assert_eq!(out, run(42));
+    }
+
+    #[test]
+    fn host_replay() {
+        let m = till_check("tests/host", &["system.zy"]).unwrap();
+        let run = |host: &mut dyn Host| {
+            let m = ZydecoFile::link(m.inner.clone()).unwrap();
+            let ds::ProgKont::ExitCode(x) = ZydecoFile::eval_host(m, host).entry else {
+                panic!("program did not exit")
+            };
+            x
+        };
+        let mut host = Recorder::new(MockHost::default().with_env("USER", "zydeco"), Vec::new());
+        let x = run(&mut host);
+        let (recorded, log) = host.exit(x).unwrap();
+        let log = String::from_utf8(log).unwrap();
+        let mut host = Replayer::new(&log, MockHost::default()).unwrap();
+        let x = run(&mut host);
+        let replayed = host.exit(x).unwrap();
+        assert_eq!(replayed.output(), recorded.output());
+        assert_eq!(replayed.errors(), recorded.errors());
+        let log = log.replace("USER\tzydeco", "USER\tsomeone");
+        let mut host = Replayer::new(&log, MockHost::default()).unwrap();
+        let x = run(&mut host);
+        let Err(divergence) = host.exit(x) else { panic!("replay did not diverge") };
+        assert_eq!(divergence.line, 4);
+        assert_eq!(divergence.found, r#"write_str "someone\n""#);
+    }
+
+    #[test]
+    fn host_sandbox() {
+        let root = Path::new(env!("CARGO_TARGET_TMPDIR")).join("sandbox");
+        std::fs::create_dir_all(root.join("data")).unwrap();
+        let mut host = OsHost::new(vec![]).sandbox(&root);
+        host.write_file("/data/../note.txt", "hi").unwrap();
+        assert_eq!(std::fs::read_to_string(root.join("note.txt")).unwrap(), "hi");
+        assert_eq!(host.list_dir("data/..").unwrap(), vec!["data", "note.txt"]);
+        assert!(host.read_file("../note.txt").is_err());
+        host.remove_file("note.txt").unwrap();
+        assert!(!host.exists("note.txt").unwrap());
+        // links can't lead out of the root either
+        let outside = Path::new(env!("CARGO_TARGET_TMPDIR")).join("outside");
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(outside.join("secret.txt"), "secret").unwrap();
+        let link = root.join("data/link");
+        let _ = std::fs::remove_file(&link);
+        std::os::unix::fs::symlink(&outside, &link).unwrap();
+        let e = host.read_file("data/link/secret.txt").unwrap_err();
+        assert_eq!(e.to_string(), "data/link/secret.txt is outside of the sandbox");
+        assert!(host.write_file("data/link/new.txt", "hi").is_err());
+        assert!(!outside.join("new.txt").exists());
+        std::fs::remove_file(&link).unwrap();
+    }
+}